@@ -60,6 +60,16 @@ impl TryInto<FeltMerkleProof> for MerkleProof {
     }
 }
 
+impl FeltMerkleProof {
+    /// Computes the root of the merkle tree containing the leaf, hashing it
+    /// with each sibling of the proof.
+    pub fn compute_root(&self, leaf: &Felt) -> Felt {
+        self.0.iter().fold(*leaf, |current_hash, sibling| {
+            pedersen_hash(&current_hash, sibling)
+        })
+    }
+}
+
 impl MerkleTree {
     pub fn new(leaves: Vec<Felt>) -> Result<Self, MerkleTreeError> {
         if leaves.is_empty() {
@@ -135,11 +145,7 @@ impl MerkleTree {
 
     /// Verify that the passed merkle proof is valid for the leaf.
     pub fn verify_proof(&self, leaf: &Felt, proof: &FeltMerkleProof) -> bool {
-        let mut current_hash = *leaf;
-        for &sibling in &proof.0 {
-            current_hash = pedersen_hash(&current_hash, &sibling);
-        }
-        current_hash == self.root_hash
+        proof.compute_root(leaf) == self.root_hash
     }
 }
 
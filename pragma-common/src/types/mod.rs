@@ -50,6 +50,8 @@ pub enum Interval {
     OneHour,
     #[serde(rename = "2h")]
     TwoHours,
+    #[serde(rename = "4h")]
+    FourHours,
     #[serde(rename = "1d")]
     OneDay,
     #[serde(rename = "1w")]
@@ -63,7 +65,8 @@ impl Interval {
             Interval::FifteenMinutes => 15,
             Interval::OneHour => 60,
             Interval::TwoHours => 120,
-            Interval::OneDay => 1400,
+            Interval::FourHours => 240,
+            Interval::OneDay => 1440,
             Interval::OneWeek => 10080,
         }
     }
@@ -71,4 +74,81 @@ impl Interval {
     pub fn to_seconds(&self) -> i64 {
         self.to_minutes() * 60
     }
+
+    /// Aligns the unix timestamp in seconds on the start of its interval,
+    /// like the timescale buckets: days start at midnight UTC and weeks on
+    /// monday.
+    pub fn align_timestamp(&self, timestamp: i64) -> i64 {
+        // The unix epoch is a thursday, the first monday is 4 days later.
+        let origin = match self {
+            Interval::OneWeek => 4 * 24 * 60 * 60,
+            _ => 0,
+        };
+        timestamp - (timestamp - origin).rem_euclid(self.to_seconds())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_to_minutes() {
+        assert_eq!(Interval::FourHours.to_minutes(), 240);
+        assert_eq!(Interval::OneDay.to_minutes(), 1440);
+        assert_eq!(Interval::OneDay.to_seconds(), 24 * 60 * 60);
+    }
+
+    #[test]
+    fn test_interval_serde() {
+        let four_hours: Interval = serde_json::from_str(r#""4h""#).unwrap();
+        assert_eq!(four_hours, Interval::FourHours);
+        let one_day: Interval = serde_json::from_str(r#""1d""#).unwrap();
+        assert_eq!(one_day, Interval::OneDay);
+    }
+
+    #[test]
+    fn test_align_timestamp() {
+        // 2024-06-10T14:37:21Z, a monday.
+        let timestamp = 1_718_030_241;
+        assert_eq!(
+            Interval::OneMinute.align_timestamp(timestamp),
+            1_718_030_220
+        );
+        assert_eq!(
+            Interval::FifteenMinutes.align_timestamp(timestamp),
+            1_718_029_800
+        );
+        assert_eq!(Interval::OneHour.align_timestamp(timestamp), 1_718_028_000);
+        assert_eq!(Interval::TwoHours.align_timestamp(timestamp), 1_718_028_000);
+        assert_eq!(
+            Interval::FourHours.align_timestamp(timestamp),
+            1_718_020_800
+        );
+        assert_eq!(Interval::OneDay.align_timestamp(timestamp), 1_717_977_600);
+        assert_eq!(Interval::OneWeek.align_timestamp(timestamp), 1_717_977_600);
+
+        // Day boundaries: 2024-06-10T00:00:00Z, 23:59:59 the same day and
+        // midnight the next day.
+        let midnight = 1_717_977_600;
+        assert_eq!(Interval::OneDay.align_timestamp(midnight), midnight);
+        assert_eq!(
+            Interval::OneDay.align_timestamp(midnight + 86_399),
+            midnight
+        );
+        assert_eq!(
+            Interval::OneDay.align_timestamp(midnight + 86_400),
+            midnight + 86_400
+        );
+        // 23:59 falls in the last 4 hours bucket of the day, starting at 20:00.
+        assert_eq!(
+            Interval::FourHours.align_timestamp(midnight + 86_340),
+            midnight + 20 * 60 * 60
+        );
+        // The sunday before belongs to the previous week.
+        assert_eq!(
+            Interval::OneWeek.align_timestamp(midnight - 1),
+            midnight - 7 * 86_400
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use pragma_common::types::Network;
 use reqwest::{
     header::{HeaderValue, InvalidHeaderValue},
@@ -5,8 +7,11 @@ use reqwest::{
 };
 
 use crate::{
-    config::{ApiConfig, PragmaBaseUrl},
-    constants::PRAGMAPI_HEALTHCHECK_ENDPOINT,
+    config::{ApiConfig, PragmaBaseUrl, RetryConfig},
+    constants::{
+        DEFAULT_MAX_CONCURRENT_FETCHES, DEFAULT_REQUEST_TIMEOUT, PRAGMAPI_API_KEY_HEADER,
+        PRAGMAPI_HEALTHCHECK_ENDPOINT,
+    },
     consumer::PragmaConsumer,
 };
 
@@ -23,11 +28,17 @@ pub enum BuilderError {
 }
 
 /// Builder of the Pragma consumer client.
-/// Default network is Sepolia.
+/// Default network is Sepolia. Failed requests are retried following the
+/// default [`RetryConfig`]. The health of the PragmAPI is not checked
+/// unless requested with [`PragmaConsumerBuilder::check_api_health`].
 #[derive(Default, Debug)]
 pub struct PragmaConsumerBuilder {
     network: Network,
     check_api_health: bool,
+    retry_config: RetryConfig,
+    verify_proofs: bool,
+    max_concurrent_fetches: Option<usize>,
+    request_timeout: Option<Duration>,
 }
 
 impl PragmaConsumerBuilder {
@@ -48,13 +59,51 @@ impl PragmaConsumerBuilder {
         self
     }
 
-    /// Perform an health check with the PragmAPI to make sur the connection is
-    /// successfuly established.
+    /// Perform an health check with the PragmAPI when building the consumer, to
+    /// make sure the connection is successfuly established.
+    /// Skipped by default, so building the consumer doesn't wait for the PragmAPI.
     pub fn check_api_health(mut self) -> Self {
         self.check_api_health = true;
         self
     }
 
+    /// Verify the merkle proofs returned by the PragmAPI against the root of
+    /// the merkle tree of the block before returning the calldata.
+    /// Costs an additional request per calldata.
+    pub fn verify_proofs(mut self) -> Self {
+        self.verify_proofs = true;
+        self
+    }
+
+    /// Maximum number of calldata fetched concurrently by
+    /// [`PragmaConsumer::get_merkle_feed_calldata_batch`]. Defaults to 8.
+    pub fn with_max_concurrent_fetches(mut self, max_concurrent_fetches: usize) -> Self {
+        self.max_concurrent_fetches = Some(max_concurrent_fetches.max(1));
+        self
+    }
+
+    /// Timeout of each request to the PragmAPI, retried once elapsed.
+    /// Defaults to 10 seconds.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Configure how the requests to the PragmAPI failing with a 5xx status,
+    /// timing out or failing to connect are retried.
+    pub fn with_retries(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
+    /// Builds the consumer, querying the PragmAPI over HTTP.
+    /// Same as [`PragmaConsumerBuilder::with_http`].
+    pub async fn with_api(self, api_config: ApiConfig) -> Result<PragmaConsumer, BuilderError> {
+        self.with_http(api_config).await
+    }
+
+    /// Builds the consumer, querying the PragmAPI over HTTP.
+    /// Only returns a [`BuilderError::HealthCheck`] if the health check was requested.
     pub async fn with_http(self, api_config: ApiConfig) -> Result<PragmaConsumer, BuilderError> {
         let http_client = self.build_http_client(&api_config)?;
 
@@ -67,6 +116,11 @@ impl PragmaConsumerBuilder {
             network: self.network,
             http_client,
             base_url: api_config.base_url,
+            retry_config: self.retry_config,
+            verify_proofs: self.verify_proofs,
+            max_concurrent_fetches: self
+                .max_concurrent_fetches
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_FETCHES),
         })
     }
 
@@ -75,11 +129,12 @@ impl PragmaConsumerBuilder {
             .default_headers({
                 let mut headers = reqwest::header::HeaderMap::new();
                 headers.insert(
-                    "x-api-key",
+                    PRAGMAPI_API_KEY_HEADER,
                     HeaderValue::from_str(&api_config.api_key).map_err(BuilderError::Header)?,
                 );
                 headers
             })
+            .timeout(self.request_timeout.unwrap_or(DEFAULT_REQUEST_TIMEOUT))
             .build()?)
     }
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 /// PragmAPI Base url. Can be either Dev, Prod or a Custom url.
 #[derive(Debug, Clone)]
 pub enum PragmaBaseUrl {
@@ -22,3 +24,39 @@ pub struct ApiConfig {
     pub base_url: PragmaBaseUrl,
     pub api_key: String,
 }
+
+/// Retries of the requests to the PragmAPI failing with a 5xx status, timing
+/// out or failing to connect. The other failures, like 4xx statuses, are
+/// never retried.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Maximum number of attempts of a request, the first one included.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled before each following one.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Never retries the requests.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay to wait after the failed `attempt` (starting at 1).
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
@@ -1,5 +1,22 @@
+use std::time::Duration;
+
 /// The prefix our API containing the version.
 pub const PRAGMAPI_PATH_PREFIX: &str = "node/v1/merkle_feeds";
 
+/// Endpoint (after the prefix) returning the block published at a timestamp.
+pub const PRAGMAPI_BLOCK_AT_TIMESTAMP_ENDPOINT: &str = "block";
+
+/// Endpoint (after the prefix) returning the root of the merkle tree of a block.
+pub const PRAGMAPI_MERKLE_ROOT_ENDPOINT: &str = "root";
+
+/// Header carrying the API key of each request.
+pub const PRAGMAPI_API_KEY_HEADER: &str = "x-api-key";
+
 /// Endpoint that can be called (without the prefix) to healthcheck the HTTP connection.
 pub const PRAGMAPI_HEALTHCHECK_ENDPOINT: &str = "node";
+
+/// Default maximum number of calldata fetched concurrently in a batch.
+pub const DEFAULT_MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// Default timeout of each request to the PragmAPI, after which it is retried.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
@@ -1,13 +1,21 @@
+use futures_util::stream::{self, StreamExt};
 use reqwest::{Response, StatusCode};
+use starknet::core::types::Felt;
 
 use pragma_common::types::{
     block_id::{BlockId, BlockTag},
-    merkle_tree::MerkleProof,
+    merkle_tree::{FeltMerkleProof, MerkleProof, MerkleTreeError},
     options::{Instrument, OptionData},
     Network,
 };
 
-use crate::{config::PragmaBaseUrl, constants::PRAGMAPI_PATH_PREFIX, types::MerkleFeedCalldata};
+use crate::{
+    config::{PragmaBaseUrl, RetryConfig},
+    constants::{
+        PRAGMAPI_BLOCK_AT_TIMESTAMP_ENDPOINT, PRAGMAPI_MERKLE_ROOT_ENDPOINT, PRAGMAPI_PATH_PREFIX,
+    },
+    types::MerkleFeedCalldata,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ConsumerError {
@@ -21,12 +29,21 @@ pub enum ConsumerError {
     Serde(#[from] serde_json::Error),
     #[error("could not compute the pedersen hash for option: `{:?}`", 0)]
     OptionHash(OptionData),
+    #[error("no block found for timestamp `{0}`")]
+    UnresolvableTimestamp(u64),
+    #[error("option for instrument `{}` not found for block `{1}`", .0.name())]
+    OptionNotFound(Instrument, BlockId),
+    #[error("merkle proof of option `{0}` does not lead to the merkle root `{1}`")]
+    MerkleProofVerification(String, String),
 }
 
 pub struct PragmaConsumer {
     pub(crate) network: Network,
     pub(crate) http_client: reqwest::Client,
     pub(crate) base_url: PragmaBaseUrl,
+    pub(crate) retry_config: RetryConfig,
+    pub(crate) verify_proofs: bool,
+    pub(crate) max_concurrent_fetches: usize,
 }
 
 impl PragmaConsumer {
@@ -38,12 +55,21 @@ impl PragmaConsumer {
         block_id: Option<BlockId>,
     ) -> Result<MerkleFeedCalldata, ConsumerError> {
         let block_id = block_id.unwrap_or(BlockId::Tag(BlockTag::Pending));
-        let option_data = self.request_option(instrument.name(), block_id).await?;
+        // A tag could move to another block between our requests: the proof
+        // and the root are requested for the block the option was read at.
+        let (option_data, block_id) = self.request_option(instrument, block_id).await?;
         let option_hash = option_data
             .pedersen_hash_as_hex_string()
             .map_err(|_| ConsumerError::OptionHash(option_data.clone()))?;
 
-        let merkle_proof = self.request_merkle_proof(option_hash, block_id).await?;
+        let merkle_proof = self
+            .request_merkle_proof(option_hash.clone(), block_id)
+            .await?;
+
+        if self.verify_proofs {
+            let merkle_root = self.request_merkle_root(block_id).await?;
+            verify_merkle_proof(&option_hash, &merkle_proof, &merkle_root)?;
+        }
 
         Ok(MerkleFeedCalldata {
             merkle_proof,
@@ -51,29 +77,101 @@ impl PragmaConsumer {
         })
     }
 
+    /// Same as [`PragmaConsumer::get_merkle_feed_calldata`] for multiple
+    /// instruments, fetched concurrently. The results are in the order of the
+    /// instruments.
+    pub async fn get_merkle_feed_calldata_batch(
+        &self,
+        instruments: &[Instrument],
+        block_id: Option<BlockId>,
+    ) -> Vec<Result<MerkleFeedCalldata, ConsumerError>> {
+        let mut results: Vec<(usize, Result<MerkleFeedCalldata, ConsumerError>)> =
+            stream::iter(instruments.iter().enumerate())
+                .map(|(index, instrument)| async move {
+                    (
+                        index,
+                        self.get_merkle_feed_calldata(instrument, block_id).await,
+                    )
+                })
+                .buffer_unordered(self.max_concurrent_fetches)
+                .collect()
+                .await;
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Query the PragmAPI and returns the necessary calldata to use
+    /// with our Oracle contract, as of the block published at the provided
+    /// unix timestamp (in seconds).
+    pub async fn get_merkle_feed_calldata_at_timestamp(
+        &self,
+        instrument: &Instrument,
+        timestamp: u64,
+    ) -> Result<MerkleFeedCalldata, ConsumerError> {
+        let block_number = self.request_block_at_timestamp(timestamp).await?;
+        self.get_merkle_feed_calldata(instrument, Some(BlockId::Number(block_number)))
+            .await
+    }
+
+    /// Requests from our PragmAPI the number of the block published at a
+    /// certain timestamp.
+    async fn request_block_at_timestamp(&self, timestamp: u64) -> Result<u64, ConsumerError> {
+        let url = format!(
+            "{}/{}/{}?network={}&timestamp={}",
+            self.base_url.url(),
+            PRAGMAPI_PATH_PREFIX,
+            PRAGMAPI_BLOCK_AT_TIMESTAMP_ENDPOINT,
+            self.network,
+            timestamp,
+        );
+
+        let api_response = self.request_api(url).await?;
+        match api_response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => return Err(ConsumerError::UnresolvableTimestamp(timestamp)),
+            status => return Err(ConsumerError::HttpRequest(status)),
+        }
+
+        let contents = api_response.text().await.map_err(ConsumerError::Reqwest)?;
+        let block: serde_json::Value = serde_json::from_str(&contents)?;
+        block["block_number"]
+            .as_u64()
+            .ok_or(ConsumerError::UnresolvableTimestamp(timestamp))
+    }
+
     /// Requests from our PragmAPI the option data for a given instrument name at a
-    /// certain block.
+    /// certain block, along with the number of the block it was read at.
+    /// The requested block is returned if the API doesn't tell the number.
     async fn request_option(
         &self,
-        instrument_name: String,
+        instrument: &Instrument,
         block_id: BlockId,
-    ) -> Result<OptionData, ConsumerError> {
+    ) -> Result<(OptionData, BlockId), ConsumerError> {
         let url = format!(
             "{}/{}/options/{}?network={}&block_id={}",
             self.base_url.url(),
             PRAGMAPI_PATH_PREFIX,
-            instrument_name,
+            instrument.name(),
             self.network,
             block_id,
         );
 
         let api_response = self.request_api(url).await?;
-        if api_response.status() != StatusCode::OK {
-            return Err(ConsumerError::HttpRequest(api_response.status()));
+        match api_response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND => {
+                return Err(ConsumerError::OptionNotFound(instrument.clone(), block_id))
+            }
+            status => return Err(ConsumerError::HttpRequest(status)),
         }
 
         let contents = api_response.text().await.map_err(ConsumerError::Reqwest)?;
-        serde_json::from_str(&contents).map_err(ConsumerError::Serde)
+        let response: serde_json::Value = serde_json::from_str(&contents)?;
+        let block_id = response["block_number"]
+            .as_u64()
+            .map_or(block_id, BlockId::Number);
+        let option_data = serde_json::from_value(response)?;
+        Ok((option_data, block_id))
     }
 
     /// Requests from our PragmAPI the merkle proof for an hash at a certain block.
@@ -100,12 +198,71 @@ impl PragmaConsumer {
         serde_json::from_str(&contents).map_err(ConsumerError::Serde)
     }
 
+    /// Requests from our PragmAPI the root of the merkle tree at a certain block.
+    async fn request_merkle_root(&self, block_id: BlockId) -> Result<String, ConsumerError> {
+        let url = format!(
+            "{}/{}/{}?network={}&block_id={}",
+            self.base_url.url(),
+            PRAGMAPI_PATH_PREFIX,
+            PRAGMAPI_MERKLE_ROOT_ENDPOINT,
+            self.network,
+            block_id,
+        );
+
+        let api_response = self.request_api(url).await?;
+        if api_response.status() != StatusCode::OK {
+            return Err(ConsumerError::HttpRequest(api_response.status()));
+        }
+
+        let contents = api_response.text().await.map_err(ConsumerError::Reqwest)?;
+        let merkle_root: serde_json::Value = serde_json::from_str(&contents)?;
+        merkle_root["root_hash"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| ConsumerError::Decode(contents.clone()))
+    }
+
     /// Utility function to make an HTTP Get request to a provided URL.
+    /// The request is retried with an exponential backoff while it fails with
+    /// a 5xx status, times out or can't connect.
     async fn request_api(&self, url: String) -> Result<Response, ConsumerError> {
-        self.http_client
-            .get(url)
-            .send()
-            .await
-            .map_err(ConsumerError::Reqwest)
+        let mut attempt = 1;
+        loop {
+            let result = self.http_client.get(&url).send().await;
+            let is_transient = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+            if !is_transient || attempt >= self.retry_config.max_attempts {
+                return result.map_err(ConsumerError::Reqwest);
+            }
+            tokio::time::sleep(self.retry_config.delay_after(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Checks that walking the merkle proof from the option hash, with the sorted
+/// pedersen hash, leads to the merkle root.
+pub fn verify_merkle_proof(
+    option_hash: &str,
+    merkle_proof: &MerkleProof,
+    merkle_root: &str,
+) -> Result<(), ConsumerError> {
+    let leaf =
+        Felt::from_hex(option_hash).map_err(|_| ConsumerError::Decode(option_hash.to_string()))?;
+    let expected_root =
+        Felt::from_hex(merkle_root).map_err(|_| ConsumerError::Decode(merkle_root.to_string()))?;
+    let felt_proof: FeltMerkleProof = merkle_proof
+        .clone()
+        .try_into()
+        .map_err(|e: MerkleTreeError| ConsumerError::Decode(e.to_string()))?;
+
+    if felt_proof.compute_root(&leaf) != expected_root {
+        return Err(ConsumerError::MerkleProofVerification(
+            option_hash.to_string(),
+            merkle_root.to_string(),
+        ));
     }
+    Ok(())
 }
@@ -0,0 +1,58 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use pragma_consumer::types::Instrument;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use super::mocks::{merkle_proof_data, option_data};
+
+/// PragmAPI answering the option requests with the failing statuses in turn
+/// before answering with the option. The merkle proof is always returned.
+pub struct FlakyPragmApi {
+    pub address: SocketAddr,
+    /// Number of option requests received.
+    pub option_requests: Arc<AtomicUsize>,
+}
+
+impl FlakyPragmApi {
+    pub async fn start(instrument: &Instrument, failing_statuses: Vec<u16>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let option_requests = Arc::new(AtomicUsize::new(0));
+
+        let option = option_data(instrument).to_string();
+        let received = option_requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = vec![0; 8192];
+                let Ok(read) = stream.read(&mut buffer).await else {
+                    continue;
+                };
+                let request = String::from_utf8_lossy(&buffer[..read]);
+                let (status, body) = if request.contains("/options/") {
+                    let attempt = received.fetch_add(1, Ordering::SeqCst);
+                    match failing_statuses.get(attempt) {
+                        Some(status) => (*status, String::new()),
+                        None => (200, option.clone()),
+                    }
+                } else if request.contains("/proof/") {
+                    (200, merkle_proof_data().to_string())
+                } else {
+                    (404, String::new())
+                };
+                let response = format!(
+                    "HTTP/1.1 {status} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        Self {
+            address,
+            option_requests,
+        }
+    }
+}
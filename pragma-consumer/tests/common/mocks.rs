@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use httpmock::{prelude::*, Mock};
 use pragma_common::types::Network;
-use pragma_consumer::types::{BlockId, Instrument};
+use pragma_consumer::types::{BlockId, BlockTag, Instrument};
 use serde_json::json;
 
 pub fn mock_healthcheck(pragmapi: &MockServer) -> Mock {
@@ -10,6 +12,45 @@ pub fn mock_healthcheck(pragmapi: &MockServer) -> Mock {
     })
 }
 
+/// Healthcheck only answering the requests authenticated with the API key.
+pub fn mock_authenticated_healthcheck(pragmapi: &MockServer, api_key: &str) -> Mock {
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path("/node")
+            .header("x-api-key", api_key)
+            .header_missing("authorization");
+        then.status(200).body("Server is running!");
+    })
+}
+
+pub fn mock_unhealthy_api(pragmapi: &MockServer) -> Mock {
+    pragmapi.mock(|when, then| {
+        when.method(GET).path("/node");
+        then.status(200).body("Server is starting...");
+    })
+}
+
+pub fn mock_block_at_timestamp_response(
+    pragmapi: &MockServer,
+    network: Network,
+    timestamp: u64,
+    block_number: Option<u64>,
+) -> Mock {
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains("node/v1/merkle_feeds/block")
+            .query_param("network", network.to_string())
+            .query_param("timestamp", timestamp.to_string());
+        match block_number {
+            Some(block_number) => then
+                .status(200)
+                .header("content-type", "text/json")
+                .json_body(json!({ "block_number": block_number })),
+            None => then.status(404),
+        };
+    })
+}
+
 pub fn mock_option_response(
     pragmapi: &MockServer,
     instrument: Instrument,
@@ -17,6 +58,25 @@ pub fn mock_option_response(
     block_id: BlockId,
 ) -> Mock {
     let url = format!("node/v1/merkle_feeds/options/{}", instrument.name(),);
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains(url)
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(200)
+            .header("content-type", "text/json")
+            .json_body(option_response_data(&instrument, block_id));
+    })
+}
+
+/// Option response without the number of the block it was read at.
+pub fn mock_option_response_without_block_number(
+    pragmapi: &MockServer,
+    instrument: Instrument,
+    network: Network,
+    block_id: BlockId,
+) -> Mock {
+    let url = format!("node/v1/merkle_feeds/options/{}", instrument.name());
     pragmapi.mock(|when, then| {
         when.method(GET)
             .path_contains(url)
@@ -28,6 +88,68 @@ pub fn mock_option_response(
     })
 }
 
+pub fn mock_option_not_found_response(
+    pragmapi: &MockServer,
+    instrument: Instrument,
+    network: Network,
+    block_id: BlockId,
+) -> Mock {
+    let url = format!("node/v1/merkle_feeds/options/{}", instrument.name());
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains(url)
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(404)
+            .header("content-type", "text/json")
+            .json_body(json!({
+                "error": format!(
+                    "MerkleFeed option for instrument {} has not been found for block {}",
+                    instrument.name(),
+                    block_id
+                )
+            }));
+    })
+}
+
+/// Option response always failing with a 503 status.
+pub fn mock_failing_option_response(
+    pragmapi: &MockServer,
+    instrument: Instrument,
+    network: Network,
+    block_id: BlockId,
+) -> Mock {
+    let url = format!("node/v1/merkle_feeds/options/{}", instrument.name());
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains(url)
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(503);
+    })
+}
+
+/// Option response only answered after the provided delay.
+pub fn mock_slow_option_response(
+    pragmapi: &MockServer,
+    instrument: Instrument,
+    network: Network,
+    block_id: BlockId,
+    delay: Duration,
+) -> Mock {
+    let url = format!("node/v1/merkle_feeds/options/{}", instrument.name());
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains(url)
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(200)
+            .delay(delay)
+            .header("content-type", "text/json")
+            .json_body(option_response_data(&instrument, block_id));
+    })
+}
+
 pub fn mock_merkle_proof_response(
     pragmapi: &MockServer,
     option_hash: String,
@@ -46,6 +168,82 @@ pub fn mock_merkle_proof_response(
     })
 }
 
+/// Merkle proof response for any option hash.
+pub fn mock_any_merkle_proof_response(
+    pragmapi: &MockServer,
+    network: Network,
+    block_id: BlockId,
+) -> Mock {
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains("node/v1/merkle_feeds/proof/")
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(200)
+            .header("content-type", "text/json")
+            .json_body(merkle_proof_data());
+    })
+}
+
+/// Merkle proof response whose first sibling was replaced.
+pub fn mock_tampered_merkle_proof_response(
+    pragmapi: &MockServer,
+    option_hash: String,
+    network: Network,
+    block_id: BlockId,
+) -> Mock {
+    let url = format!("node/v1/merkle_feeds/proof/{}", &option_hash);
+    let mut merkle_proof = merkle_proof_data();
+    merkle_proof[0] = json!("0x1");
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains(url)
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(200)
+            .header("content-type", "text/json")
+            .json_body(merkle_proof);
+    })
+}
+
+pub fn mock_merkle_root_response(
+    pragmapi: &MockServer,
+    network: Network,
+    block_id: BlockId,
+) -> Mock {
+    pragmapi.mock(|when, then| {
+        when.method(GET)
+            .path_contains("node/v1/merkle_feeds/root")
+            .query_param("network", network.to_string())
+            .query_param("block_id", block_id.to_string());
+        then.status(200)
+            .header("content-type", "text/json")
+            .json_body(json!({ "root_hash": merkle_root_data() }));
+    })
+}
+
+/// Block the mocked PragmAPI resolves the latest tag to, the pending one
+/// being the next.
+pub const LATEST_BLOCK_NUMBER: u64 = 85626;
+
+/// Concrete block a block id is resolved to by the mocked PragmAPI.
+pub fn resolved_block(block_id: BlockId) -> BlockId {
+    match block_id {
+        BlockId::Number(number) => BlockId::Number(number),
+        BlockId::Tag(BlockTag::Latest) => BlockId::Number(LATEST_BLOCK_NUMBER),
+        BlockId::Tag(BlockTag::Pending) => BlockId::Number(LATEST_BLOCK_NUMBER + 1),
+    }
+}
+
+/// Option response, with the number of the block it was read at.
+pub fn option_response_data(instrument: &Instrument, block_id: BlockId) -> serde_json::Value {
+    let mut response = option_data(instrument);
+    if let BlockId::Number(block_number) = resolved_block(block_id) {
+        response["block_number"] = json!(block_number);
+    }
+    response
+}
+
 pub fn option_data(instrument: &Instrument) -> serde_json::Value {
     json!({
         "instrument_name": instrument.name(),
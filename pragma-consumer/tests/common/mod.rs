@@ -1 +1,2 @@
+pub mod flaky_pragmapi;
 pub mod mocks;
@@ -1,20 +1,27 @@
 mod common;
 
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
 use httpmock::MockServer;
 use rstest::*;
 use starknet::core::types::Felt;
 
 use pragma_common::{hash::pedersen_hash, instrument, types::Network};
 use pragma_consumer::{
-    builder::PragmaConsumerBuilder,
-    config::{ApiConfig, PragmaBaseUrl},
-    consumer::PragmaConsumer,
+    builder::{BuilderError, PragmaConsumerBuilder},
+    config::{ApiConfig, PragmaBaseUrl, RetryConfig},
+    consumer::{ConsumerError, PragmaConsumer},
     types::{BlockId, BlockTag, Instrument},
 };
 
+use common::flaky_pragmapi::FlakyPragmApi;
 use common::mocks::{
-    merkle_root_data, mock_healthcheck, mock_merkle_proof_response, mock_option_response,
-    option_data,
+    merkle_root_data, mock_any_merkle_proof_response, mock_authenticated_healthcheck,
+    mock_block_at_timestamp_response, mock_failing_option_response, mock_healthcheck,
+    mock_merkle_proof_response, mock_merkle_root_response, mock_option_not_found_response,
+    mock_option_response, mock_option_response_without_block_number, mock_slow_option_response,
+    mock_tampered_merkle_proof_response, mock_unhealthy_api, option_data, resolved_block,
 };
 
 #[rstest]
@@ -52,7 +59,7 @@ async fn test_consumer() {
             .unwrap()
             .to_owned(),
         network,
-        block_test,
+        resolved_block(block_test),
     );
 
     // 3. Fetch the calldata & assert that the mocks got correctly called
@@ -79,3 +86,520 @@ async fn test_consumer() {
 
     assert_eq!(out_merkle_root, expected_merkle_root);
 }
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_without_block_number_uses_the_requested_block() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let block_test = BlockId::Tag(BlockTag::Latest);
+    let network = Network::Sepolia;
+
+    let option_mock = mock_option_response_without_block_number(
+        &pragmapi,
+        test_instrument.clone(),
+        network,
+        block_test,
+    );
+    let merkle_proof_mock = mock_merkle_proof_response(
+        &pragmapi,
+        option_data(&test_instrument)["hash"]
+            .as_str()
+            .unwrap()
+            .to_owned(),
+        network,
+        block_test,
+    );
+
+    consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await
+        .expect("Could not fetch the calldata");
+
+    option_mock.assert();
+    merkle_proof_mock.assert();
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_at_timestamp() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+
+    // 1. Build the consumer
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    // 2. Define some fake tests instruments
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let timestamp = 1722805873;
+    let block_test = BlockId::Number(85626);
+    let network = Network::Sepolia;
+
+    // 2.5 Mock responses
+    let block_mock = mock_block_at_timestamp_response(&pragmapi, network, timestamp, Some(85626));
+    let option_mock = mock_option_response(&pragmapi, test_instrument.clone(), network, block_test);
+    let merkle_proof_mock = mock_merkle_proof_response(
+        &pragmapi,
+        option_data(&test_instrument)["hash"]
+            .as_str()
+            .unwrap()
+            .to_owned(),
+        network,
+        resolved_block(block_test),
+    );
+
+    // 3. Fetch the calldata & assert that the timestamp got resolved to the block
+    let calldata = consumer
+        .get_merkle_feed_calldata_at_timestamp(&test_instrument, timestamp)
+        .await
+        .expect("Could not fetch the calldata");
+
+    block_mock.assert();
+    option_mock.assert();
+    merkle_proof_mock.assert();
+
+    // 4. Verify the proof returned
+    let expected_merkle_root = Felt::from_hex(&merkle_root_data()).unwrap();
+
+    let mut out_merkle_root = calldata
+        .option_data
+        .pedersen_hash()
+        .expect("Could not generate the hash of option");
+
+    for sibling in calldata.merkle_proof.0 {
+        let felt_sibling = Felt::from_hex(&sibling).unwrap();
+        out_merkle_root = pedersen_hash(&out_merkle_root, &felt_sibling);
+    }
+
+    assert_eq!(out_merkle_root, expected_merkle_root);
+
+    // 5. A timestamp without block can't be resolved
+    let unknown_timestamp = 1000;
+    let unknown_block_mock =
+        mock_block_at_timestamp_response(&pragmapi, network, unknown_timestamp, None);
+    let result = consumer
+        .get_merkle_feed_calldata_at_timestamp(&test_instrument, unknown_timestamp)
+        .await;
+    unknown_block_mock.assert();
+    assert!(matches!(
+        result,
+        Err(ConsumerError::UnresolvableTimestamp(1000))
+    ));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_health_check_is_opt_in() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+
+    let unhealthy_mock = mock_unhealthy_api(&pragmapi);
+
+    // 1. Without the flag, the health endpoint is never called
+    PragmaConsumerBuilder::new()
+        .with_http(api_config.clone())
+        .await
+        .expect("Could not build PragmaConsumer");
+    PragmaConsumerBuilder::new()
+        .with_api(api_config.clone())
+        .await
+        .expect("Could not build PragmaConsumer");
+    unhealthy_mock.assert_hits(0);
+
+    // 2. With the flag, an unexpected health check response is an error
+    let result = PragmaConsumerBuilder::new()
+        .check_api_health()
+        .with_api(api_config)
+        .await;
+    unhealthy_mock.assert_hits(1);
+    assert!(matches!(result, Err(BuilderError::HealthCheck(_))));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_sends_the_api_key_header() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+
+    // 1. The key is sent as is in the `x-api-key` header
+    let healthcheck_mock = mock_authenticated_healthcheck(&pragmapi, "this_is_a_test");
+    PragmaConsumerBuilder::new()
+        .check_api_health()
+        .with_http(api_config.clone())
+        .await
+        .expect("Could not build PragmaConsumer");
+    healthcheck_mock.assert();
+
+    // 2. A key that can't be sent as a header is refused
+    let result = PragmaConsumerBuilder::new()
+        .with_http(ApiConfig {
+            api_key: "this_is\na_test".into(),
+            ..api_config
+        })
+        .await;
+    assert!(matches!(result, Err(BuilderError::Header(_))));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_retries_server_errors() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_retries(RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        })
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let block_test = BlockId::Tag(BlockTag::Latest);
+    let network = Network::Sepolia;
+
+    // 1. Server errors are attempted up to `max_attempts` times
+    let mut failing_mock =
+        mock_failing_option_response(&pragmapi, test_instrument.clone(), network, block_test);
+    let result = consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await;
+    assert!(matches!(
+        result,
+        Err(ConsumerError::HttpRequest(status)) if status.as_u16() == 503
+    ));
+    failing_mock.assert_hits(3);
+
+    // 2. The calldata is fetched once the server recovers
+    failing_mock.delete();
+    let option_mock = mock_option_response(&pragmapi, test_instrument.clone(), network, block_test);
+    let merkle_proof_mock = mock_merkle_proof_response(
+        &pragmapi,
+        option_data(&test_instrument)["hash"]
+            .as_str()
+            .unwrap()
+            .to_owned(),
+        network,
+        resolved_block(block_test),
+    );
+
+    consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await
+        .expect("Could not fetch the calldata");
+
+    option_mock.assert();
+    merkle_proof_mock.assert();
+
+    // 3. Client errors are not retried
+    let timestamp = 1722805873;
+    let block_mock = mock_block_at_timestamp_response(&pragmapi, network, timestamp, None);
+    let result = consumer
+        .get_merkle_feed_calldata_at_timestamp(&test_instrument, timestamp)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ConsumerError::UnresolvableTimestamp(t)) if t == timestamp
+    ));
+    block_mock.assert_hits(1);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_retries_until_the_server_recovers() {
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let pragmapi = FlakyPragmApi::start(&test_instrument, vec![503, 503]).await;
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address)),
+        api_key: "this_is_a_test".into(),
+    };
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_retries(RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        })
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    // The option request fails twice with a 503 before succeeding, within
+    // the same call.
+    consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(BlockId::Tag(BlockTag::Latest)))
+        .await
+        .expect("Could not fetch the calldata");
+    assert_eq!(pragmapi.option_requests.load(Ordering::SeqCst), 3);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_retries_timeouts() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_request_timeout(Duration::from_millis(50))
+        .with_retries(RetryConfig {
+            max_attempts: 2,
+            base_delay: Duration::from_millis(10),
+        })
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let block_test = BlockId::Tag(BlockTag::Latest);
+    let slow_mock = mock_slow_option_response(
+        &pragmapi,
+        test_instrument.clone(),
+        Network::Sepolia,
+        block_test,
+        Duration::from_millis(500),
+    );
+
+    let result = consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await;
+    assert!(matches!(result, Err(ConsumerError::Reqwest(e)) if e.is_timeout()));
+    slow_mock.assert_hits(2);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_retries_connection_failures() {
+    // Nothing listens on the port once the listener is dropped.
+    let address = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Could not reserve a port");
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{address}")),
+        api_key: "this_is_a_test".into(),
+    };
+    let base_delay = Duration::from_millis(100);
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_retries(RetryConfig {
+            max_attempts: 3,
+            base_delay,
+        })
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let started_at = std::time::Instant::now();
+    let result = consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(BlockId::Tag(BlockTag::Latest)))
+        .await;
+
+    assert!(matches!(result, Err(ConsumerError::Reqwest(e)) if e.is_connect()));
+    // Two backoffs of 100ms and 200ms were waited between the three attempts.
+    assert!(started_at.elapsed() >= base_delay * 3);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_option_not_found() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let block_test = BlockId::Number(85626);
+    let option_mock = mock_option_not_found_response(
+        &pragmapi,
+        test_instrument.clone(),
+        Network::Sepolia,
+        block_test,
+    );
+
+    let result = consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await;
+
+    option_mock.assert();
+    match result {
+        Err(ConsumerError::OptionNotFound(instrument, block_id)) => {
+            assert_eq!(instrument.name(), test_instrument.name());
+            assert_eq!(block_id, block_test);
+        }
+        other => panic!(
+            "expected an OptionNotFound error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_verifies_the_merkle_proof() {
+    let test_instrument: Instrument = instrument!("BTC-16AUG24-52000-P");
+    let block_test = BlockId::Tag(BlockTag::Latest);
+    let network = Network::Sepolia;
+    let option_hash = option_data(&test_instrument)["hash"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    // 1. A valid proof leads to the merkle root
+    let pragmapi = MockServer::start();
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .verify_proofs()
+        .with_http(ApiConfig {
+            base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+            api_key: "this_is_a_test".into(),
+        })
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    mock_option_response(&pragmapi, test_instrument.clone(), network, block_test);
+    mock_merkle_proof_response(
+        &pragmapi,
+        option_hash.clone(),
+        network,
+        resolved_block(block_test),
+    );
+    let merkle_root_mock =
+        mock_merkle_root_response(&pragmapi, network, resolved_block(block_test));
+
+    consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await
+        .expect("Could not verify a valid merkle proof");
+    merkle_root_mock.assert();
+
+    // 2. A tampered proof is refused
+    let pragmapi = MockServer::start();
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .verify_proofs()
+        .with_http(ApiConfig {
+            base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+            api_key: "this_is_a_test".into(),
+        })
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    mock_option_response(&pragmapi, test_instrument.clone(), network, block_test);
+    mock_tampered_merkle_proof_response(
+        &pragmapi,
+        option_hash,
+        network,
+        resolved_block(block_test),
+    );
+    mock_merkle_root_response(&pragmapi, network, resolved_block(block_test));
+
+    let result = consumer
+        .get_merkle_feed_calldata(&test_instrument, Some(block_test))
+        .await;
+    assert!(matches!(
+        result,
+        Err(ConsumerError::MerkleProofVerification(_, root)) if root == merkle_root_data()
+    ));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_consumer_batch() {
+    let pragmapi = MockServer::start();
+
+    let api_config = ApiConfig {
+        base_url: PragmaBaseUrl::Custom(format!("http://{}", pragmapi.address())),
+        api_key: "this_is_a_test".into(),
+    };
+    let consumer: PragmaConsumer = PragmaConsumerBuilder::new()
+        .on_sepolia()
+        .with_max_concurrent_fetches(2)
+        .with_http(api_config)
+        .await
+        .expect("Could not build PragmaConsumer");
+
+    // 1. Three instruments, the second one not being published
+    let instruments: Vec<Instrument> = vec![
+        instrument!("BTC-16AUG24-52000-P"),
+        instrument!("BTC-16AUG24-56000-C"),
+        instrument!("ETH-16AUG24-2600-P"),
+    ];
+    let block_test = BlockId::Number(85626);
+    let network = Network::Sepolia;
+
+    let first_option_mock =
+        mock_option_response(&pragmapi, instruments[0].clone(), network, block_test);
+    let missing_option_mock =
+        mock_option_not_found_response(&pragmapi, instruments[1].clone(), network, block_test);
+    let third_option_mock =
+        mock_option_response(&pragmapi, instruments[2].clone(), network, block_test);
+    let merkle_proof_mock = mock_any_merkle_proof_response(&pragmapi, network, block_test);
+
+    // 2. The results follow the order of the instruments
+    let results = consumer
+        .get_merkle_feed_calldata_batch(&instruments, Some(block_test))
+        .await;
+
+    first_option_mock.assert();
+    missing_option_mock.assert();
+    third_option_mock.assert();
+    merkle_proof_mock.assert_hits(2);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        results[0].as_ref().unwrap().option_data.instrument_name,
+        instruments[0].name()
+    );
+    assert!(matches!(
+        &results[1],
+        Err(ConsumerError::OptionNotFound(instrument, _)) if instrument.name() == instruments[1].name()
+    ));
+    assert_eq!(
+        results[2].as_ref().unwrap().option_data.instrument_name,
+        instruments[2].name()
+    );
+}
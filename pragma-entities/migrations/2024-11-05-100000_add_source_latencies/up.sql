@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE source_latencies (
+    source VARCHAR PRIMARY KEY,
+    latency_ms DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
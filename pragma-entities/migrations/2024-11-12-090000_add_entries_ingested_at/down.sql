@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS entries_pair_id_ingested_at_idx;
+ALTER TABLE entries DROP COLUMN IF EXISTS ingested_at;
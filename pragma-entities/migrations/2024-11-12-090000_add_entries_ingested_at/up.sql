@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Entries stored before this migration have no ingestion time: they are
+-- considered ingested at their reported timestamp.
+ALTER TABLE entries ADD COLUMN ingested_at TIMESTAMPTZ;
+ALTER TABLE entries ALTER COLUMN ingested_at SET DEFAULT NOW();
+CREATE INDEX entries_pair_id_ingested_at_idx ON entries (pair_id, ingested_at);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE entries DROP COLUMN IF EXISTS ask;
+ALTER TABLE entries DROP COLUMN IF EXISTS bid;
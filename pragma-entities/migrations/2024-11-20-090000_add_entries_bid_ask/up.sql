@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Best bid and ask reported alongside the price, if the publisher provides them.
+ALTER TABLE entries ADD COLUMN bid NUMERIC;
+ALTER TABLE entries ADD COLUMN ask NUMERIC;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP MATERIALIZED VIEW IF EXISTS new_4_h_candle;
+DROP MATERIALIZED VIEW IF EXISTS twap_4_hours_agg;
+DROP MATERIALIZED VIEW IF EXISTS twap_4_hours_agg_future;
+DROP MATERIALIZED VIEW IF EXISTS price_4_h_agg;
+DROP MATERIALIZED VIEW IF EXISTS price_4_h_agg_future;
@@ -0,0 +1,90 @@
+-- Your SQL goes here
+
+-- aggregate
+CREATE MATERIALIZED VIEW price_4_h_agg
+WITH (timescaledb.continuous, timescaledb.materialized_only = false)
+AS SELECT 
+    pair_id,
+    time_bucket('4 hours'::interval, timestamp) as bucket,
+    approx_percentile(0.5, percentile_agg(price))::numeric AS median_price,
+    COUNT(DISTINCT source) as num_sources
+FROM entries
+GROUP BY bucket, pair_id
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('price_4_h_agg',
+  start_offset => NULL,
+  end_offset => INTERVAL '4 hours',
+  schedule_interval => INTERVAL '4 hours');
+
+-- aggregate future
+CREATE MATERIALIZED VIEW price_4_h_agg_future
+WITH (timescaledb.continuous, timescaledb.materialized_only = false)
+AS SELECT 
+    pair_id,
+    time_bucket('4 hours'::interval, timestamp) as bucket,
+    expiration_timestamp,
+    approx_percentile(0.5, percentile_agg(price))::numeric AS median_price,
+    COUNT(DISTINCT source) as num_sources
+FROM future_entries
+GROUP BY bucket, pair_id, expiration_timestamp
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('price_4_h_agg_future',
+  start_offset => NULL,
+  end_offset => INTERVAL '4 hours',
+  schedule_interval => INTERVAL '4 hours');
+
+-- twap
+CREATE MATERIALIZED VIEW twap_4_hours_agg
+WITH (timescaledb.continuous, timescaledb.materialized_only = false)
+AS SELECT 
+    pair_id,
+    time_bucket('4 hours'::interval, timestamp) as bucket,
+    average(time_weight('Linear', timestamp, price))::numeric as price_twap,
+    COUNT(DISTINCT source) as num_sources
+FROM entries
+GROUP BY bucket, pair_id
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('twap_4_hours_agg',
+  start_offset => NULL,
+  end_offset => INTERVAL '4 hours',
+  schedule_interval => INTERVAL '4 hours');
+
+-- twap future
+CREATE MATERIALIZED VIEW twap_4_hours_agg_future
+WITH (timescaledb.continuous, timescaledb.materialized_only = false)
+AS SELECT 
+    pair_id,
+    time_bucket('4 hours'::interval, timestamp) as bucket,
+    expiration_timestamp,
+    average(time_weight('Linear', timestamp, price))::numeric as price_twap,
+    COUNT(DISTINCT source) as num_sources
+FROM future_entries
+GROUP BY bucket, pair_id, expiration_timestamp
+WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('twap_4_hours_agg_future',
+  start_offset => NULL,
+  end_offset => INTERVAL '4 hours',
+  schedule_interval => INTERVAL '4 hours');
+
+-- ohlc
+CREATE MATERIALIZED VIEW new_4_h_candle
+WITH (timescaledb.continuous) AS
+    SELECT
+        time_bucket('4 hours', bucket) AS ohlc_bucket,
+        pair_id,
+        FIRST(median_price, bucket) AS "open",
+        MAX(median_price) AS high,
+        MIN(median_price) AS low,
+        LAST(median_price, bucket) AS "close"
+    FROM price_10_s_agg
+    GROUP BY ohlc_bucket, pair_id
+    WITH NO DATA;
+
+SELECT add_continuous_aggregate_policy('new_4_h_candle',
+    start_offset => INTERVAL '12 hours',
+    end_offset => INTERVAL '4 hours',
+    schedule_interval => INTERVAL '4 hours');
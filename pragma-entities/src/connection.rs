@@ -1,9 +1,15 @@
+use std::time::Duration;
+
 use crate::error::ErrorKind;
 use deadpool_diesel::postgres::{Manager, Pool};
 
 pub const ENV_ONCHAIN_DATABASE_URL: &str = "ONCHAIN_DATABASE_URL";
 pub const ENV_OFFCHAIN_DATABASE_URL: &str = "OFFCHAIN_DATABASE_URL";
 const ENV_DATABASE_MAX_CONN: &str = "DATABASE_MAX_CONN";
+const ENV_DATABASE_POOL_WAIT_TIMEOUT_MS: &str = "DATABASE_POOL_WAIT_TIMEOUT_MS";
+/// Maximum time waiting for a connection when they are all in use, after
+/// which the database is considered unavailable.
+const DEFAULT_DATABASE_POOL_WAIT_TIMEOUT_MS: u64 = 5_000;
 
 pub fn init_pool(app_name: &str, database_url_env: &str) -> Result<Pool, ErrorKind> {
     if database_url_env != ENV_OFFCHAIN_DATABASE_URL && database_url_env != ENV_ONCHAIN_DATABASE_URL
@@ -24,6 +30,16 @@ pub fn init_pool(app_name: &str, database_url_env: &str) -> Result<Pool, ErrorKi
             ErrorKind::GenericInitDatabase(format!("cannot parse {}", ENV_DATABASE_MAX_CONN))
         })? as usize;
 
+    let pool_wait_timeout = match std::env::var(ENV_DATABASE_POOL_WAIT_TIMEOUT_MS) {
+        Ok(timeout) => timeout.parse::<u64>().map_err(|_| {
+            ErrorKind::GenericInitDatabase(format!(
+                "cannot parse {}",
+                ENV_DATABASE_POOL_WAIT_TIMEOUT_MS
+            ))
+        })?,
+        Err(_) => DEFAULT_DATABASE_POOL_WAIT_TIMEOUT_MS,
+    };
+
     let manager = Manager::new(
         format!("{}?application_name={}", database_url, app_name),
         deadpool_diesel::Runtime::Tokio1,
@@ -31,6 +47,8 @@ pub fn init_pool(app_name: &str, database_url_env: &str) -> Result<Pool, ErrorKi
 
     Pool::builder(manager)
         .max_size(database_max_conn)
+        .wait_timeout(Some(Duration::from_millis(pool_wait_timeout)))
+        .runtime(deadpool_diesel::Runtime::Tokio1)
         .build()
         .map_err(|e| ErrorKind::PoolDatabase(e.to_string()))
 }
@@ -19,6 +19,23 @@ pub struct Entry {
 pub struct EntriesFilter {
     pub(crate) pair_id: Option<String>,
     pub(crate) publisher_contains: Option<String>,
+    /// Sorts the entries from the most recent to the oldest.
+    #[serde(default)]
+    pub(crate) newest_first: bool,
+    #[serde(default)]
+    pub(crate) limit: Option<i64>,
+}
+
+impl EntriesFilter {
+    /// Filter returning the `limit` most recent entries of the pair.
+    pub fn most_recent(pair_id: String, limit: i64) -> Self {
+        Self {
+            pair_id: Some(pair_id),
+            publisher_contains: None,
+            newest_first: true,
+            limit: Some(limit),
+        }
+    }
 }
 
 impl From<crate::Entry> for Entry {
@@ -14,6 +14,10 @@ pub use models::{
     entry::{Entry, NewEntry},
     entry_error::{EntryError, VolatilityError},
     future_entry::{FutureEntry, NewFutureEntry},
+    payload::{EntriesPayload, PendingSignature, PendingSpotEntries},
+    price::is_valid_price,
+    priority::PublisherPriority,
     publisher::{NewPublisher, Publishers},
     publisher_error::PublisherError,
+    source_latency::{NewSourceLatency, SourceLatency},
 };
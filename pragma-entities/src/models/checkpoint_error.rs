@@ -13,12 +13,17 @@ pub enum CheckpointError {
     InvalidLimit(u64),
     #[error("no checkpoints found for requested pair")]
     NotFound,
+    #[error("base and quote are identical: {0}")]
+    IdenticalCurrencies(String),
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(i64),
 }
 
 impl From<InfraError> for CheckpointError {
     fn from(error: InfraError) -> Self {
         match error {
             InfraError::InternalServerError => Self::InternalServerError,
+            InfraError::Unavailable => Self::InternalServerError,
             InfraError::NotFound => Self::NotFound,
             InfraError::RoutingError => Self::InternalServerError,
             InfraError::DisputerNotSet => Self::InternalServerError,
@@ -40,6 +45,14 @@ impl IntoResponse for CheckpointError {
                 StatusCode::NOT_FOUND,
                 String::from("No checkpoints found for requested pair"),
             ),
+            Self::IdenticalCurrencies(pair_id) => (
+                StatusCode::BAD_REQUEST,
+                format!("Base and quote are identical for pair {}", pair_id),
+            ),
+            Self::InvalidTimestamp(timestamp) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid timestamp {}", timestamp),
+            ),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 String::from("Internal server error"),
@@ -1,12 +1,16 @@
 use crate::dto::entry as dto;
+use crate::models::entries::priority::PublisherPriority;
 use crate::models::DieselResult;
 use crate::schema::entries;
 use bigdecimal::BigDecimal;
+use diesel::dsl::sql;
 use diesel::internal::derives::multiconnection::chrono::NaiveDateTime;
+use diesel::sql_types::Bool;
 use diesel::upsert::excluded;
 use diesel::{
-    AsChangeset, ExpressionMethods, Insertable, OptionalExtension, PgConnection,
-    PgTextExpressionMethods, QueryDsl, Queryable, RunQueryDsl, Selectable, SelectableHelper,
+    AsChangeset, BoolExpressionMethods, ExpressionMethods, Insertable, OptionalExtension,
+    PgConnection, PgTextExpressionMethods, QueryDsl, Queryable, RunQueryDsl, Selectable,
+    SelectableHelper,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -22,10 +26,20 @@ pub struct Entry {
     pub timestamp: NaiveDateTime,
     pub publisher_signature: Option<String>,
     pub price: BigDecimal,
+    /// Time at which the entry was stored, None for entries stored before
+    /// the ingestion time was tracked.
+    pub ingested_at: Option<NaiveDateTime>,
+    /// Best bid reported alongside the price, if any.
+    pub bid: Option<BigDecimal>,
+    /// Best ask reported alongside the price, if any.
+    pub ask: Option<BigDecimal>,
 }
 
+/// Unknown fields are rejected, so the legacy payloads of future entries,
+/// which have an expiration timestamp, are never decoded as spot entries.
 #[derive(Serialize, Deserialize, Insertable, AsChangeset, Debug)]
 #[diesel(table_name = entries)]
+#[serde(deny_unknown_fields)]
 pub struct NewEntry {
     pub pair_id: String,
     pub publisher: String,
@@ -33,9 +47,22 @@ pub struct NewEntry {
     pub timestamp: NaiveDateTime,
     pub publisher_signature: String,
     pub price: BigDecimal,
+    #[serde(default)]
+    pub bid: Option<BigDecimal>,
+    #[serde(default)]
+    pub ask: Option<BigDecimal>,
 }
 
 impl Entry {
+    /// Returns the mid between the bid and the ask, or the price if the
+    /// publisher didn't report both of them.
+    pub fn mid(&self) -> BigDecimal {
+        match (&self.bid, &self.ask) {
+            (Some(bid), Some(ask)) => (bid + ask) / BigDecimal::from(2),
+            _ => self.price.clone(),
+        }
+    }
+
     pub fn create_one(conn: &mut PgConnection, data: NewEntry) -> DieselResult<Entry> {
         diesel::insert_into(entries::table)
             .values(data)
@@ -43,20 +70,37 @@ impl Entry {
             .get_result(conn)
     }
 
-    pub fn create_many(conn: &mut PgConnection, data: Vec<NewEntry>) -> DieselResult<Vec<Entry>> {
-        diesel::insert_into(entries::table)
+    /// Inserts the entries, overwriting the stored ones sharing the same
+    /// (pair, source, timestamp) unless they were published by a publisher
+    /// that outranks the incoming one.
+    pub fn create_many(
+        conn: &mut PgConnection,
+        data: Vec<NewEntry>,
+        priority: &PublisherPriority,
+    ) -> DieselResult<Vec<Entry>> {
+        let changes = (
+            entries::pair_id.eq(excluded(entries::pair_id)),
+            entries::publisher.eq(excluded(entries::publisher)),
+            entries::source.eq(excluded(entries::source)),
+            entries::publisher_signature.eq(excluded(entries::publisher_signature)),
+            entries::timestamp.eq(excluded(entries::timestamp)),
+            entries::price.eq(excluded(entries::price)),
+            entries::ingested_at.eq(excluded(entries::ingested_at)),
+            entries::bid.eq(excluded(entries::bid)),
+            entries::ask.eq(excluded(entries::ask)),
+        );
+        let query = diesel::insert_into(entries::table)
             .values(data)
             .returning(Entry::as_returning())
             .on_conflict((entries::pair_id, entries::source, entries::timestamp))
             .do_update()
-            .set((
-                entries::pair_id.eq(excluded(entries::pair_id)),
-                entries::publisher.eq(excluded(entries::publisher)),
-                entries::source.eq(excluded(entries::source)),
-                entries::publisher_signature.eq(excluded(entries::publisher_signature)),
-                entries::timestamp.eq(excluded(entries::timestamp)),
-                entries::price.eq(excluded(entries::price)),
-            ))
+            .set(changes);
+
+        if priority.is_empty() {
+            return query.get_results(conn);
+        }
+        query
+            .filter(sql::<Bool>(&priority.sql_incoming_not_outranked("entries")))
             .get_results(conn)
     }
 
@@ -88,6 +132,14 @@ impl Entry {
             query = query.filter(entries::publisher.ilike(format!("%{}%", publisher_contains)));
         }
 
+        if filters.newest_first {
+            query = query.order(entries::timestamp.desc());
+        }
+
+        if let Some(limit) = filters.limit {
+            query = query.limit(limit);
+        }
+
         query.select(Entry::as_select()).load::<Entry>(conn)
     }
 
@@ -113,4 +165,91 @@ impl Entry {
             .first(conn)
             .optional()
     }
+
+    /// Returns the entries of the pair reported between `start` and `end`
+    /// that were already ingested at `as_of`.
+    /// Entries without ingestion time are considered ingested at their
+    /// reported timestamp, so `end` must not be after `as_of`.
+    pub fn get_known_at(
+        conn: &mut PgConnection,
+        pair: String,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        as_of: NaiveDateTime,
+    ) -> DieselResult<Vec<Entry>> {
+        entries::table
+            .filter(entries::pair_id.eq(pair))
+            .filter(entries::timestamp.between(start, end))
+            .filter(
+                entries::ingested_at
+                    .is_null()
+                    .or(entries::ingested_at.le(as_of)),
+            )
+            .select(Entry::as_select())
+            .load::<Entry>(conn)
+    }
+
+    /// Returns the timestamps of the first and last entries of the pair.
+    pub fn get_timestamps_range(
+        conn: &mut PgConnection,
+        pair: String,
+    ) -> DieselResult<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
+        entries::table
+            .filter(entries::pair_id.eq(pair))
+            .select((
+                diesel::dsl::min(entries::timestamp),
+                diesel::dsl::max(entries::timestamp),
+            ))
+            .first(conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(price: i64, bid: Option<i64>, ask: Option<i64>) -> Entry {
+        Entry {
+            id: Uuid::new_v4(),
+            pair_id: "BTC/USD".to_string(),
+            publisher: "PRAGMA".to_string(),
+            source: "BINANCE".to_string(),
+            timestamp: NaiveDateTime::default(),
+            publisher_signature: None,
+            price: BigDecimal::from(price),
+            ingested_at: None,
+            bid: bid.map(BigDecimal::from),
+            ask: ask.map(BigDecimal::from),
+        }
+    }
+
+    #[test]
+    fn test_mid_is_computed_from_bid_and_ask() {
+        assert_eq!(
+            entry(100, Some(99), Some(102)).mid(),
+            "100.5".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mid_falls_back_to_price() {
+        assert_eq!(entry(100, None, None).mid(), BigDecimal::from(100));
+        assert_eq!(entry(100, Some(99), None).mid(), BigDecimal::from(100));
+        assert_eq!(entry(100, None, Some(102)).mid(), BigDecimal::from(100));
+    }
+
+    #[test]
+    fn test_new_entry_without_quotes_is_deserialized() {
+        let payload = r#"{
+            "pair_id": "BTC/USD",
+            "publisher": "PRAGMA",
+            "source": "BINANCE",
+            "timestamp": "2024-06-10T06:13:20",
+            "publisher_signature": "0x0",
+            "price": "100"
+        }"#;
+        let new_entry: NewEntry = serde_json::from_str(payload).unwrap();
+        assert_eq!(new_entry.bid, None);
+        assert_eq!(new_entry.ask, None);
+    }
 }
@@ -11,6 +11,12 @@ use utoipa::ToSchema;
 pub enum VolatilityError {
     #[error("invalid timestamps range: {0} > {1}")]
     InvalidTimestampsRange(u64, u64),
+    #[error("invalid volatility window: {0}")]
+    InvalidWindow(String),
+    #[error("cannot compute the volatility over a non positive price: {0}")]
+    NonPositivePrice(String),
+    #[error("not enough samples to compute the volatility: {0} < 2")]
+    NotEnoughSamples(usize),
 }
 
 #[derive(Debug, thiserror::Error, ToSchema)]
@@ -23,6 +29,8 @@ pub enum SigningError {
 pub enum EntryError {
     #[error("internal server error")]
     InternalServerError,
+    #[error("database unavailable")]
+    DatabaseUnavailable,
     #[error("bad request")]
     BadRequest,
     #[error("entry not found: {0}")]
@@ -34,6 +42,8 @@ pub enum EntryError {
     InvalidSignature(EcdsaVerifyError),
     #[error("could not sign price")]
     InvalidSigner,
+    #[error("signature is disabled for pair: {0}")]
+    PairNotSignable(String),
     #[error("unauthorized request: {0}")]
     Unauthorized(String),
     #[error("invalid timestamp: {0}")]
@@ -46,6 +56,32 @@ pub enum EntryError {
     PublisherError(#[from] PublisherError),
     #[error("pair id invalid: {0}")]
     UnknownPairId(String),
+    #[error("malformed pair id: {0}")]
+    InvalidPairId(String),
+    #[error("base and quote are identical: {0}")]
+    IdenticalCurrencies(String),
+    #[error("invalid timezone: {0}")]
+    InvalidTimezone(String),
+    #[error("invalid price: {0}")]
+    InvalidPrice(String),
+    #[error("invalid interval: {0}")]
+    InvalidInterval(String),
+    #[error("invalid source alias: {0}")]
+    InvalidSourceAlias(String),
+    #[error("invalid sources: {0}")]
+    InvalidSources(String),
+    #[error("invalid weight: {0}")]
+    InvalidWeight(f64),
+    #[error("unsupported aggregation: {0}")]
+    UnsupportedAggregation(String),
+    #[error("too many points requested: {0} > {1}")]
+    TooManyPoints(usize, usize),
+    #[error("too many pairs requested: {0} > {1}")]
+    TooManyPairs(usize, usize),
+    #[error("sources disagree on {0}: spread of {1} > {2}")]
+    SourcesDisagree(String, f64, f64),
+    #[error("price of {0} too old to be signed: {1}s > {2}s")]
+    StalePriceNotSignable(String, i64, u64),
     #[error("volatility error: {0}")]
     VolatilityError(#[from] VolatilityError),
     #[error("can't publish data: {0}")]
@@ -60,6 +96,7 @@ impl From<InfraError> for EntryError {
     fn from(error: InfraError) -> Self {
         match error {
             InfraError::InternalServerError => Self::InternalServerError,
+            InfraError::Unavailable => Self::DatabaseUnavailable,
             InfraError::NotFound => Self::NotFound("Unknown".to_string()),
             InfraError::RoutingError => Self::MissingData("Not enough data".to_string()),
             InfraError::DisputerNotSet => Self::InternalServerError,
@@ -82,6 +119,10 @@ impl IntoResponse for EntryError {
                 StatusCode::NOT_FOUND,
                 format!("Not enough data on pair {} to perform routing", pair_id),
             ),
+            Self::DatabaseUnavailable => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Database unavailable, please retry later".to_string(),
+            ),
             Self::InfraError(db_error) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Internal server error: {}", db_error),
@@ -99,6 +140,10 @@ impl IntoResponse for EntryError {
                 format!("Invalid timestamp: {}", reason),
             ),
             Self::InvalidExpiry => (StatusCode::BAD_REQUEST, "Invalid expiry".to_string()),
+            Self::PairNotSignable(pair_id) => (
+                StatusCode::FORBIDDEN,
+                format!("Signature is disabled for pair {}", pair_id),
+            ),
             Self::PublisherError(err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Publisher error: {}", err),
@@ -116,6 +161,70 @@ impl IntoResponse for EntryError {
                 StatusCode::NOT_FOUND,
                 format!("Unknown pair id: {}", pair_id),
             ),
+            Self::InvalidPairId(pair_id) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid pair id {}, expected BASE/QUOTE", pair_id),
+            ),
+            Self::IdenticalCurrencies(pair_id) => (
+                StatusCode::BAD_REQUEST,
+                format!("Base and quote are identical for pair {}", pair_id),
+            ),
+            Self::InvalidTimezone(tz) => {
+                (StatusCode::BAD_REQUEST, format!("Invalid timezone: {}", tz))
+            }
+            Self::InvalidPrice(reason) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid price: {}", reason),
+            ),
+            Self::InvalidWeight(weight) => (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Invalid weight {}, expected a value between 0 and 1",
+                    weight
+                ),
+            ),
+            Self::UnsupportedAggregation(aggregation) => (
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported aggregation: {}", aggregation),
+            ),
+            Self::InvalidInterval(interval) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid interval: {}", interval),
+            ),
+            Self::InvalidSourceAlias(alias) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid source alias {}, expected SOURCE=ALIAS", alias),
+            ),
+            Self::InvalidSources(reason) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid sources: {}", reason),
+            ),
+            Self::TooManyPoints(requested, max) => (
+                StatusCode::BAD_REQUEST,
+                format!("Too many points requested: {} (max {})", requested, max),
+            ),
+            Self::TooManyPairs(requested, max) => (
+                StatusCode::BAD_REQUEST,
+                format!("Too many pairs requested: {} (max {})", requested, max),
+            ),
+            Self::SourcesDisagree(pair_id, spread, max_spread) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "Sources disagree on pair {}: spread of {} above {}",
+                    pair_id, spread, max_spread
+                ),
+            ),
+            Self::StalePriceNotSignable(pair_id, age, max_age) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!(
+                    "Price of pair {} is {}s old, above the {}s allowed to be signed",
+                    pair_id, age, max_age
+                ),
+            ),
+            Self::VolatilityError(err @ VolatilityError::NotEnoughSamples(_)) => {
+                (StatusCode::NOT_FOUND, err.to_string())
+            }
+            Self::VolatilityError(err) => (StatusCode::BAD_REQUEST, err.to_string()),
             Self::InvalidMessage(err) => {
                 (StatusCode::BAD_REQUEST, format!("Invalid message: {}", err))
             }
@@ -1,8 +1,11 @@
 use crate::dto::entry as dto;
+use crate::models::entries::priority::PublisherPriority;
 use crate::models::DieselResult;
 use bigdecimal::BigDecimal;
 use diesel::dsl::sql;
 use diesel::internal::derives::multiconnection::chrono::NaiveDateTime;
+use diesel::sql_types::Bool;
+use diesel::upsert::excluded;
 use diesel::BoolExpressionMethods;
 use diesel::{
     AsChangeset, ExpressionMethods, Insertable, PgConnection, PgTextExpressionMethods, QueryDsl,
@@ -49,23 +52,39 @@ impl FutureEntry {
             .get_result(conn)
     }
 
+    /// Inserts the entries, overwriting the stored ones sharing the same
+    /// (pair, source, timestamp, expiration) unless they were published by a
+    /// publisher that outranks the incoming one, like [`crate::Entry::create_many`].
     pub fn create_many(
         conn: &mut PgConnection,
         data: Vec<NewFutureEntry>,
+        priority: &PublisherPriority,
     ) -> DieselResult<Vec<FutureEntry>> {
-        diesel::insert_into(future_entries::table)
+        let conflict_target = (
+            future_entries::pair_id,
+            future_entries::source,
+            future_entries::timestamp,
+            future_entries::expiration_timestamp,
+        );
+        let changes = (
+            future_entries::publisher.eq(excluded(future_entries::publisher)),
+            future_entries::publisher_signature.eq(excluded(future_entries::publisher_signature)),
+            future_entries::price.eq(excluded(future_entries::price)),
+        );
+        let query = diesel::insert_into(future_entries::table)
             .values(&data)
             .returning(FutureEntry::as_returning())
-            .on_conflict((
-                future_entries::pair_id,
-                future_entries::source,
-                future_entries::timestamp,
-                future_entries::expiration_timestamp,
+            .on_conflict(conflict_target)
+            .do_update()
+            .set(changes);
+
+        if priority.is_empty() {
+            return query.get_results(conn);
+        }
+        query
+            .filter(sql::<Bool>(
+                &priority.sql_incoming_not_outranked("future_entries"),
             ))
-            // TODO(akhercha): We are loosing some data currently because of duplicates.
-            // It happens because we don't have enough precision in the timestamp (in s, not ms).
-            // So we have multiple price for the same timestamp.
-            .do_nothing()
             .get_results(conn)
     }
 
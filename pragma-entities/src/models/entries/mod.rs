@@ -1,3 +1,6 @@
 pub mod entry;
 pub mod entry_error;
 pub mod future_entry;
+pub mod payload;
+pub mod price;
+pub mod priority;
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use starknet::core::crypto::{ecdsa_verify, Signature};
+use starknet::core::types::Felt;
+
+use crate::models::entries::entry::NewEntry;
+use crate::models::entries::future_entry::NewFutureEntry;
+
+/// Entries published on Kafka by the node and consumed by the ingestor,
+/// e.g `{"type": "spot", "entries": [...]}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "entries", rename_all = "snake_case")]
+pub enum EntriesPayload {
+    Spot(Vec<NewEntry>),
+    Future(Vec<NewFutureEntry>),
+    /// Spot entries whose signature is verified by the ingestor.
+    PendingSpot(PendingSpotEntries),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingSpotEntries {
+    pub signature: PendingSignature,
+    pub entries: Vec<NewEntry>,
+}
+
+/// Signature of a publish request whose verification was deferred.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingSignature {
+    pub public_key: Felt,
+    pub message_hash: Felt,
+    pub r: Felt,
+    pub s: Felt,
+}
+
+impl PendingSignature {
+    /// Returns true if the signature of the message hash is valid for the public key.
+    pub fn is_valid(&self) -> bool {
+        let signature = Signature {
+            r: self.r,
+            s: self.s,
+        };
+        ecdsa_verify(&self.public_key, &self.message_hash, &signature).unwrap_or(false)
+    }
+}
@@ -0,0 +1,41 @@
+use bigdecimal::{BigDecimal, Signed};
+
+/// Returns true if the price can be stored for the pair.
+/// Prices must be strictly positive, except for the pairs listed in
+/// `non_positive_price_pairs` where zero and negative prices are legitimate
+/// (e.g. some energy futures).
+pub fn is_valid_price(
+    pair_id: &str,
+    price: &BigDecimal,
+    non_positive_price_pairs: &[String],
+) -> bool {
+    price.is_positive()
+        || non_positive_price_pairs
+            .iter()
+            .any(|pair| pair.trim().eq_ignore_ascii_case(pair_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_positive_prices_are_valid() {
+        assert!(is_valid_price("BTC/USD", &BigDecimal::from(1), &[]));
+        assert!(!is_valid_price("BTC/USD", &BigDecimal::from(0), &[]));
+        assert!(!is_valid_price("BTC/USD", &BigDecimal::from(-1), &[]));
+    }
+
+    #[test]
+    fn test_non_positive_prices_are_valid_for_allowed_pairs() {
+        let allowed = vec!["POWER/EUR".to_string()];
+        assert!(is_valid_price("POWER/EUR", &BigDecimal::from(0), &allowed));
+        assert!(is_valid_price(
+            "power/eur",
+            &BigDecimal::from(-12),
+            &allowed
+        ));
+        assert!(is_valid_price("POWER/EUR", &BigDecimal::from(5), &allowed));
+        assert!(!is_valid_price("BTC/USD", &BigDecimal::from(-12), &allowed));
+    }
+}
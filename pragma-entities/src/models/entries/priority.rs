@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Rank given to publishers that are not part of the configured priority list.
+const UNRANKED: i64 = i64::MAX;
+
+/// Ordered list of publishers used to settle conflicts between entries that
+/// share the same unique key (pair, source, timestamp[, expiration]).
+///
+/// Since the source is part of the unique key, conflicts only happen when
+/// several publishers relay the same source: the entry of the publisher listed
+/// first is the one kept. Publishers not listed have the lowest priority, and
+/// between publishers of the same rank the last entry received is kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublisherPriority(Vec<String>);
+
+impl PublisherPriority {
+    pub fn new(publishers: Vec<String>) -> Self {
+        Self(
+            publishers
+                .into_iter()
+                .map(|publisher| publisher.trim().to_uppercase())
+                .filter(|publisher| !publisher.is_empty())
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the rank of the publisher, the lower the better.
+    pub fn rank(&self, publisher: &str) -> i64 {
+        self.0
+            .iter()
+            .position(|p| p.eq_ignore_ascii_case(publisher))
+            .map_or(UNRANKED, |position| position as i64)
+    }
+
+    /// Returns true if the incoming publisher strictly outranks the stored one.
+    pub fn outranks(&self, incoming: &str, stored: &str) -> bool {
+        self.rank(incoming) < self.rank(stored)
+    }
+
+    /// Keeps a single entry per key: the one of the best ranked publisher.
+    /// On equal rank, the last entry of the batch wins.
+    pub fn dedup_by_key<T, K, FK, FP>(&self, entries: Vec<T>, key: FK, publisher: FP) -> Vec<T>
+    where
+        K: Eq + Hash,
+        FK: Fn(&T) -> K,
+        FP: Fn(&T) -> &str,
+    {
+        let mut kept: HashMap<K, usize> = HashMap::new();
+        let mut slots: Vec<Option<T>> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let idx = slots.len();
+            match kept.get(&key(&entry)).copied() {
+                Some(previous) => {
+                    let stored = slots[previous].as_ref().expect("kept entry is present");
+                    if self.rank(publisher(&entry)) <= self.rank(publisher(stored)) {
+                        slots[previous] = None;
+                        kept.insert(key(&entry), idx);
+                        slots.push(Some(entry));
+                    } else {
+                        slots.push(None);
+                    }
+                }
+                None => {
+                    kept.insert(key(&entry), idx);
+                    slots.push(Some(entry));
+                }
+            }
+        }
+        slots.into_iter().flatten().collect()
+    }
+
+    /// SQL expression of the rank of the publisher column of `relation`.
+    fn sql_rank(&self, relation: &str) -> String {
+        let publishers = self
+            .0
+            .iter()
+            .map(|p| format!("'{}'", p.replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "COALESCE(array_position(ARRAY[{publishers}]::text[], UPPER({relation}.publisher)) - 1, {UNRANKED})"
+        )
+    }
+
+    /// SQL predicate for an `ON CONFLICT DO UPDATE ... WHERE` clause that lets
+    /// the incoming row replace the stored one unless the stored one outranks it.
+    pub fn sql_incoming_not_outranked(&self, table: &str) -> String {
+        format!("{} <= {}", self.sql_rank("excluded"), self.sql_rank(table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Row {
+        pair_id: &'static str,
+        source: &'static str,
+        timestamp: u64,
+        publisher: &'static str,
+        price: u64,
+    }
+
+    fn row(publisher: &'static str, timestamp: u64, price: u64) -> Row {
+        Row {
+            pair_id: "BTC/USD",
+            source: "BINANCE",
+            timestamp,
+            publisher,
+            price,
+        }
+    }
+
+    fn dedup(priority: &PublisherPriority, rows: Vec<Row>) -> Vec<Row> {
+        priority.dedup_by_key(
+            rows,
+            |r| (r.pair_id, r.source, r.timestamp),
+            |r| r.publisher,
+        )
+    }
+
+    #[test]
+    fn test_rank() {
+        let priority = PublisherPriority::new(vec!["pragma".into(), " Fourleaf ".into()]);
+        assert_eq!(priority.rank("PRAGMA"), 0);
+        assert_eq!(priority.rank("fourleaf"), 1);
+        assert_eq!(priority.rank("UNKNOWN"), UNRANKED);
+        assert!(priority.outranks("PRAGMA", "FOURLEAF"));
+        assert!(priority.outranks("FOURLEAF", "UNKNOWN"));
+        assert!(!priority.outranks("UNKNOWN", "OTHER"));
+    }
+
+    #[test]
+    fn test_high_priority_overwrites_low_priority_at_same_timestamp() {
+        let priority = PublisherPriority::new(vec!["PRAGMA".into(), "FOURLEAF".into()]);
+
+        let deduped = dedup(
+            &priority,
+            vec![row("FOURLEAF", 1, 100), row("PRAGMA", 1, 101)],
+        );
+        assert_eq!(deduped, vec![row("PRAGMA", 1, 101)]);
+
+        // The low priority entry never overwrites the high priority one.
+        let deduped = dedup(
+            &priority,
+            vec![row("PRAGMA", 1, 101), row("FOURLEAF", 1, 100)],
+        );
+        assert_eq!(deduped, vec![row("PRAGMA", 1, 101)]);
+    }
+
+    #[test]
+    fn test_dedup_keeps_distinct_keys_and_last_on_tie() {
+        let priority = PublisherPriority::default();
+        let deduped = dedup(
+            &priority,
+            vec![row("A", 1, 100), row("B", 1, 101), row("A", 2, 102)],
+        );
+        assert_eq!(deduped, vec![row("B", 1, 101), row("A", 2, 102)]);
+    }
+
+    #[test]
+    fn test_sql_predicates() {
+        let priority = PublisherPriority::new(vec!["PRAGMA".into(), "O'NEIL".into()]);
+        assert_eq!(
+            priority.sql_incoming_not_outranked("entries"),
+            format!(
+                "COALESCE(array_position(ARRAY['PRAGMA','O''NEIL']::text[], UPPER(excluded.publisher)) - 1, {UNRANKED}) \
+                 <= COALESCE(array_position(ARRAY['PRAGMA','O''NEIL']::text[], UPPER(entries.publisher)) - 1, {UNRANKED})"
+            )
+        );
+    }
+}
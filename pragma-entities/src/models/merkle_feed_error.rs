@@ -22,6 +22,8 @@ pub enum MerkleFeedError {
     MerkleProof(String),
     #[error("no merkle feeds published for network: {0}")]
     NoBlocks(String),
+    #[error("no merkle feeds block published at timestamp {0}")]
+    NoBlockAtTimestamp(u64),
 }
 
 impl From<RedisError> for MerkleFeedError {
@@ -33,6 +35,7 @@ impl From<RedisError> for MerkleFeedError {
             RedisError::InvalidOptionHash(r) => Self::InvalidOptionHash(r),
             RedisError::TreeDeserialization => Self::TreeDeserialization,
             RedisError::NoBlocks(network) => Self::NoBlocks(network),
+            RedisError::NoBlockAtTimestamp(timestamp) => Self::NoBlockAtTimestamp(timestamp),
             RedisError::InternalServerError => Self::InternalServerError,
         }
     }
@@ -71,6 +74,10 @@ impl IntoResponse for MerkleFeedError {
                 StatusCode::NOT_FOUND,
                 format!("No merkle feeds published for network {}", network),
             ),
+            Self::NoBlockAtTimestamp(timestamp) => (
+                StatusCode::NOT_FOUND,
+                format!("No merkle feeds block published at timestamp {}", timestamp),
+            ),
             Self::MerkleProof(hash) => (
                 StatusCode::NOT_FOUND,
                 format!("Could not generate a valid merkle proof for hash {}", hash),
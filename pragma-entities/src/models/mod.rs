@@ -6,7 +6,8 @@ pub mod merkle_feed_error;
 pub mod optimistic_oracle_error;
 pub mod publisher;
 pub mod publisher_error;
+pub mod source_latency;
 
-pub use entries::{entry, entry_error, future_entry};
+pub use entries::{entry, entry_error, future_entry, payload, price, priority};
 
 type DieselResult<T> = Result<T, diesel::result::Error>;
@@ -10,8 +10,12 @@ pub enum OptimisticOracleError {
     InternalServerError,
     #[error("database connection error")]
     DatabaseConnection,
-    #[error("assertion details issue: {0}")]
-    AssertionDetailsIssue(String),
+    #[error("database error")]
+    DatabaseError,
+    #[error("assertion not found: {0}")]
+    NotFound(String),
+    #[error("invalid assertion id: {0}")]
+    InvalidAssertionId(String),
     #[error("disputer not set for assertion: {0}")]
     DisputerNotSet(String),
     #[error("settler not set for assertion: {0}")]
@@ -23,6 +27,7 @@ pub enum OptimisticOracleError {
 impl From<InfraError> for OptimisticOracleError {
     fn from(error: InfraError) -> Self {
         match error {
+            InfraError::Unavailable => Self::DatabaseConnection,
             InfraError::DisputerNotSet => Self::DisputerNotSet("Unknown".to_string()),
             InfraError::SettlerNotSet => Self::SettlerNotSet("Unknown".to_string()),
             _ => Self::InternalServerError,
@@ -37,9 +42,17 @@ impl IntoResponse for OptimisticOracleError {
                 StatusCode::SERVICE_UNAVAILABLE,
                 "Could not establish a connection with the database".to_string(),
             ),
-            Self::AssertionDetailsIssue(id) => (
+            Self::DatabaseError => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Could not query the assertions".to_string(),
+            ),
+            Self::NotFound(id) => (
                 StatusCode::NOT_FOUND,
-                format!("Issue to fetch assertion details with id: {}", id),
+                format!("Assertion with id {} not found", id),
+            ),
+            Self::InvalidAssertionId(id) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid assertion id: {}", id),
             ),
             Self::DisputerNotSet(id) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -0,0 +1,53 @@
+use diesel::internal::derives::multiconnection::chrono::NaiveDateTime;
+use diesel::upsert::excluded;
+use diesel::{
+    ExpressionMethods, Insertable, PgConnection, QueryDsl, Queryable, RunQueryDsl, Selectable,
+    SelectableHelper,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::models::DieselResult;
+use crate::schema::source_latencies;
+
+/// Rolling latency between the timestamp reported by a source and the
+/// moment its entries are ingested.
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = source_latencies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SourceLatency {
+    pub source: String,
+    pub latency_ms: f64,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[diesel(table_name = source_latencies)]
+pub struct NewSourceLatency {
+    pub source: String,
+    pub latency_ms: f64,
+    pub updated_at: NaiveDateTime,
+}
+
+impl SourceLatency {
+    pub fn upsert_many(
+        conn: &mut PgConnection,
+        data: Vec<NewSourceLatency>,
+    ) -> DieselResult<usize> {
+        diesel::insert_into(source_latencies::table)
+            .values(&data)
+            .on_conflict(source_latencies::source)
+            .do_update()
+            .set((
+                source_latencies::latency_ms.eq(excluded(source_latencies::latency_ms)),
+                source_latencies::updated_at.eq(excluded(source_latencies::updated_at)),
+            ))
+            .execute(conn)
+    }
+
+    pub fn get_all(conn: &mut PgConnection) -> DieselResult<Vec<SourceLatency>> {
+        source_latencies::table
+            .select(SourceLatency::as_select())
+            .order(source_latencies::source.asc())
+            .load::<SourceLatency>(conn)
+    }
+}
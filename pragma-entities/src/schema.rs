@@ -20,6 +20,9 @@ diesel::table! {
         price -> Numeric,
         source -> Varchar,
         publisher_signature -> Nullable<Varchar>,
+        ingested_at -> Nullable<Timestamptz>,
+        bid -> Nullable<Numeric>,
+        ask -> Nullable<Numeric>,
     }
 }
 
@@ -47,4 +50,18 @@ diesel::table! {
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(currencies, entries, future_entries, publishers,);
+diesel::table! {
+    source_latencies (source) {
+        source -> Varchar,
+        latency_ms -> Float8,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(
+    currencies,
+    entries,
+    future_entries,
+    publishers,
+    source_latencies,
+);
@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use tokio::sync::{watch, Semaphore};
+use tokio::task::JoinSet;
+
+/// Returns the number of inserts that can run at once.
+/// It defaults to the size of the pool and can't exceed it, so the inserts
+/// never wait for a connection.
+pub fn insert_concurrency(configured: Option<usize>, pool_size: usize) -> usize {
+    let pool_size = pool_size.max(1);
+    configured.unwrap_or(pool_size).clamp(1, pool_size)
+}
+
+/// Spawns tasks while keeping at most `max_concurrency` of them running.
+/// Tasks sharing a key run one after the other, in the order they were
+/// spawned.
+pub struct BoundedTasks<K> {
+    semaphore: Arc<Semaphore>,
+    tasks: JoinSet<()>,
+    /// Completion of the last task spawned for each key.
+    last_task_by_key: HashMap<K, watch::Receiver<bool>>,
+}
+
+impl<K: Eq + Hash> BoundedTasks<K> {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            tasks: JoinSet::new(),
+            last_task_by_key: HashMap::new(),
+        }
+    }
+
+    /// Waits for a free slot, then spawns the task.
+    /// The task only starts once the previously spawned tasks sharing one of
+    /// its keys are completed.
+    pub async fn spawn<F>(&mut self, keys: impl IntoIterator<Item = K>, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+
+        let (done_tx, done_rx) = watch::channel(false);
+        let previous_tasks: Vec<_> = keys
+            .into_iter()
+            .collect::<HashSet<K>>()
+            .into_iter()
+            .filter_map(|key| self.last_task_by_key.insert(key, done_rx.clone()))
+            .collect();
+
+        self.tasks.spawn(async move {
+            for mut previous_task in previous_tasks {
+                // An error means the previous task was dropped, e.g. it panicked.
+                let _ = previous_task.wait_for(|done| *done).await;
+            }
+            task.await;
+            let _ = done_tx.send(true);
+            drop(permit);
+        });
+        // Reap the finished tasks so the set & the keys don't grow indefinitely.
+        while self.tasks.try_join_next().is_some() {}
+        self.last_task_by_key
+            .retain(|_, done| !*done.borrow() && done.has_changed().is_ok());
+    }
+
+    /// Waits for all the spawned tasks to complete.
+    pub async fn join_all(&mut self) {
+        while self.tasks.join_next().await.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_concurrency_is_capped_by_the_pool() {
+        assert_eq!(insert_concurrency(None, 10), 10);
+        assert_eq!(insert_concurrency(Some(4), 10), 4);
+        assert_eq!(insert_concurrency(Some(50), 10), 10);
+        assert_eq!(insert_concurrency(Some(0), 10), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_inserts_do_not_exceed_the_pool_size() {
+        let pool_size = 4;
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+
+        let mut tasks = BoundedTasks::new(insert_concurrency(None, pool_size));
+        for key in 0..20 {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            let completed = completed.clone();
+            tasks
+                .spawn([key], async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    completed.fetch_add(1, Ordering::SeqCst);
+                })
+                .await;
+        }
+        tasks.join_all().await;
+
+        assert_eq!(completed.load(Ordering::SeqCst), 20);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= pool_size);
+        // The inserts did run concurrently.
+        assert!(max_in_flight.load(Ordering::SeqCst) > 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tasks_sharing_a_key_run_in_order() {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let mut tasks = BoundedTasks::new(4);
+        // The first task on BTC/USD is the slowest: the second one must still
+        // wait for it, while the ETH/USD one can run meanwhile.
+        for (id, key, delay) in [(1, "BTC/USD", 100), (2, "BTC/USD", 10), (3, "ETH/USD", 1)] {
+            let order = order.clone();
+            tasks
+                .spawn([key], async move {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    order.lock().unwrap().push(id);
+                })
+                .await;
+        }
+        tasks.join_all().await;
+
+        assert_eq!(*order.lock().unwrap(), vec![3, 1, 2]);
+    }
+}
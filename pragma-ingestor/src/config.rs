@@ -1,3 +1,6 @@
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
@@ -13,12 +16,72 @@ pub struct Ingestor {
     pub brokers: Vec<String>,
     pub topic: String,
     pub group_id: String,
+    /// Publishers ordered by priority, used to settle conflicting entries
+    /// sharing the same (pair, source, timestamp).
+    #[serde(default)]
+    pub publisher_priority: Vec<String>,
+    /// Pairs for which zero and negative prices are legitimate.
+    /// For all the other pairs, entries with a non positive price are dropped.
+    #[serde(default)]
+    pub non_positive_price_pairs: Vec<String>,
+    /// Maximum number of payloads inserted at once.
+    /// Defaults to, and is capped by, the size of the database pool.
+    #[serde(default)]
+    pub insert_concurrency: Option<usize>,
+    /// Kafka topic receiving the payloads that couldn't be deserialized.
+    #[serde(default)]
+    pub dead_letter_topic: Option<String>,
+    /// File receiving the payloads that couldn't be deserialized, used when
+    /// no dead-letter topic is configured.
+    #[serde(default)]
+    pub dead_letter_file: Option<String>,
+    /// Size above which the dead-letter file is rotated. The previous file is
+    /// kept with a `.1` suffix, replacing the one rotated before it.
+    #[serde(default)]
+    pub dead_letter_file_max_size_in_bytes: Option<NonZeroU64>,
+    /// Port on which the lag of the consumer group is served, on `GET /lag`.
+    /// The lag is neither tracked nor exposed when not set.
+    #[serde(default)]
+    pub consumer_lag_port: Option<u16>,
+    /// Interval between two measures of the lag of the consumer group.
+    #[serde(default)]
+    pub consumer_lag_refresh_interval_in_seconds: Option<NonZeroU64>,
 }
 
+/// File receiving the payloads that couldn't be deserialized, by default.
+/// Created in the temporary directory.
+const DEFAULT_DEAD_LETTER_FILE: &str = "pragma-ingestor-dead-letters.jsonl";
+
+/// Size above which the dead-letter file is rotated, by default.
+const DEFAULT_DEAD_LETTER_FILE_MAX_SIZE_IN_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Interval between two measures of the lag of the consumer group, by default.
+const DEFAULT_CONSUMER_LAG_REFRESH_INTERVAL_IN_SECONDS: u64 = 15;
+
 impl Ingestor {
     pub fn from_env() -> Result<Self, ErrorKind> {
         envy::from_env::<Ingestor>().map_err(ErrorKind::LoadConfig)
     }
+
+    /// Path of the dead-letter file, as configured.
+    pub fn dead_letter_file(&self) -> PathBuf {
+        match &self.dead_letter_file {
+            Some(path) => PathBuf::from(path),
+            None => std::env::temp_dir().join(DEFAULT_DEAD_LETTER_FILE),
+        }
+    }
+
+    pub fn dead_letter_file_max_size(&self) -> u64 {
+        self.dead_letter_file_max_size_in_bytes
+            .map_or(DEFAULT_DEAD_LETTER_FILE_MAX_SIZE_IN_BYTES, NonZeroU64::get)
+    }
+
+    pub fn consumer_lag_refresh_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.consumer_lag_refresh_interval_in_seconds.map_or(
+            DEFAULT_CONSUMER_LAG_REFRESH_INTERVAL_IN_SECONDS,
+            NonZeroU64::get,
+        ))
+    }
 }
 
 pub fn load_configuration() -> Ingestor {
@@ -37,11 +100,57 @@ mod tests {
             brokers: brokers.clone(),
             topic: "test_topic".to_string(),
             group_id: "test_group".to_string(),
+            publisher_priority: vec![],
+            non_positive_price_pairs: vec![],
+            insert_concurrency: None,
+            dead_letter_topic: None,
+            dead_letter_file: None,
+            dead_letter_file_max_size_in_bytes: None,
+            consumer_lag_port: None,
+            consumer_lag_refresh_interval_in_seconds: None,
         };
 
         assert_eq!(ingestor.brokers, brokers);
         assert_eq!(ingestor.topic, "test_topic");
         assert_eq!(ingestor.group_id, "test_group");
+        assert_eq!(
+            ingestor.dead_letter_file(),
+            env::temp_dir().join(DEFAULT_DEAD_LETTER_FILE)
+        );
+        assert_eq!(
+            ingestor.dead_letter_file_max_size(),
+            DEFAULT_DEAD_LETTER_FILE_MAX_SIZE_IN_BYTES
+        );
+        assert_eq!(
+            ingestor.consumer_lag_refresh_interval(),
+            std::time::Duration::from_secs(DEFAULT_CONSUMER_LAG_REFRESH_INTERVAL_IN_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_dead_letter_file_is_kept_as_configured() {
+        let ingestor = |dead_letter_file: &str| Ingestor {
+            brokers: vec![],
+            topic: "test_topic".to_string(),
+            group_id: "test_group".to_string(),
+            publisher_priority: vec![],
+            non_positive_price_pairs: vec![],
+            insert_concurrency: None,
+            dead_letter_topic: None,
+            dead_letter_file: Some(dead_letter_file.to_string()),
+            dead_letter_file_max_size_in_bytes: None,
+            consumer_lag_port: None,
+            consumer_lag_refresh_interval_in_seconds: None,
+        };
+
+        assert_eq!(
+            ingestor("/var/log/dead_letters.jsonl").dead_letter_file(),
+            PathBuf::from("/var/log/dead_letters.jsonl")
+        );
+        assert_eq!(
+            ingestor("logs/dead_letters.jsonl").dead_letter_file(),
+            PathBuf::from("logs/dead_letters.jsonl")
+        );
     }
 
     #[test]
@@ -57,6 +166,7 @@ mod tests {
         assert_eq!(ingestor.brokers, vec!["localhost:9092".to_string()]);
         assert_eq!(ingestor.topic, "test_topic");
         assert_eq!(ingestor.group_id, "test_group");
+        assert!(ingestor.publisher_priority.is_empty());
         unsafe {
             env::remove_var("BROKERS");
             env::remove_var("TOPIC");
@@ -64,6 +174,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_zero_consumer_lag_refresh_interval_is_rejected() {
+        let vars = |interval: &str| {
+            vec![
+                ("BROKERS".to_string(), "localhost:9092".to_string()),
+                ("TOPIC".to_string(), "test_topic".to_string()),
+                ("GROUP_ID".to_string(), "test_group".to_string()),
+                (
+                    "CONSUMER_LAG_REFRESH_INTERVAL_IN_SECONDS".to_string(),
+                    interval.to_string(),
+                ),
+            ]
+        };
+
+        let ingestor = envy::from_iter::<_, Ingestor>(vars("30")).unwrap();
+        assert_eq!(
+            ingestor.consumer_lag_refresh_interval(),
+            std::time::Duration::from_secs(30)
+        );
+        assert!(envy::from_iter::<_, Ingestor>(vars("0")).is_err());
+    }
+
     #[test]
     fn test_env_error_handling() {
         unsafe {
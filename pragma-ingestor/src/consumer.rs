@@ -2,11 +2,12 @@ use crate::config::CONFIG;
 use rdkafka::config::{ClientConfig, RDKafkaLogLevel};
 use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
 use rdkafka::message::Message;
+use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::{error, info};
 
-pub async fn consume(tx: UnboundedSender<Vec<u8>>) {
-    let consumer: StreamConsumer = ClientConfig::new()
+pub fn create_consumer() -> StreamConsumer {
+    ClientConfig::new()
         .set("group.id", &CONFIG.group_id)
         .set("bootstrap.servers", CONFIG.brokers.join(","))
         .set("enable.partition.eof", "false")
@@ -15,8 +16,10 @@ pub async fn consume(tx: UnboundedSender<Vec<u8>>) {
         .set("enable.auto.commit", "false")
         .set_log_level(RDKafkaLogLevel::Debug)
         .create()
-        .expect("Consumer creation failed");
+        .expect("Consumer creation failed")
+}
 
+pub async fn consume(consumer: Arc<StreamConsumer>, tx: UnboundedSender<Vec<u8>>) {
     consumer
         .subscribe(&[&CONFIG.topic])
         .expect("Can't subscribe to specified topics");
@@ -0,0 +1,222 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::config::Ingestor;
+use crate::error::DeadLetterError;
+
+/// Maximum time spent waiting for a dead letter to be delivered to Kafka.
+const KAFKA_DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Payload that couldn't be deserialized, along with the reason why.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetterRecord {
+    pub error: String,
+    /// Raw bytes of the payload, hex encoded.
+    pub payload: String,
+}
+
+impl DeadLetterRecord {
+    pub fn new(payload: &[u8], error: &str) -> Self {
+        Self {
+            error: error.to_string(),
+            payload: payload.iter().map(|byte| format!("{byte:02x}")).collect(),
+        }
+    }
+}
+
+/// Sink receiving the payloads that couldn't be deserialized, so they can be
+/// inspected when debugging publishers.
+pub enum DeadLetter {
+    /// Publishes the records to a Kafka topic.
+    Kafka {
+        producer: FutureProducer,
+        topic: String,
+    },
+    /// Appends the records to a local file, one JSON record per line.
+    File(Mutex<RotatingFile>),
+    /// Logs the records, when neither the topic nor the file can be used.
+    Log,
+}
+
+impl DeadLetter {
+    /// Publishes to the dead-letter topic if configured, or else appends to
+    /// the dead-letter file.
+    pub async fn from_config(config: &Ingestor) -> Result<Self, DeadLetterError> {
+        match &config.dead_letter_topic {
+            Some(topic) => {
+                let producer = ClientConfig::new()
+                    .set("bootstrap.servers", config.brokers.join(","))
+                    .create()?;
+                Ok(Self::Kafka {
+                    producer,
+                    topic: topic.clone(),
+                })
+            }
+            None => {
+                Self::file(
+                    &config.dead_letter_file(),
+                    config.dead_letter_file_max_size(),
+                )
+                .await
+            }
+        }
+    }
+
+    pub async fn file(path: &Path, max_size: u64) -> Result<Self, DeadLetterError> {
+        let file = RotatingFile::open(path.to_path_buf(), max_size).await?;
+        Ok(Self::File(Mutex::new(file)))
+    }
+
+    pub async fn send(&self, payload: &[u8], error: &str) -> Result<(), DeadLetterError> {
+        let record = serde_json::to_vec(&DeadLetterRecord::new(payload, error))?;
+        match self {
+            Self::Kafka { producer, topic } => {
+                producer
+                    .send(
+                        FutureRecord::<(), _>::to(topic).payload(&record),
+                        KAFKA_DELIVERY_TIMEOUT,
+                    )
+                    .await
+                    .map_err(|(e, _)| e)?;
+            }
+            Self::File(file) => {
+                let mut line = record;
+                line.push(b'\n');
+                file.lock().await.append(&line).await?;
+            }
+            Self::Log => {
+                tracing::warn!("dead letter: {}", String::from_utf8_lossy(&record));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// File rotated once it grows above its maximum size, so it can't fill the
+/// disk. Only the last rotated file is kept, with a `.1` suffix.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    async fn open(path: PathBuf, max_size: u64) -> Result<Self, DeadLetterError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let size = file.metadata().await?.len();
+        Ok(Self {
+            path,
+            max_size,
+            file,
+            size,
+        })
+    }
+
+    async fn append(&mut self, line: &[u8]) -> Result<(), DeadLetterError> {
+        let len = line.len() as u64;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate().await?;
+        }
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += len;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> Result<(), DeadLetterError> {
+        tokio::fs::rename(&self.path, rotated_path(&self.path)).await?;
+        *self = Self::open(self.path.clone(), self.max_size).await?;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    PathBuf::from(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_contains_the_raw_payload() {
+        let record = DeadLetterRecord::new(b"[{", "EOF while parsing a list");
+        assert_eq!(record.payload, "5b7b");
+        assert_eq!(record.error, "EOF while parsing a list");
+    }
+
+    fn read_records(path: &Path) -> Vec<DeadLetterRecord> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<DeadLetterRecord>(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_records_are_appended_to_the_file() {
+        let path = std::env::temp_dir().join(format!(
+            "pragma-ingestor-dead-letters-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let dead_letter = DeadLetter::file(&path, 1024).await.unwrap();
+        dead_letter
+            .send(b"not json", "expected value")
+            .await
+            .unwrap();
+        dead_letter
+            .send(b"{}", "missing field `type`")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            read_records(&path),
+            vec![
+                DeadLetterRecord::new(b"not json", "expected value"),
+                DeadLetterRecord::new(b"{}", "missing field `type`"),
+            ]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_is_rotated_above_its_max_size() {
+        let path = std::env::temp_dir().join(format!(
+            "pragma-ingestor-rotated-dead-letters-{}.jsonl",
+            std::process::id()
+        ));
+        let rotated = rotated_path(&path);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&rotated);
+
+        // Each record takes 29 bytes, so every one of them triggers a rotation.
+        let dead_letter = DeadLetter::file(&path, 40).await.unwrap();
+        for (payload, error) in [(b"a", "1"), (b"b", "2"), (b"c", "3")] {
+            dead_letter.send(payload, error).await.unwrap();
+        }
+
+        assert_eq!(read_records(&path), vec![DeadLetterRecord::new(b"c", "3")]);
+        assert_eq!(
+            read_records(&rotated),
+            vec![DeadLetterRecord::new(b"b", "2")]
+        );
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+}
@@ -7,3 +7,19 @@ pub enum ErrorKind {
     #[error("load config error: {0}")]
     LoadConfig(#[from] envy::Error),
 }
+
+#[derive(Error, Debug)]
+pub enum DeadLetterError {
+    #[error("dead letter io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("dead letter kafka error: {0}")]
+    Kafka(#[from] rdkafka::error::KafkaError),
+    #[error("dead letter serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum VerificationError {
+    #[error("invalid publisher signature for message hash {0}")]
+    InvalidSignature(String),
+}
@@ -0,0 +1,389 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use opentelemetry::metrics::Gauge;
+use opentelemetry::KeyValue;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::error::KafkaError;
+use rdkafka::{Offset, TopicPartitionList};
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+/// Maximum time spent waiting for the offsets from the brokers.
+const OFFSETS_FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of consecutive failed refreshes after which the latest lag is
+/// considered outdated, and reported as unknown.
+const MAX_FAILED_REFRESHES: u32 = 3;
+
+/// Offsets of a partition of the consumed topic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionOffsets {
+    pub partition: i32,
+    /// Offset of the next message to consume, None if nothing was committed yet.
+    pub committed: Option<i64>,
+    /// Offset of the oldest message still in the partition.
+    pub low_watermark: i64,
+    /// Offset of the next message produced to the partition.
+    pub high_watermark: i64,
+}
+
+/// Provides the offsets of the consumer group for all the partitions of the
+/// topic.
+pub trait OffsetSource: Send + Sync + 'static {
+    fn partition_offsets(&self, topic: &str) -> Result<Vec<PartitionOffsets>, KafkaError>;
+}
+
+impl OffsetSource for StreamConsumer {
+    fn partition_offsets(&self, topic: &str) -> Result<Vec<PartitionOffsets>, KafkaError> {
+        // The partitions are listed from the topic metadata rather than from
+        // the assignment, which is empty before the first assignment and
+        // during a rebalance.
+        let metadata = self.fetch_metadata(Some(topic), OFFSETS_FETCH_TIMEOUT)?;
+        let mut partitions = TopicPartitionList::new();
+        for metadata_topic in metadata.topics().iter().filter(|t| t.name() == topic) {
+            for partition in metadata_topic.partitions() {
+                partitions.add_partition(topic, partition.id());
+            }
+        }
+        if partitions.count() == 0 {
+            return Err(KafkaError::MetadataFetch(
+                rdkafka::types::RDKafkaErrorCode::UnknownTopicOrPartition,
+            ));
+        }
+        let committed = self.committed_offsets(partitions, OFFSETS_FETCH_TIMEOUT)?;
+        committed
+            .elements_for_topic(topic)
+            .iter()
+            .map(|element| {
+                let (low_watermark, high_watermark) =
+                    self.fetch_watermarks(topic, element.partition(), OFFSETS_FETCH_TIMEOUT)?;
+                let committed = match element.offset() {
+                    Offset::Offset(offset) => Some(offset),
+                    _ => None,
+                };
+                Ok(PartitionOffsets {
+                    partition: element.partition(),
+                    committed,
+                    low_watermark,
+                    high_watermark,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartitionLag {
+    pub partition: i32,
+    /// Number of messages not consumed yet.
+    pub lag: i64,
+}
+
+/// Number of messages of the topic not consumed yet by the consumer group.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConsumerLag {
+    pub topic: String,
+    pub total: i64,
+    pub partitions: Vec<PartitionLag>,
+}
+
+/// Computes the lag of each partition. Without committed offset, all the
+/// messages still in the partition are unconsumed.
+pub fn compute_lag(topic: &str, offsets: &[PartitionOffsets]) -> ConsumerLag {
+    let partitions: Vec<PartitionLag> = offsets
+        .iter()
+        .map(|offsets| {
+            let consumed = offsets
+                .committed
+                .map_or(offsets.low_watermark, |committed| {
+                    committed.max(offsets.low_watermark)
+                });
+            PartitionLag {
+                partition: offsets.partition,
+                lag: (offsets.high_watermark - consumed).max(0),
+            }
+        })
+        .collect();
+    ConsumerLag {
+        topic: topic.to_string(),
+        total: partitions.iter().map(|partition| partition.lag).sum(),
+        partitions,
+    }
+}
+
+/// Latest lag of the consumer group, exposed as a metric and over HTTP.
+#[derive(Debug, Clone)]
+pub struct LagMonitor {
+    /// None until the offsets were fetched once.
+    lag: Arc<RwLock<Option<ConsumerLag>>>,
+    gauge: Gauge<u64>,
+}
+
+impl Default for LagMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LagMonitor {
+    pub fn new() -> Self {
+        let meter = opentelemetry::global::meter("pragma-ingestor-meter");
+        let gauge = meter
+            .u64_gauge("kafka_consumer_lag")
+            .with_description("Number of messages of the topic not consumed yet, per partition")
+            .init();
+        Self {
+            lag: Arc::new(RwLock::new(None)),
+            gauge,
+        }
+    }
+
+    /// Returns the latest lag, None while it is unknown.
+    pub async fn current(&self) -> Option<ConsumerLag> {
+        self.lag.read().await.clone()
+    }
+
+    /// Fetches the offsets of the consumer group and updates the lag.
+    pub async fn refresh<S: OffsetSource>(
+        &self,
+        source: Arc<S>,
+        topic: String,
+    ) -> Result<(), KafkaError> {
+        let fetched_topic = topic.clone();
+        let offsets = tokio::task::spawn_blocking(move || source.partition_offsets(&fetched_topic))
+            .await
+            .map_err(|_| KafkaError::Canceled)??;
+        let lag = compute_lag(&topic, &offsets);
+        for partition in &lag.partitions {
+            self.gauge.record(
+                partition.lag as u64,
+                &[
+                    KeyValue::new("topic", topic.clone()),
+                    KeyValue::new("partition", i64::from(partition.partition)),
+                ],
+            );
+        }
+        *self.lag.write().await = Some(lag);
+        Ok(())
+    }
+
+    /// Refreshes the lag every `interval`.
+    pub async fn run<S: OffsetSource>(self, source: Arc<S>, topic: String, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        let mut failed_refreshes = 0;
+        loop {
+            ticker.tick().await;
+            self.refresh_or_expire(source.clone(), topic.clone(), &mut failed_refreshes)
+                .await;
+        }
+    }
+
+    /// Refreshes the lag, or forgets it once the refreshes kept failing so
+    /// an outdated lag is not served.
+    async fn refresh_or_expire<S: OffsetSource>(
+        &self,
+        source: Arc<S>,
+        topic: String,
+        failed_refreshes: &mut u32,
+    ) {
+        match self.refresh(source, topic).await {
+            Ok(()) => *failed_refreshes = 0,
+            Err(e) => {
+                error!("cannot fetch the consumer group offsets : {:?}", e);
+                *failed_refreshes += 1;
+                if *failed_refreshes == MAX_FAILED_REFRESHES {
+                    warn!(
+                        "the consumer lag is unknown after {} failed refreshes",
+                        MAX_FAILED_REFRESHES
+                    );
+                    *self.lag.write().await = None;
+                }
+            }
+        }
+    }
+}
+
+/// Serves the latest lag of the consumer group on `GET /lag`, or a 503 while
+/// it is unknown.
+pub async fn serve(monitor: LagMonitor, port: u16) {
+    let app = Router::new()
+        .route("/lag", get(get_lag))
+        .with_state(monitor);
+    let listener = match tokio::net::TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                "cannot bind the consumer lag server on port {} : {}",
+                port, e
+            );
+            return;
+        }
+    };
+    info!("serving the consumer lag on port {}", port);
+    if let Err(e) = axum::serve(listener, app).await {
+        error!("consumer lag server stopped : {}", e);
+    }
+}
+
+async fn get_lag(State(monitor): State<LagMonitor>) -> Result<Json<ConsumerLag>, StatusCode> {
+    monitor
+        .current()
+        .await
+        .map(Json)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Topic whose partitions receive and consume messages on demand.
+    #[derive(Default)]
+    struct MockTopic {
+        offsets: Mutex<Vec<PartitionOffsets>>,
+        /// True while the brokers can't be reached.
+        unreachable: Mutex<bool>,
+    }
+
+    impl MockTopic {
+        fn produce(&self, partition: i32, count: i64) {
+            let mut offsets = self.offsets.lock().unwrap();
+            match offsets.iter_mut().find(|o| o.partition == partition) {
+                Some(offsets) => offsets.high_watermark += count,
+                None => offsets.push(PartitionOffsets {
+                    partition,
+                    committed: None,
+                    low_watermark: 0,
+                    high_watermark: count,
+                }),
+            }
+        }
+
+        fn consume(&self, partition: i32, count: i64) {
+            let mut offsets = self.offsets.lock().unwrap();
+            let offsets = offsets
+                .iter_mut()
+                .find(|o| o.partition == partition)
+                .unwrap();
+            offsets.committed = Some(offsets.committed.unwrap_or(offsets.low_watermark) + count);
+        }
+    }
+
+    impl OffsetSource for MockTopic {
+        fn partition_offsets(&self, _topic: &str) -> Result<Vec<PartitionOffsets>, KafkaError> {
+            if *self.unreachable.lock().unwrap() {
+                return Err(KafkaError::Canceled);
+            }
+            Ok(self.offsets.lock().unwrap().clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lag_reflects_unconsumed_messages() {
+        let topic = Arc::new(MockTopic::default());
+        let monitor = LagMonitor::new();
+        // The lag is unknown until the offsets are fetched.
+        assert!(monitor.current().await.is_none());
+
+        topic.produce(0, 10);
+        topic.produce(1, 4);
+        monitor
+            .refresh(topic.clone(), "pragma-data".to_string())
+            .await
+            .unwrap();
+        let lag = monitor.current().await.unwrap();
+        assert_eq!(lag.topic, "pragma-data");
+        assert_eq!(lag.total, 14);
+
+        topic.consume(0, 7);
+        topic.consume(1, 4);
+        topic.produce(1, 2);
+        monitor
+            .refresh(topic.clone(), "pragma-data".to_string())
+            .await
+            .unwrap();
+        let lag = monitor.current().await.unwrap();
+        assert_eq!(
+            lag.partitions,
+            vec![
+                PartitionLag {
+                    partition: 0,
+                    lag: 3
+                },
+                PartitionLag {
+                    partition: 1,
+                    lag: 2
+                },
+            ]
+        );
+        assert_eq!(lag.total, 5);
+    }
+
+    #[tokio::test]
+    async fn test_lag_expires_after_repeated_failed_refreshes() {
+        let topic = Arc::new(MockTopic::default());
+        topic.produce(0, 10);
+        let monitor = LagMonitor::new();
+        let mut failed_refreshes = 0;
+        monitor
+            .refresh_or_expire(
+                topic.clone(),
+                "pragma-data".to_string(),
+                &mut failed_refreshes,
+            )
+            .await;
+        assert_eq!(monitor.current().await.unwrap().total, 10);
+
+        // The latest lag is kept through a few failures...
+        *topic.unreachable.lock().unwrap() = true;
+        for _ in 1..MAX_FAILED_REFRESHES {
+            monitor
+                .refresh_or_expire(
+                    topic.clone(),
+                    "pragma-data".to_string(),
+                    &mut failed_refreshes,
+                )
+                .await;
+            assert_eq!(monitor.current().await.unwrap().total, 10);
+        }
+        // ...but not served forever.
+        monitor
+            .refresh_or_expire(
+                topic.clone(),
+                "pragma-data".to_string(),
+                &mut failed_refreshes,
+            )
+            .await;
+        assert!(monitor.current().await.is_none());
+
+        *topic.unreachable.lock().unwrap() = false;
+        monitor
+            .refresh_or_expire(
+                topic.clone(),
+                "pragma-data".to_string(),
+                &mut failed_refreshes,
+            )
+            .await;
+        assert_eq!(monitor.current().await.unwrap().total, 10);
+        assert_eq!(failed_refreshes, 0);
+    }
+
+    #[test]
+    fn test_lag_ignores_messages_deleted_by_retention() {
+        let offsets = [PartitionOffsets {
+            partition: 0,
+            committed: Some(5),
+            low_watermark: 20,
+            high_watermark: 30,
+        }];
+        assert_eq!(compute_lag("pragma-data", &offsets).total, 10);
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use opentelemetry::metrics::Histogram;
+use opentelemetry::KeyValue;
+use pragma_entities::NewSourceLatency;
+
+/// Smoothing factor of the exponential moving average used for the rolling latency.
+/// The higher, the more weight is given to the latest observations.
+const LATENCY_SMOOTHING_FACTOR: f64 = 0.2;
+
+/// Tracks, per source, the rolling delay between the timestamp reported
+/// in the entries and the moment they are ingested.
+#[derive(Debug)]
+pub struct SourceLatencyTracker {
+    latencies: HashMap<String, f64>,
+    histogram: Histogram<f64>,
+}
+
+impl Default for SourceLatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceLatencyTracker {
+    pub fn new() -> Self {
+        let meter = opentelemetry::global::meter("pragma-ingestor-meter");
+        let histogram = meter
+            .f64_histogram("source_ingestion_latency")
+            .with_description("Delay between the entry timestamp and its ingestion, per source")
+            .with_unit("ms")
+            .init();
+
+        Self {
+            latencies: HashMap::new(),
+            histogram,
+        }
+    }
+
+    /// Records the latency of a newly ingested entry & returns the updated
+    /// rolling latency of its source.
+    pub fn record(&mut self, source: &str, latency_ms: f64) -> f64 {
+        let source = source.to_uppercase();
+        self.histogram
+            .record(latency_ms, &[KeyValue::new("source", source.clone())]);
+
+        let rolling_latency = match self.latencies.get(&source) {
+            Some(previous) => previous + LATENCY_SMOOTHING_FACTOR * (latency_ms - previous),
+            None => latency_ms,
+        };
+        self.latencies.insert(source, rolling_latency);
+        rolling_latency
+    }
+
+    /// Records the latency of entries ingested now.
+    pub fn record_entries(&mut self, entries: &[(String, NaiveDateTime)]) {
+        let ingested_at = Utc::now().naive_utc();
+        for (source, timestamp) in entries {
+            self.record(source, ingestion_latency_ms(*timestamp, ingested_at));
+        }
+    }
+
+    /// Returns the rolling latency of the source, if known.
+    pub fn latency_ms(&self, source: &str) -> Option<f64> {
+        self.latencies.get(&source.to_uppercase()).copied()
+    }
+
+    /// Returns the current rolling latencies of the provided sources, ready
+    /// to be stored.
+    pub fn snapshot(&self, sources: &[String]) -> Vec<NewSourceLatency> {
+        let updated_at = Utc::now().naive_utc();
+        let mut snapshot: Vec<NewSourceLatency> = sources
+            .iter()
+            .filter_map(|source| {
+                let source = source.to_uppercase();
+                self.latencies
+                    .get(&source)
+                    .map(|latency_ms| NewSourceLatency {
+                        source,
+                        latency_ms: *latency_ms,
+                        updated_at,
+                    })
+            })
+            .collect();
+        snapshot.sort_by(|a, b| a.source.cmp(&b.source));
+        snapshot.dedup_by(|a, b| a.source == b.source);
+        snapshot
+    }
+}
+
+/// Delay in milliseconds between the entry timestamp and its ingestion.
+/// Entries in the future are considered as having no latency.
+pub fn ingestion_latency_ms(timestamp: NaiveDateTime, ingested_at: NaiveDateTime) -> f64 {
+    (ingested_at - timestamp).num_milliseconds().max(0) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delayed_entry_increases_latency() {
+        let mut tracker = SourceLatencyTracker::new();
+
+        tracker.record("binance", 100.0);
+        tracker.record("binance", 100.0);
+        let before = tracker.latency_ms("BINANCE").unwrap();
+        assert_eq!(before, 100.0);
+
+        let after = tracker.record("binance", 5_000.0);
+        assert!(after > before);
+        assert_eq!(tracker.latency_ms("binance"), Some(after));
+
+        // Other sources are not impacted
+        tracker.record("okx", 50.0);
+        assert_eq!(tracker.latency_ms("okx"), Some(50.0));
+        assert_eq!(tracker.latency_ms("binance"), Some(after));
+    }
+
+    #[test]
+    fn test_ingestion_latency_ms() {
+        let ingested_at = chrono::DateTime::from_timestamp(1_718_000_010, 0)
+            .unwrap()
+            .naive_utc();
+        let timestamp = chrono::DateTime::from_timestamp(1_718_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        assert_eq!(ingestion_latency_ms(timestamp, ingested_at), 10_000.0);
+        assert_eq!(ingestion_latency_ms(ingested_at, timestamp), 0.0);
+    }
+}
@@ -1,14 +1,31 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
 use deadpool_diesel::postgres::Pool;
+use diesel::Connection;
 use dotenvy::dotenv;
 use pragma_entities::connection::ENV_OFFCHAIN_DATABASE_URL;
 use pragma_entities::{
-    adapt_infra_error, Entry, FutureEntry, InfraError, NewEntry, NewFutureEntry,
+    adapt_infra_error, is_valid_price, EntriesPayload, Entry, FutureEntry, InfraError, NewEntry,
+    NewFutureEntry, NewSourceLatency, PublisherPriority, SourceLatency,
 };
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex};
 use tracing::{error, info};
+
+use crate::concurrency::{insert_concurrency, BoundedTasks};
+use crate::dead_letter::DeadLetter;
+use crate::lag::LagMonitor;
+use crate::latency::SourceLatencyTracker;
+use crate::payload::{conflict_keys, decode_payload, verified_entries};
+
+mod concurrency;
 mod config;
 mod consumer;
+mod dead_letter;
 mod error;
+mod lag;
+mod latency;
+mod payload;
 
 #[tokio::main]
 #[tracing::instrument]
@@ -29,83 +46,190 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let pool = pragma_entities::connection::init_pool("pragma-ingestor", ENV_OFFCHAIN_DATABASE_URL)
         .expect("cannot connect to offchain database");
 
+    let latency_tracker = Arc::new(Mutex::new(SourceLatencyTracker::new()));
+    let dead_letter = match DeadLetter::from_config(&config::CONFIG).await {
+        Ok(dead_letter) => dead_letter,
+        Err(e) => {
+            error!("cannot create the dead-letter sink, logging the dead letters instead: {e}");
+            DeadLetter::Log
+        }
+    };
+    let dead_letter = Arc::new(dead_letter);
+
+    let concurrency = insert_concurrency(config::CONFIG.insert_concurrency, pool.status().max_size);
+    info!("inserting up to {} payloads concurrently", concurrency);
+    let mut inserts = BoundedTasks::new(concurrency);
+
     let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
-    tokio::spawn(consumer::consume(tx));
+    let kafka_consumer = Arc::new(consumer::create_consumer());
+    if let Some(port) = config::CONFIG.consumer_lag_port {
+        let lag_monitor = LagMonitor::new();
+        tokio::spawn(lag_monitor.clone().run(
+            kafka_consumer.clone(),
+            config::CONFIG.topic.clone(),
+            config::CONFIG.consumer_lag_refresh_interval(),
+        ));
+        tokio::spawn(lag::serve(lag_monitor, port));
+    }
+    tokio::spawn(consumer::consume(kafka_consumer, tx));
     loop {
         while let Some(payload) = rx.recv().await {
-            if let Err(e) = process_payload(&pool, payload).await {
-                error!("error while processing payload: {:?}", e);
-            }
+            let pool = pool.clone();
+            let latency_tracker = latency_tracker.clone();
+            let dead_letter = dead_letter.clone();
+            let decoded = decode_payload(&payload);
+            // Payloads upserting the same entries are inserted in the order
+            // they were consumed, so the publisher priority ties are resolved
+            // as if they were inserted one by one.
+            let keys = decoded.as_ref().map(conflict_keys).unwrap_or_default();
+            inserts
+                .spawn(keys, async move {
+                    if let Err(e) =
+                        process_payload(&pool, &latency_tracker, &dead_letter, payload, decoded)
+                            .await
+                    {
+                        error!("error while processing payload: {:?}", e);
+                    }
+                })
+                .await;
         }
+        inserts.join_all().await;
     }
 }
 
-#[tracing::instrument(skip(pool, payload))]
-async fn process_payload(pool: &Pool, payload: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
-    let decoded_payload = String::from_utf8_lossy(&payload);
-    let is_future_entries = decoded_payload.contains("expiration_timestamp");
-    if is_future_entries {
-        match serde_json::from_slice::<Vec<NewFutureEntry>>(&payload) {
-            Ok(future_entries) => {
-                if !future_entries.is_empty() {
-                    if let Err(e) = insert_future_entries(pool, future_entries).await {
-                        error!("error while inserting future entries : {:?}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to deserialize payload: {:?}", e);
+#[tracing::instrument(skip(pool, latency_tracker, dead_letter, payload, decoded))]
+async fn process_payload(
+    pool: &Pool,
+    latency_tracker: &Mutex<SourceLatencyTracker>,
+    dead_letter: &DeadLetter,
+    payload: Vec<u8>,
+    decoded: Result<EntriesPayload, serde_json::Error>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let decoded = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            error!("Failed to deserialize payload: {:?}", e);
+            if let Err(e) = dead_letter.send(&payload, &e.to_string()).await {
+                error!("cannot send payload to the dead-letter sink: {:?}", e);
             }
+            return Ok(());
         }
-    } else {
-        match serde_json::from_slice::<Vec<NewEntry>>(&payload) {
-            Ok(entries) => {
-                info!("[SPOT] total of '{}' new entries available.", entries.len());
-                if let Err(e) = insert_spot_entries(pool, entries).await {
-                    error!("error while inserting entries : {:?}", e);
-                }
-            }
-            Err(e) => {
-                error!("Failed to deserialize payload: {:?}", e);
+    };
+    let (entries, future_entries) = match verified_entries(decoded) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Dropping payload: {:?}", e);
+            if let Err(e) = dead_letter.send(&payload, &e.to_string()).await {
+                error!("cannot send payload to the dead-letter sink: {:?}", e);
             }
+            return Ok(());
+        }
+    };
+
+    let entries = drop_invalid_prices(
+        entries,
+        |e| (e.pair_id.as_str(), &e.price),
+        &config::CONFIG.non_positive_price_pairs,
+    );
+    let future_entries = drop_invalid_prices(
+        future_entries,
+        |e| (e.pair_id.as_str(), &e.price),
+        &config::CONFIG.non_positive_price_pairs,
+    );
+    let (perp_entries, future_entries) = split_perp_entries(future_entries);
+
+    info!("[SPOT] {} new entries available", entries.len());
+    info!("[PERP] {} new entries available", perp_entries.len());
+    info!("[FUTURE] {} new entries available", future_entries.len());
+    if entries.is_empty() && perp_entries.is_empty() && future_entries.is_empty() {
+        return Ok(());
+    }
+
+    let observed = entries
+        .iter()
+        .map(|entry| (entry.source.clone(), entry.timestamp))
+        .chain(
+            perp_entries
+                .iter()
+                .chain(&future_entries)
+                .map(|entry| (entry.source.clone(), entry.timestamp)),
+        )
+        .collect::<Vec<_>>();
+    match insert_all_entries(pool, entries, perp_entries, future_entries).await {
+        Ok(count) => {
+            info!("{} entries inserted", count);
+            track_sources_latency(pool, latency_tracker, observed).await;
         }
+        Err(e) => error!("error while inserting entries : {:?}", e),
     }
     Ok(())
 }
 
+/// Drops the entries with a non positive price, unless their pair is
+/// allowed to have such prices.
+fn drop_invalid_prices<T, F>(entries: Vec<T>, pair_and_price: F, allowed_pairs: &[String]) -> Vec<T>
+where
+    F: Fn(&T) -> (&str, &BigDecimal),
+{
+    entries
+        .into_iter()
+        .filter(|entry| {
+            let (pair_id, price) = pair_and_price(entry);
+            let is_valid = is_valid_price(pair_id, price, allowed_pairs);
+            if !is_valid {
+                error!(
+                    "dropping entry of {} with a non positive price: {}",
+                    pair_id, price
+                );
+            }
+            is_valid
+        })
+        .collect()
+}
+
+/// Updates the rolling latency of the sources of the freshly inserted entries
+/// and stores it so it can be exposed by the node.
+#[tracing::instrument(skip(pool, latency_tracker, observed))]
+async fn track_sources_latency(
+    pool: &Pool,
+    latency_tracker: &Mutex<SourceLatencyTracker>,
+    observed: Vec<(String, NaiveDateTime)>,
+) {
+    let sources: Vec<String> = observed.iter().map(|(source, _)| source.clone()).collect();
+    let latencies = {
+        let mut latency_tracker = latency_tracker.lock().await;
+        latency_tracker.record_entries(&observed);
+        latency_tracker.snapshot(&sources)
+    };
+    if let Err(e) = insert_sources_latencies(pool, latencies).await {
+        error!("error while storing sources latencies : {:?}", e);
+    }
+}
+
 #[tracing::instrument(skip(pool))]
-pub async fn insert_spot_entries(
+pub async fn insert_sources_latencies(
     pool: &Pool,
-    new_entries: Vec<NewEntry>,
+    latencies: Vec<NewSourceLatency>,
 ) -> Result<(), InfraError> {
+    if latencies.is_empty() {
+        return Ok(());
+    }
     let conn = pool.get().await.map_err(adapt_infra_error)?;
-    let entries = conn
-        .interact(move |conn| Entry::create_many(conn, new_entries))
+    conn.interact(move |conn| SourceLatency::upsert_many(conn, latencies))
         .await
         .map_err(adapt_infra_error)?
         .map_err(adapt_infra_error)?;
-
-    for entry in &entries {
-        info!(
-            "new entry created {} - {}({}) - {}",
-            entry.publisher, entry.pair_id, entry.price, entry.source
-        );
-    }
-
     Ok(())
 }
 
-#[tracing::instrument(skip(pool))]
-pub async fn insert_future_entries(
-    pool: &Pool,
-    new_entries: Vec<NewFutureEntry>,
-) -> Result<(), InfraError> {
-    let conn = pool.get().await.map_err(adapt_infra_error)?;
-
-    // Double check that we don't have expiration_timestamp set to 0,
-    // if we do, we set them to NULL to be extra clear in the database
-    // those future entries are perp entries.
-    let new_entries = new_entries
+/// Splits the future entries between perp entries, which have no expiration
+/// timestamp, and the other future entries.
+/// An expiration timestamp set to 0 is cleared to be extra clear in the
+/// database that the entry is a perp entry.
+fn split_perp_entries(
+    future_entries: Vec<NewFutureEntry>,
+) -> (Vec<NewFutureEntry>, Vec<NewFutureEntry>) {
+    future_entries
         .into_iter()
         .map(|mut entry| {
             if let Some(expiration_timestamp) = entry.expiration_timestamp {
@@ -115,29 +239,161 @@ pub async fn insert_future_entries(
             }
             entry
         })
-        .collect::<Vec<_>>();
-
-    let len_perp_entries = new_entries
-        .iter()
-        .filter(|entry| entry.expiration_timestamp.is_none())
-        .count();
+        .partition(|entry| entry.expiration_timestamp.is_none())
+}
 
-    info!("[PERP] {} new entries available", len_perp_entries);
-    info!(
-        "[FUTURE] {} new entries available",
-        new_entries.len() - len_perp_entries
+/// Inserts the spot, perp and future entries of a payload in a single
+/// transaction: either all of them are stored or none are.
+/// Returns the number of stored entries.
+#[tracing::instrument(skip(pool))]
+pub async fn insert_all_entries(
+    pool: &Pool,
+    spot_entries: Vec<NewEntry>,
+    perp_entries: Vec<NewFutureEntry>,
+    future_entries: Vec<NewFutureEntry>,
+) -> Result<usize, InfraError> {
+    let priority = PublisherPriority::new(config::CONFIG.publisher_priority.clone());
+    // A conflict target can't be affected twice by the same upsert so we
+    // only keep the entry of the best ranked publisher per key.
+    let spot_entries = priority.dedup_by_key(
+        spot_entries,
+        |e| (e.pair_id.clone(), e.source.clone(), e.timestamp),
+        |e| e.publisher.as_str(),
     );
+    let future_entries_key = |e: &NewFutureEntry| {
+        (
+            e.pair_id.clone(),
+            e.source.clone(),
+            e.timestamp,
+            e.expiration_timestamp,
+        )
+    };
+    let perp_entries =
+        priority.dedup_by_key(perp_entries, future_entries_key, |e| e.publisher.as_str());
+    let future_entries =
+        priority.dedup_by_key(future_entries, future_entries_key, |e| e.publisher.as_str());
 
-    let entries = conn
-        .interact(move |conn| FutureEntry::create_many(conn, new_entries))
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    let (entries, future_entries) = conn
+        .interact(move |conn| {
+            conn.transaction(|conn| {
+                let mut entries = Vec::new();
+                if !spot_entries.is_empty() {
+                    entries = Entry::create_many(conn, spot_entries, &priority)?;
+                }
+                let mut created_future_entries = Vec::new();
+                for new_entries in [perp_entries, future_entries] {
+                    if !new_entries.is_empty() {
+                        created_future_entries.extend(FutureEntry::create_many(
+                            conn,
+                            new_entries,
+                            &priority,
+                        )?);
+                    }
+                }
+                Ok::<_, diesel::result::Error>((entries, created_future_entries))
+            })
+        })
         .await
         .map_err(adapt_infra_error)?
         .map_err(adapt_infra_error)?;
+
     for entry in &entries {
+        info!(
+            "new entry created {} - {}({}) - {}",
+            entry.publisher, entry.pair_id, entry.price, entry.source
+        );
+    }
+    for entry in &future_entries {
         info!(
             "new future entry created {} - {}({}) - {}",
             entry.publisher, entry.pair_id, entry.price, entry.source
         );
     }
-    Ok(())
+    Ok(entries.len() + future_entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pair_id: &str, price: i64) -> NewEntry {
+        NewEntry {
+            pair_id: pair_id.to_string(),
+            publisher: "PRAGMA".to_string(),
+            source: "BINANCE".to_string(),
+            timestamp: chrono::DateTime::from_timestamp(1718000000, 0)
+                .unwrap()
+                .naive_utc(),
+            publisher_signature: "0x0".to_string(),
+            price: BigDecimal::from(price),
+            bid: None,
+            ask: None,
+        }
+    }
+
+    fn future_entry(pair_id: &str, expiration_timestamp: Option<i64>) -> NewFutureEntry {
+        NewFutureEntry {
+            pair_id: pair_id.to_string(),
+            publisher: "PRAGMA".to_string(),
+            source: "BINANCE".to_string(),
+            timestamp: chrono::DateTime::from_timestamp(1718000000, 0)
+                .unwrap()
+                .naive_utc(),
+            expiration_timestamp: expiration_timestamp.map(|timestamp| {
+                chrono::DateTime::from_timestamp(timestamp, 0)
+                    .unwrap()
+                    .naive_utc()
+            }),
+            publisher_signature: "0x0".to_string(),
+            price: BigDecimal::from(100),
+        }
+    }
+
+    fn pairs(entries: &[NewEntry]) -> Vec<(&str, BigDecimal)> {
+        entries
+            .iter()
+            .map(|e| (e.pair_id.as_str(), e.price.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_invalid_prices() {
+        let entries = vec![
+            entry("BTC/USD", 100),
+            entry("ETH/USD", 0),
+            entry("SOL/USD", -1),
+            entry("POWER/EUR", -5),
+        ];
+        let allowed = vec!["POWER/EUR".to_string()];
+
+        let kept = drop_invalid_prices(entries, |e| (e.pair_id.as_str(), &e.price), &allowed);
+
+        assert_eq!(
+            pairs(&kept),
+            vec![
+                ("BTC/USD", BigDecimal::from(100)),
+                ("POWER/EUR", BigDecimal::from(-5))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_perp_entries() {
+        let (perp_entries, future_entries) = split_perp_entries(vec![
+            future_entry("BTC/USD", None),
+            future_entry("ETH/USD", Some(0)),
+            future_entry("BTC/USD", Some(1719000000)),
+        ]);
+
+        assert_eq!(
+            perp_entries
+                .iter()
+                .map(|e| (e.pair_id.as_str(), e.expiration_timestamp))
+                .collect::<Vec<_>>(),
+            vec![("BTC/USD", None), ("ETH/USD", None)]
+        );
+        assert_eq!(future_entries.len(), 1);
+        assert!(future_entries[0].expiration_timestamp.is_some());
+    }
 }
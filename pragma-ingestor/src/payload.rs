@@ -0,0 +1,173 @@
+use pragma_entities::{EntriesPayload, NewEntry, NewFutureEntry};
+
+use crate::error::VerificationError;
+
+/// Decodes a payload consumed from Kafka.
+/// Payloads published before the envelope was introduced are plain arrays of
+/// entries: they are decoded as spot entries, then as future entries.
+pub fn decode_payload(payload: &[u8]) -> Result<EntriesPayload, serde_json::Error> {
+    let envelope_error = match serde_json::from_slice::<EntriesPayload>(payload) {
+        Ok(decoded) => return Ok(decoded),
+        Err(e) => e,
+    };
+    if let Ok(entries) = serde_json::from_slice(payload) {
+        return Ok(EntriesPayload::Spot(entries));
+    }
+    if let Ok(future_entries) = serde_json::from_slice(payload) {
+        return Ok(EntriesPayload::Future(future_entries));
+    }
+    Err(envelope_error)
+}
+
+/// Returns the pairs & sources of the entries of the payload, which cover the
+/// conflict targets of their upserts.
+pub fn conflict_keys(payload: &EntriesPayload) -> Vec<(String, String)> {
+    let key = |pair_id: &str, source: &str| (pair_id.to_string(), source.to_string());
+    match payload {
+        EntriesPayload::Spot(entries) => {
+            entries.iter().map(|e| key(&e.pair_id, &e.source)).collect()
+        }
+        EntriesPayload::PendingSpot(pending) => pending
+            .entries
+            .iter()
+            .map(|e| key(&e.pair_id, &e.source))
+            .collect(),
+        EntriesPayload::Future(entries) => {
+            entries.iter().map(|e| key(&e.pair_id, &e.source)).collect()
+        }
+    }
+}
+
+/// Returns the spot & future entries of the payload.
+/// Entries whose signature verification was deferred by the node are only
+/// returned if their signature is valid.
+pub fn verified_entries(
+    payload: EntriesPayload,
+) -> Result<(Vec<NewEntry>, Vec<NewFutureEntry>), VerificationError> {
+    match payload {
+        EntriesPayload::Spot(entries) => Ok((entries, Vec::new())),
+        EntriesPayload::Future(future_entries) => Ok((Vec::new(), future_entries)),
+        EntriesPayload::PendingSpot(pending) => {
+            if !pending.signature.is_valid() {
+                return Err(VerificationError::InvalidSignature(format!(
+                    "{:#x}",
+                    pending.signature.message_hash
+                )));
+            }
+            Ok((pending.entries, Vec::new()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+    use starknet::signers::SigningKey;
+
+    use super::*;
+
+    const SPOT_ENTRY: &str = r#"{
+        "pair_id": "BTC/USD",
+        "publisher": "PRAGMA",
+        "source": "BINANCE",
+        "timestamp": "2024-06-10T06:13:20",
+        "publisher_signature": "0x0",
+        "price": "100"
+    }"#;
+
+    const FUTURE_ENTRY: &str = r#"{
+        "pair_id": "BTC/USD",
+        "publisher": "PRAGMA",
+        "source": "BINANCE",
+        "timestamp": "2024-06-10T06:13:20",
+        "expiration_timestamp": null,
+        "publisher_signature": "0x0",
+        "price": "100"
+    }"#;
+
+    #[test]
+    fn test_enveloped_payloads_are_decoded() {
+        let spot = format!(r#"{{"type": "spot", "entries": [{SPOT_ENTRY}]}}"#);
+        assert!(matches!(
+            decode_payload(spot.as_bytes()),
+            Ok(EntriesPayload::Spot(entries)) if entries.len() == 1
+        ));
+
+        let future = format!(r#"{{"type": "future", "entries": [{FUTURE_ENTRY}]}}"#);
+        assert!(matches!(
+            decode_payload(future.as_bytes()),
+            Ok(EntriesPayload::Future(entries)) if entries.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_legacy_payloads_are_decoded() {
+        let spot = format!("[{SPOT_ENTRY}, {SPOT_ENTRY}]");
+        assert!(matches!(
+            decode_payload(spot.as_bytes()),
+            Ok(EntriesPayload::Spot(entries)) if entries.len() == 2
+        ));
+
+        // Perp entries have no expiration timestamp, but still have the field.
+        let future = format!("[{FUTURE_ENTRY}]");
+        assert!(matches!(
+            decode_payload(future.as_bytes()),
+            Ok(EntriesPayload::Future(entries)) if entries.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_invalid_payloads_are_rejected() {
+        assert!(decode_payload(b"not json").is_err());
+        assert!(decode_payload(br#"{"type": "option", "entries": []}"#).is_err());
+    }
+
+    /// Returns a payload of entries pending verification, signed by `signer`
+    /// for the message hash `signed_hash`.
+    fn pending_payload(signer: &SigningKey, signed_hash: Felt, message_hash: Felt) -> Vec<u8> {
+        let signature = signer.sign(&signed_hash).unwrap();
+        format!(
+            r#"{{"type": "pending_spot", "entries": {{
+                "signature": {{"public_key": "{:#x}", "message_hash": "{:#x}", "r": "{:#x}", "s": "{:#x}"}},
+                "entries": [{SPOT_ENTRY}]
+            }}}}"#,
+            signer.verifying_key().scalar(),
+            message_hash,
+            signature.r,
+            signature.s,
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_pending_entries_with_a_valid_signature_are_kept() {
+        let signer = SigningKey::from_random();
+        let payload = pending_payload(&signer, Felt::from(42), Felt::from(42));
+
+        let (entries, future_entries) =
+            verified_entries(decode_payload(&payload).unwrap()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(future_entries.is_empty());
+    }
+
+    #[test]
+    fn test_pending_entries_with_a_bad_signature_are_dropped() {
+        let signer = SigningKey::from_random();
+        // Signature of another message than the published one.
+        let payload = pending_payload(&signer, Felt::from(43), Felt::from(42));
+
+        assert!(matches!(
+            verified_entries(decode_payload(&payload).unwrap()),
+            Err(VerificationError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_conflict_keys_are_the_pairs_and_sources() {
+        let spot = format!(r#"{{"type": "spot", "entries": [{SPOT_ENTRY}]}}"#);
+        assert_eq!(
+            conflict_keys(&decode_payload(spot.as_bytes()).unwrap()),
+            vec![("BTC/USD".to_string(), "BINANCE".to_string())]
+        );
+    }
+}
@@ -1,15 +1,21 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Arc;
 use std::time::Duration;
 
 use moka::future::Cache;
 use pragma_common::types::merkle_tree::MerkleTree;
+use pragma_entities::{dto, InfraError};
 
 use crate::constants::caches::{
-    MERKLE_FEED_TREE_CACHE_TIME_TO_IDLE_IN_SECONDS, MERKLE_FEED_TREE_CACHE_TIME_TO_LIVE_IN_SECONDS,
+    DECIMALS_CACHE_TIME_TO_LIVE_IN_SECONDS, MERKLE_FEED_TREE_CACHE_TIME_TO_IDLE_IN_SECONDS,
+    MERKLE_FEED_TREE_CACHE_TIME_TO_LIVE_IN_SECONDS, ONCHAIN_DECIMALS_CACHE_TIME_TO_LIVE_IN_SECONDS,
     PUBLISHERS_UDPATES_CACHE_TIME_TO_IDLE_IN_SECONDS,
     PUBLISHERS_UDPATES_CACHE_TIME_TO_LIVE_IN_SECONDS,
 };
 use crate::infra::repositories::onchain_repository::publisher::RawPublisherUpdates;
+use crate::utils::VerifiedSignature;
 
 /// Structure responsible of holding our Databases caches.
 /// All the caches are initialized empty with their associated time to live in the
@@ -18,11 +24,16 @@ use crate::infra::repositories::onchain_repository::publisher::RawPublisherUpdat
 pub struct CacheRegistry {
     onchain_publishers_updates: Cache<String, HashMap<String, RawPublisherUpdates>>,
     merkle_feed_tree: Cache<u64, MerkleTree>,
+    verified_signatures: Option<Cache<VerifiedSignature, ()>>,
+    onchain_decimals: Cache<String, u32>,
+    decimals: Cache<String, u32>,
+    publishers: Cache<String, dto::Publisher>,
 }
 
 impl CacheRegistry {
     /// Initialize all of our caches empty.
-    pub fn new() -> Self {
+    /// The verified signatures cache is disabled if no time to live is provided.
+    pub fn new(verified_signatures_ttl: Option<Duration>, publishers_ttl: Duration) -> Self {
         let onchain_publishers_updates_cache = Cache::builder()
             .time_to_live(Duration::from_secs(
                 PUBLISHERS_UDPATES_CACHE_TIME_TO_LIVE_IN_SECONDS,
@@ -41,9 +52,28 @@ impl CacheRegistry {
             ))
             .build();
 
+        let verified_signatures_cache =
+            verified_signatures_ttl.map(|ttl| Cache::builder().time_to_live(ttl).build());
+
+        let onchain_decimals_cache = Cache::builder()
+            .time_to_live(Duration::from_secs(
+                ONCHAIN_DECIMALS_CACHE_TIME_TO_LIVE_IN_SECONDS,
+            ))
+            .build();
+
+        let decimals_cache = Cache::builder()
+            .time_to_live(Duration::from_secs(DECIMALS_CACHE_TIME_TO_LIVE_IN_SECONDS))
+            .build();
+
+        let publishers_cache = Cache::builder().time_to_live(publishers_ttl).build();
+
         CacheRegistry {
             onchain_publishers_updates: onchain_publishers_updates_cache,
             merkle_feed_tree: merkle_feed_tree_cache,
+            verified_signatures: verified_signatures_cache,
+            onchain_decimals: onchain_decimals_cache,
+            decimals: decimals_cache,
+            publishers: publishers_cache,
         }
     }
 
@@ -56,4 +86,159 @@ impl CacheRegistry {
     pub fn merkle_feeds_tree(&self) -> &Cache<u64, MerkleTree> {
         &self.merkle_feed_tree
     }
+
+    pub fn verified_signatures(&self) -> Option<&Cache<VerifiedSignature, ()>> {
+        self.verified_signatures.as_ref()
+    }
+
+    pub fn onchain_decimals(&self) -> &Cache<String, u32> {
+        &self.onchain_decimals
+    }
+
+    pub fn decimals(&self) -> &Cache<String, u32> {
+        &self.decimals
+    }
+
+    pub fn publishers(&self) -> &Cache<String, dto::Publisher> {
+        &self.publishers
+    }
+
+    /// Removes the cached publishers that differ from their database row,
+    /// e.g when they were deactivated or rotated their keys, so they are
+    /// fetched again on their next publish.
+    pub async fn sync_publishers(&self, publishers: &[dto::Publisher]) {
+        let publishers: HashMap<&str, &dto::Publisher> = publishers
+            .iter()
+            .map(|publisher| (publisher.name.as_str(), publisher))
+            .collect();
+        let updated: Vec<Arc<String>> = self
+            .publishers
+            .iter()
+            .filter(|(name, cached)| publishers.get(name.as_str()) != Some(&cached))
+            .map(|(name, _)| name)
+            .collect();
+        for name in updated {
+            self.publishers.invalidate(name.as_str()).await;
+        }
+    }
+}
+
+/// Returns the cached value of `key`, or fetches it with `fetch` and caches it.
+/// Concurrent misses on the same key wait for a single fetch, and failed
+/// fetches are not cached.
+pub async fn get_or_fetch<K, V, Fut>(
+    cache: &Cache<K, V>,
+    key: K,
+    fetch: Fut,
+) -> Result<V, InfraError>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<V, InfraError>>,
+{
+    cache
+        .try_get_with(key, fetch)
+        .await
+        .map_err(|e| Arc::try_unwrap(e).unwrap_or_else(|shared| shared_infra_error(&shared)))
+}
+
+/// Rebuilds the error of a fetch shared by several callers.
+fn shared_infra_error(error: &InfraError) -> InfraError {
+    match error {
+        InfraError::Unavailable => InfraError::Unavailable,
+        InfraError::RoutingError => InfraError::RoutingError,
+        InfraError::NotFound => InfraError::NotFound,
+        InfraError::InvalidTimestamp(e) => InfraError::InvalidTimestamp(e.clone()),
+        _ => InfraError::InternalServerError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_or_fetch_caches_successful_fetches_only() {
+        let cache: Cache<String, u32> = Cache::new(16);
+        let fetches = AtomicUsize::new(0);
+        let fetches_ref = &fetches;
+        let fetch = move |result: Result<u32, InfraError>| async move {
+            fetches_ref.fetch_add(1, Ordering::SeqCst);
+            result
+        };
+
+        assert!(matches!(
+            get_or_fetch(
+                &cache,
+                "BTC/USD".to_string(),
+                fetch(Err(InfraError::NotFound))
+            )
+            .await,
+            Err(InfraError::NotFound)
+        ));
+        for _ in 0..2 {
+            let value = get_or_fetch(&cache, "BTC/USD".to_string(), fetch(Ok(8)))
+                .await
+                .unwrap();
+            assert_eq!(value, 8);
+        }
+        // The failure was not cached, the value then came from the cache.
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.get("BTC/USD").await, Some(8));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_shares_concurrent_misses() {
+        let cache: Cache<String, u32> = Cache::new(16);
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            Ok::<u32, InfraError>(18)
+        };
+
+        let (first, second) = tokio::join!(
+            get_or_fetch(&cache, "ETH/USD".to_string(), fetch()),
+            get_or_fetch(&cache, "ETH/USD".to_string(), fetch()),
+        );
+        assert_eq!((first.unwrap(), second.unwrap()), (18, 18));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_updated_publisher_is_fetched_again() {
+        let caches = CacheRegistry::new(None, Duration::from_secs(60));
+        let publisher = |active_key: &str| dto::Publisher {
+            id: uuid::Uuid::nil(),
+            name: "PRAGMA".to_string(),
+            master_key: "0x1".to_string(),
+            active_key: active_key.to_string(),
+            account_address: "0x3".to_string(),
+            active: true,
+        };
+        let get = |active_key: &str| {
+            get_or_fetch(
+                caches.publishers(),
+                "PRAGMA".to_string(),
+                std::future::ready(Ok(publisher(active_key))),
+            )
+        };
+
+        assert_eq!(get("0x2").await.unwrap().active_key, "0x2");
+        assert_eq!(get("0x4").await.unwrap().active_key, "0x2");
+
+        // Unchanged publishers stay cached.
+        caches.sync_publishers(&[publisher("0x2")]).await;
+        assert_eq!(get("0x4").await.unwrap().active_key, "0x2");
+
+        // e.g after the publisher rotated its key.
+        caches.sync_publishers(&[publisher("0x4")]).await;
+        assert_eq!(get("0x4").await.unwrap().active_key, "0x4");
+
+        // Deleted publishers are evicted too.
+        caches.sync_publishers(&[]).await;
+        assert_eq!(get("0x5").await.unwrap().active_key, "0x5");
+    }
 }
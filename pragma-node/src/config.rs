@@ -1,7 +1,26 @@
+use std::num::{NonZeroU32, NonZeroU64};
+use std::str::FromStr;
+
+use nonzero_ext::nonzero;
+use pragma_common::types::Network;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use tokio::sync::OnceCell;
 
+use crate::constants::caches::{
+    PUBLISHERS_CACHE_SYNC_INTERVAL_IN_SECONDS, PUBLISHERS_CACHE_TIME_TO_LIVE_IN_SECONDS,
+    VERIFIED_SIGNATURES_CACHE_TIME_TO_LIVE_IN_SECONDS,
+};
+use crate::constants::others::{
+    DEFAULT_BLENDED_PERP_WEIGHT, DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+    DEFAULT_ONCHAIN_CIRCUIT_BREAKER_COOLDOWN_IN_SECONDS, DEFAULT_PUBLISHER_REPUTATION,
+    DEFAULT_TIME_ORACLE_REFRESH_INTERVAL_IN_SECONDS, DEFAULT_TIME_ORACLE_TIMEOUT_IN_MS,
+    DEFAULT_TWAP_LOOKBACK_IN_SECONDS, DEFAULT_WS_BYTES_LIMIT_PER_IP_PER_SECOND,
+    DEFAULT_WS_HEARTBEAT_INTERVAL_IN_SECONDS,
+};
+
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     host: String,
     port: u16,
@@ -17,6 +36,7 @@ impl Default for ServerConfig {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct KafkaConfig {
     pub topic: String,
 }
@@ -30,6 +50,7 @@ impl Default for KafkaConfig {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct RedisConfig {
     redis_host: String,
     redis_port: u16,
@@ -44,6 +65,233 @@ impl Default for RedisConfig {
     }
 }
 
+#[derive(Default, Debug, Deserialize)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// Pairs that can be signed by the Pragma signer, e.g `BTC/USD,ETH/USD`.
+    /// When not set, all the pairs are signable.
+    signable_pairs: Option<Vec<String>>,
+    /// Time to live of the verified publishers signatures cache.
+    /// Set it to 0 to always verify the signatures.
+    verified_signatures_cache_ttl_in_seconds: Option<u64>,
+    /// If true, the prices sent on the entry websocket are signed all at once
+    /// through the merkle root of their hashes instead of one by one.
+    batch_signing: Option<bool>,
+    /// If true, the signatures of the published entries are verified by the
+    /// ingestor instead of in the request path.
+    async_signature_verification: Option<bool>,
+    /// Time to live of the cached publishers keys & addresses.
+    publishers_cache_ttl_in_seconds: Option<u64>,
+    /// Interval between two syncs of the cached publishers with the database.
+    publishers_cache_sync_interval_in_seconds: Option<NonZeroU64>,
+    /// Url of the trusted time source used to timestamp the signed prices.
+    /// When not set, the local clock is trusted.
+    time_oracle_url: Option<String>,
+    /// Interval between two synchronizations with the time oracle.
+    time_oracle_refresh_interval_in_seconds: Option<u64>,
+    /// Timeout of the requests to the time oracle.
+    time_oracle_timeout_in_ms: Option<u64>,
+    /// Maximum age of the Pragma signer key before it is reloaded, so a
+    /// rotation is picked up. When not set, it's only reloaded on SIGHUP.
+    signer_key_max_age_in_seconds: Option<NonZeroU64>,
+    /// Prices older than this are not signed. When not set, prices are
+    /// signed whatever their age.
+    max_signed_price_age_in_seconds: Option<u64>,
+    /// Maximum age of the signed prices per pair, overriding the default one,
+    /// e.g `BTC/USD=30,LORDS/USD=600`.
+    max_signed_price_age_per_pair: Option<Vec<String>>,
+}
+
+impl SigningConfig {
+    /// Checks the settings whose malformed values would otherwise be ignored.
+    fn validate(&self) -> Result<(), String> {
+        for setting in self.max_signed_price_age_per_pair.iter().flatten() {
+            if parse_setting::<u64>(setting).is_none() {
+                return Err(format!(
+                    "MAX_SIGNED_PRICE_AGE_PER_PAIR expects PAIR=seconds, got {setting}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default)]
+pub struct PairsConfig {
+    /// If true, requesting a pair whose base and quote are identical returns
+    /// a constant price of 1 instead of being rejected.
+    return_identity_price: bool,
+    /// Pairs for which zero prices are accepted on publish, e.g `POWER/EUR`.
+    non_positive_price_pairs: Vec<String>,
+    /// Deprecated pairs and the pair replacing them, e.g `MATIC/USD=POL/USD`.
+    deprecated_pairs: Vec<String>,
+    /// Tags grouping the pairs, e.g `BTC/USD=majors,ETH/USD=majors,ETH/USD=defi`.
+    pair_tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PublishConfig {
+    /// Published entries older than this are rejected.
+    max_entry_age_in_seconds: u64,
+    /// Published entries further than this in the future are rejected.
+    max_entry_future_drift_in_seconds: u64,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        Self {
+            max_entry_age_in_seconds: 60 * 60,
+            max_entry_future_drift_in_seconds: 5 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Pairs whose ingestion lag is monitored, e.g `BTC/USD,ETH/USD`.
+    /// The ingestion lag is not monitored when empty.
+    ingestion_lag_pairs: Vec<String>,
+    /// Ingestion lag above which the node is reported as degraded.
+    ingestion_lag_threshold_in_seconds: u64,
+    /// If true, the node is reported as unavailable on
+    /// `/node/v1/health/dependencies` when Redis can't be reached.
+    redis_required: bool,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            ingestion_lag_pairs: vec![],
+            ingestion_lag_threshold_in_seconds: 5 * 60,
+            redis_required: false,
+        }
+    }
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default)]
+pub struct WebSocketConfig {
+    /// Maximum number of bytes that can be sent per second per IP address
+    /// on a websocket.
+    ws_bytes_limit_per_ip_per_second: Option<u32>,
+    /// Maximum number of concurrent websocket connections on the node.
+    /// Unlimited when not set.
+    max_ws_connections: Option<usize>,
+    /// Interval between two pings sent to the websocket clients.
+    ws_heartbeat_interval_in_seconds: Option<NonZeroU64>,
+    /// If true, the periodic updates are skipped while the previous messages
+    /// are not flushed to the client, and the next update is flagged as
+    /// coalesced.
+    ws_coalesce_updates: bool,
+    /// Maximum number of pairs, spot and perp combined, a websocket
+    /// connection can subscribe to.
+    max_subscriptions_per_connection: Option<usize>,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default)]
+pub struct AggregationConfig {
+    /// Maximum number of sources aggregated per pair, by every pricer. When
+    /// more sources are available, only the ones with the most recent prices
+    /// are used, with all their publishers.
+    max_sources_per_pair: Option<usize>,
+    /// Maximum relative spread between the lowest and the highest price of
+    /// the sources of a pair, e.g 0.05 for 5% of the median price. Above it,
+    /// the aggregation is rejected unless the caller opts in to best effort.
+    max_sources_spread: Option<f64>,
+    /// Lookback window of the TWAP, so it never scans the whole history.
+    /// Widened to the requested interval if it is shorter.
+    twap_default_lookback_in_seconds: Option<u64>,
+    /// Default weight of the perp mark price in the blended spot & perp
+    /// price, between 0 and 1.
+    blended_perp_weight: Option<f64>,
+    /// Reputation score of the publishers, between 0 and 1, returned along
+    /// with their prices e.g `PRAGMA=1,SKYNET=0.3`.
+    publisher_reputations: Option<Vec<String>>,
+}
+
+impl AggregationConfig {
+    /// Checks the values that are only valid within a range.
+    fn validate(&self) -> Result<(), String> {
+        if let Some(weight) = self.blended_perp_weight {
+            if !(0.0..=1.0).contains(&weight) {
+                return Err(format!(
+                    "BLENDED_PERP_WEIGHT must be between 0 and 1, got {weight}"
+                ));
+            }
+        }
+        for setting in self.publisher_reputations.iter().flatten() {
+            match parse_setting::<f64>(setting) {
+                Some((_, reputation)) if (0.0..=1.0).contains(&reputation) => {}
+                _ => {
+                    return Err(format!(
+                        "PUBLISHER_REPUTATIONS expects PUBLISHER=score with a score between 0 and 1, got {setting}"
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct OnchainConfig {
+    /// Networks returned by the merged onchain endpoint, e.g `sepolia,mainnet`.
+    merged_networks: Vec<Network>,
+    /// If true, the decimals of the onchain endpoints are read from the
+    /// oracle contract instead of the offchain database.
+    onchain_decimals_from_rpc: bool,
+    /// Starknet RPC used to read the mainnet oracle contract.
+    starknet_mainnet_rpc_url: Option<String>,
+    /// Starknet RPC used to read the sepolia oracle contract.
+    starknet_sepolia_rpc_url: Option<String>,
+    /// Number of consecutive failures after which the onchain requests are
+    /// short-circuited. When not set, the circuit breaker is disabled.
+    onchain_circuit_breaker_failure_threshold: Option<u32>,
+    /// Duration during which the onchain requests are short-circuited.
+    onchain_circuit_breaker_cooldown_in_seconds: Option<u64>,
+    /// Quote currencies tried in order to route a pair that is not published
+    /// onchain, e.g `USD,USDT,USDC`. When empty, the abstract currencies of
+    /// the offchain database are used.
+    routing_quote_currencies: Vec<String>,
+}
+
+impl Default for OnchainConfig {
+    fn default() -> Self {
+        Self {
+            merged_networks: vec![Network::Sepolia, Network::Mainnet],
+            onchain_decimals_from_rpc: false,
+            starknet_mainnet_rpc_url: None,
+            starknet_sepolia_rpc_url: None,
+            onchain_circuit_breaker_failure_threshold: None,
+            onchain_circuit_breaker_cooldown_in_seconds: None,
+            routing_quote_currencies: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WarmupConfig {
+    /// If true, the data endpoints return a `503` until the node is warmed up.
+    reject_during_warmup: bool,
+    /// Minimum duration of the warmup period after boot.
+    warmup_duration_in_seconds: u64,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            reject_during_warmup: false,
+            warmup_duration_in_seconds: 10,
+        }
+    }
+}
+
 #[derive(Default, Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Mode {
@@ -53,6 +301,7 @@ pub enum Mode {
 }
 
 #[derive(Default, Debug, Deserialize)]
+#[serde(default)]
 pub struct ModeConfig {
     mode: Mode,
 }
@@ -63,6 +312,14 @@ pub struct Config {
     server: ServerConfig,
     kafka: KafkaConfig,
     redis: RedisConfig,
+    signing: SigningConfig,
+    warmup: WarmupConfig,
+    onchain: OnchainConfig,
+    pairs: PairsConfig,
+    publish: PublishConfig,
+    aggregation: AggregationConfig,
+    health: HealthConfig,
+    websocket: WebSocketConfig,
 }
 
 impl Config {
@@ -89,21 +346,314 @@ impl Config {
     pub fn redis_port(&self) -> u16 {
         self.redis.redis_port
     }
+
+    pub fn return_identity_price(&self) -> bool {
+        self.pairs.return_identity_price
+    }
+
+    pub fn non_positive_price_pairs(&self) -> &[String] {
+        &self.pairs.non_positive_price_pairs
+    }
+
+    /// Returns the pair replacing the provided one if it is deprecated.
+    pub fn canonical_pair(&self, pair_id: &str) -> Option<String> {
+        self.pairs
+            .deprecated_pairs
+            .iter()
+            .find_map(|deprecated_pair| {
+                let (deprecated, canonical) = deprecated_pair.split_once('=')?;
+                deprecated
+                    .trim()
+                    .eq_ignore_ascii_case(pair_id)
+                    .then(|| canonical.trim().to_uppercase())
+            })
+    }
+
+    /// Returns the tags of the provided pair, lowercased and deduplicated.
+    pub fn pair_tags(&self, pair_id: &str) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .pairs
+            .pair_tags
+            .iter()
+            .filter_map(|pair_tag| {
+                let (pair, tag) = pair_tag.split_once('=')?;
+                pair.trim()
+                    .eq_ignore_ascii_case(pair_id)
+                    .then(|| tag.trim().to_lowercase())
+            })
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    pub fn max_entry_age(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.publish.max_entry_age_in_seconds)
+    }
+
+    pub fn max_entry_future_drift(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.publish.max_entry_future_drift_in_seconds)
+    }
+
+    pub fn ingestion_lag_pairs(&self) -> &[String] {
+        &self.health.ingestion_lag_pairs
+    }
+
+    pub fn ingestion_lag_threshold(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.health.ingestion_lag_threshold_in_seconds)
+    }
+
+    pub fn redis_required(&self) -> bool {
+        self.health.redis_required
+    }
+
+    pub fn max_sources_per_pair(&self) -> Option<usize> {
+        self.aggregation.max_sources_per_pair
+    }
+
+    pub fn max_sources_spread(&self) -> Option<f64> {
+        self.aggregation.max_sources_spread
+    }
+
+    pub fn twap_default_lookback(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.aggregation
+                .twap_default_lookback_in_seconds
+                .unwrap_or(DEFAULT_TWAP_LOOKBACK_IN_SECONDS),
+        )
+    }
+
+    pub fn blended_perp_weight(&self) -> f64 {
+        self.aggregation
+            .blended_perp_weight
+            .unwrap_or(DEFAULT_BLENDED_PERP_WEIGHT)
+    }
+
+    /// Returns the reputation score of the publisher, neutral if it has none.
+    pub fn publisher_reputation(&self, publisher: &str) -> f64 {
+        let publisher_reputations = self.aggregation.publisher_reputations.as_deref();
+        parse_per_key(publisher_reputations.unwrap_or_default(), publisher)
+            .unwrap_or(DEFAULT_PUBLISHER_REPUTATION)
+    }
+
+    pub fn merged_networks(&self) -> &[Network] {
+        &self.onchain.merged_networks
+    }
+
+    /// Returns the Starknet RPC used to read the onchain decimals of the
+    /// network, or None if they must be read from the offchain database.
+    pub fn onchain_decimals_rpc_url(&self, network: Network) -> Option<&str> {
+        if !self.onchain.onchain_decimals_from_rpc {
+            return None;
+        }
+        match network {
+            Network::Mainnet => self.onchain.starknet_mainnet_rpc_url.as_deref(),
+            Network::Sepolia => self.onchain.starknet_sepolia_rpc_url.as_deref(),
+        }
+    }
+
+    /// Returns the maximum number of bytes that can be sent per second per
+    /// IP address on a websocket.
+    pub fn ws_bytes_limit_per_ip_per_second(&self) -> NonZeroU32 {
+        self.websocket
+            .ws_bytes_limit_per_ip_per_second
+            .and_then(NonZeroU32::new)
+            .unwrap_or(nonzero!(DEFAULT_WS_BYTES_LIMIT_PER_IP_PER_SECOND))
+    }
+
+    pub fn onchain_circuit_breaker_failure_threshold(&self) -> Option<u32> {
+        self.onchain.onchain_circuit_breaker_failure_threshold
+    }
+
+    pub fn routing_quote_currencies(&self) -> &[String] {
+        &self.onchain.routing_quote_currencies
+    }
+
+    pub fn onchain_circuit_breaker_cooldown(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.onchain
+                .onchain_circuit_breaker_cooldown_in_seconds
+                .unwrap_or(DEFAULT_ONCHAIN_CIRCUIT_BREAKER_COOLDOWN_IN_SECONDS),
+        )
+    }
+
+    pub fn max_ws_connections(&self) -> Option<usize> {
+        self.websocket.max_ws_connections
+    }
+
+    pub fn max_subscriptions_per_connection(&self) -> usize {
+        self.websocket
+            .max_subscriptions_per_connection
+            .unwrap_or(DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION)
+    }
+
+    pub fn ws_heartbeat_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.websocket
+                .ws_heartbeat_interval_in_seconds
+                .map_or(DEFAULT_WS_HEARTBEAT_INTERVAL_IN_SECONDS, NonZeroU64::get),
+        )
+    }
+
+    pub fn ws_coalesce_updates(&self) -> bool {
+        self.websocket.ws_coalesce_updates
+    }
+
+    pub fn reject_during_warmup(&self) -> bool {
+        self.warmup.reject_during_warmup
+    }
+
+    pub fn warmup_duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.warmup.warmup_duration_in_seconds)
+    }
+
+    /// Returns the time to live of the verified signatures cache, or None if
+    /// the cache is disabled.
+    pub fn verified_signatures_cache_ttl(&self) -> Option<std::time::Duration> {
+        let ttl = self
+            .signing
+            .verified_signatures_cache_ttl_in_seconds
+            .unwrap_or(VERIFIED_SIGNATURES_CACHE_TIME_TO_LIVE_IN_SECONDS);
+        (ttl > 0).then(|| std::time::Duration::from_secs(ttl))
+    }
+
+    pub fn batch_signing(&self) -> bool {
+        self.signing.batch_signing.unwrap_or(false)
+    }
+
+    pub fn async_signature_verification(&self) -> bool {
+        self.signing.async_signature_verification.unwrap_or(false)
+    }
+
+    pub fn publishers_cache_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.signing
+                .publishers_cache_ttl_in_seconds
+                .unwrap_or(PUBLISHERS_CACHE_TIME_TO_LIVE_IN_SECONDS),
+        )
+    }
+
+    pub fn publishers_cache_sync_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.signing
+                .publishers_cache_sync_interval_in_seconds
+                .map_or(PUBLISHERS_CACHE_SYNC_INTERVAL_IN_SECONDS, NonZeroU64::get),
+        )
+    }
+
+    pub fn time_oracle_url(&self) -> Option<&str> {
+        self.signing.time_oracle_url.as_deref()
+    }
+
+    pub fn time_oracle_refresh_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(
+            self.signing
+                .time_oracle_refresh_interval_in_seconds
+                .unwrap_or(DEFAULT_TIME_ORACLE_REFRESH_INTERVAL_IN_SECONDS),
+        )
+    }
+
+    pub fn time_oracle_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(
+            self.signing
+                .time_oracle_timeout_in_ms
+                .unwrap_or(DEFAULT_TIME_ORACLE_TIMEOUT_IN_MS),
+        )
+    }
+
+    pub fn signer_key_max_age(&self) -> Option<std::time::Duration> {
+        self.signing
+            .signer_key_max_age_in_seconds
+            .map(|max_age| std::time::Duration::from_secs(max_age.get()))
+    }
+
+    /// Returns the maximum age of the prices of the pair that can be signed,
+    /// if any. Mark prices (suffixed with `:MARK`) follow the setting of their pair.
+    pub fn max_signed_price_age(&self, pair_id: &str) -> Option<std::time::Duration> {
+        let pair_id = pair_id.strip_suffix(":MARK").unwrap_or(pair_id);
+        let max_signed_price_age_per_pair = self.signing.max_signed_price_age_per_pair.as_deref();
+        parse_per_key(max_signed_price_age_per_pair.unwrap_or_default(), pair_id)
+            .or(self.signing.max_signed_price_age_in_seconds)
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// Returns true if the pair can be signed by the Pragma signer.
+    /// Mark prices (suffixed with `:MARK`) follow the setting of their pair.
+    pub fn is_signable_pair(&self, pair_id: &str) -> bool {
+        let pair_id = pair_id.strip_suffix(":MARK").unwrap_or(pair_id);
+        match &self.signing.signable_pairs {
+            Some(signable_pairs) => signable_pairs
+                .iter()
+                .any(|signable_pair| signable_pair.trim().eq_ignore_ascii_case(pair_id)),
+            None => true,
+        }
+    }
 }
 
 pub static CONFIG: OnceCell<Config> = OnceCell::const_new();
 
+/// Parses a setting of the form `KEY=value`, e.g `BTC/USD=30`.
+fn parse_setting<T: FromStr>(setting: &str) -> Option<(&str, T)> {
+    let (key, value) = setting.split_once('=')?;
+    Some((key.trim(), value.trim().parse().ok()?))
+}
+
+/// Returns the value of the key, case insensitive, in settings of the form
+/// `KEY=value`. Malformed settings are skipped.
+fn parse_per_key<T: FromStr>(settings: &[String], key: &str) -> Option<T> {
+    settings
+        .iter()
+        .filter_map(|setting| parse_setting(setting))
+        .find_map(|(setting_key, value)| setting_key.eq_ignore_ascii_case(key).then_some(value))
+}
+
+/// Parses a configuration from the environment variables, the unset ones
+/// taking their default value.
+/// If a variable is malformed, the configuration falls back to its defaults
+/// and the error is logged.
+fn parse_config<T: DeserializeOwned + Default>(
+    name: &str,
+    vars: impl Iterator<Item = (String, String)>,
+) -> T {
+    envy::from_iter(vars).unwrap_or_else(|e| {
+        tracing::error!("Invalid {name} configuration, using the defaults: {e}");
+        T::default()
+    })
+}
+
 async fn init_config() -> Config {
-    let server_config = envy::from_env::<ServerConfig>().unwrap_or_default();
-    let kafka_config = envy::from_env::<KafkaConfig>().unwrap_or_default();
-    let redis_config = envy::from_env::<RedisConfig>().unwrap_or_default();
-    let mode_config = envy::from_env::<ModeConfig>().unwrap_or_default();
+    let server_config = parse_config("server", std::env::vars());
+    let kafka_config = parse_config("kafka", std::env::vars());
+    let redis_config = parse_config("redis", std::env::vars());
+    let mode_config = parse_config("mode", std::env::vars());
+    let signing_config: SigningConfig = parse_config("signing", std::env::vars());
+    if let Err(e) = signing_config.validate() {
+        panic!("Invalid signing configuration: {e}");
+    }
+    let warmup_config = parse_config("warmup", std::env::vars());
+    let onchain_config = parse_config("onchain", std::env::vars());
+    let pairs_config = parse_config("pairs", std::env::vars());
+    let publish_config = parse_config("publish", std::env::vars());
+    let aggregation_config: AggregationConfig = parse_config("aggregation", std::env::vars());
+    if let Err(e) = aggregation_config.validate() {
+        panic!("Invalid aggregation configuration: {e}");
+    }
+    let health_config = parse_config("health", std::env::vars());
+    let websocket_config = parse_config("websocket", std::env::vars());
 
     Config {
         server: server_config,
         kafka: kafka_config,
         redis: redis_config,
         mode: mode_config,
+        signing: signing_config,
+        warmup: warmup_config,
+        onchain: onchain_config,
+        pairs: pairs_config,
+        publish: publish_config,
+        aggregation: aggregation_config,
+        health: health_config,
+        websocket: websocket_config,
     }
 }
 
@@ -135,4 +685,303 @@ mod tests {
         assert_eq!(config.server_port(), 3000);
         assert_eq!(config.kafka_topic(), "pragma-data");
     }
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_unset_variables_take_their_default_value() {
+        let server_config: ServerConfig = parse_config("server", vars(&[("PORT", "8080")]));
+        assert_eq!(server_config.host, "0.0.0.0");
+        assert_eq!(server_config.port, 8080);
+
+        let aggregation_config: AggregationConfig =
+            parse_config("aggregation", vars(&[("MAX_SOURCES_PER_PAIR", "3")]));
+        assert_eq!(aggregation_config.max_sources_per_pair, Some(3));
+        assert_eq!(aggregation_config.max_sources_spread, None);
+    }
+
+    #[test]
+    fn test_blended_perp_weight_is_validated() {
+        let config: AggregationConfig =
+            parse_config("aggregation", vars(&[("BLENDED_PERP_WEIGHT", "0.3")]));
+        assert!(config.validate().is_ok());
+
+        for weight in ["-0.1", "1.5", "NaN"] {
+            let config: AggregationConfig =
+                parse_config("aggregation", vars(&[("BLENDED_PERP_WEIGHT", weight)]));
+            assert!(config.validate().is_err(), "{weight} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_max_signed_price_age_per_pair_is_validated() {
+        let config: SigningConfig = parse_config(
+            "signing",
+            vars(&[("MAX_SIGNED_PRICE_AGE_PER_PAIR", "BTC/USD=30,LORDS/USD=600")]),
+        );
+        assert!(config.validate().is_ok());
+
+        for ages in ["BTC/USD=thirty", "BTC/USD=-1", "BTC/USD"] {
+            let config: SigningConfig =
+                parse_config("signing", vars(&[("MAX_SIGNED_PRICE_AGE_PER_PAIR", ages)]));
+            assert!(config.validate().is_err(), "{ages} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_publisher_reputations_are_validated() {
+        let config: AggregationConfig = parse_config(
+            "aggregation",
+            vars(&[("PUBLISHER_REPUTATIONS", "PRAGMA=1,SKYNET=0.3")]),
+        );
+        assert!(config.validate().is_ok());
+
+        for reputations in ["PRAGMA=1.5", "PRAGMA=-1", "PRAGMA=high", "PRAGMA"] {
+            let config: AggregationConfig = parse_config(
+                "aggregation",
+                vars(&[("PUBLISHER_REPUTATIONS", reputations)]),
+            );
+            assert!(
+                config.validate().is_err(),
+                "{reputations} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_interval_of_zero_is_rejected() {
+        let websocket: WebSocketConfig = parse_config(
+            "websocket",
+            vars(&[("WS_HEARTBEAT_INTERVAL_IN_SECONDS", "0")]),
+        );
+        assert!(websocket.ws_heartbeat_interval_in_seconds.is_none());
+        let config = Config {
+            websocket,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.ws_heartbeat_interval(),
+            std::time::Duration::from_secs(DEFAULT_WS_HEARTBEAT_INTERVAL_IN_SECONDS)
+        );
+    }
+
+    #[test]
+    fn test_signer_key_max_age_of_zero_is_rejected() {
+        let signing: SigningConfig =
+            parse_config("signing", vars(&[("SIGNER_KEY_MAX_AGE_IN_SECONDS", "0")]));
+        assert!(signing.signer_key_max_age_in_seconds.is_none());
+
+        let signing: SigningConfig = parse_config(
+            "signing",
+            vars(&[("SIGNER_KEY_MAX_AGE_IN_SECONDS", "3600")]),
+        );
+        let config = Config {
+            signing,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.signer_key_max_age(),
+            Some(std::time::Duration::from_secs(3600))
+        );
+    }
+
+    #[test]
+    fn test_malformed_variable_falls_back_to_the_defaults() {
+        let aggregation_config: AggregationConfig = parse_config(
+            "aggregation",
+            vars(&[
+                ("MAX_SOURCES_PER_PAIR", "twenty"),
+                ("MAX_SOURCES_SPREAD", "0.1"),
+            ]),
+        );
+        assert_eq!(aggregation_config.max_sources_per_pair, None);
+        assert_eq!(aggregation_config.max_sources_spread, None);
+    }
+
+    #[tokio::test]
+    async fn test_all_pairs_signable_by_default() {
+        let config = Config::default();
+        assert!(config.is_signable_pair("BTC/USD"));
+        assert!(config.is_signable_pair("ETH/USD:MARK"));
+    }
+
+    #[tokio::test]
+    async fn test_signable_pairs() {
+        let config = Config {
+            signing: SigningConfig {
+                signable_pairs: Some(vec!["BTC/USD".to_string(), "eth/usd".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.is_signable_pair("BTC/USD"));
+        assert!(config.is_signable_pair("BTC/USD:MARK"));
+        assert!(config.is_signable_pair("ETH/USD"));
+        assert!(!config.is_signable_pair("SOL/USD"));
+        assert!(!config.is_signable_pair("SOL/USD:MARK"));
+    }
+
+    #[tokio::test]
+    async fn test_max_signed_price_age() {
+        assert!(Config::default().max_signed_price_age("BTC/USD").is_none());
+
+        let config = Config {
+            signing: SigningConfig {
+                max_signed_price_age_in_seconds: Some(120),
+                max_signed_price_age_per_pair: Some(vec![
+                    "BTC/USD=30".to_string(),
+                    " lords/usd = 600".to_string(),
+                ]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let seconds = std::time::Duration::from_secs;
+        assert_eq!(config.max_signed_price_age("BTC/USD"), Some(seconds(30)));
+        assert_eq!(
+            config.max_signed_price_age("BTC/USD:MARK"),
+            Some(seconds(30))
+        );
+        assert_eq!(config.max_signed_price_age("LORDS/USD"), Some(seconds(600)));
+        assert_eq!(config.max_signed_price_age("ETH/USD"), Some(seconds(120)));
+    }
+
+    #[tokio::test]
+    async fn test_publisher_reputation() {
+        assert_eq!(
+            Config::default().publisher_reputation("PRAGMA"),
+            DEFAULT_PUBLISHER_REPUTATION
+        );
+
+        let config = Config {
+            aggregation: AggregationConfig {
+                publisher_reputations: Some(vec![
+                    "PRAGMA=1".to_string(),
+                    " skynet = 0.3".to_string(),
+                    "FOURLEAF=high".to_string(),
+                ]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(config.publisher_reputation("PRAGMA"), 1.0);
+        assert_eq!(config.publisher_reputation("SKYNET"), 0.3);
+        assert_eq!(
+            config.publisher_reputation("FOURLEAF"),
+            DEFAULT_PUBLISHER_REPUTATION
+        );
+        assert_eq!(
+            config.publisher_reputation("AVNU"),
+            DEFAULT_PUBLISHER_REPUTATION
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verified_signatures_cache_ttl() {
+        let config = Config::default();
+        assert_eq!(
+            config.verified_signatures_cache_ttl(),
+            Some(std::time::Duration::from_secs(
+                VERIFIED_SIGNATURES_CACHE_TIME_TO_LIVE_IN_SECONDS
+            ))
+        );
+
+        let config = Config {
+            signing: SigningConfig {
+                verified_signatures_cache_ttl_in_seconds: Some(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(config.verified_signatures_cache_ttl().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_onchain_decimals_rpc_url() {
+        let onchain = OnchainConfig {
+            starknet_mainnet_rpc_url: Some("http://mainnet.rpc".to_string()),
+            ..Default::default()
+        };
+        let config = Config {
+            onchain,
+            ..Default::default()
+        };
+        assert!(config.onchain_decimals_rpc_url(Network::Mainnet).is_none());
+
+        let config = Config {
+            onchain: OnchainConfig {
+                onchain_decimals_from_rpc: true,
+                ..config.onchain
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            config.onchain_decimals_rpc_url(Network::Mainnet),
+            Some("http://mainnet.rpc")
+        );
+        assert!(config.onchain_decimals_rpc_url(Network::Sepolia).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_canonical_pair() {
+        let config = Config {
+            pairs: PairsConfig {
+                deprecated_pairs: vec![
+                    "MATIC/USD=POL/USD".to_string(),
+                    " ftm/usd = s/usd".to_string(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            config.canonical_pair("MATIC/USD"),
+            Some("POL/USD".to_string())
+        );
+        assert_eq!(config.canonical_pair("FTM/USD"), Some("S/USD".to_string()));
+        assert!(config.canonical_pair("POL/USD").is_none());
+        assert!(Config::default().canonical_pair("MATIC/USD").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pair_tags() {
+        let config = Config {
+            pairs: PairsConfig {
+                pair_tags: vec![
+                    "BTC/USD=majors".to_string(),
+                    " eth/usd = Majors".to_string(),
+                    "ETH/USD=defi".to_string(),
+                    "ETH/USD=majors".to_string(),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(config.pair_tags("BTC/USD"), vec!["majors".to_string()]);
+        assert_eq!(
+            config.pair_tags("ETH/USD"),
+            vec!["defi".to_string(), "majors".to_string()]
+        );
+        assert!(config.pair_tags("SOL/USD").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ws_bytes_limit_per_ip_per_second() {
+        let config = Config::default();
+        assert_eq!(config.ws_bytes_limit_per_ip_per_second().get(), 256 * 1024);
+
+        let config = Config {
+            websocket: WebSocketConfig {
+                ws_bytes_limit_per_ip_per_second: Some(1024),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(config.ws_bytes_limit_per_ip_per_second().get(), 1024);
+    }
 }
@@ -11,3 +11,23 @@ pub const PUBLISHERS_UDPATES_CACHE_TIME_TO_IDLE_IN_SECONDS: u64 = 5 * 60; // 5 m
 /// Since this value never change we can cache it for faster iterations.
 pub const MERKLE_FEED_TREE_CACHE_TIME_TO_LIVE_IN_SECONDS: u64 = 6 * 60; // 6 minutes
 pub const MERKLE_FEED_TREE_CACHE_TIME_TO_IDLE_IN_SECONDS: u64 = 60; // 1 minutes
+
+/// Cache of the publishers signatures that were successfully verified, so a
+/// publisher republishing the exact same message skips the verification.
+pub const VERIFIED_SIGNATURES_CACHE_TIME_TO_LIVE_IN_SECONDS: u64 = 30; // 30 seconds
+
+/// Cache of the decimals read from the onchain oracle contracts.
+/// Decimals of a pair barely ever change onchain.
+pub const ONCHAIN_DECIMALS_CACHE_TIME_TO_LIVE_IN_SECONDS: u64 = 60 * 60; // 1 hour
+
+/// Cache of the decimals of the pairs, computed from the currencies stored
+/// in the offchain database.
+pub const DECIMALS_CACHE_TIME_TO_LIVE_IN_SECONDS: u64 = 5 * 60; // 5 minutes
+
+/// Cache of the publishers keys & addresses used to verify the published entries.
+/// Can be overriden with the `PUBLISHERS_CACHE_TTL_IN_SECONDS` env variable.
+pub const PUBLISHERS_CACHE_TIME_TO_LIVE_IN_SECONDS: u64 = 60; // 1 minute
+/// Interval between two comparisons of the cached publishers with the database,
+/// evicting the ones that were updated, e.g deactivated or with a rotated key.
+/// Can be overriden with the `PUBLISHERS_CACHE_SYNC_INTERVAL_IN_SECONDS` env variable.
+pub const PUBLISHERS_CACHE_SYNC_INTERVAL_IN_SECONDS: u64 = 5;
@@ -5,3 +5,98 @@
 /// ROUTING_FRESHNESS_THRESHOLD seconds ago.
 /// Otherwise, we return the price by routing through USD pairs.
 pub const ROUTING_FRESHNESS_THRESHOLD: i64 = 60; // 1 minute
+
+/// Value of the `Retry-After` header returned by the data endpoints while
+/// the node is still warming up.
+pub const WARMUP_RETRY_AFTER_IN_SECONDS: u64 = 5;
+
+/// Number of seconds after which an onchain pair that has not been updated
+/// is flagged as stale in the merged networks response.
+pub const ONCHAIN_STALENESS_THRESHOLD_IN_SECONDS: u64 = 60 * 60; // 1 hour
+
+/// Largest lookback window accepted by the volatility term structure.
+pub const MAX_VOLATILITY_WINDOW_IN_SECONDS: u64 = 365 * 24 * 60 * 60; // 1 year
+
+/// Maximum number of median prices the volatility is computed from. Longer
+/// periods are sampled less often.
+pub const MAX_VOLATILITY_POINTS: u64 = 10_000;
+
+/// Maximum number of points returned by a multi intervals history request,
+/// all intervals combined.
+pub const MAX_HISTORY_POINTS: usize = 10_000;
+
+/// Addresses of the Pragma oracle contracts, used to read the onchain decimals.
+pub const PRAGMA_ORACLE_ADDRESS_MAINNET: &str =
+    "0x2a85bd616f912537c50a49a4076db02c00b29b2cdc8a197ce92ed1837fa875b";
+pub const PRAGMA_ORACLE_ADDRESS_SEPOLIA: &str =
+    "0x36031daa264c24520b11d93af622c848b2499b66b41d611bac95e13cfca131a";
+
+/// Default maximum number of bytes that can be sent per second per IP address
+/// on a websocket. If the limit is exceeded, the connection is closed.
+pub const DEFAULT_WS_BYTES_LIMIT_PER_IP_PER_SECOND: u32 = 256 * 1024; // 256 KiB
+
+/// Largest sliding window of the rolling means pushed on the price websocket.
+pub const MAX_ROLLING_WINDOW_IN_SECONDS: u64 = 60 * 60; // 1 hour
+
+/// Default number of seconds during which the onchain requests are
+/// short-circuited once the circuit breaker opened.
+pub const DEFAULT_ONCHAIN_CIRCUIT_BREAKER_COOLDOWN_IN_SECONDS: u64 = 30;
+
+/// Default interval between two pings sent to the websocket clients.
+pub const DEFAULT_WS_HEARTBEAT_INTERVAL_IN_SECONDS: u64 = 30;
+
+/// Maximum number of pairs that can be fetched by a single batch request.
+pub const MAX_BATCH_PAIRS: usize = 100;
+
+/// Maximum number of pairs of a batch request computed at once, so a single
+/// request can't hold all the connections of the database pool.
+pub const MAX_BATCH_CONCURRENCY: usize = 8;
+
+/// Default number of significant digits of the prices formatted in scientific notation.
+pub const DEFAULT_SIGNIFICANT_DIGITS: u32 = 6;
+/// Maximum number of significant digits of the prices formatted in scientific notation.
+pub const MAX_SIGNIFICANT_DIGITS: u32 = 40;
+
+/// Window over which the sources backing each pair are counted.
+pub const SOURCE_COVERAGE_WINDOW_IN_SECONDS: u64 = 5 * 60; // 5 minutes
+
+/// Default interval between two synchronizations with the time oracle.
+pub const DEFAULT_TIME_ORACLE_REFRESH_INTERVAL_IN_SECONDS: u64 = 60;
+
+/// Default timeout of the requests to the time oracle. Kept short as the
+/// signed prices wait for it.
+pub const DEFAULT_TIME_ORACLE_TIMEOUT_IN_MS: u64 = 500;
+
+/// Time during which the prices are signed with the local clock once the time
+/// oracle failed, before trying it again.
+pub const TIME_ORACLE_RETRY_INTERVAL_IN_SECONDS: u64 = 10;
+
+/// Default lookback window of the TWAP aggregation.
+pub const DEFAULT_TWAP_LOOKBACK_IN_SECONDS: u64 = 60 * 60; // 1 hour
+
+/// Default weight of the perp mark price in the blended spot & perp price.
+pub const DEFAULT_BLENDED_PERP_WEIGHT: f64 = 0.5;
+
+/// Maximum number of pairs a websocket connection can subscribe to.
+pub const DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 50;
+
+/// Reputation score of the publishers without configured score.
+pub const DEFAULT_PUBLISHER_REPUTATION: f64 = 0.5;
+
+/// Default number of entries returned by the recent entries endpoint.
+pub const DEFAULT_RECENT_ENTRIES_LIMIT: u64 = 100;
+
+/// Maximum number of entries returned by the recent entries endpoint.
+pub const MAX_RECENT_ENTRIES_LIMIT: u64 = 1000;
+
+/// Timeout of the reload of the Pragma signer key from AWS.
+pub const SIGNER_RELOAD_TIMEOUT_IN_SECONDS: u64 = 10;
+
+/// Delay before retrying a failed reload of the Pragma signer key, doubled
+/// after each consecutive failure up to the maximum.
+pub const SIGNER_RELOAD_RETRY_DELAY_IN_SECONDS: u64 = 5;
+pub const SIGNER_RELOAD_MAX_RETRY_DELAY_IN_SECONDS: u64 = 300;
+
+/// Timeout of the calls to the Starknet RPC reading the onchain decimals,
+/// after which the decimals of the database are used.
+pub const ORACLE_RPC_TIMEOUT_IN_MS: u64 = 2_000;
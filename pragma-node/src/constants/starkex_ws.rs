@@ -25,3 +25,8 @@ pub const INTERVAL_INCREMENT_IN_MS: u64 = 500;
 /// for a pair_id in order to return the computed price.
 /// TODO: should be lower for development mode (1)
 pub const MINIMUM_NUMBER_OF_PUBLISHERS: usize = 1;
+
+/// Used for the subscription to the entry websocket.
+/// Represents the maximum number of aggregated prices sent per pair when a client
+/// requests a backfill on subscribe. The most recent ones are kept.
+pub const MAX_BACKFILL_ENTRIES_PER_PAIR: usize = 300;
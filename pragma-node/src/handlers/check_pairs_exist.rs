@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use pragma_entities::EntryError;
+
+use crate::constants::others::MAX_BATCH_PAIRS;
+use crate::utils::only_existing_pairs;
+use crate::AppState;
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct CheckPairsExistRequest {
+    /// Pairs to check, e.g `["BTC/USD", "ETH/USD:MARK"]`. Perp pairs have
+    /// the `:MARK` suffix.
+    pub pairs: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToResponse, ToSchema, PartialEq)]
+pub struct CheckPairsExistResponse {
+    pub spot_pairs: Vec<String>,
+    /// Known perp pairs, with the `:MARK` suffix.
+    pub perp_pairs: Vec<String>,
+    pub unknown_pairs: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/node/v1/data/pairs/exists",
+    request_body = CheckPairsExistRequest,
+    responses(
+        (status = 200, description = "Split the pairs between the known spot pairs, the known perp pairs and the unknown ones", body = CheckPairsExistResponse),
+        (status = 400, description = "Too many pairs requested", body = EntryError),
+        (status = 503, description = "The database is unavailable", body = EntryError)
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn check_pairs_exist(
+    State(state): State<AppState>,
+    Json(request): Json<CheckPairsExistRequest>,
+) -> Result<Json<CheckPairsExistResponse>, EntryError> {
+    let pairs = normalize_pairs(request.pairs);
+    if pairs.len() > MAX_BATCH_PAIRS {
+        return Err(EntryError::TooManyPairs(pairs.len(), MAX_BATCH_PAIRS));
+    }
+
+    let (spot_pairs, perp_pairs) = only_existing_pairs(&state.offchain_pool, pairs.clone())
+        .await
+        .map_err(|e| e.to_entry_error(&pairs.join(",")))?;
+
+    Ok(Json(split_pairs(pairs, spot_pairs, perp_pairs)))
+}
+
+/// Uppercases the pairs like [`only_existing_pairs`] does, without duplicates.
+fn normalize_pairs(pairs: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    pairs
+        .into_iter()
+        .map(|pair| pair.to_uppercase().trim().to_string())
+        .filter(|pair| seen.insert(pair.clone()))
+        .collect()
+}
+
+/// Splits the requested pairs between the existing spot & perp pairs and
+/// the unknown ones, in the order of the request.
+fn split_pairs(
+    pairs: Vec<String>,
+    existing_spot_pairs: Vec<String>,
+    existing_perp_pairs: Vec<String>,
+) -> CheckPairsExistResponse {
+    let existing_spot_pairs: HashSet<String> = existing_spot_pairs.into_iter().collect();
+    let existing_perp_pairs: HashSet<String> = existing_perp_pairs
+        .into_iter()
+        .map(|pair| format!("{}:MARK", pair))
+        .collect();
+
+    let mut response = CheckPairsExistResponse::default();
+    for pair in pairs {
+        if existing_spot_pairs.contains(&pair) {
+            response.spot_pairs.push(pair);
+        } else if existing_perp_pairs.contains(&pair) {
+            response.perp_pairs.push(pair);
+        } else {
+            response.unknown_pairs.push(pair);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_pairs_are_split_by_existence() {
+        let pairs = normalize_pairs(strings(&[
+            "btc/usd",
+            "ETH/USD:MARK",
+            "DOGE/USD",
+            "BTC/USD ",
+            "SOL/USD:MARK",
+        ]));
+        assert_eq!(
+            pairs,
+            strings(&["BTC/USD", "ETH/USD:MARK", "DOGE/USD", "SOL/USD:MARK"])
+        );
+
+        let response = split_pairs(
+            pairs,
+            strings(&["BTC/USD", "ETH/USD"]),
+            strings(&["ETH/USD"]),
+        );
+        assert_eq!(
+            response,
+            CheckPairsExistResponse {
+                spot_pairs: strings(&["BTC/USD"]),
+                perp_pairs: strings(&["ETH/USD:MARK"]),
+                unknown_pairs: strings(&["DOGE/USD", "SOL/USD:MARK"]),
+            }
+        );
+    }
+}
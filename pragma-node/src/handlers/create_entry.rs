@@ -1,16 +1,16 @@
-use axum::extract::{self, State};
+use axum::extract::{self, Query, State};
 use axum::Json;
-use chrono::{DateTime, Utc};
-use pragma_entities::{EntryError, NewEntry, PublisherError};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use pragma_entities::{EntriesPayload, EntryError, NewEntry, PendingSpotEntries, PublisherError};
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
-use utoipa::{ToResponse, ToSchema};
+use utoipa::{IntoParams, ToResponse, ToSchema};
 
 use crate::config::config;
-use crate::infra::kafka;
+use crate::infra::kafka::{self, KafkaDelivery};
 use crate::infra::repositories::publisher_repository;
-use crate::types::entries::Entry;
-use crate::utils::{assert_request_signature_is_valid, felt_from_decimal};
+use crate::types::entries::{assert_prices_are_valid, assert_quotes_are_valid, Entry};
+use crate::utils::{assert_request_signature_is_valid, felt_from_decimal, get_pending_signature};
 use crate::AppState;
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -33,9 +33,64 @@ impl AsRef<[Entry]> for CreateEntryRequest {
     }
 }
 
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct CreateEntryParams {
+    /// Dev mode only: if true, returns where the entries were delivered in Kafka.
+    #[serde(default)]
+    pub with_kafka_delivery: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
 pub struct CreateEntryResponse {
     number_entries_created: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kafka_delivery: Option<KafkaDelivery>,
+}
+
+/// Returns the Kafka delivery of the entries if it was requested.
+/// It is never exposed outside of the dev mode.
+pub(crate) fn kafka_delivery_for_response(
+    requested: bool,
+    is_production_mode: bool,
+    topic: &str,
+    (partition, offset): (i32, i64),
+) -> Option<KafkaDelivery> {
+    (requested && !is_production_mode).then(|| KafkaDelivery {
+        topic: topic.to_string(),
+        partition,
+        offset,
+    })
+}
+
+/// Converts the timestamp in seconds of a published entry, rejecting it if it
+/// is older than `max_age` or further than `max_future_drift` in the future.
+fn entry_datetime(
+    timestamp: u64,
+    now: DateTime<Utc>,
+    max_age: std::time::Duration,
+    max_future_drift: std::time::Duration,
+) -> Result<NaiveDateTime, EntryError> {
+    let dt = DateTime::<Utc>::from_timestamp(timestamp as i64, 0).ok_or_else(|| {
+        EntryError::InvalidTimestamp(format!("Could not convert {} to DateTime", timestamp))
+    })?;
+    let age = now.signed_duration_since(dt);
+    if age.num_seconds() > max_age.as_secs() as i64 {
+        return Err(EntryError::InvalidTimestamp(format!(
+            "Entry timestamp {} is {}s old, the maximum is {}s",
+            timestamp,
+            age.num_seconds(),
+            max_age.as_secs()
+        )));
+    }
+    if -age.num_seconds() > max_future_drift.as_secs() as i64 {
+        return Err(EntryError::InvalidTimestamp(format!(
+            "Entry timestamp {} is {}s in the future, the maximum is {}s",
+            timestamp,
+            -age.num_seconds(),
+            max_future_drift.as_secs()
+        )));
+    }
+    Ok(dt.naive_utc())
 }
 
 #[utoipa::path(
@@ -45,11 +100,13 @@ pub struct CreateEntryResponse {
     responses(
         (status = 200, description = "Entries published successfuly", body = CreateEntryResponse),
         (status = 401, description = "Unauthorized Publisher", body = EntryError)
-    )
+    ),
+    params(CreateEntryParams)
 )]
 #[tracing::instrument(skip(state))]
 pub async fn create_entries(
     State(state): State<AppState>,
+    Query(params): Query<CreateEntryParams>,
     extract::Json(new_entries): extract::Json<CreateEntryRequest>,
 ) -> Result<Json<CreateEntryResponse>, EntryError> {
     tracing::info!("Received new entries: {:?}", new_entries);
@@ -58,14 +115,22 @@ pub async fn create_entries(
     if new_entries.entries.is_empty() {
         return Ok(Json(CreateEntryResponse {
             number_entries_created: 0,
+            kafka_delivery: None,
         }));
     }
 
+    assert_prices_are_valid(&new_entries.entries, config.non_positive_price_pairs())?;
+    assert_quotes_are_valid(&new_entries.entries)?;
+
     let publisher_name = new_entries.entries[0].base.publisher.clone();
 
-    let publisher = publisher_repository::get(&state.offchain_pool, publisher_name.clone())
-        .await
-        .map_err(EntryError::InfraError)?;
+    let publisher = publisher_repository::get_cached(
+        &state.offchain_pool,
+        state.caches.publishers(),
+        publisher_name.clone(),
+    )
+    .await
+    .map_err(EntryError::InfraError)?;
 
     // Check if publisher is active
     publisher.assert_is_active()?;
@@ -76,34 +141,41 @@ pub async fn create_entries(
     let public_key = Felt::from_hex(&public_key)
         .map_err(|_| EntryError::PublisherError(PublisherError::InvalidKey(public_key)))?;
 
-    // Fetch account address from database
-    // TODO: Cache it
-    let account_address = publisher_repository::get(&state.offchain_pool, publisher_name.clone())
-        .await
-        .map_err(EntryError::InfraError)?
-        .account_address;
+    let account_address = publisher.account_address;
     let account_address = Felt::from_hex(&account_address)
         .map_err(|_| EntryError::PublisherError(PublisherError::InvalidAddress(account_address)))?;
 
-    let signature = assert_request_signature_is_valid::<CreateEntryRequest, Entry>(
-        &new_entries,
-        &account_address,
-        &public_key,
-    )?;
+    // In async mode, the signature is verified by the ingestor, which drops
+    // the entries if it is invalid.
+    let (signature, pending_signature) = if config.async_signature_verification() {
+        let (signature, pending_signature) = get_pending_signature::<CreateEntryRequest, Entry>(
+            &new_entries,
+            &account_address,
+            &public_key,
+        )?;
+        (signature, Some(pending_signature))
+    } else {
+        let signature = assert_request_signature_is_valid::<CreateEntryRequest, Entry>(
+            &new_entries,
+            &account_address,
+            &public_key,
+            state.caches.verified_signatures(),
+        )
+        .await?;
+        (signature, None)
+    };
 
+    let now = Utc::now();
     let new_entries_db = new_entries
         .entries
         .iter()
         .map(|entry| {
-            let dt = match DateTime::<Utc>::from_timestamp(entry.base.timestamp as i64, 0) {
-                Some(dt) => dt.naive_utc(),
-                None => {
-                    return Err(EntryError::InvalidTimestamp(format!(
-                        "Could not convert {} to DateTime",
-                        entry.base.timestamp
-                    )))
-                }
-            };
+            let dt = entry_datetime(
+                entry.base.timestamp,
+                now,
+                config.max_entry_age(),
+                config.max_entry_future_drift(),
+            )?;
 
             Ok(NewEntry {
                 pair_id: entry.pair_id.clone(),
@@ -112,22 +184,39 @@ pub async fn create_entries(
                 timestamp: dt,
                 publisher_signature: format!("0x{}", signature),
                 price: entry.price.into(),
+                bid: entry.bid.map(Into::into),
+                ask: entry.ask.map(Into::into),
             })
         })
         .collect::<Result<Vec<NewEntry>, EntryError>>()?;
 
-    let data =
-        serde_json::to_vec(&new_entries_db).map_err(|e| EntryError::PublishData(e.to_string()))?;
+    let payload = match pending_signature {
+        Some(signature) => EntriesPayload::PendingSpot(PendingSpotEntries {
+            signature,
+            entries: new_entries_db,
+        }),
+        None => EntriesPayload::Spot(new_entries_db),
+    };
+    let data = serde_json::to_vec(&payload).map_err(|e| EntryError::PublishData(e.to_string()))?;
 
-    if let Err(e) = kafka::send_message(config.kafka_topic(), &data, &publisher_name).await {
-        tracing::error!("Error sending message to kafka: {:?}", e);
-        return Err(EntryError::PublishData(String::from(
-            "Error sending message to kafka",
-        )));
+    let delivery = match kafka::send_message(config.kafka_topic(), &data, &publisher_name).await {
+        Ok(delivery) => delivery,
+        Err(e) => {
+            tracing::error!("Error sending message to kafka: {:?}", e);
+            return Err(EntryError::PublishData(String::from(
+                "Error sending message to kafka",
+            )));
+        }
     };
 
     Ok(Json(CreateEntryResponse {
         number_entries_created: new_entries.entries.len(),
+        kafka_delivery: kafka_delivery_for_response(
+            params.with_kafka_delivery,
+            config.is_production_mode(),
+            config.kafka_topic(),
+            delivery,
+        ),
     }))
 }
 
@@ -162,6 +251,8 @@ mod tests {
             pair_id: "pair_id".to_string(),
             price: 0,
             volume: 0,
+            bid: None,
+            ask: None,
         }];
         let typed_data = build_publish_message(&entries).unwrap();
 
@@ -175,4 +266,74 @@ mod tests {
         // Hash computed with the Pragma SDK (python)
         assert_eq!(msg_hash, Felt::from_hex("").unwrap());
     }
+
+    #[rstest]
+    fn test_entry_timestamps_out_of_bounds_are_rejected() {
+        // Whole seconds, as the published timestamps are.
+        let now = DateTime::<Utc>::from_timestamp(1_718_000_000, 0).unwrap();
+        let max_age = std::time::Duration::from_secs(60 * 60);
+        let max_future_drift = std::time::Duration::from_secs(5 * 60);
+        let timestamp_at = |offset: chrono::Duration| (now + offset).timestamp() as u64;
+
+        let dt = entry_datetime(
+            timestamp_at(chrono::Duration::minutes(-10)),
+            now,
+            max_age,
+            max_future_drift,
+        )
+        .unwrap();
+        assert_eq!(dt.and_utc().timestamp(), now.timestamp() - 10 * 60);
+
+        let two_hours_old = entry_datetime(
+            timestamp_at(chrono::Duration::hours(-2)),
+            now,
+            max_age,
+            max_future_drift,
+        );
+        assert!(matches!(
+            two_hours_old,
+            Err(EntryError::InvalidTimestamp(reason)) if reason.contains("7200s old")
+        ));
+
+        let ten_minutes_ahead = entry_datetime(
+            timestamp_at(chrono::Duration::minutes(10)),
+            now,
+            max_age,
+            max_future_drift,
+        );
+        assert!(matches!(
+            ten_minutes_ahead,
+            Err(EntryError::InvalidTimestamp(reason)) if reason.contains("600s in the future")
+        ));
+    }
+
+    #[rstest]
+    fn test_kafka_delivery_only_in_dev_mode() {
+        let delivery = kafka_delivery_for_response(true, false, "pragma-data", (3, 42));
+        assert_eq!(
+            delivery,
+            Some(KafkaDelivery {
+                topic: "pragma-data".to_string(),
+                partition: 3,
+                offset: 42,
+            })
+        );
+        let response = CreateEntryResponse {
+            number_entries_created: 1,
+            kafka_delivery: delivery,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["kafka_delivery"]["topic"], "pragma-data");
+        assert_eq!(json["kafka_delivery"]["partition"], 3);
+
+        // Never exposed in production, nor when not requested.
+        assert!(kafka_delivery_for_response(true, true, "pragma-data", (3, 42)).is_none());
+        assert!(kafka_delivery_for_response(false, false, "pragma-data", (3, 42)).is_none());
+        let response = CreateEntryResponse {
+            number_entries_created: 1,
+            kafka_delivery: None,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("kafka_delivery").is_none());
+    }
 }
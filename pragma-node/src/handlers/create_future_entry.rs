@@ -1,7 +1,7 @@
 use axum::extract::{self, State};
 use axum::Json;
 use chrono::{DateTime, Utc};
-use pragma_entities::{EntryError, NewFutureEntry, PublisherError};
+use pragma_entities::{EntriesPayload, EntryError, NewFutureEntry, PublisherError};
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 use utoipa::{ToResponse, ToSchema};
@@ -9,7 +9,7 @@ use utoipa::{ToResponse, ToSchema};
 use crate::config::config;
 use crate::infra::kafka;
 use crate::infra::repositories::publisher_repository;
-use crate::types::entries::FutureEntry;
+use crate::types::entries::{assert_prices_are_valid, FutureEntry};
 use crate::utils::{assert_request_signature_is_valid, felt_from_decimal};
 use crate::AppState;
 
@@ -61,11 +61,17 @@ pub async fn create_future_entries(
         }));
     }
 
+    assert_prices_are_valid(&new_entries.entries, config.non_positive_price_pairs())?;
+
     let publisher_name = new_entries.entries[0].base.publisher.clone();
 
-    let publisher = publisher_repository::get(&state.offchain_pool, publisher_name.clone())
-        .await
-        .map_err(EntryError::InfraError)?;
+    let publisher = publisher_repository::get_cached(
+        &state.offchain_pool,
+        state.caches.publishers(),
+        publisher_name.clone(),
+    )
+    .await
+    .map_err(EntryError::InfraError)?;
 
     // Check if publisher is active
     publisher.assert_is_active()?;
@@ -76,12 +82,7 @@ pub async fn create_future_entries(
     let public_key = Felt::from_hex(&public_key)
         .map_err(|_| EntryError::PublisherError(PublisherError::InvalidKey(public_key)))?;
 
-    // Fetch account address from database
-    // TODO: Cache it
-    let account_address = publisher_repository::get(&state.offchain_pool, publisher_name.clone())
-        .await
-        .map_err(EntryError::InfraError)?
-        .account_address;
+    let account_address = publisher.account_address;
     let account_address = Felt::from_hex(&account_address)
         .map_err(|_| EntryError::PublisherError(PublisherError::InvalidAddress(account_address)))?;
 
@@ -89,54 +90,19 @@ pub async fn create_future_entries(
         &new_entries,
         &account_address,
         &public_key,
-    )?;
+        state.caches.verified_signatures(),
+    )
+    .await?;
 
+    let publisher_signature = format!("0x{}", signature);
     let new_entries_db = new_entries
         .entries
         .iter()
-        .map(|future_entry| {
-            let dt = match DateTime::<Utc>::from_timestamp(future_entry.base.timestamp as i64, 0) {
-                Some(dt) => dt.naive_utc(),
-                None => {
-                    return Err(EntryError::InvalidTimestamp(format!(
-                        "Could not convert {} to DateTime",
-                        future_entry.base.timestamp
-                    )))
-                }
-            };
-
-            // For expiration_timestamp, 0 is sent by publishers for perpetual entries.
-            // We set them to None in the database to easily filter them out.
-            let expiry_dt = if future_entry.expiration_timestamp == 0 {
-                None
-            } else {
-                match DateTime::<Utc>::from_timestamp_millis(
-                    future_entry.expiration_timestamp as i64,
-                ) {
-                    Some(dt) => Some(dt.naive_utc()),
-                    None => {
-                        return Err(EntryError::InvalidTimestamp(format!(
-                            "Could not convert {} to DateTime",
-                            future_entry.expiration_timestamp
-                        )))
-                    }
-                }
-            };
-
-            Ok(NewFutureEntry {
-                pair_id: future_entry.pair_id.clone(),
-                publisher: future_entry.base.publisher.clone(),
-                source: future_entry.base.source.clone(),
-                timestamp: dt,
-                expiration_timestamp: expiry_dt,
-                publisher_signature: format!("0x{}", signature),
-                price: future_entry.price.into(),
-            })
-        })
+        .map(|future_entry| adapt_future_entry(future_entry, &publisher_signature))
         .collect::<Result<Vec<NewFutureEntry>, EntryError>>()?;
 
-    let data =
-        serde_json::to_vec(&new_entries_db).map_err(|e| EntryError::PublishData(e.to_string()))?;
+    let data = serde_json::to_vec(&EntriesPayload::Future(new_entries_db))
+        .map_err(|e| EntryError::PublishData(e.to_string()))?;
 
     if let Err(e) = kafka::send_message(config.kafka_topic(), &data, &publisher_name).await {
         tracing::error!("Error sending message to kafka: {:?}", e);
@@ -150,11 +116,92 @@ pub async fn create_future_entries(
     }))
 }
 
+/// Converts a published future entry to the entry stored in the database.
+fn adapt_future_entry(
+    future_entry: &FutureEntry,
+    publisher_signature: &str,
+) -> Result<NewFutureEntry, EntryError> {
+    let dt = match DateTime::<Utc>::from_timestamp(future_entry.base.timestamp as i64, 0) {
+        Some(dt) => dt.naive_utc(),
+        None => {
+            return Err(EntryError::InvalidTimestamp(format!(
+                "Could not convert {} to DateTime",
+                future_entry.base.timestamp
+            )))
+        }
+    };
+
+    // For expiration_timestamp, 0 is sent by publishers for perpetual entries.
+    // We set them to None in the database to easily filter them out.
+    let expiry_dt = if future_entry.expiration_timestamp == 0 {
+        None
+    } else {
+        match DateTime::<Utc>::from_timestamp_millis(future_entry.expiration_timestamp as i64) {
+            Some(dt) => Some(dt.naive_utc()),
+            None => {
+                return Err(EntryError::InvalidTimestamp(format!(
+                    "Could not convert {} to DateTime",
+                    future_entry.expiration_timestamp
+                )))
+            }
+        }
+    };
+
+    Ok(NewFutureEntry {
+        pair_id: future_entry.pair_id.clone(),
+        publisher: future_entry.base.publisher.clone(),
+        source: future_entry.base.source.clone(),
+        timestamp: dt,
+        expiration_timestamp: expiry_dt,
+        publisher_signature: publisher_signature.to_string(),
+        price: future_entry.price.into(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
 
-    use crate::types::entries::{build_publish_message, FutureEntry, PerpEntry};
+    use super::adapt_future_entry;
+    use crate::types::entries::{build_publish_message, BaseEntry, FutureEntry, PerpEntry};
+
+    fn future_entry(expiration_timestamp: u64) -> FutureEntry {
+        FutureEntry {
+            base: BaseEntry {
+                timestamp: 1718000000,
+                source: "BINANCE".to_string(),
+                publisher: "PRAGMA".to_string(),
+            },
+            pair_id: "BTC/USD".to_string(),
+            price: 6_000_000_000_000,
+            volume: 0,
+            expiration_timestamp,
+        }
+    }
+
+    #[rstest]
+    fn test_perpetual_entry_has_no_expiration() {
+        let entry = adapt_future_entry(&future_entry(0), "0x1").unwrap();
+        assert_eq!(entry.expiration_timestamp, None);
+        assert_eq!(entry.timestamp.and_utc().timestamp(), 1718000000);
+        assert_eq!(entry.publisher_signature, "0x1");
+        assert_eq!(
+            entry.price,
+            bigdecimal::BigDecimal::from(6_000_000_000_000_u64)
+        );
+    }
+
+    #[rstest]
+    fn test_dated_future_keeps_its_expiration() {
+        // 27 December 2024 08:00:00 UTC, in milliseconds.
+        let entry = adapt_future_entry(&future_entry(1735286400000), "0x1").unwrap();
+        assert_eq!(
+            entry
+                .expiration_timestamp
+                .map(|expiry| expiry.and_utc().timestamp_millis()),
+            Some(1735286400000)
+        );
+    }
 
     #[rstest]
     fn test_build_publish_message_empty() {
@@ -0,0 +1,192 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use bigdecimal::{BigDecimal, FromPrimitive};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use pragma_common::types::{AggregationMode, DataType};
+use pragma_entities::EntryError;
+
+use crate::config::config;
+use crate::handlers::get_perp_entry::compute_mark_entry;
+use crate::infra::repositories::entry_repository::{self, MedianEntryWithComponents};
+use crate::types::pricer::{IndexPricer, Pricer};
+use crate::utils::{
+    assert_currencies_are_distinct, big_decimal_price_to_hex, currency_pair_to_pair_id,
+    PathExtractor,
+};
+use crate::AppState;
+
+#[derive(Debug, Default, Deserialize, IntoParams, ToSchema)]
+pub struct GetBlendedEntryParams {
+    /// Weight of the perp mark price in the blended price, between 0 and 1.
+    /// The spot index price is weighted by its complement.
+    pub perp_weight: Option<f64>,
+}
+
+/// Price of one of the legs of the blended price.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BlendedComponent {
+    pub price: String,
+    pub weight: f64,
+    pub num_sources_aggregated: usize,
+    /// Number of sources left out of the aggregation, only the most recent
+    /// ones being aggregated when their number is limited.
+    pub num_sources_dropped: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
+pub struct GetBlendedEntryResponse {
+    pub pair_id: String,
+    /// Weighted average of the spot index price and the perp mark price.
+    pub price: String,
+    /// Unix timestamp in milliseconds of the computation of the price.
+    pub timestamp: u64,
+    pub decimals: u32,
+    /// Spot index price of the pair.
+    pub spot: BlendedComponent,
+    /// Perp mark price of the pair.
+    pub perp: BlendedComponent,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/data/blended/{base}/{quote}",
+    responses(
+        (status = 200, description = "Get the current price of a pair, blending its spot index & perp mark prices", body = GetBlendedEntryResponse),
+        (status = 400, description = "Invalid perp weight", body = EntryError),
+        (status = 404, description = "Missing spot or perp price", body = EntryError)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+        GetBlendedEntryParams
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_blended_entry(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+    Query(params): Query<GetBlendedEntryParams>,
+) -> Result<Json<GetBlendedEntryResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
+    let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+    let perp_weight = params
+        .perp_weight
+        .unwrap_or(config().await.blended_perp_weight());
+    if !(0.0..=1.0).contains(&perp_weight) {
+        return Err(EntryError::InvalidWeight(perp_weight));
+    }
+
+    let spot_pricer = IndexPricer::new(vec![pair_id.clone()], DataType::SpotEntry);
+    let (spot_entries, perp_entry) = tokio::join!(
+        spot_pricer.compute(&state.offchain_pool, AggregationMode::Median),
+        compute_mark_entry(&state.offchain_pool, &pair_id)
+    );
+    let spot_entry = spot_entries?
+        .into_iter()
+        .find(|entry| entry.pair_id == pair_id)
+        .ok_or_else(|| EntryError::NotFound(pair_id.clone()))?;
+    let perp_entry = perp_entry.map_err(|e| match e {
+        EntryError::UnknownPairId(_) | EntryError::NotFound(_) => {
+            EntryError::NotFound(format!("{pair_id}:MARK"))
+        }
+        e => e,
+    })?;
+
+    let decimals = entry_repository::get_decimals(&state.offchain_pool, &pair_id).await?;
+
+    adapt_to_blended_response(
+        pair_id,
+        &spot_entry,
+        &perp_entry,
+        perp_weight,
+        decimals,
+        chrono::Utc::now().timestamp_millis() as u64,
+    )
+    .map(Json)
+}
+
+/// Blends the spot index price and the perp mark price of the pair, the
+/// perp one being weighted by `perp_weight`.
+fn adapt_to_blended_response(
+    pair_id: String,
+    spot_entry: &MedianEntryWithComponents,
+    perp_entry: &MedianEntryWithComponents,
+    perp_weight: f64,
+    decimals: u32,
+    timestamp: u64,
+) -> Result<GetBlendedEntryResponse, EntryError> {
+    let spot_weight = 1.0 - perp_weight;
+    let price = &spot_entry.median_price
+        * BigDecimal::from_f64(spot_weight).ok_or(EntryError::InvalidWeight(spot_weight))?
+        + &perp_entry.median_price
+            * BigDecimal::from_f64(perp_weight).ok_or(EntryError::InvalidWeight(perp_weight))?;
+
+    Ok(GetBlendedEntryResponse {
+        pair_id,
+        price: big_decimal_price_to_hex(&price),
+        timestamp,
+        decimals,
+        spot: BlendedComponent {
+            price: big_decimal_price_to_hex(&spot_entry.median_price),
+            weight: spot_weight,
+            num_sources_aggregated: spot_entry.components.len(),
+            num_sources_dropped: spot_entry.num_sources_dropped,
+        },
+        perp: BlendedComponent {
+            price: big_decimal_price_to_hex(&perp_entry.median_price),
+            weight: perp_weight,
+            num_sources_aggregated: perp_entry.components.len(),
+            num_sources_dropped: perp_entry.num_sources_dropped,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::repositories::entry_repository::tests::{median_entry, pair_component};
+
+    fn entry(pair_id: &str, price: u64, publishers: &[&str]) -> MedianEntryWithComponents {
+        let components = publishers
+            .iter()
+            .map(|publisher| pair_component(pair_id, publisher, price, 1718000000))
+            .collect();
+        median_entry(pair_id, price, components)
+    }
+
+    #[test]
+    fn test_blended_price_weights_spot_and_perp() {
+        // 60_000 & 60_400 with 8 decimals.
+        let spot = entry("BTC/USD", 6_000_000_000_000, &["BINANCE", "OKX", "BYBIT"]);
+        let perp = entry("BTC/USD", 6_040_000_000_000, &["BINANCE", "OKX"]);
+
+        let response =
+            adapt_to_blended_response("BTC/USD".to_string(), &spot, &perp, 0.25, 8, 1718000000000)
+                .unwrap();
+        // 0.75 * 60_000 + 0.25 * 60_400 = 60_100
+        assert_eq!(
+            response.price,
+            big_decimal_price_to_hex(&BigDecimal::from(6_010_000_000_000_u64))
+        );
+        assert_eq!(response.spot.weight, 0.75);
+        assert_eq!(response.spot.num_sources_aggregated, 3);
+        assert_eq!(response.perp.weight, 0.25);
+        assert_eq!(response.perp.num_sources_aggregated, 2);
+        assert_eq!(
+            response.perp.price,
+            big_decimal_price_to_hex(&BigDecimal::from(6_040_000_000_000_u64))
+        );
+
+        // Each weight bound returns one of the legs.
+        let only_spot =
+            adapt_to_blended_response("BTC/USD".to_string(), &spot, &perp, 0.0, 8, 1718000000000)
+                .unwrap();
+        assert_eq!(only_spot.price, only_spot.spot.price);
+        let only_perp =
+            adapt_to_blended_response("BTC/USD".to_string(), &spot, &perp, 1.0, 8, 1718000000000)
+                .unwrap();
+        assert_eq!(only_perp.price, only_perp.perp.price);
+    }
+}
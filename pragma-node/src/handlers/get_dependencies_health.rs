@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use crate::config::config;
+use crate::handlers::get_database_health::check_database;
+use crate::infra::redis;
+use crate::AppState;
+
+/// Maximum time spent pinging Redis.
+const REDIS_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Availability of each dependency of the node.
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema, PartialEq)]
+pub struct GetDependenciesHealthResponse {
+    /// True if both the offchain and the onchain databases are reachable.
+    pub postgres: bool,
+    /// True if Redis, used by the merkle feeds, is reachable.
+    pub redis: bool,
+}
+
+impl GetDependenciesHealthResponse {
+    /// Returns true if all the required dependencies are available. Redis
+    /// is only required if configured so.
+    pub fn is_healthy(&self, redis_required: bool) -> bool {
+        self.postgres && (self.redis || !redis_required)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/health/dependencies",
+    responses(
+        (status = 200, description = "All the required dependencies are available", body = GetDependenciesHealthResponse),
+        (status = 503, description = "A required dependency is unavailable", body = GetDependenciesHealthResponse)
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_dependencies_health(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<GetDependenciesHealthResponse>) {
+    let (offchain, onchain, redis) = tokio::join!(
+        check_database(&state.offchain_pool),
+        check_database(&state.onchain_pool),
+        redis_health(state.redis_client.as_deref())
+    );
+    let response = GetDependenciesHealthResponse {
+        postgres: offchain.is_ok() && onchain.is_ok(),
+        redis,
+    };
+    let status = if response.is_healthy(config().await.redis_required()) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(response))
+}
+
+/// Returns true if Redis answers a ping through the client of the merkle feeds.
+pub(crate) async fn redis_health(redis_client: Option<&::redis::Client>) -> bool {
+    let Some(redis_client) = redis_client else {
+        return false;
+    };
+    match tokio::time::timeout(REDIS_CHECK_TIMEOUT, redis::ping(redis_client)).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("Redis is unreachable: {}", e);
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Redis ping timed out");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependencies_health_status() {
+        let response = GetDependenciesHealthResponse {
+            postgres: true,
+            redis: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({"postgres": true, "redis": false})
+        );
+        assert!(response.is_healthy(false));
+        assert!(!response.is_healthy(true));
+
+        let response = GetDependenciesHealthResponse {
+            postgres: false,
+            redis: true,
+        };
+        assert!(!response.is_healthy(false));
+    }
+
+    #[tokio::test]
+    async fn test_redis_is_down_without_client() {
+        assert!(!redis_health(None).await);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use futures_util::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use pragma_entities::EntryError;
+
+use crate::constants::others::{MAX_BATCH_CONCURRENCY, MAX_BATCH_PAIRS};
+use crate::handlers::get_entry::{
+    compute_entry_response, EntryResponseOptions, GetEntryResponse, RoutingParams,
+};
+use crate::utils::pair_id_to_currency_pair;
+use crate::AppState;
+
+use super::GetEntryParams;
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct GetEntriesBatchRequest {
+    /// Pairs to fetch, e.g `["BTC/USD", "ETH/USD"]`.
+    pub pairs: Vec<String>,
+}
+
+/// Entry of a pair of the batch, or the reason why it couldn't be computed.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum BatchEntry {
+    Entry(GetEntryResponse),
+    Error { error: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetEntriesBatchResponse(pub BTreeMap<String, BatchEntry>);
+
+#[utoipa::path(
+    post,
+    path = "/node/v1/data/batch",
+    request_body = GetEntriesBatchRequest,
+    responses(
+        (status = 200, description = "Get the entries of several pairs", body = GetEntriesBatchResponse),
+        (status = 400, description = "Too many pairs requested", body = EntryError)
+    ),
+    params(GetEntryParams),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_entries_batch(
+    State(state): State<AppState>,
+    Query(params): Query<GetEntryParams>,
+    Json(request): Json<GetEntriesBatchRequest>,
+) -> Result<Json<GetEntriesBatchResponse>, EntryError> {
+    let pairs = dedup_pairs(request.pairs, MAX_BATCH_PAIRS)?;
+    let is_routing = params.routing.unwrap_or(false);
+    let options = EntryResponseOptions::from(&params);
+    let routing_params = RoutingParams::try_from(params)?;
+
+    let response: BTreeMap<_, _> = stream::iter(pairs)
+        .map(|pair_id| {
+            let routing_params = routing_params.clone();
+            let state = &state;
+            async move {
+                let entry =
+                    compute_pair_entry(state, &pair_id, is_routing, routing_params, options).await;
+                let entry = match entry {
+                    Ok(entry) => BatchEntry::Entry(entry),
+                    Err(e) => BatchEntry::Error {
+                        error: e.to_string(),
+                    },
+                };
+                (pair_id, entry)
+            }
+        })
+        .buffer_unordered(MAX_BATCH_CONCURRENCY)
+        .collect()
+        .await;
+    Ok(Json(GetEntriesBatchResponse(response)))
+}
+
+/// Computes the entry of a single pair of the batch.
+async fn compute_pair_entry(
+    state: &AppState,
+    pair_id: &str,
+    is_routing: bool,
+    routing_params: RoutingParams,
+    options: EntryResponseOptions,
+) -> Result<GetEntryResponse, EntryError> {
+    let (base, quote) = pair_id_to_currency_pair(pair_id)?;
+    compute_entry_response(state, (&base, &quote), is_routing, routing_params, options).await
+}
+
+/// Uppercases and deduplicates the requested pairs.
+/// Returns an error if there are more than `max_pairs` distinct pairs.
+fn dedup_pairs(pairs: Vec<String>, max_pairs: usize) -> Result<Vec<String>, EntryError> {
+    let mut deduped: Vec<String> = pairs
+        .into_iter()
+        .map(|pair_id| pair_id.trim().to_uppercase())
+        .collect();
+    deduped.sort();
+    deduped.dedup();
+    if deduped.len() > max_pairs {
+        return Err(EntryError::TooManyPairs(deduped.len(), max_pairs));
+    }
+    Ok(deduped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_pairs() {
+        let pairs = vec!["btc/usd".into(), "ETH/USD".into(), " BTC/USD ".into()];
+        assert_eq!(
+            dedup_pairs(pairs, 2).unwrap(),
+            vec!["BTC/USD".to_string(), "ETH/USD".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_too_many_pairs_are_rejected() {
+        let pairs = (0..=MAX_BATCH_PAIRS).map(|i| format!("T{i}/USD")).collect();
+        assert!(matches!(
+            dedup_pairs(pairs, MAX_BATCH_PAIRS),
+            Err(EntryError::TooManyPairs(requested, max)) if requested == MAX_BATCH_PAIRS + 1 && max == MAX_BATCH_PAIRS
+        ));
+    }
+
+    #[test]
+    fn test_pairs_without_data_are_returned_as_errors() {
+        let response = GetEntriesBatchResponse(BTreeMap::from([(
+            "FOO/USD".to_string(),
+            BatchEntry::Error {
+                error: EntryError::NotFound("FOO/USD".to_string()).to_string(),
+            },
+        )]));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"FOO/USD": {"error": "entry not found: FOO/USD"}})
+        );
+    }
+}
@@ -1,19 +1,28 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
+
 use axum::extract::{Query, State};
 use axum::Json;
+use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::{DateTime, NaiveDateTime, Utc};
 
 use pragma_common::types::{AggregationMode, DataType, Interval};
-use pragma_entities::EntryError;
+use pragma_entities::{EntryError, InfraError};
 use serde::{Deserialize, Serialize};
 use utoipa::{ToResponse, ToSchema};
 
-use crate::infra::repositories::entry_repository::{self, MedianEntry};
+use crate::config::config;
+use crate::constants::others::{DEFAULT_SIGNIFICANT_DIGITS, MAX_SIGNIFICANT_DIGITS};
+use crate::infra::repositories::entry_repository::{self, MedianEntry, PublisherSourcePriceRaw};
 use crate::utils::PathExtractor;
 use crate::AppState;
 
-use crate::utils::{big_decimal_price_to_hex, currency_pair_to_pair_id};
+use crate::utils::{
+    assert_currencies_are_distinct, big_decimal_price_to_hex, computation_time_ms,
+    currency_pair_to_pair_id, format_bigdecimal_price, format_bigdecimal_price_scientific,
+};
 
-use super::GetEntryParams;
+use super::{GetEntryParams, PriceNotation, PriceRounding, SourcesGrouping};
 
 #[derive(Default, Clone, Debug)]
 pub struct RoutingParams {
@@ -22,6 +31,8 @@ pub struct RoutingParams {
     pub aggregation_mode: AggregationMode,
     pub data_type: DataType,
     pub expiry: String,
+    pub as_of: Option<i64>,
+    pub sources: Option<Vec<String>>,
 }
 
 impl TryFrom<GetEntryParams> for RoutingParams {
@@ -33,7 +44,7 @@ impl TryFrom<GetEntryParams> for RoutingParams {
         let timestamp = if let Some(timestamp) = params.timestamp {
             timestamp
         } else {
-            now
+            params.as_of.unwrap_or(now).min(now)
         };
 
         if timestamp > now {
@@ -42,16 +53,29 @@ impl TryFrom<GetEntryParams> for RoutingParams {
             )));
         }
 
+        if let Some(as_of) = params.as_of {
+            if as_of > now {
+                return Err(EntryError::InvalidTimestamp(format!(
+                    "As of timestamp is in the future: {as_of}"
+                )));
+            }
+            if timestamp > as_of {
+                return Err(EntryError::InvalidTimestamp(format!(
+                    "Timestamp {timestamp} is after the as of timestamp {as_of}"
+                )));
+            }
+        }
+
         let interval = if let Some(interval) = params.interval {
             interval
         } else {
             Interval::TwoHours
         };
 
-        let aggregation_mode = if let Some(aggregation_mode) = params.aggregation {
-            aggregation_mode
-        } else {
-            AggregationMode::Twap
+        let aggregation_mode = match (params.aggregation, params.as_of) {
+            (Some(aggregation_mode), _) => aggregation_mode,
+            (None, Some(_)) => AggregationMode::Median,
+            (None, None) => AggregationMode::Twap,
         };
 
         let data_type = if let Some(entry_type) = params.entry_type {
@@ -60,6 +84,24 @@ impl TryFrom<GetEntryParams> for RoutingParams {
             DataType::SpotEntry
         };
 
+        if params.as_of.is_some()
+            && (!matches!(aggregation_mode, AggregationMode::Median)
+                || data_type != DataType::SpotEntry)
+        {
+            return Err(EntryError::InvalidTimestamp(
+                "As of is only supported with the median aggregation on spot entries".to_string(),
+            ));
+        }
+
+        let sources = params.sources.as_deref().map(parse_sources).transpose()?;
+        if sources.is_some()
+            && (!matches!(aggregation_mode, AggregationMode::Median) || params.as_of.is_some())
+        {
+            return Err(EntryError::InvalidSources(
+                "Sources are only supported with the median aggregation".to_string(),
+            ));
+        }
+
         let expiry = if let Some(expiry) = params.expiry {
             let expiry_dt = NaiveDateTime::parse_from_str(&expiry, "%Y-%m-%dT%H:%M:%S")
                 .map(|naive| DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc));
@@ -77,10 +119,68 @@ impl TryFrom<GetEntryParams> for RoutingParams {
             aggregation_mode,
             data_type,
             expiry,
+            as_of: params.as_of,
+            sources,
         })
     }
 }
 
+impl RoutingParams {
+    /// Returns the lookback window of the TWAP in seconds: the default one,
+    /// widened to the interval so at least one bucket can be found.
+    pub fn twap_lookback_in_seconds(&self, default_lookback: std::time::Duration) -> i64 {
+        (default_lookback.as_secs() as i64).max(self.interval.to_seconds())
+    }
+}
+
+/// Parses a comma-separated list of sources, uppercased like they are stored.
+fn parse_sources(sources: &str) -> Result<Vec<String>, EntryError> {
+    let sources: Vec<String> = sources
+        .split(',')
+        .map(|source| source.trim().to_uppercase())
+        .filter(|source| !source.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if sources.is_empty() {
+        return Err(EntryError::InvalidSources(
+            "Expected at least one source".to_string(),
+        ));
+    }
+    Ok(sources)
+}
+
+/// Optional computations of the entry response, requested in the params.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct EntryResponseOptions {
+    pub with_timing: bool,
+    pub with_mid: bool,
+    pub best_effort: bool,
+    pub notation: Option<PriceNotation>,
+    pub significant_digits: u32,
+    pub rounding: PriceRounding,
+    pub with_components: bool,
+    pub group_by: Option<SourcesGrouping>,
+}
+
+impl From<&GetEntryParams> for EntryResponseOptions {
+    fn from(params: &GetEntryParams) -> Self {
+        Self {
+            with_timing: params.timing.unwrap_or(false),
+            with_mid: params.mid.unwrap_or(false),
+            best_effort: params.best_effort.unwrap_or(false),
+            notation: params.notation,
+            significant_digits: params
+                .significant_digits
+                .unwrap_or(DEFAULT_SIGNIFICANT_DIGITS)
+                .clamp(1, MAX_SIGNIFICANT_DIGITS),
+            rounding: params.rounding.unwrap_or_default(),
+            with_components: params.show_components.unwrap_or(false),
+            group_by: params.group_by,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
 pub struct GetEntryResponse {
     num_sources_aggregated: usize,
@@ -88,13 +188,78 @@ pub struct GetEntryResponse {
     price: String,
     timestamp: u64,
     decimals: u32,
+    /// Set when the base and the quote are identical: the price is always 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identity: Option<bool>,
+    /// Set when the pair is deprecated: the data is the one of the canonical pair.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deprecated: Option<bool>,
+    /// Pair replacing the requested one if it is deprecated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical_pair: Option<String>,
+    /// Time spent computing the aggregation in milliseconds, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    computation_time_ms: Option<f64>,
+    /// Median of the mid between the bid and the ask of the sources, if
+    /// requested. Sources not reporting them contribute their price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mid: Option<String>,
+    /// Price adjusted by its decimals in the requested notation, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    formatted_price: Option<String>,
+    /// Lookback window of the TWAP in seconds, set when the price is a TWAP.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    twap_lookback_in_seconds: Option<i64>,
+    /// Latest price of each source that contributed to the aggregation, if
+    /// requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources: Option<Vec<EntrySource>>,
+    /// Latest price of each source keyed by source name, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources_by_name: Option<BTreeMap<String, SourceLatestPrice>>,
+}
+
+/// Latest price of a source over the aggregation interval, among all the
+/// publishers relaying it.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct SourceLatestPrice {
+    pub publisher: String,
+    pub price: String,
+    pub timestamp: u64,
+}
+
+/// Latest price of a (publisher, source) over the aggregation interval.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct EntrySource {
+    pub publisher: String,
+    pub source: String,
+    pub price: String,
+    pub timestamp: u64,
+    /// Reputation score of the publisher, between 0 and 1.
+    pub reputation: f64,
+}
+
+impl EntrySource {
+    pub fn new(raw_price: PublisherSourcePriceRaw, reputation: f64) -> Self {
+        Self {
+            publisher: raw_price.publisher,
+            source: raw_price.source,
+            price: big_decimal_price_to_hex(&raw_price.price),
+            timestamp: raw_price.time.and_utc().timestamp_millis() as u64,
+            reputation,
+        }
+    }
 }
 
+/// Decimals used for the price of identity pairs.
+const IDENTITY_PRICE_DECIMALS: u32 = 8;
+
 #[utoipa::path(
     get,
     path = "/node/v1/data/{base}/{quote}",
     responses(
-        (status = 200, description = "Get median entry successfuly", body = [GetEntryResponse])
+        (status = 200, description = "Get median entry successfuly", body = [GetEntryResponse]),
+        (status = 503, description = "The database is unavailable", body = EntryError)
     ),
     params(
         ("base" = String, Path, description = "Base Asset"),
@@ -109,31 +274,272 @@ pub async fn get_entry(
     Query(params): Query<GetEntryParams>,
 ) -> Result<Json<GetEntryResponse>, EntryError> {
     let is_routing = params.routing.unwrap_or(false);
+    let options = EntryResponseOptions::from(&params);
 
     let routing_params = RoutingParams::try_from(params)?;
 
-    let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+    let response = compute_entry_response(
+        &state,
+        (&pair.0, &pair.1),
+        is_routing,
+        routing_params,
+        options,
+    )
+    .await?;
+    Ok(Json(response))
+}
 
+/// Computes the entry of a pair.
+/// Deprecated pairs are served with the data of the pair replacing them.
+/// Unless in best effort, the aggregation is rejected if the prices of the
+/// sources spread more than the configured maximum.
+pub(crate) async fn compute_entry_response(
+    state: &AppState,
+    (base, quote): (&str, &str),
+    is_routing: bool,
+    routing_params: RoutingParams,
+    options: EntryResponseOptions,
+) -> Result<GetEntryResponse, EntryError> {
+    let pair_id = currency_pair_to_pair_id(base, quote);
+    if let Err(e) = assert_currencies_are_distinct(base, quote) {
+        if config().await.return_identity_price() {
+            return Ok(identity_entry_response(pair_id));
+        }
+        return Err(e);
+    }
+
+    let canonical_pair = config().await.canonical_pair(&pair_id);
+    let data_pair_id = canonical_pair.clone().unwrap_or_else(|| pair_id.clone());
+
+    let started_at = Instant::now();
     let (entry, decimals) = entry_repository::routing(
         &state.offchain_pool,
         is_routing,
-        pair_id.clone(),
-        routing_params,
+        data_pair_id.clone(),
+        routing_params.clone(),
     )
     .await
-    .map_err(|e| e.to_entry_error(&(pair_id)))?;
+    .map_err(|e| match (e, &routing_params.sources) {
+        // Tell that the pair has no data from the requested sources only.
+        (InfraError::NotFound, Some(sources)) => EntryError::NotFound(format!(
+            "{data_pair_id} from the sources {}",
+            sources.join(", ")
+        )),
+        (e, _) => e.to_entry_error(&data_pair_id),
+    })?;
+    let computation_time_ms = computation_time_ms(options.with_timing, started_at);
+    let entry = MedianEntry {
+        median_price: round_price(&entry.median_price, options.rounding),
+        ..entry
+    };
+
+    let max_spread = config()
+        .await
+        .max_sources_spread()
+        .filter(|_| !options.best_effort);
+    let sources_prices =
+        if max_spread.is_some() || options.with_components || options.group_by.is_some() {
+            let mut sources_prices = entry_repository::get_sources_prices(
+                &state.offchain_pool,
+                data_pair_id.clone(),
+                &routing_params,
+            )
+            .await?;
+            retain_requested_sources(&mut sources_prices, routing_params.sources.as_deref());
+            sources_prices
+        } else {
+            Vec::new()
+        };
+    if let Some(max_spread) = max_spread {
+        let prices: Vec<BigDecimal> = sources_prices.iter().map(|p| p.price.clone()).collect();
+        assert_sources_agree(&pair_id, &prices, max_spread)?;
+    }
+    let sources_by_name = options.group_by.map(|grouping| match grouping {
+        SourcesGrouping::Source => group_by_source(&sources_prices),
+    });
+    let sources = if options.with_components {
+        let config = config().await;
+        Some(adapt_entry_sources(sources_prices, |publisher| {
+            config.publisher_reputation(publisher)
+        }))
+    } else {
+        None
+    };
+
+    let mid = if options.with_mid {
+        let mid = match routing_params.data_type {
+            DataType::SpotEntry => {
+                entry_repository::get_mid(
+                    &state.offchain_pool,
+                    data_pair_id.clone(),
+                    &routing_params,
+                )
+                .await?
+            }
+            _ => None,
+        };
+        Some(big_decimal_price_to_hex(&round_price(
+            &mid.unwrap_or_else(|| entry.median_price.clone()),
+            options.rounding,
+        )))
+    } else {
+        None
+    };
 
     let last_updated_timestamp: NaiveDateTime =
-        entry_repository::get_last_updated_timestamp(&state.offchain_pool, pair_id.to_owned())
+        entry_repository::get_last_updated_timestamp(&state.offchain_pool, data_pair_id)
             .await?
             .unwrap_or(entry.time);
 
-    Ok(Json(adapt_entry_to_entry_response(
-        pair_id,
-        &entry,
-        decimals,
-        last_updated_timestamp,
-    )))
+    let formatted_price = options.notation.map(|notation| {
+        format_price(
+            &entry.median_price,
+            decimals,
+            notation,
+            options.significant_digits,
+        )
+    });
+
+    let twap_lookback_in_seconds = match routing_params.aggregation_mode {
+        AggregationMode::Twap => {
+            Some(routing_params.twap_lookback_in_seconds(config().await.twap_default_lookback()))
+        }
+        _ => None,
+    };
+
+    let response = GetEntryResponse {
+        computation_time_ms,
+        mid,
+        formatted_price,
+        twap_lookback_in_seconds,
+        sources,
+        sources_by_name,
+        ..adapt_entry_to_entry_response(pair_id, &entry, decimals, last_updated_timestamp)
+    };
+    Ok(with_canonical_pair(response, canonical_pair))
+}
+
+/// Only keeps the prices of the requested sources, if any.
+fn retain_requested_sources(
+    sources_prices: &mut Vec<PublisherSourcePriceRaw>,
+    requested_sources: Option<&[String]>,
+) {
+    if let Some(requested_sources) = requested_sources {
+        sources_prices.retain(|price| requested_sources.contains(&price.source));
+    }
+}
+
+/// Converts the prices of the sources, along with the reputation of their publisher.
+fn adapt_entry_sources(
+    sources_prices: Vec<PublisherSourcePriceRaw>,
+    reputation: impl Fn(&str) -> f64,
+) -> Vec<EntrySource> {
+    sources_prices
+        .into_iter()
+        .map(|raw_price| {
+            let reputation = reputation(&raw_price.publisher);
+            EntrySource::new(raw_price, reputation)
+        })
+        .collect()
+}
+
+/// Pivots the prices by source, keeping the latest price of each source.
+fn group_by_source(
+    sources_prices: &[PublisherSourcePriceRaw],
+) -> BTreeMap<String, SourceLatestPrice> {
+    let mut latest_prices: BTreeMap<String, &PublisherSourcePriceRaw> = BTreeMap::new();
+    for price in sources_prices {
+        latest_prices
+            .entry(price.source.clone())
+            .and_modify(|latest| {
+                if price.time > latest.time {
+                    *latest = price;
+                }
+            })
+            .or_insert(price);
+    }
+    latest_prices
+        .into_iter()
+        .map(|(source, price)| {
+            (
+                source,
+                SourceLatestPrice {
+                    publisher: price.publisher.clone(),
+                    price: big_decimal_price_to_hex(&price.price),
+                    timestamp: price.time.and_utc().timestamp_millis() as u64,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Returns the spread between the lowest and the highest price, relative to
+/// the median price. Returns None if there are less than two prices.
+fn sources_spread(prices: &[BigDecimal]) -> Option<f64> {
+    if prices.len() < 2 {
+        return None;
+    }
+    let mut prices = prices.to_vec();
+    prices.sort();
+    let mid = prices.len() / 2;
+    let median = if prices.len() % 2 == 0 {
+        (&prices[mid - 1] + &prices[mid]) / BigDecimal::from(2)
+    } else {
+        prices[mid].clone()
+    };
+    if median == BigDecimal::from(0) {
+        return None;
+    }
+    let spread = (&prices[prices.len() - 1] - &prices[0]) / median;
+    spread.abs().to_f64()
+}
+
+/// Returns an error if the prices of the sources spread more than `max_spread`.
+fn assert_sources_agree(
+    pair_id: &str,
+    prices: &[BigDecimal],
+    max_spread: f64,
+) -> Result<(), EntryError> {
+    match sources_spread(prices) {
+        Some(spread) if spread > max_spread => Err(EntryError::SourcesDisagree(
+            pair_id.to_string(),
+            spread,
+            max_spread,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Rounds the price, expressed in units of its decimals, to an integer.
+fn round_price(price: &BigDecimal, rounding: PriceRounding) -> BigDecimal {
+    price.with_scale_round(0, rounding.into())
+}
+
+/// Formats the price adjusted by its decimals in the provided notation.
+fn format_price(
+    price: &BigDecimal,
+    decimals: u32,
+    notation: PriceNotation,
+    significant_digits: u32,
+) -> String {
+    match notation {
+        PriceNotation::Decimal => format_bigdecimal_price(price.clone(), decimals),
+        PriceNotation::Scientific => {
+            format_bigdecimal_price_scientific(price, decimals, significant_digits)
+        }
+    }
+}
+
+/// Flags the response as deprecated if the requested pair has been replaced.
+fn with_canonical_pair(
+    response: GetEntryResponse,
+    canonical_pair: Option<String>,
+) -> GetEntryResponse {
+    GetEntryResponse {
+        deprecated: canonical_pair.is_some().then_some(true),
+        canonical_pair,
+        ..response
+    }
 }
 
 fn adapt_entry_to_entry_response(
@@ -148,5 +554,356 @@ fn adapt_entry_to_entry_response(
         num_sources_aggregated: entry.num_sources as usize,
         price: big_decimal_price_to_hex(&entry.median_price),
         decimals,
+        identity: None,
+        deprecated: None,
+        canonical_pair: None,
+        computation_time_ms: None,
+        mid: None,
+        formatted_price: None,
+        twap_lookback_in_seconds: None,
+        sources: None,
+        sources_by_name: None,
+    }
+}
+
+/// Builds the response of a pair whose base and quote are identical,
+/// i.e a constant price of 1.
+fn identity_entry_response(pair_id: String) -> GetEntryResponse {
+    let price = BigDecimal::from(10_u64.pow(IDENTITY_PRICE_DECIMALS));
+    GetEntryResponse {
+        num_sources_aggregated: 0,
+        pair_id,
+        price: big_decimal_price_to_hex(&price),
+        timestamp: chrono::Utc::now().timestamp_millis() as u64,
+        decimals: IDENTITY_PRICE_DECIMALS,
+        identity: Some(true),
+        deprecated: None,
+        canonical_pair: None,
+        computation_time_ms: None,
+        mid: None,
+        formatted_price: None,
+        twap_lookback_in_seconds: None,
+        sources: None,
+        sources_by_name: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_entry_response() {
+        let response = identity_entry_response("USD/USD".to_string());
+        assert_eq!(response.pair_id, "USD/USD");
+        assert_eq!(response.price, "0x5f5e100");
+        assert_eq!(response.decimals, 8);
+        assert_eq!(response.identity, Some(true));
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["identity"], true);
+    }
+
+    #[test]
+    fn test_identity_flag_is_omitted_for_regular_pairs() {
+        let entry = MedianEntry {
+            time: chrono::DateTime::from_timestamp(1718000000, 0)
+                .unwrap()
+                .naive_utc(),
+            median_price: BigDecimal::from(100),
+            num_sources: 3,
+        };
+        let response = adapt_entry_to_entry_response("BTC/USD".to_string(), &entry, 8, entry.time);
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("identity").is_none());
+    }
+
+    #[test]
+    fn test_deprecated_pair_resolves_to_canonical_data() {
+        let entry = MedianEntry {
+            time: chrono::DateTime::from_timestamp(1718000000, 0)
+                .unwrap()
+                .naive_utc(),
+            median_price: BigDecimal::from(45000000),
+            num_sources: 5,
+        };
+        // The entry is the one fetched for the canonical pair.
+        let response = with_canonical_pair(
+            adapt_entry_to_entry_response("MATIC/USD".to_string(), &entry, 8, entry.time),
+            Some("POL/USD".to_string()),
+        );
+        assert_eq!(response.pair_id, "MATIC/USD");
+        assert_eq!(
+            response.price,
+            big_decimal_price_to_hex(&entry.median_price)
+        );
+        assert_eq!(response.num_sources_aggregated, 5);
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["deprecated"], true);
+        assert_eq!(json["canonical_pair"], "POL/USD");
+
+        let response = with_canonical_pair(
+            adapt_entry_to_entry_response("POL/USD".to_string(), &entry, 8, entry.time),
+            None,
+        );
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("deprecated").is_none());
+        assert!(json.get("canonical_pair").is_none());
+    }
+
+    #[test]
+    fn test_timing_is_only_requested_with_the_param() {
+        let options = EntryResponseOptions::from(&GetEntryParams::default());
+        assert!(!options.with_timing);
+
+        let params = GetEntryParams {
+            timing: Some(true),
+            ..Default::default()
+        };
+        assert!(EntryResponseOptions::from(&params).with_timing);
+    }
+
+    fn prices(prices: &[i64]) -> Vec<BigDecimal> {
+        prices
+            .iter()
+            .map(|price| BigDecimal::from(*price))
+            .collect()
+    }
+
+    #[test]
+    fn test_sources_within_max_spread_agree() {
+        let prices = prices(&[100, 101, 99, 102]);
+        assert!(assert_sources_agree("BTC/USD", &prices, 0.05).is_ok());
+        // A single source can't disagree.
+        assert!(assert_sources_agree("BTC/USD", &[BigDecimal::from(100)], 0.0).is_ok());
+    }
+
+    #[test]
+    fn test_sources_above_max_spread_disagree() {
+        let prices = prices(&[100, 100, 150]);
+        assert!(matches!(
+            assert_sources_agree("BTC/USD", &prices, 0.05),
+            Err(EntryError::SourcesDisagree(pair_id, spread, max_spread))
+                if pair_id == "BTC/USD" && (spread - 0.5).abs() < 1e-9 && max_spread == 0.05
+        ));
+    }
+
+    #[test]
+    fn test_sources_are_parsed_uppercased_and_deduplicated() {
+        assert_eq!(
+            parse_sources(" okx,binance, OKX,").unwrap(),
+            vec!["BINANCE".to_string(), "OKX".to_string()]
+        );
+        assert!(matches!(
+            parse_sources(" , "),
+            Err(EntryError::InvalidSources(_))
+        ));
+    }
+
+    #[test]
+    fn test_sources_require_the_median_aggregation() {
+        let params = GetEntryParams {
+            timestamp: None,
+            sources: Some("binance".to_string()),
+            ..Default::default()
+        };
+        let routing_params = RoutingParams::try_from(params).unwrap();
+        assert_eq!(routing_params.sources, Some(vec!["BINANCE".to_string()]));
+
+        let params = GetEntryParams {
+            timestamp: None,
+            aggregation: Some(AggregationMode::Twap),
+            sources: Some("binance".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            RoutingParams::try_from(params),
+            Err(EntryError::InvalidSources(_))
+        ));
+    }
+
+    fn source_price(source: &str, price: u64) -> PublisherSourcePriceRaw {
+        PublisherSourcePriceRaw {
+            publisher: "PRAGMA".to_string(),
+            source: source.to_string(),
+            price: BigDecimal::from(price),
+            time: chrono::DateTime::from_timestamp(1718000000, 0)
+                .unwrap()
+                .naive_utc(),
+        }
+    }
+
+    #[test]
+    fn test_sources_are_only_returned_if_requested() {
+        let entry = MedianEntry {
+            time: chrono::DateTime::from_timestamp(1718000000, 0)
+                .unwrap()
+                .naive_utc(),
+            median_price: BigDecimal::from(100),
+            num_sources: 1,
+        };
+        let response = adapt_entry_to_entry_response("BTC/USD".to_string(), &entry, 8, entry.time);
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("sources").is_none());
+
+        let response = GetEntryResponse {
+            sources: Some(vec![EntrySource::new(source_price("BINANCE", 100), 0.5)]),
+            ..adapt_entry_to_entry_response("BTC/USD".to_string(), &entry, 8, entry.time)
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json["sources"],
+            serde_json::json!([{
+                "publisher": "PRAGMA",
+                "source": "BINANCE",
+                "price": "0x64",
+                "timestamp": 1718000000000_u64,
+                "reputation": 0.5,
+            }])
+        );
+    }
+
+    #[test]
+    fn test_sources_carry_the_reputation_of_their_publisher() {
+        let mut untrusted_price = source_price("OKX", 101);
+        untrusted_price.publisher = "SKYNET".to_string();
+        let mut unknown_price = source_price("BYBIT", 102);
+        unknown_price.publisher = "AVNU".to_string();
+
+        let sources = adapt_entry_sources(
+            vec![source_price("BINANCE", 100), untrusted_price, unknown_price],
+            |publisher| match publisher {
+                "PRAGMA" => 1.0,
+                "SKYNET" => 0.2,
+                _ => 0.5,
+            },
+        );
+        let reputations: Vec<(&str, f64)> = sources
+            .iter()
+            .map(|source| (source.publisher.as_str(), source.reputation))
+            .collect();
+        assert_eq!(
+            reputations,
+            vec![("PRAGMA", 1.0), ("SKYNET", 0.2), ("AVNU", 0.5)]
+        );
+    }
+
+    #[test]
+    fn test_only_the_requested_sources_are_retained() {
+        let mut sources_prices = vec![source_price("BINANCE", 100), source_price("OKX", 101)];
+        retain_requested_sources(&mut sources_prices, None);
+        assert_eq!(sources_prices.len(), 2);
+
+        retain_requested_sources(&mut sources_prices, Some(&["OKX".to_string()]));
+        let sources: Vec<&str> = sources_prices.iter().map(|p| p.source.as_str()).collect();
+        assert_eq!(sources, vec!["OKX"]);
+    }
+
+    #[test]
+    fn test_sources_are_grouped_by_source() {
+        let at =
+            |publisher: &str, source: &str, price: u64, timestamp: i64| PublisherSourcePriceRaw {
+                publisher: publisher.to_string(),
+                time: chrono::DateTime::from_timestamp(timestamp, 0)
+                    .unwrap()
+                    .naive_utc(),
+                ..source_price(source, price)
+            };
+        let sources_prices = vec![
+            at("PRAGMA", "BINANCE", 100, 1718000000),
+            at("SKYNET", "BINANCE", 102, 1718000010),
+            at("PRAGMA", "OKX", 101, 1718000005),
+        ];
+
+        let grouped = group_by_source(&sources_prices);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(
+            grouped["BINANCE"],
+            SourceLatestPrice {
+                publisher: "SKYNET".to_string(),
+                price: "0x66".to_string(),
+                timestamp: 1718000010000,
+            }
+        );
+        assert_eq!(grouped["OKX"].price, "0x65");
+
+        let json = serde_json::to_value(&grouped).unwrap();
+        assert_eq!(json["OKX"]["publisher"], "PRAGMA");
+        assert_eq!(json["OKX"]["timestamp"], 1718000005000_u64);
+    }
+
+    #[test]
+    fn test_unranged_twap_uses_the_default_lookback() {
+        let default_lookback = std::time::Duration::from_secs(3600);
+        let params = GetEntryParams {
+            timestamp: None,
+            interval: Some(Interval::OneMinute),
+            aggregation: Some(AggregationMode::Twap),
+            ..Default::default()
+        };
+        let routing_params = RoutingParams::try_from(params).unwrap();
+        assert_eq!(
+            routing_params.twap_lookback_in_seconds(default_lookback),
+            3600
+        );
+
+        // Widened to the interval, otherwise no bucket could be found.
+        let routing_params = RoutingParams {
+            interval: Interval::OneDay,
+            ..routing_params
+        };
+        assert_eq!(
+            routing_params.twap_lookback_in_seconds(default_lookback),
+            24 * 60 * 60
+        );
+    }
+
+    #[test]
+    fn test_price_rounding_modes() {
+        use std::str::FromStr;
+
+        let round = |price: &str, rounding| {
+            round_price(&BigDecimal::from_str(price).unwrap(), rounding).to_string()
+        };
+
+        // A median between two sources falls in the middle of two units.
+        assert_eq!(
+            round("6500000000012.5", PriceRounding::Floor),
+            "6500000000012"
+        );
+        assert_eq!(
+            round("6500000000012.5", PriceRounding::Ceil),
+            "6500000000013"
+        );
+        assert_eq!(
+            round("6500000000012.5", PriceRounding::HalfEven),
+            "6500000000012"
+        );
+        assert_eq!(
+            round("6500000000013.5", PriceRounding::HalfEven),
+            "6500000000014"
+        );
+        assert_eq!(
+            round("6500000000012.2", PriceRounding::Ceil),
+            "6500000000013"
+        );
+        assert_eq!(
+            round("6500000000012.8", PriceRounding::Floor),
+            "6500000000012"
+        );
+        assert_eq!(round("-12.5", PriceRounding::Floor), "-13");
+        assert_eq!(round("-12.5", PriceRounding::Ceil), "-12");
+
+        let params: GetEntryParams = serde_json::from_str(r#"{"rounding": "ceil"}"#).unwrap();
+        assert_eq!(
+            EntryResponseOptions::from(&params).rounding,
+            PriceRounding::Ceil
+        );
+        let params: GetEntryParams = serde_json::from_str("{}").unwrap();
+        assert_eq!(
+            EntryResponseOptions::from(&params).rounding,
+            PriceRounding::HalfEven
+        );
     }
 }
@@ -8,7 +8,7 @@ use crate::infra::repositories::entry_repository;
 use crate::utils::PathExtractor;
 use crate::AppState;
 
-use crate::utils::currency_pair_to_pair_id;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id};
 
 #[utoipa::path(
     get,
@@ -26,6 +26,7 @@ pub async fn get_expiries(
     State(state): State<AppState>,
     PathExtractor(pair): PathExtractor<(String, String)>,
 ) -> Result<Json<Vec<NaiveDateTime>>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
     let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
 
     let req_result = entry_repository::get_expiries_list(&state.offchain_pool, pair_id.clone())
@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::Json;
+use chrono::NaiveDateTime;
+use deadpool_diesel::postgres::Pool;
+use opentelemetry::KeyValue;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use crate::config::config;
+use crate::infra::kafka;
+use crate::infra::repositories::entry_repository;
+use crate::types::circuit_breaker::CircuitState;
+use crate::AppState;
+
+/// Weight of the databases pools saturation in the final score.
+const DATABASE_WEIGHT: f64 = 50.0;
+/// Weight of the Kafka reachability in the final score.
+const KAFKA_WEIGHT: f64 = 25.0;
+/// Weight of the recent error rate in the final score.
+const ERROR_RATE_WEIGHT: f64 = 25.0;
+/// Maximum score of a node whose ingestion is lagging behind.
+const DEGRADED_MAX_SCORE: u8 = 50;
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetHealthScoreResponse {
+    /// Health score between 0 (unhealthy) and 100 (fully healthy).
+    pub score: u8,
+    pub offchain_pool_saturation: f64,
+    pub onchain_pool_saturation: f64,
+    pub kafka_reachable: bool,
+    pub error_rate: f64,
+    /// Seconds since the newest entry of each monitored pair, null if the
+    /// pair has no entry.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub ingestion_lag: BTreeMap<String, Option<u64>>,
+    /// True if the ingestion of a monitored pair lags behind the threshold.
+    pub degraded: bool,
+    /// State of the circuit breaker of the onchain endpoints.
+    pub onchain_circuit_breaker: CircuitState,
+}
+
+/// Inputs used to compute the health score.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthInputs {
+    /// Ratio (between 0 and 1) of used connections in the offchain pool.
+    pub offchain_pool_saturation: f64,
+    /// Ratio (between 0 and 1) of used connections in the onchain pool.
+    pub onchain_pool_saturation: f64,
+    pub kafka_reachable: bool,
+    /// Ratio (between 0 and 1) of requests that ended in a server error.
+    pub error_rate: f64,
+    /// True if the ingestion of a monitored pair lags behind the threshold.
+    pub ingestion_lagging: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/health/score",
+    responses(
+        (status = 200, description = "Get the weighted health score of the node", body = GetHealthScoreResponse)
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_health_score(State(state): State<AppState>) -> Json<GetHealthScoreResponse> {
+    let config = config().await;
+    let now = chrono::Utc::now().naive_utc();
+
+    let mut ingestion_lag = BTreeMap::new();
+    for pair_id in config.ingestion_lag_pairs() {
+        let newest_entry =
+            entry_repository::get_last_updated_timestamp(&state.offchain_pool, pair_id.clone())
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("Could not get the newest entry of {}: {:?}", pair_id, e);
+                    None
+                });
+        let lag = ingestion_lag_in_seconds(newest_entry, now);
+        if let Some(lag) = lag {
+            state
+                .metrics
+                .ingestion_lag
+                .record(lag, &[KeyValue::new("pair_id", pair_id.clone())]);
+        }
+        ingestion_lag.insert(pair_id.clone(), lag);
+    }
+
+    let inputs = HealthInputs {
+        offchain_pool_saturation: pool_saturation(&state.offchain_pool),
+        onchain_pool_saturation: pool_saturation(&state.onchain_pool),
+        kafka_reachable: kafka::is_reachable().await,
+        error_rate: state.metrics.error_rate.error_rate(),
+        ingestion_lagging: is_ingestion_lagging(&ingestion_lag, config.ingestion_lag_threshold()),
+    };
+
+    Json(GetHealthScoreResponse {
+        score: compute_health_score(&inputs),
+        offchain_pool_saturation: inputs.offchain_pool_saturation,
+        onchain_pool_saturation: inputs.onchain_pool_saturation,
+        kafka_reachable: inputs.kafka_reachable,
+        error_rate: inputs.error_rate,
+        ingestion_lag,
+        degraded: inputs.ingestion_lagging,
+        onchain_circuit_breaker: state.onchain_circuit_breaker.state(),
+    })
+}
+
+/// Returns the number of seconds between the newest entry and now, or None
+/// if there is no entry.
+fn ingestion_lag_in_seconds(
+    newest_entry: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+) -> Option<u64> {
+    newest_entry.map(|timestamp| (now - timestamp).num_seconds().max(0) as u64)
+}
+
+/// Returns true if a monitored pair has no entry or if its newest entry is
+/// older than the threshold.
+fn is_ingestion_lagging(
+    ingestion_lag: &BTreeMap<String, Option<u64>>,
+    threshold: Duration,
+) -> bool {
+    ingestion_lag.values().any(|lag| match lag {
+        Some(lag) => *lag > threshold.as_secs(),
+        None => true,
+    })
+}
+
+/// Returns the ratio of connections currently in use in the pool.
+fn pool_saturation(pool: &Pool) -> f64 {
+    let status = pool.status();
+    if status.max_size == 0 {
+        return 1.0;
+    }
+    let in_use = (status.size as f64 - status.available as f64).max(0.0);
+    (in_use / status.max_size as f64).clamp(0.0, 1.0)
+}
+
+/// Computes a health score between 0 and 100 from the provided inputs.
+pub fn compute_health_score(inputs: &HealthInputs) -> u8 {
+    let pools_saturation = inputs
+        .offchain_pool_saturation
+        .max(inputs.onchain_pool_saturation)
+        .clamp(0.0, 1.0);
+    let database_score = DATABASE_WEIGHT * (1.0 - pools_saturation);
+    let kafka_score = if inputs.kafka_reachable {
+        KAFKA_WEIGHT
+    } else {
+        0.0
+    };
+    let error_score = ERROR_RATE_WEIGHT * (1.0 - inputs.error_rate.clamp(0.0, 1.0));
+
+    let score = (database_score + kafka_score + error_score).round() as u8;
+    if inputs.ingestion_lagging {
+        score.min(DEGRADED_MAX_SCORE)
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy_inputs() -> HealthInputs {
+        HealthInputs {
+            offchain_pool_saturation: 0.0,
+            onchain_pool_saturation: 0.0,
+            kafka_reachable: true,
+            error_rate: 0.0,
+            ingestion_lagging: false,
+        }
+    }
+
+    #[test]
+    fn test_healthy_node_has_full_score() {
+        assert_eq!(compute_health_score(&healthy_inputs()), 100);
+    }
+
+    #[test]
+    fn test_pool_saturation_lowers_score() {
+        let healthy_score = compute_health_score(&healthy_inputs());
+
+        let saturated = HealthInputs {
+            offchain_pool_saturation: 0.9,
+            ..healthy_inputs()
+        };
+        let saturated_score = compute_health_score(&saturated);
+        assert!(saturated_score < healthy_score);
+
+        let fully_saturated = HealthInputs {
+            offchain_pool_saturation: 1.0,
+            onchain_pool_saturation: 1.0,
+            ..healthy_inputs()
+        };
+        assert!(compute_health_score(&fully_saturated) < saturated_score);
+        assert_eq!(compute_health_score(&fully_saturated), 50);
+    }
+
+    #[test]
+    fn test_unreachable_kafka_and_errors_lower_score() {
+        let inputs = HealthInputs {
+            kafka_reachable: false,
+            error_rate: 1.0,
+            ..healthy_inputs()
+        };
+        assert_eq!(compute_health_score(&inputs), 50);
+    }
+
+    #[test]
+    fn test_old_newest_entry_degrades_health() {
+        let now = chrono::Utc::now().naive_utc();
+        let threshold = Duration::from_secs(300);
+
+        let mut ingestion_lag = BTreeMap::new();
+        ingestion_lag.insert(
+            "BTC/USD".to_string(),
+            ingestion_lag_in_seconds(Some(now - chrono::Duration::seconds(10)), now),
+        );
+        assert!(!is_ingestion_lagging(&ingestion_lag, threshold));
+        let inputs = HealthInputs {
+            ingestion_lagging: is_ingestion_lagging(&ingestion_lag, threshold),
+            ..healthy_inputs()
+        };
+        assert_eq!(compute_health_score(&inputs), 100);
+
+        // The ingestor stalled one hour ago for ETH/USD.
+        ingestion_lag.insert(
+            "ETH/USD".to_string(),
+            ingestion_lag_in_seconds(Some(now - chrono::Duration::hours(1)), now),
+        );
+        assert_eq!(ingestion_lag["ETH/USD"], Some(3600));
+        assert!(is_ingestion_lagging(&ingestion_lag, threshold));
+        let inputs = HealthInputs {
+            ingestion_lagging: is_ingestion_lagging(&ingestion_lag, threshold),
+            ..healthy_inputs()
+        };
+        assert_eq!(compute_health_score(&inputs), DEGRADED_MAX_SCORE);
+
+        // A monitored pair without any entry is lagging too.
+        let mut ingestion_lag = BTreeMap::new();
+        ingestion_lag.insert("SOL/USD".to_string(), ingestion_lag_in_seconds(None, now));
+        assert!(is_ingestion_lagging(&ingestion_lag, threshold));
+    }
+}
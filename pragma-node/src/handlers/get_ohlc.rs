@@ -5,17 +5,27 @@ use utoipa::{ToResponse, ToSchema};
 
 use crate::handlers::Interval;
 use crate::infra::repositories::entry_repository::{self, OHLCEntry};
+use crate::types::timestamp::{to_localized_rfc3339, TimezoneParams};
 use crate::utils::PathExtractor;
 use crate::AppState;
 use pragma_entities::EntryError;
 
 use super::GetEntryParams;
-use crate::utils::currency_pair_to_pair_id;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LocalizedOHLCEntry {
+    #[serde(flatten)]
+    entry: OHLCEntry,
+    /// Time of the candle in the requested timezone (RFC3339).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_time: Option<String>,
+}
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
 pub struct GetOHLCResponse {
     pair_id: String,
-    data: Vec<OHLCEntry>,
+    data: Vec<LocalizedOHLCEntry>,
 }
 
 #[utoipa::path(
@@ -28,6 +38,7 @@ pub struct GetOHLCResponse {
             ("base" = String, Path, description = "Base Asset"),
             ("quote" = String, Path, description = "Quote Asset"),
             GetEntryParams,
+            TimezoneParams,
         ),
     )]
 #[tracing::instrument(skip(state))]
@@ -35,9 +46,12 @@ pub async fn get_ohlc(
     State(state): State<AppState>,
     PathExtractor(pair): PathExtractor<(String, String)>,
     Query(params): Query<GetEntryParams>,
+    Query(tz_params): Query<TimezoneParams>,
 ) -> Result<Json<GetOHLCResponse>, EntryError> {
     // Construct pair id
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
     let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+    let timezone = tz_params.timezone()?;
 
     let now = chrono::Utc::now().timestamp();
 
@@ -65,12 +79,27 @@ pub async fn get_ohlc(
             .await
             .map_err(|db_error| db_error.to_entry_error(&pair_id))?;
 
-    Ok(Json(adapt_entry_to_entry_response(pair_id, &entries)))
+    Ok(Json(adapt_entry_to_entry_response(
+        pair_id,
+        &entries,
+        timezone.as_ref(),
+    )))
 }
 
-fn adapt_entry_to_entry_response(pair_id: String, entries: &[OHLCEntry]) -> GetOHLCResponse {
+fn adapt_entry_to_entry_response(
+    pair_id: String,
+    entries: &[OHLCEntry],
+    timezone: Option<&chrono_tz::Tz>,
+) -> GetOHLCResponse {
     GetOHLCResponse {
         pair_id,
-        data: entries.to_vec(),
+        data: entries
+            .iter()
+            .map(|entry| LocalizedOHLCEntry {
+                entry: entry.clone(),
+                local_time: timezone
+                    .and_then(|tz| to_localized_rfc3339(entry.time.and_utc().timestamp(), tz)),
+            })
+            .collect(),
     }
 }
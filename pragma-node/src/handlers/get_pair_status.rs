@@ -0,0 +1,92 @@
+use axum::extract::State;
+use axum::Json;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use pragma_entities::EntryError;
+
+use crate::infra::repositories::entry_repository;
+use crate::types::timestamp::UnixTimestamp;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id, PathExtractor};
+use crate::AppState;
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetPairStatusResponse {
+    pub pair_id: String,
+    /// Unix timestamp in seconds of the oldest entry of the pair.
+    #[schema(value_type = i64)]
+    pub first_entry_timestamp: UnixTimestamp,
+    /// Unix timestamp in seconds of the most recent entry of the pair.
+    #[schema(value_type = i64)]
+    pub last_entry_timestamp: UnixTimestamp,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/data/{base}/{quote}/status",
+    responses(
+        (status = 200, description = "Get the data coverage of the pair", body = GetPairStatusResponse),
+        (status = 404, description = "Unknown pair", body = EntryError)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_pair_status(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+) -> Result<Json<GetPairStatusResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
+    let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+
+    let timestamps_range =
+        entry_repository::get_entries_timestamps_range(&state.offchain_pool, pair_id.clone())
+            .await
+            .map_err(|e| e.to_entry_error(&pair_id))?;
+
+    adapt_to_pair_status(pair_id, timestamps_range).map(Json)
+}
+
+fn adapt_to_pair_status(
+    pair_id: String,
+    timestamps_range: (Option<NaiveDateTime>, Option<NaiveDateTime>),
+) -> Result<GetPairStatusResponse, EntryError> {
+    match timestamps_range {
+        (Some(first), Some(last)) => Ok(GetPairStatusResponse {
+            pair_id,
+            first_entry_timestamp: first.and_utc().timestamp(),
+            last_entry_timestamp: last.and_utc().timestamp(),
+        }),
+        _ => Err(EntryError::UnknownPairId(pair_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(timestamp: i64) -> NaiveDateTime {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap()
+            .naive_utc()
+    }
+
+    #[test]
+    fn test_pair_status_matches_entries_range() {
+        let status = adapt_to_pair_status(
+            "BTC/USD".to_string(),
+            (Some(datetime(1_600_000_000)), Some(datetime(1_718_000_000))),
+        )
+        .unwrap();
+        assert_eq!(status.first_entry_timestamp, 1_600_000_000);
+        assert_eq!(status.last_entry_timestamp, 1_718_000_000);
+
+        assert!(matches!(
+            adapt_to_pair_status("FOO/USD".to_string(), (None, None)),
+            Err(EntryError::UnknownPairId(pair_id)) if pair_id == "FOO/USD"
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use pragma_entities::EntryError;
+
+use crate::config::config;
+use crate::infra::repositories::entry_repository;
+use crate::AppState;
+
+#[derive(Default, Debug, Deserialize, IntoParams, ToSchema)]
+pub struct GetPairsParams {
+    /// Only returns the pairs having this tag, e.g `majors`.
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PairWithTags {
+    pub pair_id: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetPairsResponse(pub Vec<PairWithTags>);
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/data/pairs",
+    responses(
+        (status = 200, description = "Get the available pairs with their tags", body = GetPairsResponse)
+    ),
+    params(GetPairsParams),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_pairs(
+    State(state): State<AppState>,
+    Query(params): Query<GetPairsParams>,
+) -> Result<Json<GetPairsResponse>, EntryError> {
+    let pairs = entry_repository::get_pairs(&state.offchain_pool).await?;
+
+    let config = config().await;
+
+    Ok(Json(GetPairsResponse(tag_pairs(
+        pairs,
+        |pair_id| config.pair_tags(pair_id),
+        params.tag.as_deref(),
+    ))))
+}
+
+/// Attaches their configured tags to the pairs, only keeping the ones having
+/// the provided tag if any.
+fn tag_pairs(
+    pairs: Vec<String>,
+    pair_tags: impl Fn(&str) -> Vec<String>,
+    tag: Option<&str>,
+) -> Vec<PairWithTags> {
+    pairs
+        .into_iter()
+        .map(|pair_id| PairWithTags {
+            tags: pair_tags(&pair_id),
+            pair_id,
+        })
+        .filter(|pair| match tag {
+            Some(tag) => pair.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim())),
+            None => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair_tags(pair_id: &str) -> Vec<String> {
+        match pair_id {
+            "BTC/USD" => vec!["majors".to_string()],
+            "ETH/USD" => vec!["defi".to_string(), "majors".to_string()],
+            "USDC/USD" => vec!["stablecoins".to_string()],
+            _ => vec![],
+        }
+    }
+
+    fn pairs() -> Vec<String> {
+        ["BTC/USD", "ETH/USD", "SOL/USD", "USDC/USD"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn test_pairs_are_filtered_by_tag() {
+        let tagged = tag_pairs(pairs(), pair_tags, Some("Majors"));
+        let pair_ids: Vec<&str> = tagged.iter().map(|p| p.pair_id.as_str()).collect();
+        assert_eq!(pair_ids, vec!["BTC/USD", "ETH/USD"]);
+        assert_eq!(tagged[1].tags, vec!["defi", "majors"]);
+
+        assert!(tag_pairs(pairs(), pair_tags, Some("memecoins")).is_empty());
+    }
+
+    #[test]
+    fn test_all_pairs_are_returned_without_tag() {
+        let tagged = tag_pairs(pairs(), pair_tags, None);
+        assert_eq!(tagged.len(), 4);
+        assert!(tagged[2].tags.is_empty());
+    }
+}
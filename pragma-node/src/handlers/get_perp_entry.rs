@@ -0,0 +1,140 @@
+use axum::extract::State;
+use axum::Json;
+use deadpool_diesel::postgres::Pool;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use pragma_common::types::{AggregationMode, DataType};
+use pragma_entities::EntryError;
+
+use crate::infra::repositories::entry_repository::{self, MedianEntryWithComponents};
+use crate::types::pricer::{IndexPricer, MarkPricer, Pricer};
+use crate::utils::{
+    assert_currencies_are_distinct, big_decimal_price_to_hex, currency_pair_to_pair_id,
+    is_usd_quoted, only_existing_pairs, PathExtractor,
+};
+use crate::AppState;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
+pub struct GetPerpEntryResponse {
+    pub pair_id: String,
+    /// Mark price of the perpetual pair.
+    pub price: String,
+    /// Unix timestamp in milliseconds of the computation of the mark price.
+    pub timestamp: u64,
+    pub decimals: u32,
+    pub num_sources_aggregated: usize,
+    /// Number of sources left out of the aggregation, only the most recent
+    /// ones being aggregated when their number is limited.
+    pub num_sources_dropped: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/data/perp/{base}/{quote}",
+    responses(
+        (status = 200, description = "Get the current mark price of a perpetual pair", body = GetPerpEntryResponse),
+        (status = 404, description = "Unknown perpetual pair", body = EntryError)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_perp_entry(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+) -> Result<Json<GetPerpEntryResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
+    let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+
+    let entry = compute_mark_entry(&state.offchain_pool, &pair_id).await?;
+
+    let decimals = entry_repository::get_decimals(&state.offchain_pool, &pair_id).await?;
+
+    Ok(Json(adapt_entry_to_perp_response(
+        entry,
+        decimals,
+        chrono::Utc::now().timestamp_millis() as u64,
+    )))
+}
+
+/// Computes the current mark price of the perpetual pair.
+pub(crate) async fn compute_mark_entry(
+    pool: &Pool,
+    pair_id: &str,
+) -> Result<MedianEntryWithComponents, EntryError> {
+    let (_, existing_perp_pairs) = only_existing_pairs(pool, vec![format!("{pair_id}:MARK")])
+        .await
+        .map_err(|e| e.to_entry_error(&pair_id.to_string()))?;
+    if !existing_perp_pairs.iter().any(|pair| pair == pair_id) {
+        return Err(EntryError::UnknownPairId(pair_id.to_string()));
+    }
+
+    // USD quoted perps are priced with the median of their entries, the other
+    // ones are converted to USD with the index price of their quote.
+    let entries = if is_usd_quoted(pair_id) {
+        IndexPricer::new(vec![pair_id.to_string()], DataType::PerpEntry)
+            .compute(pool, AggregationMode::Median)
+            .await?
+    } else {
+        MarkPricer::new(vec![pair_id.to_string()], DataType::PerpEntry)
+            .compute(pool, AggregationMode::Median)
+            .await?
+    };
+    entries
+        .into_iter()
+        .find(|entry| entry.pair_id == pair_id)
+        .ok_or_else(|| EntryError::NotFound(pair_id.to_string()))
+}
+
+fn adapt_entry_to_perp_response(
+    entry: MedianEntryWithComponents,
+    decimals: u32,
+    timestamp: u64,
+) -> GetPerpEntryResponse {
+    GetPerpEntryResponse {
+        num_sources_aggregated: entry.components.len(),
+        num_sources_dropped: entry.num_sources_dropped,
+        price: big_decimal_price_to_hex(&entry.median_price),
+        pair_id: entry.pair_id,
+        timestamp,
+        decimals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+
+    use super::*;
+    use crate::infra::repositories::entry_repository::EntryComponent;
+
+    fn component(publisher: &str) -> EntryComponent {
+        EntryComponent {
+            pair_id: "BTC/USD".to_string(),
+            price: BigDecimal::from(6_500_000_000_000_u64),
+            timestamp: "1718000000".to_string(),
+            publisher: publisher.to_string(),
+            publisher_address: "0x1".to_string(),
+            publisher_signature: "0x2".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_perp_response_counts_the_aggregated_sources() {
+        let entry = MedianEntryWithComponents {
+            pair_id: "BTC/USD".to_string(),
+            median_price: BigDecimal::from(6_500_000_000_000_u64),
+            components: vec![component("BINANCE"), component("BYBIT")],
+            num_sources_dropped: 1,
+        };
+        let response = adapt_entry_to_perp_response(entry, 8, 1718000000000);
+        assert_eq!(response.pair_id, "BTC/USD");
+        assert_eq!(response.price, "0x5e96630e800");
+        assert_eq!(response.num_sources_aggregated, 2);
+        assert_eq!(response.num_sources_dropped, 1);
+        assert_eq!(response.decimals, 8);
+    }
+}
@@ -0,0 +1,106 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use pragma_entities::{dto, PublisherError};
+
+use crate::infra::repositories::publisher_repository;
+use crate::AppState;
+
+#[derive(Default, Debug, Deserialize, IntoParams, ToSchema)]
+pub struct GetPublishersParams {
+    /// If true, only returns the active publishers.
+    pub active_only: Option<bool>,
+}
+
+/// Publisher registered on the node. Its keys are public, they are the
+/// ones verifying the signatures of its entries.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct PublisherInfo {
+    pub name: String,
+    pub account_address: String,
+    pub active_key: String,
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetPublishersResponse(pub Vec<PublisherInfo>);
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/publishers",
+    responses(
+        (status = 200, description = "Get the publishers registered on the node", body = GetPublishersResponse)
+    ),
+    params(GetPublishersParams),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_publishers(
+    State(state): State<AppState>,
+    Query(params): Query<GetPublishersParams>,
+) -> Result<Json<GetPublishersResponse>, PublisherError> {
+    let filter = dto::PublishersFilter {
+        is_active: params.active_only.unwrap_or(false).then_some(true),
+        name_contains: None,
+    };
+    let publishers = publisher_repository::list_all(&state.offchain_pool, filter).await?;
+
+    Ok(Json(GetPublishersResponse(adapt_publishers(publishers))))
+}
+
+/// Converts the publishers, sorted by name.
+fn adapt_publishers(publishers: Vec<dto::Publisher>) -> Vec<PublisherInfo> {
+    let mut publishers: Vec<PublisherInfo> = publishers
+        .into_iter()
+        .map(|publisher| PublisherInfo {
+            name: publisher.name,
+            account_address: publisher.account_address,
+            active_key: publisher.active_key,
+            active: publisher.active,
+        })
+        .collect();
+    publishers.sort_by(|a, b| a.name.cmp(&b.name));
+    publishers
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn publisher(name: &str, active: bool) -> dto::Publisher {
+        dto::Publisher {
+            id: Uuid::nil(),
+            name: name.to_string(),
+            master_key: "0x1".to_string(),
+            active_key: "0x2".to_string(),
+            account_address: "0x3".to_string(),
+            active,
+        }
+    }
+
+    #[test]
+    fn test_publishers_are_sorted_by_name() {
+        let publishers =
+            adapt_publishers(vec![publisher("SKYNET", false), publisher("PRAGMA", true)]);
+        assert_eq!(
+            publishers,
+            vec![
+                PublisherInfo {
+                    name: "PRAGMA".to_string(),
+                    account_address: "0x3".to_string(),
+                    active_key: "0x2".to_string(),
+                    active: true,
+                },
+                PublisherInfo {
+                    name: "SKYNET".to_string(),
+                    account_address: "0x3".to_string(),
+                    active_key: "0x2".to_string(),
+                    active: false,
+                },
+            ]
+        );
+    }
+}
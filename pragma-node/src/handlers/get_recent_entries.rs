@@ -0,0 +1,109 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use pragma_entities::{dto, EntryError};
+
+use crate::constants::others::{DEFAULT_RECENT_ENTRIES_LIMIT, MAX_RECENT_ENTRIES_LIMIT};
+use crate::infra::repositories::entry_repository;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id, PathExtractor};
+use crate::AppState;
+
+#[derive(Default, Debug, Deserialize, IntoParams, ToSchema)]
+pub struct GetRecentEntriesParams {
+    /// Number of entries to return, 100 by default and 1000 at most.
+    pub limit: Option<u64>,
+}
+
+/// Entry as published, without aggregation.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct RecentEntry {
+    pub publisher: String,
+    pub source: String,
+    /// Unix timestamp in milliseconds of the entry.
+    pub timestamp: u64,
+    pub price: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetRecentEntriesResponse(pub Vec<RecentEntry>);
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/data/{base}/{quote}/history",
+    responses(
+        (status = 200, description = "Get the most recent entries of the pair, newest first. Empty if the pair has no entries", body = GetRecentEntriesResponse)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+        GetRecentEntriesParams
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_recent_entries(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+    Query(params): Query<GetRecentEntriesParams>,
+) -> Result<Json<GetRecentEntriesResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
+    let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+
+    let entries = entry_repository::get_recent_entries(
+        &state.offchain_pool,
+        pair_id.clone(),
+        recent_entries_limit(params.limit),
+    )
+    .await
+    .map_err(|e| e.to_entry_error(&pair_id))?;
+
+    Ok(Json(GetRecentEntriesResponse(
+        entries.into_iter().map(adapt_recent_entry).collect(),
+    )))
+}
+
+/// Returns the requested number of entries, capped to the maximum.
+fn recent_entries_limit(limit: Option<u64>) -> i64 {
+    limit
+        .unwrap_or(DEFAULT_RECENT_ENTRIES_LIMIT)
+        .min(MAX_RECENT_ENTRIES_LIMIT) as i64
+}
+
+fn adapt_recent_entry(entry: dto::Entry) -> RecentEntry {
+    RecentEntry {
+        publisher: entry.publisher,
+        source: entry.source,
+        timestamp: entry.timestamp,
+        price: format!("0x{:x}", entry.price),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_recent_entries_limit_is_capped() {
+        assert_eq!(recent_entries_limit(None), 100);
+        assert_eq!(recent_entries_limit(Some(10)), 10);
+        assert_eq!(recent_entries_limit(Some(50_000)), 1000);
+    }
+
+    #[test]
+    fn test_recent_entry_price_is_hex() {
+        let entry = adapt_recent_entry(dto::Entry {
+            id: Uuid::nil(),
+            pair_id: "BTC/USD".to_string(),
+            publisher: "PRAGMA".to_string(),
+            source: "BINANCE".to_string(),
+            timestamp: 1718000000000,
+            publisher_signature: None,
+            price: 6_000_000_000_000,
+        });
+        assert_eq!(entry.price, "0x574fbde6000");
+        assert_eq!(entry.timestamp, 1718000000000);
+    }
+}
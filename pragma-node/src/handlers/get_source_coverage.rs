@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use pragma_entities::EntryError;
+
+use crate::constants::others::SOURCE_COVERAGE_WINDOW_IN_SECONDS;
+use crate::infra::repositories::entry_repository::{self, PairSourceCoverage};
+use crate::AppState;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
+pub struct GetSourceCoverageResponse {
+    /// Window over which the sources are counted, in seconds.
+    pub window_in_seconds: u64,
+    /// Number of distinct sources of each pair.
+    pub pairs: Vec<PairSourceCoverage>,
+    /// Number of pairs per number of sources, e.g `{"1": 3}` for three
+    /// single-source pairs.
+    pub distribution: BTreeMap<i64, usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/metrics/source-coverage",
+    responses(
+        (status = 200, description = "Get the number of sources backing each pair", body = GetSourceCoverageResponse),
+        (status = 503, description = "The database is unavailable", body = EntryError)
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_source_coverage(
+    State(state): State<AppState>,
+) -> Result<Json<GetSourceCoverageResponse>, EntryError> {
+    let pairs = entry_repository::get_sources_coverage(
+        &state.offchain_pool,
+        SOURCE_COVERAGE_WINDOW_IN_SECONDS,
+    )
+    .await?;
+
+    Ok(Json(GetSourceCoverageResponse {
+        window_in_seconds: SOURCE_COVERAGE_WINDOW_IN_SECONDS,
+        distribution: sources_distribution(&pairs),
+        pairs,
+    }))
+}
+
+/// Returns the number of pairs per number of sources.
+fn sources_distribution(pairs: &[PairSourceCoverage]) -> BTreeMap<i64, usize> {
+    let mut distribution = BTreeMap::new();
+    for pair in pairs {
+        *distribution.entry(pair.num_sources).or_insert(0) += 1;
+    }
+    distribution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage(pair_id: &str, num_sources: i64) -> PairSourceCoverage {
+        PairSourceCoverage {
+            pair_id: pair_id.to_string(),
+            num_sources,
+        }
+    }
+
+    #[test]
+    fn test_sources_distribution() {
+        let pairs = vec![
+            coverage("BTC/USD", 5),
+            coverage("ETH/USD", 5),
+            coverage("LORDS/USD", 1),
+            coverage("STRK/USD", 3),
+            coverage("ZEND/USD", 1),
+        ];
+
+        let distribution = sources_distribution(&pairs);
+        assert_eq!(distribution, BTreeMap::from([(1, 2), (3, 1), (5, 2)]));
+
+        let response = GetSourceCoverageResponse {
+            window_in_seconds: SOURCE_COVERAGE_WINDOW_IN_SECONDS,
+            pairs,
+            distribution,
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["distribution"]["1"], 2);
+        assert_eq!(json["pairs"][2]["pair_id"], "LORDS/USD");
+
+        assert!(sources_distribution(&[]).is_empty());
+    }
+}
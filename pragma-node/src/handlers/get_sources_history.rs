@@ -0,0 +1,169 @@
+use std::collections::BTreeSet;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use pragma_common::types::Interval;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use pragma_entities::EntryError;
+
+use crate::constants::others::MAX_HISTORY_POINTS;
+use crate::infra::repositories::entry_repository::{self, SourcesBucketRaw};
+use crate::types::timestamp::{TimestampRange, UnixTimestamp};
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id, PathExtractor};
+use crate::AppState;
+
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct GetSourcesHistoryParams {
+    /// Range of the history, as `start,end` unix timestamps in seconds.
+    pub timestamp: TimestampRange,
+    /// Width of the buckets, one minute by default.
+    pub interval: Option<Interval>,
+}
+
+/// Sources that published the pair during a bucket.
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct SourcesBucket {
+    /// Unix timestamp in seconds of the start of the bucket.
+    #[schema(value_type = i64)]
+    pub timestamp: UnixTimestamp,
+    pub sources: Vec<String>,
+    /// Sources absent from the previous bucket.
+    pub joined: Vec<String>,
+    /// Sources of the previous bucket that are absent from this one.
+    pub left: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetSourcesHistoryResponse {
+    pub pair_id: String,
+    pub interval: Interval,
+    /// Buckets with at least one entry, sorted by timestamp.
+    pub buckets: Vec<SourcesBucket>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/data/{base}/{quote}/sources/history",
+    responses(
+        (status = 200, description = "Get the sources that contributed to the pair over time", body = GetSourcesHistoryResponse),
+        (status = 400, description = "Invalid range or too many buckets", body = EntryError)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+        GetSourcesHistoryParams
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_sources_history(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+    Query(params): Query<GetSourcesHistoryParams>,
+) -> Result<Json<GetSourcesHistoryResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
+    let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
+    let range = params.timestamp.assert_time_is_valid()?.0;
+    let interval = params.interval.unwrap_or_default();
+
+    let buckets_count = ((range.end() - range.start()) / interval.to_seconds()) as usize + 1;
+    if buckets_count > MAX_HISTORY_POINTS {
+        return Err(EntryError::TooManyPoints(buckets_count, MAX_HISTORY_POINTS));
+    }
+
+    let raw_buckets = entry_repository::get_sources_history(
+        &state.offchain_pool,
+        pair_id.clone(),
+        *range.start(),
+        *range.end(),
+        interval,
+    )
+    .await
+    .map_err(|e| e.to_entry_error(&pair_id))?;
+
+    Ok(Json(GetSourcesHistoryResponse {
+        pair_id,
+        interval,
+        buckets: adapt_sources_history(raw_buckets),
+    }))
+}
+
+/// Converts the buckets fetched from the database, computing the sources
+/// that joined or left since the previous bucket.
+fn adapt_sources_history(raw_buckets: Vec<SourcesBucketRaw>) -> Vec<SourcesBucket> {
+    let mut previous: Option<BTreeSet<String>> = None;
+    let mut buckets = Vec::with_capacity(raw_buckets.len());
+    for raw in raw_buckets {
+        let current: BTreeSet<String> = raw.sources.into_iter().collect();
+        let (joined, left) = match &previous {
+            Some(previous) => (
+                current.difference(previous).cloned().collect(),
+                previous.difference(&current).cloned().collect(),
+            ),
+            None => (current.iter().cloned().collect(), vec![]),
+        };
+        buckets.push(SourcesBucket {
+            timestamp: raw.bucket.and_utc().timestamp(),
+            sources: current.iter().cloned().collect(),
+            joined,
+            left,
+        });
+        previous = Some(current);
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_bucket(timestamp: i64, sources: &[&str]) -> SourcesBucketRaw {
+        SourcesBucketRaw {
+            bucket: chrono::DateTime::from_timestamp(timestamp, 0)
+                .unwrap()
+                .naive_utc(),
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sources_history_per_bucket() {
+        let buckets = adapt_sources_history(vec![
+            raw_bucket(1_718_000_000, &["OKX", "BINANCE"]),
+            raw_bucket(1_718_000_060, &["BINANCE", "OKX", "BYBIT"]),
+            raw_bucket(1_718_000_120, &["BYBIT", "BINANCE"]),
+            // No entry during the next two minutes.
+            raw_bucket(1_718_000_300, &["BYBIT", "BINANCE"]),
+        ]);
+
+        assert_eq!(
+            buckets
+                .iter()
+                .map(|bucket| bucket.timestamp)
+                .collect::<Vec<_>>(),
+            vec![1_718_000_000, 1_718_000_060, 1_718_000_120, 1_718_000_300]
+        );
+        assert_eq!(buckets[0].sources, strings(&["BINANCE", "OKX"]));
+        assert_eq!(buckets[0].joined, strings(&["BINANCE", "OKX"]));
+        assert!(buckets[0].left.is_empty());
+
+        assert_eq!(buckets[1].sources, strings(&["BINANCE", "BYBIT", "OKX"]));
+        assert_eq!(buckets[1].joined, strings(&["BYBIT"]));
+        assert!(buckets[1].left.is_empty());
+
+        assert_eq!(buckets[2].sources, strings(&["BINANCE", "BYBIT"]));
+        assert!(buckets[2].joined.is_empty());
+        assert_eq!(buckets[2].left, strings(&["OKX"]));
+
+        assert_eq!(buckets[3].sources, strings(&["BINANCE", "BYBIT"]));
+        assert!(buckets[3].joined.is_empty());
+        assert!(buckets[3].left.is_empty());
+
+        assert!(adapt_sources_history(vec![]).is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{ToResponse, ToSchema};
+
+use pragma_entities::{EntryError, SourceLatency};
+
+use crate::infra::repositories::source_repository;
+use crate::AppState;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SourceLatencyResponse {
+    pub source: String,
+    /// Rolling delay between the timestamp reported by the source and the
+    /// ingestion of its entries, in milliseconds.
+    pub latency_ms: f64,
+    /// Unix timestamp in seconds of the last update of the latency.
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetSourcesLatencyResponse(pub Vec<SourceLatencyResponse>);
+
+impl From<SourceLatency> for SourceLatencyResponse {
+    fn from(latency: SourceLatency) -> Self {
+        Self {
+            source: latency.source,
+            latency_ms: latency.latency_ms,
+            updated_at: latency.updated_at.and_utc().timestamp(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/sources/latency",
+    responses(
+        (status = 200, description = "Get the ingestion latency of each source", body = GetSourcesLatencyResponse)
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_sources_latency(
+    State(state): State<AppState>,
+) -> Result<Json<GetSourcesLatencyResponse>, EntryError> {
+    let latencies = source_repository::get_sources_latencies(&state.offchain_pool).await?;
+
+    Ok(Json(GetSourcesLatencyResponse(
+        latencies
+            .into_iter()
+            .map(SourceLatencyResponse::from)
+            .collect(),
+    )))
+}
@@ -1,22 +1,31 @@
+use std::collections::BTreeMap;
+
 use axum::extract::{Query, State};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToResponse, ToSchema};
 
+use crate::constants::others::{MAX_VOLATILITY_POINTS, MAX_VOLATILITY_WINDOW_IN_SECONDS};
 use crate::infra::repositories::entry_repository::{self, MedianEntry};
 use crate::utils::PathExtractor;
 use crate::AppState;
 use pragma_entities::{EntryError, VolatilityError};
 
-use crate::utils::{compute_volatility, currency_pair_to_pair_id};
+use crate::utils::{assert_currencies_are_distinct, compute_volatility, currency_pair_to_pair_id};
 
-/// Volatility query
+/// Volatility query, also served on `/node/v1/data/{base}/{quote}/volatility`
+/// with `from` and `to` as aliases of `start` and `end`.
 #[derive(Deserialize, IntoParams, Debug)]
 pub struct VolatilityQuery {
     /// Initial timestamp, combined with final_timestamp, it helps define the period over which the mean is computed
+    #[serde(alias = "from")]
     start: u64,
     /// Final timestamp
+    #[serde(alias = "to")]
     end: u64,
+    /// Optional comma separated lookback windows ending at `end` (e.g. `1h,1d,7d`).
+    /// Supported units are `m`, `h`, `d` and `w`.
+    windows: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
@@ -24,6 +33,9 @@ pub struct GetVolatilityResponse {
     pair_id: String,
     volatility: f64,
     decimals: u32,
+    /// Annualized volatility per requested lookback window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    term_structure: Option<BTreeMap<String, f64>>,
 }
 
 #[utoipa::path(
@@ -45,6 +57,7 @@ pub async fn get_volatility(
     Query(volatility_query): Query<VolatilityQuery>,
 ) -> Result<Json<GetVolatilityResponse>, EntryError> {
     // Construct pair id
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
     let pair_id = currency_pair_to_pair_id(&pair.0, &pair.1);
 
     if volatility_query.start > volatility_query.end {
@@ -53,36 +66,205 @@ pub async fn get_volatility(
         ));
     }
 
-    // Fetch entries between start and end timestamps
-    let entries = entry_repository::get_entries_between(
+    let windows = match &volatility_query.windows {
+        Some(windows) => Some(parse_windows(windows)?),
+        None => None,
+    };
+
+    // Fetch the entries once, over the largest period needed, sampled so
+    // long periods don't load every minute
+    let longest_window = windows
+        .iter()
+        .flatten()
+        .map(|(_, duration)| *duration)
+        .max()
+        .unwrap_or(0);
+    let fetch_start = volatility_query
+        .start
+        .min(volatility_query.end.saturating_sub(longest_window));
+    let mut entries = entry_repository::get_sampled_entries_between(
         &state.offchain_pool,
         pair_id.clone(),
-        volatility_query.start,
+        fetch_start,
         volatility_query.end,
+        MAX_VOLATILITY_POINTS,
     )
     .await?;
 
     if entries.is_empty() {
         return Err(EntryError::UnknownPairId(pair_id));
     }
+    // Log returns are computed between consecutive entries in chronological order
+    entries.sort_by_key(|entry| entry.time);
 
     let decimals = entry_repository::get_decimals(&state.offchain_pool, &pair_id).await?;
 
+    let period_entries = entries_since(&entries, volatility_query.start);
+    let term_structure = windows
+        .map(|windows| compute_term_structure(&entries, volatility_query.end, &windows))
+        .transpose()?;
+
     Ok(Json(adapt_entry_to_entry_response(
-        pair_id, &entries, decimals,
-    )))
+        pair_id,
+        period_entries,
+        decimals,
+        term_structure,
+    )?))
 }
 
 fn adapt_entry_to_entry_response(
     pair_id: String,
     entries: &[MedianEntry],
     decimals: u32,
-) -> GetVolatilityResponse {
-    let volatility = compute_volatility(entries);
+    term_structure: Option<BTreeMap<String, f64>>,
+) -> Result<GetVolatilityResponse, EntryError> {
+    // A single sample would be reported as a flat price.
+    if entries.len() < 2 {
+        return Err(EntryError::VolatilityError(
+            VolatilityError::NotEnoughSamples(entries.len()),
+        ));
+    }
+    let volatility = compute_volatility(entries)?;
 
-    GetVolatilityResponse {
+    Ok(GetVolatilityResponse {
         pair_id,
         volatility,
         decimals,
+        term_structure,
+    })
+}
+
+/// Parses a comma separated list of windows (e.g. `1h,1d,7d`) into
+/// their label and duration in seconds.
+fn parse_windows(windows: &str) -> Result<Vec<(String, u64)>, VolatilityError> {
+    windows
+        .split(',')
+        .map(str::trim)
+        .map(|window| parse_window(window).map(|duration| (window.to_string(), duration)))
+        .collect()
+}
+
+/// Parses a single window (e.g. `30m`, `1h`, `7d`, `2w`) into seconds.
+fn parse_window(window: &str) -> Result<u64, VolatilityError> {
+    let invalid = || VolatilityError::InvalidWindow(window.to_string());
+    if window.len() < 2 || !window.is_ascii() {
+        return Err(invalid());
+    }
+    let (amount, unit) = window.split_at(window.len() - 1);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit_in_seconds = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    match amount.checked_mul(unit_in_seconds) {
+        Some(duration) if duration > 0 && duration <= MAX_VOLATILITY_WINDOW_IN_SECONDS => {
+            Ok(duration)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Returns the entries (sorted chronologically) at or after `start`.
+fn entries_since(entries: &[MedianEntry], start: u64) -> &[MedianEntry] {
+    let first = entries.partition_point(|entry| entry.time.and_utc().timestamp() < start as i64);
+    &entries[first..]
+}
+
+/// Computes the volatility of each window, over the entries between
+/// `end - window` and `end`.
+fn compute_term_structure(
+    entries: &[MedianEntry],
+    end: u64,
+    windows: &[(String, u64)],
+) -> Result<BTreeMap<String, f64>, EntryError> {
+    windows
+        .iter()
+        .map(|(label, duration)| {
+            let window_entries = entries_since(entries, end.saturating_sub(*duration));
+            Ok((label.clone(), compute_volatility(window_entries)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::DateTime;
+
+    fn new_entry(median_price: u32, timestamp: i64) -> MedianEntry {
+        MedianEntry {
+            time: DateTime::from_timestamp(timestamp, 0).unwrap().naive_utc(),
+            median_price: BigDecimal::from(median_price),
+            num_sources: 5,
+        }
+    }
+
+    #[test]
+    fn test_parse_windows() {
+        assert_eq!(
+            parse_windows("30m,1h, 1d,2w").unwrap(),
+            vec![
+                ("30m".to_string(), 1800),
+                ("1h".to_string(), 3600),
+                ("1d".to_string(), 86400),
+                ("2w".to_string(), 1_209_600),
+            ]
+        );
+        for invalid in ["", "h", "1", "0h", "-1h", "1y", "1.5h", "1h,", "400d", "1é"] {
+            assert!(
+                matches!(
+                    parse_windows(invalid),
+                    Err(VolatilityError::InvalidWindow(_))
+                ),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_term_structure_has_distinct_volatility_per_window() {
+        let end = 1_700_000_000;
+        // Calm prices for the last hour, wild swings before.
+        let mut entries: Vec<MedianEntry> = (0..46)
+            .map(|i| {
+                let price = if i % 2 == 0 { 100 } else { 150 };
+                new_entry(price, end - 86_400 + i * 1_800)
+            })
+            .collect();
+        entries.extend((0..12).map(|i| new_entry(100 + i as u32, end - 3_600 + i * 300)));
+        entries.sort_by_key(|entry| entry.time);
+
+        let windows = parse_windows("1h,1d").unwrap();
+        let term_structure = compute_term_structure(&entries, end as u64, &windows).unwrap();
+
+        let one_hour = term_structure["1h"];
+        let one_day = term_structure["1d"];
+        assert!(one_hour > 0.0);
+        assert!(one_day > one_hour);
+        assert_eq!(
+            one_hour,
+            compute_volatility(entries_since(&entries, end as u64 - 3_600)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_volatility_requires_two_samples() {
+        let entries = vec![new_entry(100, 1_700_000_000)];
+        assert!(matches!(
+            adapt_entry_to_entry_response("BTC/USD".to_string(), &entries, 8, None),
+            Err(EntryError::VolatilityError(
+                VolatilityError::NotEnoughSamples(1)
+            ))
+        ));
+
+        // A flat price has a null volatility.
+        let entries = vec![new_entry(100, 1_700_000_000), new_entry(100, 1_700_000_060)];
+        let response =
+            adapt_entry_to_entry_response("BTC/USD".to_string(), &entries, 8, None).unwrap();
+        assert_eq!(response.volatility, 0.0);
     }
 }
@@ -0,0 +1,52 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use pragma_common::types::Network;
+use pragma_entities::models::merkle_feed_error::MerkleFeedError;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use crate::infra::redis;
+use crate::AppState;
+
+#[derive(Default, Deserialize, IntoParams, ToSchema, Debug)]
+pub struct GetBlockAtTimestampQuery {
+    pub network: Option<Network>,
+    /// Unix timestamp, in seconds.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetBlockAtTimestampResponse {
+    /// Most recent block published at or before the requested timestamp.
+    pub block_number: u64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/merkle_feeds/block",
+    responses(
+        (status = 200, description = "Get the block published at a timestamp", body = [GetBlockAtTimestampResponse]),
+        (status = 404, description = "No block published at or before the timestamp", body = [MerkleFeedError])
+    ),
+    params(
+        GetBlockAtTimestampQuery
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_merkle_feeds_block_at_timestamp(
+    State(state): State<AppState>,
+    Query(params): Query<GetBlockAtTimestampQuery>,
+) -> Result<Json<GetBlockAtTimestampResponse>, MerkleFeedError> {
+    if state.redis_client.is_none() {
+        return Err(MerkleFeedError::RedisConnection);
+    }
+
+    let network = params.network.unwrap_or_default();
+
+    let block_number =
+        redis::get_block_at_timestamp(state.redis_client.unwrap(), network, params.timestamp)
+            .await
+            .map_err(MerkleFeedError::from)?;
+
+    Ok(Json(GetBlockAtTimestampResponse { block_number }))
+}
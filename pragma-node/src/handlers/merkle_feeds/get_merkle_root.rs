@@ -0,0 +1,58 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use pragma_common::types::block_id::{BlockId, BlockTag};
+use pragma_common::types::Network;
+use pragma_entities::models::merkle_feed_error::MerkleFeedError;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use crate::infra::redis;
+use crate::AppState;
+
+#[derive(Default, Deserialize, IntoParams, ToSchema, Debug)]
+pub struct GetMerkleRootQuery {
+    pub network: Option<Network>,
+    pub block_id: Option<BlockId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetMerkleRootResponse {
+    /// Hexadecimal root hash of the merkle tree published at the block.
+    pub root_hash: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/merkle_feeds/root",
+    responses(
+        (status = 200, description = "Get the root of the merkle tree", body = [GetMerkleRootResponse])
+    ),
+    params(
+        GetMerkleRootQuery
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_merkle_feeds_root(
+    State(state): State<AppState>,
+    Query(params): Query<GetMerkleRootQuery>,
+) -> Result<Json<GetMerkleRootResponse>, MerkleFeedError> {
+    if state.redis_client.is_none() {
+        return Err(MerkleFeedError::RedisConnection);
+    }
+
+    let network = params.network.unwrap_or_default();
+    let block_id = params.block_id.unwrap_or(BlockId::Tag(BlockTag::Latest));
+
+    let merkle_tree = redis::get_merkle_tree(
+        state.redis_client.unwrap(),
+        network,
+        block_id,
+        state.caches.merkle_feeds_tree().clone(),
+    )
+    .await
+    .map_err(MerkleFeedError::from)?;
+
+    Ok(Json(GetMerkleRootResponse {
+        root_hash: format!("{:#x}", merkle_tree.root_hash),
+    }))
+}
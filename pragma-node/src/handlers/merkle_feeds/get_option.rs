@@ -25,6 +25,9 @@ pub struct GetOptionResponse {
     #[serde(flatten)]
     pub option_data: OptionData,
     pub hash: String,
+    /// Number of the block the option was read at, so the proof and the root
+    /// can be requested for the same block when a tag was provided.
+    pub block_number: u64,
 }
 
 #[utoipa::path(
@@ -51,7 +54,7 @@ pub async fn get_merkle_feeds_option(
     let network = params.network.unwrap_or_default();
     let block_id = params.block_id.unwrap_or(BlockId::Tag(BlockTag::Latest));
 
-    let option_data =
+    let (block_number, option_data) =
         redis::get_option_data(state.redis_client.unwrap(), network, block_id, instrument)
             .await
             .map_err(MerkleFeedError::from)?;
@@ -63,5 +66,6 @@ pub async fn get_merkle_feeds_option(
     Ok(Json(GetOptionResponse {
         hash: option_data_hash,
         option_data,
+        block_number,
     }))
 }
@@ -1,2 +1,4 @@
+pub mod get_block_at_timestamp;
 pub mod get_merkle_proof;
+pub mod get_merkle_root;
 pub mod get_option;
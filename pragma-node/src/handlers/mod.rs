@@ -1,8 +1,22 @@
+pub mod check_pairs_exist;
 pub mod create_entry;
 pub mod create_future_entry;
+pub mod get_blended_entry;
+pub mod get_database_health;
+pub mod get_dependencies_health;
+pub mod get_entries_batch;
 pub mod get_entry;
 pub mod get_expiries;
+pub mod get_health_score;
 pub mod get_ohlc;
+pub mod get_pair_status;
+pub mod get_pairs;
+pub mod get_perp_entry;
+pub mod get_publishers;
+pub mod get_recent_entries;
+pub mod get_source_coverage;
+pub mod get_sources_history;
+pub mod get_sources_latency;
 pub mod get_volatility;
 pub mod merkle_feeds;
 pub mod onchain;
@@ -10,11 +24,25 @@ pub mod optimistic_oracle;
 pub mod subscribe_to_entry;
 pub mod subscribe_to_price;
 
+pub use check_pairs_exist::check_pairs_exist;
 pub use create_entry::create_entries;
 pub use create_future_entry::create_future_entries;
+pub use get_blended_entry::get_blended_entry;
+pub use get_database_health::get_database_health;
+pub use get_dependencies_health::get_dependencies_health;
+pub use get_entries_batch::get_entries_batch;
 pub use get_entry::get_entry;
 pub use get_expiries::get_expiries;
+pub use get_health_score::get_health_score;
 pub use get_ohlc::get_ohlc;
+pub use get_pair_status::get_pair_status;
+pub use get_pairs::get_pairs;
+pub use get_perp_entry::get_perp_entry;
+pub use get_publishers::get_publishers;
+pub use get_recent_entries::get_recent_entries;
+pub use get_source_coverage::get_source_coverage;
+pub use get_sources_history::get_sources_history;
+pub use get_sources_latency::get_sources_latency;
 pub use get_volatility::get_volatility;
 pub use subscribe_to_entry::subscribe_to_entry;
 pub use subscribe_to_price::subscribe_to_price;
@@ -47,6 +75,49 @@ impl From<EntryType> for DataType {
     }
 }
 
+/// Notation of the formatted price, adjusted by its decimals.
+#[derive(Default, Debug, Deserialize, ToSchema, Clone, Copy, PartialEq)]
+pub enum PriceNotation {
+    #[serde(rename = "decimal")]
+    #[default]
+    Decimal,
+    #[serde(rename = "scientific")]
+    Scientific,
+}
+
+/// Rounding of the price to its decimals.
+#[derive(Default, Debug, Deserialize, ToSchema, Clone, Copy, PartialEq)]
+pub enum PriceRounding {
+    /// Rounds towards negative infinity, e.g for collateral valuation.
+    #[serde(rename = "floor")]
+    Floor,
+    /// Rounds towards positive infinity, e.g for liabilities.
+    #[serde(rename = "ceil")]
+    Ceil,
+    /// Rounds to the nearest value, ties to the even one.
+    #[serde(rename = "half_even")]
+    #[default]
+    HalfEven,
+}
+
+impl From<PriceRounding> for bigdecimal::RoundingMode {
+    fn from(value: PriceRounding) -> Self {
+        match value {
+            PriceRounding::Floor => bigdecimal::RoundingMode::Floor,
+            PriceRounding::Ceil => bigdecimal::RoundingMode::Ceiling,
+            PriceRounding::HalfEven => bigdecimal::RoundingMode::HalfEven,
+        }
+    }
+}
+
+/// Grouping of the sources returned along with the aggregated price.
+#[derive(Debug, Deserialize, ToSchema, Clone, Copy, PartialEq)]
+pub enum SourcesGrouping {
+    /// Latest price of each source, keyed by source name.
+    #[serde(rename = "source")]
+    Source,
+}
+
 #[derive(Debug, Deserialize, IntoParams, ToSchema)]
 pub struct GetEntryParams {
     /// The unix timestamp in seconds. This endpoint will return the first update whose
@@ -58,6 +129,37 @@ pub struct GetEntryParams {
     pub aggregation: Option<AggregationMode>,
     pub entry_type: Option<EntryType>,
     pub expiry: Option<String>,
+    /// The unix timestamp in seconds of the point-in-time view: only entries
+    /// ingested before it are aggregated, making the price reproducible.
+    /// Only supported with the median aggregation on spot entries.
+    #[schema(value_type = i64)]
+    pub as_of: Option<UnixTimestamp>,
+    /// If true, the response contains the time spent computing the aggregation.
+    pub timing: Option<bool>,
+    /// If true, the response contains the mid computed from the bid and ask
+    /// reported by the sources, falling back to their price, aggregated like
+    /// the price.
+    /// Only supported on spot entries.
+    pub mid: Option<bool>,
+    /// If true, the aggregation is returned even if the prices of the sources
+    /// spread more than the configured maximum.
+    pub best_effort: Option<bool>,
+    /// If set, the response contains the price adjusted by its decimals in
+    /// this notation.
+    pub notation: Option<PriceNotation>,
+    /// Significant digits of the price formatted in scientific notation.
+    pub significant_digits: Option<u32>,
+    /// Rounding of the price to its decimals, `half_even` by default.
+    pub rounding: Option<PriceRounding>,
+    /// Comma-separated list of sources to aggregate, e.g. `BINANCE,OKX`.
+    /// Only supported with the median aggregation.
+    pub sources: Option<String>,
+    /// If true, the response contains the latest price of each source that
+    /// contributed to the aggregation.
+    pub show_components: Option<bool>,
+    /// If set to `source`, the response contains the latest price of each
+    /// source, keyed by source name.
+    pub group_by: Option<SourcesGrouping>,
 }
 
 impl Default for GetEntryParams {
@@ -69,6 +171,16 @@ impl Default for GetEntryParams {
             aggregation: Some(AggregationMode::default()),
             entry_type: Some(EntryType::default()),
             expiry: None,
+            as_of: None,
+            timing: None,
+            mid: None,
+            best_effort: None,
+            notation: None,
+            significant_digits: None,
+            rounding: None,
+            sources: None,
+            show_components: None,
+            group_by: None,
         }
     }
 }
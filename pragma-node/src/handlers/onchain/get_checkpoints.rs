@@ -8,8 +8,8 @@ use utoipa::{IntoParams, ToResponse, ToSchema};
 
 use crate::infra::repositories::entry_repository::get_decimals;
 use crate::infra::repositories::onchain_repository::checkpoint::get_checkpoints;
-use crate::utils::currency_pair_to_pair_id;
 use crate::utils::PathExtractor;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id};
 use crate::AppState;
 
 pub const DEFAULT_LIMIT: u64 = 100;
@@ -60,6 +60,8 @@ pub async fn get_onchain_checkpoints(
     Query(params): Query<GetOnchainCheckpointsParams>,
 ) -> Result<Json<GetOnchainCheckpointsResponse>, CheckpointError> {
     let pair_id: String = currency_pair_to_pair_id(&pair.0, &pair.1);
+    assert_currencies_are_distinct(&pair.0, &pair.1)
+        .map_err(|_| CheckpointError::IdenticalCurrencies(pair_id.clone()))?;
 
     let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
     if !(1..=MAX_LIMIT).contains(&limit) {
@@ -1,29 +1,51 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use axum::extract::{Query, State};
 use axum::Json;
 use bigdecimal::BigDecimal;
+use pragma_common::hash::pedersen_hash;
 use pragma_common::types::{AggregationMode, Interval, Network};
+use pragma_common::utils::field_element_as_hex_string;
 use pragma_entities::EntryError;
 use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use starknet::core::utils::starknet_keccak;
 use utoipa::{IntoParams, ToResponse, ToSchema};
 
+use crate::handlers::onchain::{
+    assert_onchain_interval_is_supported, assert_twap_interval_is_valid,
+};
 use crate::infra::repositories::onchain_repository::entry::{
-    get_last_updated_timestamp, get_variations, routing, OnchainRoutingArguments,
+    get_last_updated_timestamp, get_variations, routing, OnchainAsOf, OnchainRoutingArguments,
+    RawOnchainData,
 };
-use crate::utils::{big_decimal_price_to_hex, PathExtractor};
+use crate::utils::{big_decimal_price_to_hex, computation_time_ms, PathExtractor};
 use crate::AppState;
 
-use crate::utils::currency_pair_to_pair_id;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id};
 
 #[derive(Debug, Default, Deserialize, IntoParams, ToSchema)]
 pub struct GetOnchainEntryParams {
     pub network: Network,
     pub aggregation: Option<AggregationMode>,
+    /// Window of the TWAP, required with the `twap` aggregation. It is at most
+    /// one hour, the window of the returned components.
+    pub interval: Option<Interval>,
     pub routing: Option<bool>,
     pub timestamp: Option<i64>,
+    /// Block number at which the price is resolved, instead of a timestamp.
+    pub block: Option<u64>,
     pub components: Option<bool>,
     pub variations: Option<bool>,
+    /// If true, the response contains the time spent computing the aggregation.
+    pub timing: Option<bool>,
+    /// If true, the response contains a hash of the inputs & output of the
+    /// aggregation.
+    pub computation_hash: Option<bool>,
+    /// Names under which the sources of the components are returned,
+    /// e.g `BINANCE=binance_spot,OKX=okx`.
+    pub source_aliases: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
@@ -33,6 +55,9 @@ pub struct OnchainEntry {
     pub price: String,
     pub tx_hash: String,
     pub timestamp: u64,
+    /// Name of the source in Pragma, only set when the source is aliased.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_source: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
@@ -45,6 +70,16 @@ pub struct GetOnchainEntryResponse {
     asset_type: String,
     components: Option<Vec<OnchainEntry>>,
     variations: Option<HashMap<Interval, f32>>,
+    /// Time spent computing the aggregation in milliseconds, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    computation_time_ms: Option<f64>,
+    /// Deterministic hash of the inputs & output of the aggregation, if
+    /// requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    computation_hash: Option<String>,
+    /// Pairs traversed to compute the price, only set when it was routed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<Vec<String>>,
 }
 
 #[utoipa::path(
@@ -65,32 +100,50 @@ pub async fn get_onchain_entry(
     PathExtractor(pair): PathExtractor<(String, String)>,
     Query(params): Query<GetOnchainEntryParams>,
 ) -> Result<Json<GetOnchainEntryResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
     let pair_id: String = currency_pair_to_pair_id(&pair.0, &pair.1);
     let with_components = params.components.unwrap_or(true);
     let with_variations = params.variations.unwrap_or(true);
+    let with_timing = params.timing.unwrap_or(false);
+    let with_computation_hash = params.computation_hash.unwrap_or(false);
+    let source_aliases = params
+        .source_aliases
+        .as_deref()
+        .map(parse_source_aliases)
+        .transpose()?;
 
-    let now = chrono::Utc::now().timestamp();
-    let timestamp = if let Some(timestamp) = params.timestamp {
-        timestamp
-    } else {
-        now
-    };
+    let as_of = onchain_as_of(
+        params.timestamp,
+        params.block,
+        chrono::Utc::now().timestamp(),
+    )?;
 
+    let aggregation_mode = params.aggregation.unwrap_or_default();
+    assert_twap_interval_is_valid(aggregation_mode, params.interval)?;
+    if let Some(interval) = params.interval {
+        assert_onchain_interval_is_supported(interval)?;
+    }
     let routing_arguments = OnchainRoutingArguments {
         pair_id: pair_id.clone(),
         network: params.network,
-        timestamp: (timestamp as u64),
-        aggregation_mode: params.aggregation.unwrap_or_default(),
+        as_of,
+        aggregation_mode,
+        interval: params.interval,
         is_routing: params.routing.unwrap_or(false),
     };
 
-    let raw_data = routing(&state.onchain_pool, &state.offchain_pool, routing_arguments)
-        .await
-        .map_err(|db_error| db_error.to_entry_error(&pair_id))?;
+    let started_at = Instant::now();
+    let raw_data = routing(
+        &state.onchain_pool,
+        &state.offchain_pool,
+        &state.caches,
+        routing_arguments,
+    )
+    .await
+    .map_err(|db_error| db_error.to_entry_error(&pair_id))?;
+    let computation_time_ms = computation_time_ms(with_timing, started_at);
 
-    let entry = raw_data
-        .first()
-        .ok_or_else(|| EntryError::NotFound(pair_id.to_string()))?;
+    let entry = first_entry_with_sources(&raw_data, &pair_id)?;
 
     let last_updated_timestamp =
         get_last_updated_timestamp(&state.onchain_pool, params.network, entry.pair_used.clone())
@@ -107,15 +160,152 @@ pub async fn get_onchain_entry(
         None
     };
 
-    Ok(Json(adapt_entries_to_onchain_response(
-        pair_id.clone(),
-        entry.decimal,
-        entry.sources.clone(),
-        entry.price.clone(),
-        last_updated_timestamp,
-        variations,
-        with_components,
-    )))
+    // The hash is computed over the canonical sources, before any alias.
+    let computation_hash = if with_computation_hash {
+        compute_computation_hash(
+            &pair_id,
+            aggregation_mode,
+            &entry.sources,
+            &big_decimal_price_to_hex(&entry.price),
+            entry.decimal,
+        )
+        .inspect_err(|e| tracing::warn!("Could not compute the hash of {pair_id}: {e}"))
+        .ok()
+    } else {
+        None
+    };
+
+    let mut sources = entry.sources.clone();
+    if let Some(source_aliases) = &source_aliases {
+        apply_source_aliases(&mut sources, source_aliases);
+    }
+
+    Ok(Json(GetOnchainEntryResponse {
+        computation_time_ms,
+        computation_hash,
+        route: routed_pairs(&entry.pair_used),
+        ..adapt_entries_to_onchain_response(
+            pair_id.clone(),
+            entry.decimal,
+            sources,
+            entry.price.clone(),
+            last_updated_timestamp,
+            variations,
+            with_components,
+        )
+    }))
+}
+
+/// Returns the first entry returned by the routing, as long as at least one
+/// onchain source contributed to its price.
+fn first_entry_with_sources<'a>(
+    raw_data: &'a [RawOnchainData],
+    pair_id: &str,
+) -> Result<&'a RawOnchainData, EntryError> {
+    raw_data
+        .first()
+        .filter(|entry| !entry.sources.is_empty())
+        .ok_or_else(|| EntryError::NotFound(pair_id.to_string()))
+}
+
+/// Returns the pairs used to route the price, or None if the pair was
+/// priced directly.
+fn routed_pairs(pair_used: &[String]) -> Option<Vec<String>> {
+    (pair_used.len() > 1).then(|| pair_used.to_vec())
+}
+
+/// Returns the point at which the price is resolved: the requested block or
+/// timestamp, `now` by default. Both can't be requested at once.
+fn onchain_as_of(
+    timestamp: Option<i64>,
+    block: Option<u64>,
+    now: i64,
+) -> Result<OnchainAsOf, EntryError> {
+    match (timestamp, block) {
+        (Some(_), Some(_)) => Err(EntryError::InvalidTimestamp(
+            "Cannot query both a timestamp and a block".to_string(),
+        )),
+        (None, Some(block)) => Ok(OnchainAsOf::Block(block)),
+        (timestamp, None) => Ok(OnchainAsOf::Timestamp(timestamp.unwrap_or(now) as u64)),
+    }
+}
+
+/// Parses a comma separated list of source aliases, e.g `BINANCE=binance_spot,OKX=okx`.
+/// The sources are uppercased since it's how they are stored.
+fn parse_source_aliases(aliases: &str) -> Result<HashMap<String, String>, EntryError> {
+    let mut parsed = HashMap::new();
+    for alias in aliases.split(',').map(str::trim) {
+        match alias.split_once('=') {
+            Some((source, name)) if !source.trim().is_empty() && !name.trim().is_empty() => {
+                parsed.insert(source.trim().to_uppercase(), name.trim().to_string());
+            }
+            _ => return Err(EntryError::InvalidSourceAlias(alias.to_string())),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Renames the sources of the components with their alias, keeping the Pragma
+/// name in `canonical_source`.
+fn apply_source_aliases(components: &mut [OnchainEntry], aliases: &HashMap<String, String>) {
+    for component in components {
+        if let Some(alias) = aliases.get(&component.source.to_uppercase()) {
+            let canonical_source = std::mem::replace(&mut component.source, alias.clone());
+            component.canonical_source = Some(canonical_source);
+        }
+    }
+}
+
+/// Computes the pedersen hash chain of the pair, the aggregation mode, the
+/// components (sorted, so the hash doesn't depend on their order) and the
+/// aggregated price & decimals.
+/// Strings are hashed with keccak first so they can be of any length.
+fn compute_computation_hash(
+    pair_id: &str,
+    aggregation_mode: AggregationMode,
+    components: &[OnchainEntry],
+    price: &str,
+    decimals: u32,
+) -> Result<String, String> {
+    let hex_to_felt =
+        |hex: &str| Felt::from_hex(hex).map_err(|_| format!("{hex} is not a valid felt"));
+
+    let mut components_felts = components
+        .iter()
+        .map(|component| {
+            Ok([
+                starknet_keccak(component.publisher.as_bytes()),
+                starknet_keccak(component.source.as_bytes()),
+                hex_to_felt(&component.price)?,
+                hex_to_felt(&component.tx_hash)?,
+                Felt::from(component.timestamp),
+            ])
+        })
+        .collect::<Result<Vec<[Felt; 5]>, String>>()?;
+    components_felts.sort();
+
+    let mut elements = vec![
+        starknet_keccak(pair_id.as_bytes()),
+        starknet_keccak(aggregation_mode_name(aggregation_mode).as_bytes()),
+        Felt::from(components.len()),
+    ];
+    elements.extend(components_felts.into_iter().flatten());
+    elements.push(hex_to_felt(price)?);
+    elements.push(Felt::from(decimals));
+
+    let hash = elements
+        .iter()
+        .fold(Felt::ZERO, |hash, element| pedersen_hash(&hash, element));
+    Ok(field_element_as_hex_string(&hash))
+}
+
+/// Stable name of the aggregation mode, part of the computation hash.
+const fn aggregation_mode_name(aggregation_mode: AggregationMode) -> &'static str {
+    match aggregation_mode {
+        AggregationMode::Median => "median",
+        AggregationMode::Mean => "mean",
+        AggregationMode::Twap => "twap",
+    }
 }
 
 fn adapt_entries_to_onchain_response(
@@ -137,5 +327,198 @@ fn adapt_entries_to_onchain_response(
         asset_type: "Crypto".to_string(),
         components: with_components.then_some(sources),
         variations,
+        computation_time_ms: None,
+        computation_hash: None,
+        route: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(source: &str) -> OnchainEntry {
+        OnchainEntry {
+            publisher: "PRAGMA".to_string(),
+            source: source.to_string(),
+            price: "0x5f5e100".to_string(),
+            tx_hash: "0x1".to_string(),
+            timestamp: 1718000000,
+            canonical_source: None,
+        }
+    }
+
+    #[test]
+    fn test_source_aliases_are_applied() {
+        let aliases = parse_source_aliases("binance=binance_spot, OKX=okx").unwrap();
+        let mut components = vec![component("BINANCE"), component("OKX"), component("BYBIT")];
+
+        apply_source_aliases(&mut components, &aliases);
+
+        assert_eq!(components[0].source, "binance_spot");
+        assert_eq!(components[0].canonical_source.as_deref(), Some("BINANCE"));
+        assert_eq!(components[1].source, "okx");
+        assert_eq!(components[1].canonical_source.as_deref(), Some("OKX"));
+        // Sources without alias are returned unchanged.
+        assert_eq!(components[2].source, "BYBIT");
+        let json = serde_json::to_value(&components[2]).unwrap();
+        assert!(json.get("canonical_source").is_none());
+    }
+
+    #[test]
+    fn test_computation_hash_is_deterministic() {
+        let hash = |components: &[OnchainEntry], price: &str| {
+            compute_computation_hash("BTC/USD", AggregationMode::Median, components, price, 8)
+                .unwrap()
+        };
+        let components = vec![component("BINANCE"), component("OKX")];
+        let reference = hash(&components, "0x5f5e100");
+
+        // Identical inputs, even in another order, yield the same hash.
+        assert_eq!(hash(&components, "0x5f5e100"), reference);
+        let reversed: Vec<OnchainEntry> = components.iter().rev().cloned().collect();
+        assert_eq!(hash(&reversed, "0x5f5e100"), reference);
+
+        // Any changed input or output yields another hash.
+        let mut changed = components.clone();
+        changed[1].price = "0x5f5e101".to_string();
+        assert_ne!(hash(&changed, "0x5f5e100"), reference);
+        assert_ne!(hash(&components[..1], "0x5f5e100"), reference);
+        assert_ne!(hash(&components, "0x5f5e101"), reference);
+        let mean = compute_computation_hash(
+            "BTC/USD",
+            AggregationMode::Mean,
+            &components,
+            "0x5f5e100",
+            8,
+        )
+        .unwrap();
+        assert_ne!(mean, reference);
+
+        // Prices are hashed by value, whatever their hex representation.
+        let mut padded = components.clone();
+        padded[0].price = "0x0005F5E100".to_string();
+        assert_eq!(hash(&padded, "0x5f5e100"), reference);
+    }
+
+    #[test]
+    fn test_computation_hash_of_malformed_components_is_an_error() {
+        let mut malformed = component("BINANCE");
+        malformed.tx_hash = "not a felt".to_string();
+
+        assert!(compute_computation_hash(
+            "BTC/USD",
+            AggregationMode::Median,
+            &[malformed],
+            "0x5f5e100",
+            8
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_computation_hash_is_opt_in() {
+        let response = adapt_entries_to_onchain_response(
+            "BTC/USD".to_string(),
+            8,
+            vec![component("BINANCE")],
+            BigDecimal::from(100_000_000),
+            1718000000,
+            None,
+            true,
+        );
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("computation_hash").is_none());
+    }
+
+    #[test]
+    fn test_invalid_source_aliases() {
+        for aliases in ["BINANCE", "BINANCE=", "=binance", "BINANCE=binance,"] {
+            assert!(matches!(
+                parse_source_aliases(aliases),
+                Err(EntryError::InvalidSourceAlias(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_onchain_as_of_timestamp_or_block() {
+        let now = 1_718_000_000;
+        assert_eq!(
+            onchain_as_of(None, None, now).unwrap(),
+            OnchainAsOf::Timestamp(1_718_000_000)
+        );
+        assert_eq!(
+            onchain_as_of(Some(1_717_000_000), None, now).unwrap(),
+            OnchainAsOf::Timestamp(1_717_000_000)
+        );
+        assert_eq!(
+            onchain_as_of(None, Some(650_000), now).unwrap(),
+            OnchainAsOf::Block(650_000)
+        );
+        assert!(matches!(
+            onchain_as_of(Some(1_717_000_000), Some(650_000), now),
+            Err(EntryError::InvalidTimestamp(_))
+        ));
+    }
+
+    #[test]
+    fn test_entry_without_onchain_components_is_not_found() {
+        let raw_entry = |sources: Vec<OnchainEntry>| RawOnchainData {
+            price: BigDecimal::from(0),
+            decimal: 8,
+            sources,
+            pair_used: vec!["BTC/USD".to_string()],
+        };
+
+        let no_components = [raw_entry(vec![])];
+        assert!(matches!(
+            first_entry_with_sources(&no_components, "BTC/USD"),
+            Err(EntryError::NotFound(pair_id)) if pair_id == "BTC/USD"
+        ));
+        assert!(matches!(
+            first_entry_with_sources(&[], "BTC/USD"),
+            Err(EntryError::NotFound(_))
+        ));
+
+        let with_components = [raw_entry(vec![component("BINANCE")])];
+        let entry = first_entry_with_sources(&with_components, "BTC/USD").unwrap();
+        assert_eq!(entry.sources.len(), 1);
+    }
+
+    #[test]
+    fn test_route_is_only_set_for_routed_pairs() {
+        let two_hops = vec!["STRK/USDT".to_string(), "ETH/USDT".to_string()];
+        assert_eq!(routed_pairs(&two_hops), Some(two_hops.clone()));
+        assert_eq!(routed_pairs(&["STRK/ETH".to_string()]), None);
+
+        let response = GetOnchainEntryResponse {
+            route: routed_pairs(&two_hops),
+            ..adapt_entries_to_onchain_response(
+                "STRK/ETH".to_string(),
+                18,
+                vec![component("BINANCE")],
+                BigDecimal::from(1),
+                1718000000,
+                None,
+                true,
+            )
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["route"], serde_json::json!(["STRK/USDT", "ETH/USDT"]));
+
+        let direct = adapt_entries_to_onchain_response(
+            "STRK/ETH".to_string(),
+            18,
+            vec![component("BINANCE")],
+            BigDecimal::from(1),
+            1718000000,
+            None,
+            true,
+        );
+        assert!(serde_json::to_value(&direct)
+            .unwrap()
+            .get("route")
+            .is_none());
     }
 }
@@ -1,25 +1,40 @@
+use std::collections::BTreeMap;
+
 use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use bigdecimal::BigDecimal;
+use chrono::DateTime;
+use chrono_tz::Tz;
 use pragma_common::types::{Interval, Network};
 use pragma_entities::EntryError;
+use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToResponse, ToSchema};
 
+use crate::constants::others::MAX_HISTORY_POINTS;
+use crate::handlers::onchain::assert_onchain_interval_is_supported;
 use crate::infra::repositories::onchain_repository::history::{
     get_historical_entries_and_decimals, retry_with_routing, HistoricalEntryRaw,
 };
-use crate::types::timestamp::TimestampRange;
+use crate::types::timestamp::{parse_timezone, to_localized_rfc3339, TimestampRange};
 use crate::utils::{big_decimal_price_to_hex, PathExtractor};
 use crate::AppState;
 
-use crate::utils::currency_pair_to_pair_id;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id};
 
 #[derive(Debug, Deserialize, IntoParams, ToSchema)]
 pub struct GetOnchainHistoryParams {
     pub network: Network,
     pub timestamp: TimestampRange,
     pub chunk_interval: Option<Interval>,
+    /// Optional comma separated list of intervals (e.g. `1min,1h`). If provided,
+    /// a series is returned per interval and `chunk_interval` is ignored.
+    pub intervals: Option<String>,
     pub routing: Option<bool>,
+    /// Optional IANA timezone name (e.g. `Europe/Paris`). If provided, each
+    /// entry also contains its timestamp as a localized RFC3339 string.
+    pub tz: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -29,16 +44,24 @@ pub struct GetOnchainHistoryEntry {
     median_price: String,
     decimals: u32,
     nb_sources_aggregated: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_timestamp: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
 pub struct GetOnchainHistoryResponse(pub Vec<GetOnchainHistoryEntry>);
 
+/// Series of historical entries per requested interval.
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetOnchainMultiIntervalsHistoryResponse(
+    pub BTreeMap<String, Vec<GetOnchainHistoryEntry>>,
+);
+
 #[utoipa::path(
     get,
     path = "/node/v1/onchain/history/{base}/{quote}",
     responses(
-        (status = 200, description = "Get the historical onchain median price", body = GetOnchainHistoryResponse)
+        (status = 200, description = "Get the historical onchain median price. With `intervals`, a `GetOnchainMultiIntervalsHistoryResponse` is returned instead.", body = GetOnchainHistoryResponse)
     ),
     params(
         ("base" = String, Path, description = "Base Asset"),
@@ -51,12 +74,28 @@ pub async fn get_onchain_history(
     State(state): State<AppState>,
     PathExtractor(pair): PathExtractor<(String, String)>,
     Query(params): Query<GetOnchainHistoryParams>,
-) -> Result<Json<GetOnchainHistoryResponse>, EntryError> {
+) -> Result<Response, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
     let pair_id: String = currency_pair_to_pair_id(&pair.0, &pair.1);
     let network = params.network;
     let timestamp_range = params.timestamp.assert_time_is_valid()?;
-    let chunk_interval = params.chunk_interval.unwrap_or_default();
+    let intervals = params
+        .intervals
+        .as_deref()
+        .map(parse_intervals)
+        .transpose()?;
+    // With multiple intervals, the entries are fetched once with the smallest one.
+    let chunk_interval = match &intervals {
+        Some(intervals) => {
+            let range = &timestamp_range.0;
+            assert_points_are_capped(range.end() - range.start(), intervals)?;
+            smallest_interval(intervals)
+        }
+        None => params.chunk_interval.unwrap_or_default(),
+    };
+    assert_onchain_interval_is_supported(chunk_interval)?;
     let with_routing = params.routing.unwrap_or(false);
+    let timezone = params.tz.as_deref().map(parse_timezone).transpose()?;
 
     // We first try to get the historical entries for the selected pair
     let query_result = get_historical_entries_and_decimals(
@@ -88,18 +127,133 @@ pub async fn get_onchain_history(
         Err(e) => return Err(e.to_entry_error(&pair_id)),
     };
 
-    let response = prepare_response(raw_entries, decimals);
-    Ok(Json(response))
+    if let Some(intervals) = intervals {
+        let response =
+            prepare_multi_intervals_response(&raw_entries, decimals, timezone.as_ref(), &intervals);
+        return Ok(Json(response).into_response());
+    }
+
+    let response = prepare_response(raw_entries, decimals, timezone.as_ref());
+    Ok(Json(response).into_response())
+}
+
+/// Parses a comma separated list of intervals, e.g `1min,1h`.
+fn parse_intervals(intervals: &str) -> Result<Vec<Interval>, EntryError> {
+    let mut parsed: Vec<Interval> = vec![];
+    for interval in intervals.split(',').map(str::trim) {
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+            interval.into_deserializer();
+        let interval = Interval::deserialize(deserializer)
+            .map_err(|_| EntryError::InvalidInterval(interval.to_string()))?;
+        if !parsed.contains(&interval) {
+            parsed.push(interval);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Returns the smallest of the intervals.
+fn smallest_interval(intervals: &[Interval]) -> Interval {
+    intervals
+        .iter()
+        .copied()
+        .min_by_key(Interval::to_seconds)
+        .unwrap_or_default()
+}
+
+/// Asserts that the number of points of all the series combined is below
+/// [`MAX_HISTORY_POINTS`].
+fn assert_points_are_capped(
+    range_in_seconds: i64,
+    intervals: &[Interval],
+) -> Result<(), EntryError> {
+    let points: usize = intervals
+        .iter()
+        .map(|interval| (range_in_seconds / interval.to_seconds()) as usize + 1)
+        .sum();
+    if points > MAX_HISTORY_POINTS {
+        return Err(EntryError::TooManyPoints(points, MAX_HISTORY_POINTS));
+    }
+    Ok(())
+}
+
+/// Builds a series for each interval from the entries fetched with the
+/// smallest interval.
+/// Entries are bucketed by interval: the price of a bucket is the median
+/// of the prices of its entries.
+fn prepare_multi_intervals_response(
+    raw_entries: &[HistoricalEntryRaw],
+    decimals: u32,
+    timezone: Option<&Tz>,
+    intervals: &[Interval],
+) -> GetOnchainMultiIntervalsHistoryResponse {
+    GetOnchainMultiIntervalsHistoryResponse(
+        intervals
+            .iter()
+            .map(|interval| {
+                let series = bucket_entries(raw_entries, interval)
+                    .into_iter()
+                    .map(|entry| raw_entry_to_onchain_history_entry(entry, decimals, timezone))
+                    .collect();
+                (interval_name(interval), series)
+            })
+            .collect(),
+    )
+}
+
+/// Groups the entries (sorted by timestamp) in buckets of the interval.
+fn bucket_entries(
+    raw_entries: &[HistoricalEntryRaw],
+    interval: &Interval,
+) -> Vec<HistoricalEntryRaw> {
+    let mut buckets: BTreeMap<i64, Vec<&HistoricalEntryRaw>> = BTreeMap::new();
+    for entry in raw_entries {
+        let bucket = interval.align_timestamp(entry.timestamp.and_utc().timestamp());
+        buckets.entry(bucket).or_default().push(entry);
+    }
+
+    buckets
+        .into_iter()
+        .filter_map(|(bucket, entries)| {
+            let mut prices: Vec<&BigDecimal> = entries.iter().map(|e| &e.median_price).collect();
+            prices.sort();
+            let mid = prices.len() / 2;
+            let median_price = if prices.len() % 2 == 0 {
+                (prices[mid - 1] + prices[mid]) / BigDecimal::from(2)
+            } else {
+                prices[mid].clone()
+            };
+            Some(HistoricalEntryRaw {
+                pair_id: entries[0].pair_id.clone(),
+                timestamp: DateTime::from_timestamp(bucket, 0)?.naive_utc(),
+                median_price,
+                nb_sources_aggregated: entries
+                    .iter()
+                    .map(|e| e.nb_sources_aggregated)
+                    .max()
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the name of the interval, as used in the query parameters.
+fn interval_name(interval: &Interval) -> String {
+    serde_json::to_value(interval)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
 }
 
 fn prepare_response(
     raw_entries: Vec<HistoricalEntryRaw>,
     decimals: u32,
+    timezone: Option<&Tz>,
 ) -> GetOnchainHistoryResponse {
     GetOnchainHistoryResponse(
         raw_entries
             .into_iter()
-            .map(|entry| raw_entry_to_onchain_history_entry(entry, decimals))
+            .map(|entry| raw_entry_to_onchain_history_entry(entry, decimals, timezone))
             .collect(),
     )
 }
@@ -107,12 +261,84 @@ fn prepare_response(
 fn raw_entry_to_onchain_history_entry(
     entry: HistoricalEntryRaw,
     decimals: u32,
+    timezone: Option<&Tz>,
 ) -> GetOnchainHistoryEntry {
+    let timestamp = entry.timestamp.and_utc().timestamp();
     GetOnchainHistoryEntry {
         pair_id: entry.pair_id,
-        timestamp: (entry.timestamp.and_utc().timestamp() as u64),
+        timestamp: (timestamp as u64),
         median_price: big_decimal_price_to_hex(&entry.median_price),
         nb_sources_aggregated: (entry.nb_sources_aggregated as u32),
         decimals,
+        local_timestamp: timezone.and_then(|tz| to_localized_rfc3339(timestamp, tz)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_entry(timestamp: i64, price: u32) -> HistoricalEntryRaw {
+        HistoricalEntryRaw {
+            pair_id: "BTC/USD".to_string(),
+            timestamp: DateTime::from_timestamp(timestamp, 0).unwrap().naive_utc(),
+            median_price: BigDecimal::from(price),
+            nb_sources_aggregated: 3,
+        }
+    }
+
+    #[test]
+    fn test_parse_intervals() {
+        assert_eq!(
+            parse_intervals("1min, 1h,1min").unwrap(),
+            vec![Interval::OneMinute, Interval::OneHour]
+        );
+        assert!(matches!(
+            parse_intervals("1min,3h"),
+            Err(EntryError::InvalidInterval(interval)) if interval == "3h"
+        ));
+    }
+
+    #[test]
+    fn test_points_are_capped() {
+        let intervals = vec![Interval::OneMinute, Interval::OneHour];
+        assert!(assert_points_are_capped(24 * 3600, &intervals).is_ok());
+        assert!(matches!(
+            assert_points_are_capped(30 * 24 * 3600, &intervals),
+            Err(EntryError::TooManyPoints(_, MAX_HISTORY_POINTS))
+        ));
+    }
+
+    #[test]
+    fn test_multi_intervals_history_from_one_fetch() {
+        let intervals = parse_intervals("1min,1h").unwrap();
+        assert_eq!(smallest_interval(&intervals), Interval::OneMinute);
+
+        // Two hours of 1 minute entries, fetched once.
+        let start = 1_717_200_000; // aligned on the hour
+        let raw_entries: Vec<HistoricalEntryRaw> = (0..120)
+            .map(|i| raw_entry(start + i * 60, 100 + i as u32))
+            .collect();
+
+        let response = prepare_multi_intervals_response(&raw_entries, 8, None, &intervals);
+
+        assert_eq!(response.0.len(), 2);
+        let one_minute = &response.0["1min"];
+        assert_eq!(one_minute.len(), 120);
+        assert_eq!(one_minute[0].median_price, "0x64");
+
+        let one_hour = &response.0["1h"];
+        assert_eq!(one_hour.len(), 2);
+        assert_eq!(one_hour[0].timestamp, start as u64);
+        assert_eq!(one_hour[1].timestamp, start as u64 + 3600);
+        // Median of 100..=159 and 160..=219
+        assert_eq!(
+            one_hour[0].median_price,
+            big_decimal_price_to_hex(&BigDecimal::from(129))
+        );
+        assert_eq!(
+            one_hour[1].median_price,
+            big_decimal_price_to_hex(&BigDecimal::from(189))
+        );
     }
 }
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use pragma_common::types::{AggregationMode, Interval, Network};
+use pragma_entities::{EntryError, InfraError};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use crate::config::config;
+use crate::constants::others::ONCHAIN_STALENESS_THRESHOLD_IN_SECONDS;
+use crate::handlers::onchain::{
+    assert_onchain_interval_is_supported, assert_twap_interval_is_valid,
+};
+use crate::infra::repositories::onchain_repository::entry::{
+    get_last_updated_timestamp, routing, OnchainAsOf, OnchainRoutingArguments,
+};
+use crate::utils::{
+    assert_currencies_are_distinct, big_decimal_price_to_hex, currency_pair_to_pair_id,
+    PathExtractor,
+};
+use crate::AppState;
+
+#[derive(Debug, Default, Deserialize, IntoParams, ToSchema)]
+pub struct GetOnchainMergedEntryParams {
+    pub aggregation: Option<AggregationMode>,
+    /// Window of the TWAP, required with the `twap` aggregation. It is at most
+    /// one hour, the window of the returned components.
+    pub interval: Option<Interval>,
+    pub routing: Option<bool>,
+    pub timestamp: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NetworkOnchainEntry {
+    pub price: String,
+    pub decimals: u32,
+    pub nb_sources_aggregated: u32,
+    pub last_updated_timestamp: u64,
+    /// Number of seconds since the last update of the pair on this network.
+    pub staleness_in_seconds: u64,
+    pub is_stale: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, ToResponse)]
+pub struct GetOnchainMergedEntryResponse {
+    pub pair_id: String,
+    /// Entries keyed by network. Networks without data for the pair are omitted.
+    pub networks: BTreeMap<String, NetworkOnchainEntry>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/onchain/merged/{base}/{quote}",
+    responses(
+        (status = 200, description = "Get the onchain entry of all the configured networks", body = GetOnchainMergedEntryResponse),
+        (status = 400, description = "Missing or unsupported TWAP interval", body = EntryError),
+        (status = 404, description = "No network has data for the pair", body = EntryError),
+        (status = 503, description = "The database is unavailable", body = EntryError)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+        GetOnchainMergedEntryParams
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_onchain_merged_entry(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+    Query(params): Query<GetOnchainMergedEntryParams>,
+) -> Result<Json<GetOnchainMergedEntryResponse>, EntryError> {
+    assert_currencies_are_distinct(&pair.0, &pair.1)?;
+    let pair_id: String = currency_pair_to_pair_id(&pair.0, &pair.1);
+    let now = chrono::Utc::now().timestamp() as u64;
+    let timestamp = params.timestamp.map(|t| t as u64).unwrap_or(now);
+    let aggregation_mode = params.aggregation.unwrap_or_default();
+    assert_twap_interval_is_valid(aggregation_mode, params.interval)?;
+    if let Some(interval) = params.interval {
+        assert_onchain_interval_is_supported(interval)?;
+    }
+
+    let mut network_entries = Vec::new();
+    for network in config().await.merged_networks() {
+        let routing_arguments = OnchainRoutingArguments {
+            pair_id: pair_id.clone(),
+            network: *network,
+            as_of: OnchainAsOf::Timestamp(timestamp),
+            aggregation_mode,
+            interval: params.interval,
+            is_routing: params.routing.unwrap_or(false),
+        };
+
+        let raw_data = match routing(
+            &state.onchain_pool,
+            &state.offchain_pool,
+            &state.caches,
+            routing_arguments,
+        )
+        .await
+        {
+            Ok(raw_data) => raw_data,
+            // Networks without data for the pair are omitted.
+            Err(e @ (InfraError::NotFound | InfraError::RoutingError)) => {
+                tracing::debug!("No onchain data for {} on {}: {}", pair_id, network, e);
+                continue;
+            }
+            Err(e) => return Err(e.to_entry_error(&pair_id)),
+        };
+        let Some(entry) = raw_data.first() else {
+            continue;
+        };
+
+        let last_updated_timestamp =
+            get_last_updated_timestamp(&state.onchain_pool, *network, entry.pair_used.clone())
+                .await
+                .map_err(|db_error| db_error.to_entry_error(&pair_id))?;
+
+        let staleness_in_seconds = now.saturating_sub(last_updated_timestamp);
+        network_entries.push((
+            *network,
+            NetworkOnchainEntry {
+                price: big_decimal_price_to_hex(&entry.price),
+                decimals: entry.decimal,
+                nb_sources_aggregated: entry.sources.len() as u32,
+                last_updated_timestamp,
+                staleness_in_seconds,
+                is_stale: staleness_in_seconds > ONCHAIN_STALENESS_THRESHOLD_IN_SECONDS,
+            },
+        ));
+    }
+
+    if network_entries.is_empty() {
+        return Err(EntryError::NotFound(pair_id));
+    }
+
+    Ok(Json(merge_network_entries(pair_id, network_entries)))
+}
+
+/// Merges the entries of each network into a single response keyed by network.
+fn merge_network_entries(
+    pair_id: String,
+    network_entries: Vec<(Network, NetworkOnchainEntry)>,
+) -> GetOnchainMergedEntryResponse {
+    GetOnchainMergedEntryResponse {
+        pair_id,
+        networks: network_entries
+            .into_iter()
+            .map(|(network, entry)| (network.to_string(), entry))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network_entry(price: &str, staleness_in_seconds: u64) -> NetworkOnchainEntry {
+        NetworkOnchainEntry {
+            price: price.to_string(),
+            decimals: 8,
+            nb_sources_aggregated: 5,
+            last_updated_timestamp: 1718000000,
+            staleness_in_seconds,
+            is_stale: staleness_in_seconds > ONCHAIN_STALENESS_THRESHOLD_IN_SECONDS,
+        }
+    }
+
+    #[test]
+    fn test_merge_two_networks() {
+        let merged = merge_network_entries(
+            "ETH/USD".to_string(),
+            vec![
+                (Network::Sepolia, network_entry("0x5f5e100", 10)),
+                (Network::Mainnet, network_entry("0x5f5e101", 7200)),
+            ],
+        );
+
+        assert_eq!(merged.pair_id, "ETH/USD");
+        assert_eq!(merged.networks.len(), 2);
+        assert_eq!(merged.networks["sepolia"].price, "0x5f5e100");
+        assert!(!merged.networks["sepolia"].is_stale);
+        assert_eq!(merged.networks["mainnet"].price, "0x5f5e101");
+        assert!(merged.networks["mainnet"].is_stale);
+
+        let json = serde_json::to_value(&merged).unwrap();
+        assert!(json["networks"]["sepolia"].is_object());
+        assert!(json["networks"]["mainnet"].is_object());
+    }
+}
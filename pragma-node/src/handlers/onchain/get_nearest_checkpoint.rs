@@ -0,0 +1,84 @@
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::DateTime;
+use pragma_common::types::Network;
+use pragma_entities::CheckpointError;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToResponse, ToSchema};
+
+use crate::handlers::onchain::get_checkpoints::Checkpoint;
+use crate::infra::repositories::entry_repository::get_decimals;
+use crate::infra::repositories::onchain_repository::checkpoint::get_nearest_checkpoint;
+use crate::types::timestamp::UnixTimestamp;
+use crate::utils::PathExtractor;
+use crate::utils::{assert_currencies_are_distinct, currency_pair_to_pair_id};
+use crate::AppState;
+
+/// Checkpoint to select around the requested timestamp.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointLookup {
+    /// The checkpoint whose time is the closest to the timestamp.
+    #[default]
+    Nearest,
+    /// The most recent checkpoint at or before the timestamp, i.e the one
+    /// active at that time.
+    AtOrBefore,
+}
+
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct GetOnchainNearestCheckpointParams {
+    pub network: Network,
+    /// The unix timestamp in seconds.
+    #[schema(value_type = i64)]
+    pub timestamp: UnixTimestamp,
+    pub lookup: Option<CheckpointLookup>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct GetOnchainNearestCheckpointResponse(pub Checkpoint);
+
+#[utoipa::path(
+    get,
+    path = "/node/v1/onchain/checkpoints/{base}/{quote}/nearest",
+    responses(
+        (status = 200, description = "Get the onchain checkpoint nearest to a timestamp", body = GetOnchainNearestCheckpointResponse)
+    ),
+    params(
+        ("base" = String, Path, description = "Base Asset"),
+        ("quote" = String, Path, description = "Quote Asset"),
+        GetOnchainNearestCheckpointParams
+    ),
+)]
+#[tracing::instrument(skip(state))]
+pub async fn get_onchain_nearest_checkpoint(
+    State(state): State<AppState>,
+    PathExtractor(pair): PathExtractor<(String, String)>,
+    Query(params): Query<GetOnchainNearestCheckpointParams>,
+) -> Result<Json<GetOnchainNearestCheckpointResponse>, CheckpointError> {
+    let pair_id: String = currency_pair_to_pair_id(&pair.0, &pair.1);
+    assert_currencies_are_distinct(&pair.0, &pair.1)
+        .map_err(|_| CheckpointError::IdenticalCurrencies(pair_id.clone()))?;
+
+    let timestamp = DateTime::from_timestamp(params.timestamp, 0)
+        .ok_or(CheckpointError::InvalidTimestamp(params.timestamp))?
+        .naive_utc();
+
+    let decimals = get_decimals(&state.offchain_pool, &pair_id)
+        .await
+        .map_err(CheckpointError::from)?;
+
+    let checkpoint = get_nearest_checkpoint(
+        &state.onchain_pool,
+        params.network,
+        pair_id,
+        decimals,
+        timestamp,
+        params.lookup.unwrap_or_default(),
+    )
+    .await
+    .map_err(CheckpointError::from)?
+    .ok_or(CheckpointError::NotFound)?;
+
+    Ok(Json(GetOnchainNearestCheckpointResponse(checkpoint)))
+}
@@ -1,5 +1,91 @@
 pub mod get_checkpoints;
 pub mod get_entry;
 pub mod get_history;
+pub mod get_merged_entry;
+pub mod get_nearest_checkpoint;
 pub mod get_publishers;
 pub mod subscribe_to_ohlc;
+
+use pragma_common::types::{AggregationMode, Interval};
+use pragma_entities::EntryError;
+
+use crate::infra::repositories::onchain_repository::entry::ENTRIES_BACKWARD_INTERVAL_IN_SECONDS;
+
+/// The onchain aggregates & candles have no 4 hours interval.
+pub(crate) fn assert_onchain_interval_is_supported(interval: Interval) -> Result<(), EntryError> {
+    if interval == Interval::FourHours {
+        return Err(EntryError::InvalidInterval(
+            "4h is not available for onchain data".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns an error if the TWAP is requested without its window, or with a
+/// window longer than the one of the returned components.
+pub(crate) fn assert_twap_interval_is_valid(
+    aggregation_mode: AggregationMode,
+    interval: Option<Interval>,
+) -> Result<(), EntryError> {
+    if !matches!(aggregation_mode, AggregationMode::Twap) {
+        return Ok(());
+    }
+    match interval {
+        None => Err(EntryError::InvalidInterval(
+            "An interval is required with the twap aggregation".to_string(),
+        )),
+        Some(interval) if interval.to_seconds() > ENTRIES_BACKWARD_INTERVAL_IN_SECONDS => {
+            Err(EntryError::InvalidInterval(format!(
+                "The twap aggregation supports intervals up to {} seconds",
+                ENTRIES_BACKWARD_INTERVAL_IN_SECONDS
+            )))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_hours_interval_is_rejected_onchain() {
+        assert!(matches!(
+            assert_onchain_interval_is_supported(Interval::FourHours),
+            Err(EntryError::InvalidInterval(_))
+        ));
+        assert!(assert_onchain_interval_is_supported(Interval::TwoHours).is_ok());
+    }
+
+    #[test]
+    fn test_twap_requires_an_interval() {
+        assert!(matches!(
+            assert_twap_interval_is_valid(AggregationMode::Twap, None),
+            Err(EntryError::InvalidInterval(_))
+        ));
+        assert!(
+            assert_twap_interval_is_valid(AggregationMode::Twap, Some(Interval::OneHour)).is_ok()
+        );
+        assert!(assert_twap_interval_is_valid(AggregationMode::Median, None).is_ok());
+    }
+
+    #[test]
+    fn test_twap_interval_longer_than_the_components_is_rejected() {
+        assert!(matches!(
+            assert_twap_interval_is_valid(AggregationMode::Twap, Some(Interval::OneDay)),
+            Err(EntryError::InvalidInterval(_))
+        ));
+        assert!(matches!(
+            assert_twap_interval_is_valid(AggregationMode::Twap, Some(Interval::TwoHours)),
+            Err(EntryError::InvalidInterval(_))
+        ));
+        assert!(assert_twap_interval_is_valid(
+            AggregationMode::Twap,
+            Some(Interval::FifteenMinutes)
+        )
+        .is_ok());
+        assert!(
+            assert_twap_interval_is_valid(AggregationMode::Median, Some(Interval::OneDay)).is_ok()
+        );
+    }
+}
@@ -11,9 +11,12 @@ use serde::{Deserialize, Serialize};
 use pragma_common::types::{Interval, Network};
 use utoipa::{ToResponse, ToSchema};
 
+use crate::handlers::onchain::assert_onchain_interval_is_supported;
 use crate::infra::repositories::entry_repository::OHLCEntry;
 use crate::infra::repositories::onchain_repository;
-use crate::types::ws::{ChannelHandler, Subscriber, SubscriptionType};
+use crate::types::ws::{
+    too_many_connections_response, ChannelHandler, Subscriber, SubscriptionType,
+};
 use crate::utils::is_onchain_existing_pair;
 use crate::{metrics, AppState};
 
@@ -31,7 +34,14 @@ pub async fn subscribe_to_onchain_ohlc(
     State(state): State<AppState>,
     ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| create_new_subscriber(socket, state, client_addr))
+    let Some(permit) = state.ws_connections.try_acquire() else {
+        return too_many_connections_response(&state, "subscribe_to_ohlc");
+    };
+    ws.on_upgrade(move |socket| async move {
+        create_new_subscriber(socket, state, client_addr).await;
+        drop(permit);
+    })
+    .into_response()
 }
 
 /// Interval in milliseconds that the channel will update the client with the latest prices.
@@ -93,6 +103,10 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, InfraError> for WsOH
     ) -> Result<(), InfraError> {
         match subscription.msg_type {
             SubscriptionType::Subscribe => {
+                if let Err(e) = assert_onchain_interval_is_supported(subscription.interval) {
+                    subscriber.send_err(&e.to_string()).await;
+                    return Ok(());
+                }
                 let pair_exists = is_onchain_existing_pair(
                     &subscriber.app_state.onchain_pool,
                     &subscription.pair,
@@ -117,6 +131,11 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, InfraError> for WsOH
                 let mut state = subscriber.state.lock().await;
                 *state = SubscriptionState::default();
             }
+            SubscriptionType::Status => {
+                let error_msg = "Status messages are not supported for OHLC subscriptions.";
+                subscriber.send_err(error_msg).await;
+                return Ok(());
+            }
         };
         self.send_ack_message(subscriber, subscription).await?;
         // Trigger the first update manually
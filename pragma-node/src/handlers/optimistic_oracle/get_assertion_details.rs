@@ -10,7 +10,9 @@ use crate::handlers::optimistic_oracle::types::AssertionDetails;
     get,
     path = "node/v1/optimistic/assertions/{assertion_id}",
     responses(
-        (status = 200, description = "Get assertion details successfully", body = AssertionDetails)
+        (status = 200, description = "Get assertion details successfully", body = AssertionDetails),
+        (status = 400, description = "Malformed assertion id", body = OptimisticOracleError),
+        (status = 404, description = "Unknown assertion", body = OptimisticOracleError)
     ),
     params(
         ("assertion_id" = String, Path, description = "Unique identifier of the assertion"),
@@ -21,9 +23,74 @@ pub async fn get_assertion_details(
     State(state): State<AppState>,
     Path(assertion_id): Path<String>,
 ) -> Result<Json<AssertionDetails>, OptimisticOracleError> {
-    let assertion_details = assertions::get_assertion_details(&state.onchain_pool, &assertion_id)
+    let assertion_id = parse_assertion_id(&assertion_id)?;
+    let assertion_details = assertions::get_assertion_details(&state.onchain_pool, assertion_id)
         .await
         .map_err(OptimisticOracleError::from)?;
 
     Ok(Json(assertion_details))
 }
+
+/// Maximum number of hex digits of an assertion id, which is a felt.
+const MAX_ASSERTION_ID_HEX_DIGITS: usize = 64;
+
+/// Checks that the assertion id is a 0x-prefixed hex felt. Its case and
+/// padding are ignored when looking it up.
+fn parse_assertion_id(assertion_id: &str) -> Result<&str, OptimisticOracleError> {
+    let invalid = |reason: &str| {
+        OptimisticOracleError::InvalidAssertionId(format!("{assertion_id} ({reason})"))
+    };
+    let digits = assertion_id
+        .strip_prefix("0x")
+        .ok_or_else(|| invalid("expected a 0x prefix"))?;
+    if digits.is_empty() || digits.len() > MAX_ASSERTION_ID_HEX_DIGITS {
+        return Err(invalid("expected 1 to 64 hex digits"));
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("expected hex digits"));
+    }
+    Ok(assertion_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_assertion_id() {
+        let assertion_id = "0x2b3d0a1e9c8f7b6a5d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b4a3f2e";
+        assert_eq!(parse_assertion_id(assertion_id).unwrap(), assertion_id);
+    }
+
+    #[test]
+    fn test_parse_short_or_uppercase_assertion_id() {
+        assert_eq!(parse_assertion_id("0x1AbC").unwrap(), "0x1AbC");
+        assert_eq!(parse_assertion_id("0x0001abc").unwrap(), "0x0001abc");
+    }
+
+    #[test]
+    fn test_parse_non_hex_assertion_id() {
+        assert!(matches!(
+            parse_assertion_id("0x12zz"),
+            Err(OptimisticOracleError::InvalidAssertionId(_))
+        ));
+        assert!(matches!(
+            parse_assertion_id("1234"),
+            Err(OptimisticOracleError::InvalidAssertionId(_))
+        ));
+        assert!(parse_assertion_id("not-an-id").is_err());
+    }
+
+    #[test]
+    fn test_parse_wrong_length_assertion_id() {
+        assert!(matches!(
+            parse_assertion_id("0x"),
+            Err(OptimisticOracleError::InvalidAssertionId(_))
+        ));
+        let too_long = format!("0x{}", "1".repeat(MAX_ASSERTION_ID_HEX_DIGITS + 1));
+        assert!(matches!(
+            parse_assertion_id(&too_long),
+            Err(OptimisticOracleError::InvalidAssertionId(_))
+        ));
+    }
+}
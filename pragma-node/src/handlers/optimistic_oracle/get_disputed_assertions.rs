@@ -28,13 +28,12 @@ pub async fn get_disputed_assertions(
     let page = params.page.unwrap_or(1);
     let page_size = params.limit.unwrap_or(DEFAULT_LIMIT);
 
-    let disputed_assertions =
+    let (disputed_assertions, total_count) =
         assertions::get_disputed_assertions(&state.onchain_pool, page, page_size)
             .await
             .map_err(OptimisticOracleError::from)?;
 
-    let total_count = disputed_assertions.len();
-    let total_pages = (total_count as f64 / page_size as f64).ceil() as u32;
+    let total_pages = total_pages(total_count, page_size);
 
     let response = GetDisputedAssertionsResponse {
         disputed_assertions,
@@ -45,3 +44,27 @@ pub async fn get_disputed_assertions(
 
     Ok(Json(response))
 }
+
+/// Number of pages needed to list `total_count` items, `page_size` at a time.
+fn total_pages(total_count: i64, page_size: u32) -> u32 {
+    if page_size == 0 {
+        return 0;
+    }
+    let page_size = i64::from(page_size);
+    ((total_count.max(0) + page_size - 1) / page_size) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_pages_uses_the_total_count() {
+        // 250 disputed assertions while a page only returns 100 of them.
+        assert_eq!(total_pages(250, 100), 3);
+        assert_eq!(total_pages(200, 100), 2);
+        assert_eq!(total_pages(1, DEFAULT_LIMIT), 1);
+        assert_eq!(total_pages(0, DEFAULT_LIMIT), 0);
+        assert_eq!(total_pages(250, 0), 0);
+    }
+}
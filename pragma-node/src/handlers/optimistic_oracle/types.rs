@@ -126,7 +126,7 @@ pub struct GetDisputedAssertionsParams {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct GetDisputedAssertionsResponse {
     pub disputed_assertions: Vec<DisputedAssertion>,
-    pub total_count: usize,
+    pub total_count: i64,
     pub current_page: u32,
     pub total_pages: u32,
 }
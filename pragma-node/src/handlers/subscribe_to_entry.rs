@@ -1,24 +1,37 @@
 use std::collections::HashSet;
+use std::io::Write;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::extract::ws::{WebSocket, WebSocketUpgrade};
-use axum::extract::{ConnectInfo, State};
+use axum::extract::{ConnectInfo, Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use bigdecimal::BigDecimal;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
+use starknet::signers::SigningKey;
 
-use pragma_common::types::DataType;
+use pragma_common::types::merkle_tree::MerkleProof;
+use pragma_common::types::{AggregationMode, DataType};
+use pragma_common::utils::field_element_as_hex_string;
 use pragma_entities::EntryError;
-use utoipa::{ToResponse, ToSchema};
+use utoipa::{IntoParams, ToResponse, ToSchema};
 
-use crate::constants::starkex_ws::PRAGMA_ORACLE_NAME_FOR_STARKEX;
-use crate::infra::repositories::entry_repository::MedianEntryWithComponents;
+use crate::config::config;
+use crate::constants::starkex_ws::{MAX_BACKFILL_ENTRIES_PER_PAIR, PRAGMA_ORACLE_NAME_FOR_STARKEX};
+use crate::infra::repositories::entry_repository::{self, MedianEntry, MedianEntryWithComponents};
 use crate::types::pricer::{IndexPricer, MarkPricer, Pricer};
+use crate::types::signer::SignerKey;
 use crate::types::timestamp::UnixTimestamp;
-use crate::types::ws::{ChannelHandler, Subscriber, SubscriptionType};
-use crate::utils::{only_existing_pairs, sign_data, StarkexPrice};
+use crate::types::ws::{
+    too_many_connections_response, ChannelHandler, Subscriber, SubscriptionType,
+};
+use crate::utils::{
+    is_usd_quoted, only_existing_pairs, sign_data, sign_data_in_batch, StarkexNoData, StarkexPrice,
+};
 use crate::AppState;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
@@ -36,6 +49,22 @@ pub struct AssetOraclePrice {
     pub median_price: String,
     pub signature: String,
     pub signed_prices: Vec<SignedPublisherPrice>,
+    /// Publishers whose entries contributed to the median price.
+    pub publishers: Vec<String>,
+    /// Proof that the price belongs to the merkle root of the batch signature.
+    /// Only set when the prices are signed in batch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merkle_proof: Option<MerkleProof>,
+    /// Signed price serialized as the oracle contract calldata.
+    #[serde(skip)]
+    pub calldata: Vec<String>,
+}
+
+/// Signature of the merkle root of all the prices of an update.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct BatchSignature {
+    pub merkle_root: String,
+    pub signature: String,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, ToResponse, ToSchema)]
@@ -43,6 +72,165 @@ pub struct SubscribeToEntryResponse {
     pub oracle_prices: Vec<AssetOraclePrice>,
     #[schema(value_type = i64)]
     pub timestamp: UnixTimestamp,
+    /// Number of the update on the connection, starting at 1 and increased
+    /// by 1 with each update, so a client can detect the updates it missed.
+    /// It is reset when the client reconnects.
+    pub sequence: u64,
+    /// Replaces the signatures of the prices when they are signed in batch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_signature: Option<BatchSignature>,
+    /// Set when the update replaces the ones the client was too slow to receive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coalesced: Option<bool>,
+    /// Set when the time oracle could not be reached and the prices were
+    /// signed with the local time of the node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_with_local_clock: Option<bool>,
+    /// Fingerprint of the key that signed the update, changing on rotation.
+    pub signer_fingerprint: String,
+    /// Subscribed pairs without price, if requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub no_data_attestations: Vec<NoDataAttestation>,
+}
+
+/// Status of a signed attestation that is not a price.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AttestationStatus {
+    NoData,
+}
+
+/// Signed attestation that a subscribed pair had no price to sign.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NoDataAttestation {
+    pub pair_id: String,
+    #[schema(value_type = i64)]
+    pub timestamp: UnixTimestamp,
+    pub status: AttestationStatus,
+    pub signature: String,
+}
+
+/// Oracle price serialized as calldata, i.e a hex felt array.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct AssetOracleCalldata {
+    pub global_asset_id: String,
+    pub calldata: Vec<String>,
+}
+
+/// Compact response returned when the `calldata` format is requested.
+#[derive(Debug, Default, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct SubscribeToEntryCalldataResponse {
+    pub oracle_prices: Vec<AssetOracleCalldata>,
+    #[schema(value_type = i64)]
+    pub timestamp: UnixTimestamp,
+    pub sequence: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coalesced: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_with_local_clock: Option<bool>,
+    pub signer_fingerprint: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub no_data_attestations: Vec<NoDataAttestation>,
+}
+
+impl From<SubscribeToEntryResponse> for SubscribeToEntryCalldataResponse {
+    fn from(response: SubscribeToEntryResponse) -> Self {
+        Self {
+            oracle_prices: response
+                .oracle_prices
+                .into_iter()
+                .map(|oracle_price| AssetOracleCalldata {
+                    global_asset_id: oracle_price.global_asset_id,
+                    calldata: oracle_price.calldata,
+                })
+                .collect(),
+            timestamp: response.timestamp,
+            sequence: response.sequence,
+            coalesced: response.coalesced,
+            signed_with_local_clock: response.signed_with_local_clock,
+            signer_fingerprint: response.signer_fingerprint,
+            no_data_attestations: response.no_data_attestations,
+        }
+    }
+}
+
+/// Format of the prices sent to the client.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceFormat {
+    #[default]
+    Json,
+    /// Signed prices pre-packed as the oracle contract calldata.
+    Calldata,
+}
+
+/// Encoding of the price updates sent to the client, negotiated with a
+/// subscription message.
+/// Only the price updates are affected: acks, status, errors and backfill
+/// messages are always sent as JSON text messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    #[default]
+    Json,
+    /// MessagePack, sent as binary messages tagged with [`BinaryFrame::MsgpackUpdate`].
+    Msgpack,
+}
+
+/// Kind of a binary message, sent as its first byte so the client knows how
+/// to decode the rest of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+enum BinaryFrame {
+    /// A MessagePack price update.
+    MsgpackUpdate = 0x01,
+    /// A gzip-compressed snapshot, with the encoding of the updates.
+    GzipSnapshot = 0x02,
+}
+
+impl BinaryFrame {
+    fn tag(self, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(payload.len() + 1);
+        frame.push(self as u8);
+        frame.extend_from_slice(payload);
+        frame
+    }
+}
+
+/// Price update serialized with the encoding of the client.
+enum EncodedUpdate {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl EncodedUpdate {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Binary(bytes) => bytes,
+        }
+    }
+}
+
+impl Encoding {
+    /// Serializes a price update. MessagePack updates keep the field names,
+    /// so they decode to the same structure as the JSON ones.
+    fn encode<T: Serialize>(self, update: &T) -> Option<EncodedUpdate> {
+        match self {
+            Self::Json => serde_json::to_string(update).ok().map(EncodedUpdate::Text),
+            Self::Msgpack => rmp_serde::to_vec_named(update)
+                .ok()
+                .map(EncodedUpdate::Binary),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct SubscribeToEntryParams {
+    pub format: Option<PriceFormat>,
+    /// If true, the subscribed pairs without price are sent as signed no
+    /// data attestations instead of being left out of the updates.
+    pub no_data_attestations: Option<bool>,
 }
 
 #[tracing::instrument(skip(state, ws), fields(endpoint_name = "subscribe_to_entry"))]
@@ -50,11 +238,26 @@ pub async fn subscribe_to_entry(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
     ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    Query(params): Query<SubscribeToEntryParams>,
 ) -> impl IntoResponse {
-    if state.pragma_signer.is_none() {
+    if !state.pragma_signer.is_available() {
         return (StatusCode::LOCKED, "Locked: Pragma signer not found").into_response();
     }
-    ws.on_upgrade(move |socket| create_new_subscriber(socket, state, client_addr))
+    let Some(permit) = state.ws_connections.try_acquire() else {
+        return too_many_connections_response(&state, "subscribe_to_entry");
+    };
+    let handler = WsEntriesHandler {
+        format: params.format.unwrap_or_default(),
+        no_data_attestations: params.no_data_attestations.unwrap_or(false),
+        snapshot: None,
+        encoding: Encoding::default(),
+        aggregation: AggregationMode::default(),
+    };
+    ws.on_upgrade(move |socket| async move {
+        create_new_subscriber(socket, state, client_addr, handler).await;
+        drop(permit);
+    })
+    .into_response()
 }
 
 /// Interval in milliseconds that the channel will update the client with the latest prices.
@@ -67,7 +270,12 @@ const CHANNEL_UPDATE_INTERVAL_IN_MS: u64 = 500;
         client_ip = %client_addr.ip()
     )
 )]
-async fn create_new_subscriber(socket: WebSocket, app_state: AppState, client_addr: SocketAddr) {
+async fn create_new_subscriber(
+    socket: WebSocket,
+    app_state: AppState,
+    client_addr: SocketAddr,
+    handler: WsEntriesHandler,
+) {
     let (mut subscriber, _) = match Subscriber::<SubscriptionState>::new(
         "subscribe_to_entry".into(),
         socket,
@@ -86,7 +294,6 @@ async fn create_new_subscriber(socket: WebSocket, app_state: AppState, client_ad
     };
 
     // Main event loop for the subscriber
-    let handler = WsEntriesHandler;
     let status = subscriber.listen(handler).await;
     if let Err(e) = status {
         tracing::error!(
@@ -97,7 +304,56 @@ async fn create_new_subscriber(socket: WebSocket, app_state: AppState, client_ad
     }
 }
 
-struct WsEntriesHandler;
+struct WsEntriesHandler {
+    format: PriceFormat,
+    no_data_attestations: bool,
+    /// Cadence of the compressed snapshots, if requested by the client.
+    snapshot: Option<SnapshotSchedule>,
+    /// Encoding of the price updates, JSON unless requested otherwise.
+    encoding: Encoding,
+    /// Aggregation of the sources of each pair, the median by default.
+    aggregation: AggregationMode,
+}
+
+/// Cadence of the compressed snapshots of all the subscribed pairs, sent
+/// in place of the periodic updates.
+#[derive(Debug)]
+struct SnapshotSchedule {
+    interval: Duration,
+    next_at: Option<Instant>,
+}
+
+impl SnapshotSchedule {
+    /// Snapshots are sent at most once per update.
+    fn new(interval_in_ms: u64) -> Self {
+        Self {
+            interval: Duration::from_millis(interval_in_ms.max(CHANNEL_UPDATE_INTERVAL_IN_MS)),
+            next_at: None,
+        }
+    }
+
+    /// Returns true if a snapshot must be sent instead of the update of `now`.
+    fn is_due(&mut self, now: Instant) -> bool {
+        // The updates fire slightly late, so a snapshot due within half an
+        // update is sent right away instead of one update later.
+        let tolerance = Duration::from_millis(CHANNEL_UPDATE_INTERVAL_IN_MS / 2);
+        if self
+            .next_at
+            .is_some_and(|next_at| now + tolerance < next_at)
+        {
+            return false;
+        }
+        self.next_at = Some(now + self.interval);
+        true
+    }
+}
+
+/// Compresses a snapshot with gzip.
+fn compress_snapshot(snapshot: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(snapshot)?;
+    encoder.finish()
+}
 
 impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEntriesHandler {
     #[tracing::instrument(
@@ -113,21 +369,105 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEn
         subscriber: &mut Subscriber<SubscriptionState>,
         request: SubscriptionRequest,
     ) -> Result<(), EntryError> {
-        let (existing_spot_pairs, existing_perp_pairs) =
+        if let SubscriptionType::Status = request.msg_type {
+            let status = subscriber.state.lock().await.status();
+            match serde_json::to_string(&status) {
+                Ok(status_message) => {
+                    if subscriber.send_msg(status_message).await.is_err() {
+                        let error_msg = "Message received but could not send status message.";
+                        subscriber.send_err(error_msg).await;
+                    }
+                }
+                Err(_) => {
+                    let error_msg = "Could not serialize status message.";
+                    subscriber.send_err(error_msg).await;
+                }
+            }
+            return Ok(());
+        }
+        // Reject the whole request before touching the subscription.
+        if let Some(AggregationMode::Twap) = request.aggregation {
+            let err = EntryError::UnsupportedAggregation("twap".to_string());
+            subscriber.send_err(&err.to_string()).await;
+            return Ok(());
+        }
+        let existing_pairs =
             only_existing_pairs(&subscriber.app_state.offchain_pool, request.pairs).await;
+        let (mut existing_spot_pairs, mut existing_perp_pairs) = match existing_pairs {
+            Ok(existing_pairs) => existing_pairs,
+            Err(e) => {
+                tracing::error!("Could not check the requested pairs: {e}");
+                subscriber
+                    .send_err("Could not check the requested pairs.")
+                    .await;
+                return Ok(());
+            }
+        };
+        if let SubscriptionType::Subscribe = request.msg_type {
+            // Refuse to subscribe to pairs that can't be served as signed oracle values.
+            let config = config().await;
+            let mut not_signable_pairs = vec![];
+            for pairs in [&mut existing_spot_pairs, &mut existing_perp_pairs] {
+                let (signable, not_signable): (Vec<String>, Vec<String>) = pairs
+                    .drain(..)
+                    .partition(|pair| config.is_signable_pair(pair));
+                *pairs = signable;
+                not_signable_pairs.extend(not_signable);
+            }
+            if !not_signable_pairs.is_empty() {
+                let err = EntryError::PairNotSignable(not_signable_pairs.join(", "));
+                subscriber.send_err(&err.to_string()).await;
+            }
+        }
+        if let (SubscriptionType::Subscribe, Some(snapshot_interval_in_ms)) =
+            (&request.msg_type, request.snapshot_interval_ms)
+        {
+            self.snapshot = (snapshot_interval_in_ms > 0)
+                .then(|| SnapshotSchedule::new(snapshot_interval_in_ms));
+        }
+        if let Some(encoding) = request.encoding {
+            self.encoding = encoding;
+        }
+        if let Some(aggregation) = request.aggregation {
+            self.aggregation = aggregation;
+        }
+        // Spot pairs for which the client asked for a backfill of the missed prices.
+        let backfill_since = request.backfill_since();
+        let mut backfill_pairs = match backfill_since {
+            Some(_) => existing_spot_pairs.clone(),
+            None => vec![],
+        };
+        let max_pairs = config().await.max_subscriptions_per_connection();
         let mut state = subscriber.state.lock().await;
+        let mut dropped_pairs = vec![];
         match request.msg_type {
             SubscriptionType::Subscribe => {
-                state.add_spot_pairs(existing_spot_pairs);
-                state.add_perp_pairs(existing_perp_pairs);
+                let dropped_spot_pairs = state.add_spot_pairs(existing_spot_pairs, max_pairs);
+                backfill_pairs.retain(|pair| !dropped_spot_pairs.contains(pair));
+                dropped_pairs.extend(dropped_spot_pairs);
+                let dropped_perp_pairs = state.add_perp_pairs(existing_perp_pairs, max_pairs);
+                dropped_pairs.extend(
+                    dropped_perp_pairs
+                        .into_iter()
+                        .map(|pair| format!("{}:MARK", pair)),
+                );
             }
             SubscriptionType::Unsubscribe => {
                 state.remove_spot_pairs(&existing_spot_pairs);
                 state.remove_perp_pairs(&existing_perp_pairs);
             }
+            SubscriptionType::Status => unreachable!("status requests are answered above"),
         };
         let subscribed_pairs = state.get_fmt_subscribed_pairs();
         drop(state);
+        if !dropped_pairs.is_empty() {
+            let error_msg = format!(
+                "Subscriptions are limited to {} pairs per connection, dropped: {}",
+                max_pairs,
+                dropped_pairs.join(", ")
+            );
+            subscriber.send_err(&error_msg).await;
+        }
         // We send an ack message to the client with the subscribed pairs (so
         // the client knows which pairs are successfully subscribed).
         if let Ok(ack_message) = serde_json::to_string(&SubscriptionAck {
@@ -142,6 +482,12 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEn
             let error_msg = "Could not serialize ack message.";
             subscriber.send_err(error_msg).await;
         }
+        // The backfill is sent before returning to the listening loop, so it
+        // always precedes the next live update.
+        if let Some(backfill_since) = backfill_since {
+            self.send_backfill(subscriber, backfill_pairs, backfill_since)
+                .await;
+        }
         Ok(())
     }
 
@@ -155,15 +501,26 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEn
         &mut self,
         subscriber: &mut Subscriber<SubscriptionState>,
     ) -> Result<(), EntryError> {
-        let subscription = subscriber.state.lock().await;
+        let mut subscription = subscriber.state.lock().await;
         if subscription.is_empty() {
             return Ok(());
         }
-        let response = match self
+        let (spot_pairs, perp_pairs) =
+            (subscription.spot_pairs.len(), subscription.perp_pairs.len());
+        subscriber.record_subscribed_pairs(spot_pairs, perp_pairs);
+        tracing::debug!(spot_pairs, perp_pairs, "Pricing the subscribed pairs");
+        let (response, stale_prices) = match self
             .get_subscribed_pairs_medians(&subscriber.app_state, &subscription)
             .await
         {
-            Ok(response) => response,
+            Ok((response, stale_prices)) => (
+                SubscribeToEntryResponse {
+                    sequence: subscription.next_sequence(),
+                    coalesced: subscriber.coalesced.then_some(true),
+                    ..response
+                },
+                stale_prices,
+            ),
             Err(e) => {
                 drop(subscription);
                 subscriber.send_err(&e.to_string()).await;
@@ -171,19 +528,101 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEn
             }
         };
         drop(subscription);
-        if let Ok(json_response) = serde_json::to_string(&response) {
-            if subscriber.send_msg(json_response).await.is_err() {
-                subscriber.send_err("Could not send prices.").await;
-            }
-        } else {
+        for stale_price in stale_prices {
+            subscriber.send_err(&stale_price.to_string()).await;
+        }
+        let encoded_response = match self.format {
+            PriceFormat::Json => self.encoding.encode(&response),
+            PriceFormat::Calldata => self
+                .encoding
+                .encode(&SubscribeToEntryCalldataResponse::from(response)),
+        };
+        let Some(encoded_response) = encoded_response else {
             subscriber.send_err("Could not serialize prices.").await;
+            return Ok(());
+        };
+        // When due, the snapshot is sent instead of the update: it is the
+        // same update, compressed.
+        let snapshot_is_due = self
+            .snapshot
+            .as_mut()
+            .is_some_and(|snapshot| snapshot.is_due(Instant::now()));
+        if snapshot_is_due {
+            match compress_snapshot(encoded_response.as_bytes()) {
+                Ok(snapshot) => {
+                    let snapshot = BinaryFrame::GzipSnapshot.tag(&snapshot);
+                    if subscriber.send_binary(snapshot).await.is_err() {
+                        subscriber.send_err("Could not send snapshot.").await;
+                    }
+                    return Ok(());
+                }
+                Err(e) => tracing::warn!("Could not compress snapshot, sending the update: {e}"),
+            }
+        }
+        let sent = match encoded_response {
+            EncodedUpdate::Text(text) => subscriber.send_msg(text).await,
+            EncodedUpdate::Binary(bytes) => {
+                subscriber
+                    .send_binary(BinaryFrame::MsgpackUpdate.tag(&bytes))
+                    .await
+            }
+        };
+        if sent.is_err() {
+            subscriber.send_err("Could not send prices.").await;
         }
         Ok(())
     }
 }
 
 impl WsEntriesHandler {
+    /// Sends the aggregated prices (bucketed per minute) of the pairs since the
+    /// provided timestamp. Only spot pairs are backfilled.
+    #[tracing::instrument(skip(self, subscriber))]
+    async fn send_backfill(
+        &self,
+        subscriber: &mut Subscriber<SubscriptionState>,
+        pairs: Vec<String>,
+        since: UnixTimestamp,
+    ) {
+        let now = chrono::Utc::now().timestamp();
+        if since < 0 || since > now {
+            subscriber
+                .send_err("Invalid backfill_since timestamp.")
+                .await;
+            return;
+        }
+        for pair_id in pairs {
+            let entries = match entry_repository::get_entries_between(
+                &subscriber.app_state.offchain_pool,
+                pair_id.clone(),
+                since as u64,
+                now as u64,
+                // One more entry than sent, to know if the backfill is truncated.
+                Some(MAX_BACKFILL_ENTRIES_PER_PAIR as u64 + 1),
+            )
+            .await
+            {
+                Ok(entries) => entries,
+                Err(e) => {
+                    let err = e.to_entry_error(&pair_id);
+                    subscriber.send_err(&err.to_string()).await;
+                    continue;
+                }
+            };
+            let backfill = build_backfill(pair_id, entries, MAX_BACKFILL_ENTRIES_PER_PAIR);
+            match serde_json::to_string(&backfill) {
+                Ok(msg) => {
+                    if subscriber.send_msg(msg).await.is_err() {
+                        subscriber.send_err("Could not send backfill.").await;
+                    }
+                }
+                Err(_) => subscriber.send_err("Could not serialize backfill.").await,
+            }
+        }
+    }
+
     /// Get the current median entries for the subscribed pairs and sign them as Pragma.
+    /// The prices too old to be signed are returned apart, as errors.
     #[tracing::instrument(
         skip(self, state, subscription),
         fields(
@@ -195,40 +634,112 @@ impl WsEntriesHandler {
         &self,
         state: &AppState,
         subscription: &SubscriptionState,
-    ) -> Result<SubscribeToEntryResponse, EntryError> {
+    ) -> Result<(SubscribeToEntryResponse, Vec<EntryError>), EntryError> {
         let median_entries = self.get_all_entries(state, subscription).await?;
 
-        let mut response: SubscribeToEntryResponse = Default::default();
-        let now = chrono::Utc::now().timestamp();
-
-        let pragma_signer = state
+        let signing_time = state.time_oracle.now().await;
+        let now = signing_time.timestamp;
+        let config = config().await;
+        // A stale pair is not signed, but doesn't prevent signing the others.
+        let (median_entries, stale_prices) = drop_stale_entries(median_entries, now, |pair_id| {
+            config.max_signed_price_age(pair_id)
+        });
+        let priced_pairs: HashSet<String> = median_entries
+            .iter()
+            .map(|entry| entry.pair_id.clone())
+            .collect();
+        let signer_key = state
             .pragma_signer
-            .as_ref()
+            .current()
             // Should not happen, as the endpoint is disabled if the signer is not found.
             .ok_or(EntryError::InternalServerError)?;
+        // The calldata embeds the signature of each price, so they are always
+        // signed one by one for this format.
+        let batch_signing = config.batch_signing() && self.format == PriceFormat::Json;
+        let unpriced_pairs = if self.no_data_attestations {
+            pairs_without_price(subscription, &priced_pairs)
+        } else {
+            vec![]
+        };
+        let mut response = self.sign_entries(
+            &signer_key,
+            median_entries,
+            unpriced_pairs,
+            now,
+            batch_signing,
+            |pair_id| config.is_signable_pair(pair_id),
+        )?;
+        response.signed_with_local_clock = signing_time.is_local_fallback.then_some(true);
+        Ok((response, stale_prices))
+    }
 
+    /// Signs the prices of the entries as Pragma, along with the no data
+    /// attestations of the unpriced pairs.
+    fn sign_entries(
+        &self,
+        signer_key: &SignerKey,
+        median_entries: Vec<MedianEntryWithComponents>,
+        unpriced_pairs: Vec<String>,
+        now: i64,
+        batch_signing: bool,
+        is_signable: impl Fn(&str) -> bool,
+    ) -> Result<SubscribeToEntryResponse, EntryError> {
+        let pragma_signer = signer_key.key.as_ref();
+        let mut response = SubscribeToEntryResponse {
+            signer_fingerprint: signer_key.fingerprint.clone(),
+            ..Default::default()
+        };
+
+        let mut starkex_prices = Vec::with_capacity(median_entries.len());
         for entry in median_entries {
             let pair_id = entry.pair_id.clone();
-            // Scale price from 8 decimals to 18 decimals for StarkEx
-            // TODO: dont hardcode the decimals, deduce it from the currency decimals
-            let price_with_18_decimals =
-                entry.median_price.clone() * BigDecimal::from(10_u64.pow(10));
-
-            let starkex_price = StarkexPrice {
-                oracle_name: PRAGMA_ORACLE_NAME_FOR_STARKEX.to_string(),
-                pair_id: pair_id.clone(),
-                timestamp: now as u64,
-                price: price_with_18_decimals.clone(),
-            };
-            let signature =
-                sign_data(pragma_signer, &starkex_price).map_err(|_| EntryError::InvalidSigner)?;
+            if !is_signable(&pair_id) {
+                return Err(EntryError::PairNotSignable(pair_id));
+            }
+            let starkex_price = build_starkex_price(&entry, now as u64);
 
             // Create AssetOraclePrice with the original entry (it will be scaled in the TryFrom implementation)
             let mut oracle_price: AssetOraclePrice = entry
                 .try_into()
                 .map_err(|_| EntryError::InternalServerError)?;
-            oracle_price.signature = signature;
+            if !batch_signing {
+                let signature = sign_data(pragma_signer, &starkex_price)
+                    .map_err(|_| EntryError::InvalidSigner)?;
+                oracle_price.calldata = match self.format {
+                    PriceFormat::Json => vec![],
+                    PriceFormat::Calldata => starkex_price
+                        .to_calldata(&signature)
+                        .map_err(|_| EntryError::InternalServerError)?
+                        .iter()
+                        .map(field_element_as_hex_string)
+                        .collect(),
+                };
+                oracle_price.signature = format!("0x{:}", signature);
+            }
             response.oracle_prices.push(oracle_price);
+            starkex_prices.push(starkex_price);
+        }
+        if batch_signing && !starkex_prices.is_empty() {
+            response.batch_signature = Some(sign_prices_in_batch(
+                pragma_signer,
+                &starkex_prices,
+                &mut response.oracle_prices,
+            )?);
+        }
+        for pair_id in unpriced_pairs {
+            let no_data = StarkexNoData {
+                oracle_name: PRAGMA_ORACLE_NAME_FOR_STARKEX.to_string(),
+                pair_id: pair_id.clone(),
+                timestamp: now as u64,
+            };
+            let signature =
+                sign_data(pragma_signer, &no_data).map_err(|_| EntryError::InvalidSigner)?;
+            response.no_data_attestations.push(NoDataAttestation {
+                pair_id,
+                timestamp: now,
+                status: AttestationStatus::NoData,
+                signature: format!("0x{:}", signature),
+            });
         }
         response.timestamp = now;
         Ok(response)
@@ -249,10 +760,7 @@ impl WsEntriesHandler {
         let (usd_pairs, non_usd_pairs): (Vec<String>, Vec<String>) = subscription
             .get_subscribed_perp_pairs()
             .into_iter()
-            .partition(|pair| {
-                tracing::debug!("Checking pair for USD: {}", pair);
-                pair.ends_with("USD")
-            });
+            .partition(|pair| is_usd_quoted(pair));
         tracing::debug!(
             "USD pairs: {:?}, non-USD pairs: {:?}",
             usd_pairs,
@@ -263,9 +771,9 @@ impl WsEntriesHandler {
 
         // Compute entries concurrently
         let (index_entries, usd_mark_entries, non_usd_mark_entries) = tokio::join!(
-            index_pricer.compute(&state.offchain_pool),
-            mark_pricer_usd.compute(&state.offchain_pool),
-            mark_pricer_non_usd.compute(&state.offchain_pool)
+            index_pricer.compute(&state.offchain_pool, self.aggregation),
+            mark_pricer_usd.compute(&state.offchain_pool, self.aggregation),
+            mark_pricer_non_usd.compute(&state.offchain_pool, self.aggregation)
         );
 
         let mut median_entries = vec![];
@@ -288,10 +796,182 @@ impl WsEntriesHandler {
     }
 }
 
+/// Builds the StarkEx price signed for the aggregated price of the entry.
+fn build_starkex_price(entry: &MedianEntryWithComponents, timestamp: u64) -> StarkexPrice {
+    // Scale price from 8 decimals to 18 decimals for StarkEx
+    // TODO: dont hardcode the decimals, deduce it from the currency decimals
+    let price_with_18_decimals = entry.median_price.clone() * BigDecimal::from(10_u64.pow(10));
+
+    StarkexPrice {
+        oracle_name: PRAGMA_ORACLE_NAME_FOR_STARKEX.to_string(),
+        pair_id: entry.pair_id.clone(),
+        timestamp,
+        price: price_with_18_decimals,
+    }
+}
+
+/// Refuses to sign a price whose most recent component is older than `max_age`.
+fn assert_price_is_signable(
+    entry: &MedianEntryWithComponents,
+    now: i64,
+    max_age: Option<std::time::Duration>,
+) -> Result<(), EntryError> {
+    let Some(max_age) = max_age else {
+        return Ok(());
+    };
+    let age = entry
+        .last_update_timestamp()
+        .map_or(i64::MAX, |timestamp| now.saturating_sub(timestamp));
+    if age > max_age.as_secs() as i64 {
+        return Err(EntryError::StalePriceNotSignable(
+            entry.pair_id.clone(),
+            age,
+            max_age.as_secs(),
+        ));
+    }
+    Ok(())
+}
+
+/// Splits the entries between the ones that can be signed and the errors of
+/// the prices too old to be signed.
+fn drop_stale_entries(
+    entries: Vec<MedianEntryWithComponents>,
+    now: i64,
+    max_age: impl Fn(&str) -> Option<std::time::Duration>,
+) -> (Vec<MedianEntryWithComponents>, Vec<EntryError>) {
+    let mut stale_prices = vec![];
+    let signable = entries
+        .into_iter()
+        .filter(
+            |entry| match assert_price_is_signable(entry, now, max_age(&entry.pair_id)) {
+                Ok(()) => true,
+                Err(e) => {
+                    stale_prices.push(e);
+                    false
+                }
+            },
+        )
+        .collect();
+    (signable, stale_prices)
+}
+
+/// Returns the subscribed pairs, perps with the MARK suffix, that have no price.
+fn pairs_without_price(
+    subscription: &SubscriptionState,
+    priced_pairs: &HashSet<String>,
+) -> Vec<String> {
+    let mut pairs: Vec<String> = subscription
+        .get_subscribed_spot_pairs()
+        .into_iter()
+        .chain(subscription.get_fmt_subscribed_perp_pairs())
+        .filter(|pair_id| !priced_pairs.contains(pair_id))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+/// Signs the merkle root of the prices and attaches to each oracle price the
+/// proof that it belongs to the root.
+/// The oracle prices must be in the same order as the starkex prices.
+fn sign_prices_in_batch(
+    signer: &SigningKey,
+    starkex_prices: &[StarkexPrice],
+    oracle_prices: &mut [AssetOraclePrice],
+) -> Result<BatchSignature, EntryError> {
+    let (merkle_tree, leaves, signature) =
+        sign_data_in_batch(signer, starkex_prices).map_err(|_| EntryError::InvalidSigner)?;
+    for (oracle_price, leaf) in oracle_prices.iter_mut().zip(leaves) {
+        oracle_price.merkle_proof = merkle_tree.get_proof(&leaf).map(MerkleProof::from);
+    }
+    Ok(BatchSignature {
+        merkle_root: field_element_as_hex_string(&merkle_tree.root_hash),
+        signature: format!("0x{:}", signature),
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SubscriptionRequest {
+    /// Subscribe when omitted, so `{"encoding": "msgpack"}` alone switches
+    /// the encoding.
+    #[serde(default)]
     msg_type: SubscriptionType,
+    #[serde(default)]
     pairs: Vec<String>,
+    /// If set, the aggregated prices since this timestamp are sent before
+    /// the live updates. Ignored if not subscribing.
+    #[serde(default)]
+    backfill_since: Option<UnixTimestamp>,
+    /// If set, a gzip-compressed snapshot of all the subscribed pairs is
+    /// sent as a binary message every `snapshot_interval_ms`, in place of the
+    /// update due at that time. 0 stops the snapshots.
+    /// Binary messages start with a [`BinaryFrame`] byte telling a snapshot
+    /// from a MessagePack update.
+    #[serde(default)]
+    snapshot_interval_ms: Option<u64>,
+    /// If set, changes the encoding of the following price updates.
+    #[serde(default)]
+    encoding: Option<Encoding>,
+    /// If set, changes the aggregation of the following prices, `median` or
+    /// `mean`.
+    #[serde(default)]
+    aggregation: Option<AggregationMode>,
+}
+
+impl SubscriptionRequest {
+    /// Timestamp since which the missed prices are backfilled, only when
+    /// subscribing.
+    fn backfill_since(&self) -> Option<UnixTimestamp> {
+        match self.msg_type {
+            SubscriptionType::Subscribe => self.backfill_since,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BackfillPrice {
+    #[schema(value_type = i64)]
+    pub timestamp: UnixTimestamp,
+    pub median_price: String,
+    pub num_sources: i64,
+}
+
+/// Aggregated prices sent after a subscription with a `backfill_since`.
+#[derive(Debug, Serialize, Deserialize, ToResponse, ToSchema)]
+pub struct BackfillResponse {
+    pub msg_type: String,
+    pub pair_id: String,
+    /// Prices, ordered from the oldest to the most recent.
+    pub prices: Vec<BackfillPrice>,
+    /// True if older prices were dropped because of the backfill size cap.
+    pub truncated: bool,
+}
+
+/// Builds the backfill message of a pair from its entries.
+/// Only the `max_entries` most recent entries are kept.
+fn build_backfill(
+    pair_id: String,
+    mut entries: Vec<MedianEntry>,
+    max_entries: usize,
+) -> BackfillResponse {
+    entries.sort_by(|a, b| b.time.cmp(&a.time));
+    let truncated = entries.len() > max_entries;
+    entries.truncate(max_entries);
+    let prices = entries
+        .into_iter()
+        .rev()
+        .map(|entry| BackfillPrice {
+            timestamp: entry.time.and_utc().timestamp(),
+            median_price: entry.median_price.to_string(),
+            num_sources: entry.num_sources,
+        })
+        .collect();
+    BackfillResponse {
+        msg_type: "backfill".to_string(),
+        pair_id,
+        prices,
+        truncated,
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -300,23 +980,51 @@ struct SubscriptionAck {
     pairs: Vec<String>,
 }
 
+/// Current subscription of a client, sent in response to a `status` message.
+#[derive(Debug, Serialize, Deserialize)]
+struct SubscriptionStatus {
+    msg_type: SubscriptionType,
+    spot_pairs: Vec<String>,
+    /// Perp pairs, with the MARK suffix.
+    perp_pairs: Vec<String>,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 struct SubscriptionState {
     spot_pairs: HashSet<String>,
     perp_pairs: HashSet<String>,
+    /// Sequence number of the last update sent on the connection.
+    sequence: u64,
 }
 
 impl SubscriptionState {
+    /// Returns the sequence number of the next update.
+    fn next_sequence(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
     fn is_empty(&self) -> bool {
         self.spot_pairs.is_empty() && self.perp_pairs.is_empty()
     }
 
-    fn add_spot_pairs(&mut self, pairs: Vec<String>) {
-        self.spot_pairs.extend(pairs);
+    /// Number of subscribed pairs, spot and perp combined.
+    fn len(&self) -> usize {
+        self.spot_pairs.len() + self.perp_pairs.len()
+    }
+
+    /// Adds the spot pairs until the subscription holds `max_pairs` pairs.
+    /// Returns the pairs that were dropped.
+    fn add_spot_pairs(&mut self, pairs: Vec<String>, max_pairs: usize) -> Vec<String> {
+        let available = max_pairs.saturating_sub(self.len());
+        add_pairs_up_to(&mut self.spot_pairs, pairs, available)
     }
 
-    fn add_perp_pairs(&mut self, pairs: Vec<String>) {
-        self.perp_pairs.extend(pairs);
+    /// Adds the perp pairs until the subscription holds `max_pairs` pairs.
+    /// Returns the pairs that were dropped.
+    fn add_perp_pairs(&mut self, pairs: Vec<String>, max_pairs: usize) -> Vec<String> {
+        let available = max_pairs.saturating_sub(self.len());
+        add_pairs_up_to(&mut self.perp_pairs, pairs, available)
     }
 
     fn remove_spot_pairs(&mut self, pairs: &[String]) {
@@ -357,4 +1065,655 @@ impl SubscriptionState {
         spot_pairs.extend(perp_pairs);
         spot_pairs
     }
+
+    /// Get the current subscription, with the pairs sorted.
+    fn status(&self) -> SubscriptionStatus {
+        let mut spot_pairs = self.get_subscribed_spot_pairs();
+        spot_pairs.sort();
+        let mut perp_pairs = self.get_fmt_subscribed_perp_pairs();
+        perp_pairs.sort();
+        SubscriptionStatus {
+            msg_type: SubscriptionType::Status,
+            spot_pairs,
+            perp_pairs,
+        }
+    }
+}
+
+/// Adds at most `available` new pairs to the subscribed ones, pairs already
+/// subscribed being free. Returns the pairs that were dropped.
+fn add_pairs_up_to(
+    subscribed_pairs: &mut HashSet<String>,
+    pairs: Vec<String>,
+    mut available: usize,
+) -> Vec<String> {
+    let mut dropped_pairs = vec![];
+    for pair in pairs {
+        if subscribed_pairs.contains(&pair) {
+            continue;
+        }
+        if available == 0 {
+            dropped_pairs.push(pair);
+            continue;
+        }
+        subscribed_pairs.insert(pair);
+        available -= 1;
+    }
+    dropped_pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::repositories::entry_repository::tests::{median_entry, pair_component};
+    use crate::types::ws::testing;
+
+    fn median_entry_at(timestamp: i64, price: u32) -> MedianEntry {
+        MedianEntry {
+            time: chrono::DateTime::from_timestamp(timestamp, 0)
+                .unwrap()
+                .naive_utc(),
+            median_price: BigDecimal::from(price),
+            num_sources: 3,
+        }
+    }
+
+    #[test]
+    fn test_backfill_keeps_the_most_recent_entries_in_chronological_order() {
+        let now = chrono::Utc::now().timestamp();
+        // Entries are returned from the most recent to the oldest by the database.
+        let entries = (0..5)
+            .map(|i| median_entry_at(now - 60 * (i + 1), 100 + i as u32))
+            .collect();
+
+        let backfill = build_backfill("BTC/USD".to_string(), entries, 3);
+
+        assert_eq!(backfill.msg_type, "backfill");
+        assert!(backfill.truncated);
+        assert_eq!(backfill.prices.len(), 3);
+        // Only the most recent entries are kept, replayed in chronological order...
+        let timestamps: Vec<i64> = backfill.prices.iter().map(|p| p.timestamp).collect();
+        assert_eq!(timestamps, vec![now - 180, now - 120, now - 60]);
+        // ... and all of them are older than the next live update.
+        assert!(timestamps.iter().all(|timestamp| *timestamp < now));
+    }
+
+    /// Answers a subscription like [`WsEntriesHandler`], with a backfill
+    /// slower to fetch than the update period.
+    struct SlowBackfillHandler;
+
+    impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for SlowBackfillHandler {
+        async fn handle_client_msg(
+            &mut self,
+            subscriber: &mut Subscriber<SubscriptionState>,
+            _request: SubscriptionRequest,
+        ) -> Result<(), EntryError> {
+            let _ = subscriber.send_msg("ack".to_string()).await;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let _ = subscriber.send_msg("backfill".to_string()).await;
+            Ok(())
+        }
+
+        async fn periodic_interval(
+            &mut self,
+            subscriber: &mut Subscriber<SubscriptionState>,
+        ) -> Result<(), EntryError> {
+            let _ = subscriber.send_msg("update".to_string()).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_is_delivered_before_the_next_live_update() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let (socket, mut client) = testing::connected_websocket().await;
+        let (mut subscriber, _) = Subscriber::new(
+            "subscribe_to_entry".into(),
+            socket,
+            std::net::Ipv4Addr::LOCALHOST.into(),
+            testing::app_state(),
+            None,
+            10,
+        )
+        .await
+        .unwrap();
+
+        let received = async {
+            client
+                .send(ClientMessage::Text(
+                    r#"{"msg_type":"subscribe","pairs":["BTC/USD"],"backfill_since":1718000000}"#
+                        .to_string(),
+                ))
+                .await
+                .unwrap();
+            let mut received = vec![];
+            while received.len() < 3 {
+                if let Some(Ok(ClientMessage::Text(text))) = client.next().await {
+                    // The updates sent before the subscription is received don't matter.
+                    if text != "update" || !received.is_empty() {
+                        received.push(text);
+                    }
+                }
+            }
+            received
+        };
+
+        let received = tokio::select! {
+            _ = subscriber.listen(SlowBackfillHandler) => panic!("the connection ended"),
+            received = tokio::time::timeout(Duration::from_secs(5), received) => received.unwrap(),
+        };
+        assert_eq!(received, vec!["ack", "backfill", "update"]);
+    }
+
+    #[test]
+    fn test_only_a_subscription_is_backfilled() {
+        let request: SubscriptionRequest = serde_json::from_str(
+            r#"{"msg_type":"subscribe","pairs":["BTC/USD"],"backfill_since":1718000000}"#,
+        )
+        .unwrap();
+        assert_eq!(request.backfill_since(), Some(1718000000));
+
+        let request: SubscriptionRequest = serde_json::from_str(
+            r#"{"msg_type":"unsubscribe","pairs":["BTC/USD"],"backfill_since":1718000000}"#,
+        )
+        .unwrap();
+        assert_eq!(request.backfill_since(), None);
+    }
+
+    #[test]
+    fn test_backfill_request_is_optional() {
+        let request: SubscriptionRequest =
+            serde_json::from_str(r#"{"msg_type":"subscribe","pairs":["BTC/USD"]}"#).unwrap();
+        assert!(request.backfill_since.is_none());
+
+        let request: SubscriptionRequest = serde_json::from_str(
+            r#"{"msg_type":"subscribe","pairs":["BTC/USD"],"backfill_since":1718000000}"#,
+        )
+        .unwrap();
+        assert_eq!(request.backfill_since, Some(1718000000));
+    }
+
+    #[test]
+    fn test_batch_signed_price_is_verified_with_its_proof() {
+        use pragma_common::hash::pedersen_hash;
+        use pragma_common::types::merkle_tree::FeltMerkleProof;
+        use starknet::core::crypto::{ecdsa_verify, Signature};
+        use starknet::core::types::Felt;
+
+        use crate::utils::Signable;
+
+        let signer = SigningKey::from_random();
+        let starkex_prices: Vec<StarkexPrice> = ["BTC/USD", "ETH/USD", "SOL/USD"]
+            .iter()
+            .enumerate()
+            .map(|(i, pair_id)| StarkexPrice {
+                oracle_name: PRAGMA_ORACLE_NAME_FOR_STARKEX.to_string(),
+                pair_id: pair_id.to_string(),
+                timestamp: 1718000000,
+                price: BigDecimal::from(1000 + i as u32),
+            })
+            .collect();
+        let mut oracle_prices: Vec<AssetOraclePrice> = starkex_prices
+            .iter()
+            .map(|_| AssetOraclePrice::default())
+            .collect();
+
+        let batch_signature =
+            sign_prices_in_batch(&signer, &starkex_prices, &mut oracle_prices).unwrap();
+
+        let merkle_root = Felt::from_hex(&batch_signature.merkle_root).unwrap();
+        let signature: Signature = {
+            let hex = batch_signature.signature.trim_start_matches("0x");
+            Signature {
+                r: Felt::from_hex(&hex[..64]).unwrap(),
+                s: Felt::from_hex(&hex[64..]).unwrap(),
+            }
+        };
+        assert!(ecdsa_verify(&signer.verifying_key().scalar(), &merkle_root, &signature).unwrap());
+
+        // Each price can be verified individually against the signed root.
+        for (starkex_price, oracle_price) in starkex_prices.iter().zip(&oracle_prices) {
+            assert!(oracle_price.signature.is_empty());
+            let proof: FeltMerkleProof = oracle_price
+                .merkle_proof
+                .clone()
+                .unwrap()
+                .try_into()
+                .unwrap();
+            let leaf = starkex_price.try_get_hash().unwrap();
+            let computed_root = proof
+                .0
+                .iter()
+                .fold(leaf, |hash, sibling| pedersen_hash(&hash, sibling));
+            assert_eq!(computed_root, merkle_root);
+        }
+
+        // A tampered price doesn't match the root anymore.
+        let tampered = StarkexPrice {
+            price: BigDecimal::from(999),
+            ..starkex_prices.into_iter().next().unwrap()
+        };
+        let proof: FeltMerkleProof = oracle_prices[0]
+            .merkle_proof
+            .clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let computed_root = proof
+            .0
+            .iter()
+            .fold(tampered.try_get_hash().unwrap(), |hash, sibling| {
+                pedersen_hash(&hash, sibling)
+            });
+        assert_ne!(computed_root, merkle_root);
+    }
+
+    #[test]
+    fn test_status_returns_the_subscribed_pairs() {
+        let request: SubscriptionRequest =
+            serde_json::from_str(r#"{"msg_type":"status"}"#).unwrap();
+        assert!(matches!(request.msg_type, SubscriptionType::Status));
+        assert!(request.pairs.is_empty());
+
+        let mut state = SubscriptionState::default();
+        state.add_spot_pairs(
+            vec!["ETH/USD".into(), "BTC/USD".into(), "SOL/USD".into()],
+            50,
+        );
+        state.add_perp_pairs(vec!["BTC/USD".into()], 50);
+        state.remove_spot_pairs(&["SOL/USD".to_string()]);
+
+        let status = serde_json::to_value(state.status()).unwrap();
+        assert_eq!(
+            status,
+            serde_json::json!({
+                "msg_type": "status",
+                "spot_pairs": ["BTC/USD", "ETH/USD"],
+                "perp_pairs": ["BTC/USD:MARK"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_pairs_without_price() {
+        let mut subscription = SubscriptionState::default();
+        subscription.add_spot_pairs(vec!["BTC/USD".to_string(), "ETH/USD".to_string()], 50);
+        subscription.add_perp_pairs(vec!["BTC/USD".to_string()], 50);
+        let priced_pairs: HashSet<String> = ["BTC/USD".to_string()].into();
+
+        assert_eq!(
+            pairs_without_price(&subscription, &priced_pairs),
+            vec!["BTC/USD:MARK".to_string(), "ETH/USD".to_string()]
+        );
+    }
+
+    /// Entry of the pair whose components were published at `timestamps`.
+    fn entry_at(pair_id: &str, timestamps: &[i64]) -> MedianEntryWithComponents {
+        median_entry(
+            pair_id,
+            60_000,
+            timestamps
+                .iter()
+                .map(|timestamp| pair_component(pair_id, "PRAGMA", 60_000, *timestamp))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_response_advertises_the_signer_fingerprint() {
+        use starknet::core::types::Felt;
+
+        let handler = WsEntriesHandler {
+            format: PriceFormat::Json,
+            no_data_attestations: true,
+            snapshot: None,
+            encoding: Encoding::default(),
+            aggregation: AggregationMode::Median,
+        };
+        let sign = |signer_key: &SignerKey| {
+            handler
+                .sign_entries(
+                    signer_key,
+                    vec![entry_at("BTC/USD", &[1_718_000_000])],
+                    vec!["ETH/USD".to_string()],
+                    1_718_000_000,
+                    false,
+                    |_| true,
+                )
+                .unwrap()
+        };
+
+        let old_key = SignerKey::new(SigningKey::from_secret_scalar(Felt::from(1_u32)));
+        let response = sign(&old_key);
+        assert_eq!(response.signer_fingerprint, old_key.fingerprint);
+        assert_eq!(response.oracle_prices.len(), 1);
+        assert_eq!(response.no_data_attestations.len(), 1);
+
+        // After a rotation, the new fingerprint is advertised.
+        let new_key = SignerKey::new(SigningKey::from_secret_scalar(Felt::from(2_u32)));
+        assert_eq!(sign(&new_key).signer_fingerprint, new_key.fingerprint);
+        assert_ne!(old_key.fingerprint, new_key.fingerprint);
+    }
+
+    #[test]
+    fn test_stale_price_is_not_signed() {
+        let now = 1_718_000_000;
+        let entry = |timestamps: &[i64]| entry_at("BTC/USD", timestamps);
+        let max_age = Some(std::time::Duration::from_secs(60));
+
+        // The age of the price is the one of its most recent component.
+        let fresh = entry(&[now - 3600, now - 10]);
+        assert!(assert_price_is_signable(&fresh, now, max_age).is_ok());
+
+        let stale = entry(&[now - 7200, now - 3600]);
+        assert!(matches!(
+            assert_price_is_signable(&stale, now, max_age),
+            Err(EntryError::StalePriceNotSignable(pair_id, 3600, 60)) if pair_id == "BTC/USD"
+        ));
+        assert!(assert_price_is_signable(&entry(&[]), now, max_age).is_err());
+
+        // Without max age, prices are signed whatever their age.
+        assert!(assert_price_is_signable(&stale, now, None).is_ok());
+    }
+
+    #[test]
+    fn test_stale_pair_does_not_prevent_signing_the_others() {
+        let now = 1_718_000_000;
+        let entries = vec![
+            entry_at("BTC/USD", &[now - 10]),
+            entry_at("LORDS/USD", &[now - 3600]),
+        ];
+
+        let (signable, stale_prices) =
+            drop_stale_entries(entries, now, |_| Some(std::time::Duration::from_secs(60)));
+        assert_eq!(
+            signable
+                .iter()
+                .map(|entry| entry.pair_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["BTC/USD"]
+        );
+        // The client is told why the stale pair is not signed.
+        assert!(matches!(
+            stale_prices.as_slice(),
+            [EntryError::StalePriceNotSignable(pair_id, 3600, 60)] if pair_id == "LORDS/USD"
+        ));
+
+        // The stale pair is then reported without price.
+        let mut subscription = SubscriptionState::default();
+        subscription.add_spot_pairs(vec!["BTC/USD".to_string(), "LORDS/USD".to_string()], 50);
+        let priced_pairs: HashSet<String> =
+            signable.iter().map(|entry| entry.pair_id.clone()).collect();
+        assert_eq!(
+            pairs_without_price(&subscription, &priced_pairs),
+            vec!["LORDS/USD".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_snapshots_are_sent_on_the_requested_cadence() {
+        let request: SubscriptionRequest = serde_json::from_str(
+            r#"{"msg_type":"subscribe","pairs":["BTC/USD"],"snapshot_interval_ms":2000}"#,
+        )
+        .unwrap();
+        let mut schedule = SnapshotSchedule::new(request.snapshot_interval_ms.unwrap());
+
+        // Updates over 10 seconds, each firing a few milliseconds late.
+        let started_at = Instant::now();
+        let snapshots: Vec<u64> = (0..=20)
+            .filter_map(|i| {
+                let update_in_ms = i * CHANNEL_UPDATE_INTERVAL_IN_MS;
+                let update_at = started_at + Duration::from_millis(update_in_ms + i % 3);
+                schedule.is_due(update_at).then_some(update_in_ms)
+            })
+            .collect();
+        assert_eq!(snapshots, vec![0, 2000, 4000, 6000, 8000, 10000]);
+
+        // Snapshots can't be more frequent than the updates.
+        let mut schedule = SnapshotSchedule::new(10);
+        assert!(schedule.is_due(started_at));
+        assert!(!schedule.is_due(started_at + Duration::from_millis(100)));
+        assert!(schedule.is_due(started_at + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_snapshot_is_compressed() {
+        use std::io::Read;
+
+        let update = serde_json::to_string(&SubscribeToEntryResponse {
+            oracle_prices: (0..50).map(|_| AssetOraclePrice::default()).collect(),
+            timestamp: 1718000000,
+            ..Default::default()
+        })
+        .unwrap();
+        let snapshot = compress_snapshot(update.as_bytes()).unwrap();
+        assert!(snapshot.len() < update.len());
+        assert_eq!(BinaryFrame::GzipSnapshot.tag(&snapshot)[0], 0x02);
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(snapshot.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, update);
+    }
+
+    #[test]
+    fn test_subscriptions_are_capped_per_connection() {
+        let max_pairs = 50;
+        let mut state = SubscriptionState::default();
+        let spot_pairs: Vec<String> = (0..40).map(|i| format!("TOKEN{i}/USD")).collect();
+        assert!(state.add_spot_pairs(spot_pairs, max_pairs).is_empty());
+
+        // Perp pairs count against the same cap.
+        let perp_pairs: Vec<String> = (0..10).map(|i| format!("TOKEN{i}/USD")).collect();
+        assert!(state.add_perp_pairs(perp_pairs, max_pairs).is_empty());
+        assert_eq!(state.len(), 50);
+
+        // The 51st pair is rejected, but re-subscribing to a pair is not.
+        let dropped = state.add_spot_pairs(
+            vec!["TOKEN0/USD".to_string(), "TOKEN50/USD".to_string()],
+            max_pairs,
+        );
+        assert_eq!(dropped, vec!["TOKEN50/USD".to_string()]);
+        assert_eq!(state.len(), 50);
+
+        // A request is accepted up to the cap once pairs are unsubscribed.
+        state.remove_spot_pairs(&["TOKEN1/USD".to_string()]);
+        let dropped = state.add_spot_pairs(
+            vec!["TOKEN50/USD".to_string(), "TOKEN51/USD".to_string()],
+            max_pairs,
+        );
+        assert_eq!(dropped, vec!["TOKEN51/USD".to_string()]);
+        assert!(state.spot_pairs.contains("TOKEN50/USD"));
+    }
+
+    #[test]
+    fn test_sequence_increments_with_each_update() {
+        let mut subscription = SubscriptionState::default();
+        subscription.add_spot_pairs(vec!["BTC/USD".to_string()], 50);
+
+        let updates: Vec<serde_json::Value> = (0..2)
+            .map(|_| {
+                let response = SubscribeToEntryResponse {
+                    sequence: subscription.next_sequence(),
+                    timestamp: 1718000000,
+                    ..Default::default()
+                };
+                serde_json::to_value(SubscribeToEntryCalldataResponse::from(response)).unwrap()
+            })
+            .collect();
+        assert_eq!(updates[0]["sequence"], 1);
+        assert_eq!(updates[1]["sequence"], 2);
+
+        // A new connection starts a new sequence.
+        assert_eq!(SubscriptionState::default().next_sequence(), 1);
+    }
+
+    #[test]
+    fn test_msgpack_encoding_round_trip() {
+        let request: SubscriptionRequest =
+            serde_json::from_str(r#"{"encoding": "msgpack"}"#).unwrap();
+        assert!(matches!(request.msg_type, SubscriptionType::Subscribe));
+        assert_eq!(request.encoding, Some(Encoding::Msgpack));
+
+        let response = SubscribeToEntryResponse {
+            oracle_prices: vec![AssetOraclePrice {
+                global_asset_id: "0x4254432d5553442d38000000000000".to_string(),
+                median_price: "6000000000000".to_string(),
+                publishers: vec!["BINANCE".to_string(), "OKX".to_string()],
+                ..Default::default()
+            }],
+            timestamp: 1718000000,
+            sequence: 7,
+            signer_fingerprint: "0xabc".to_string(),
+            no_data_attestations: vec![NoDataAttestation {
+                pair_id: "ETH/USD".to_string(),
+                timestamp: 1718000000,
+                status: AttestationStatus::NoData,
+                signature: "0x1".to_string(),
+            }],
+            ..Default::default()
+        };
+        let Some(EncodedUpdate::Binary(bytes)) = Encoding::Msgpack.encode(&response) else {
+            panic!("msgpack updates must be binary");
+        };
+        let frame = BinaryFrame::MsgpackUpdate.tag(&bytes);
+        assert_eq!(frame[0], 0x01);
+        assert_eq!(&frame[1..], bytes.as_slice());
+        let decoded: SubscribeToEntryResponse = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&response).unwrap()
+        );
+        assert!(matches!(
+            Encoding::default().encode(&response),
+            Some(EncodedUpdate::Text(_))
+        ));
+    }
+
+    #[test]
+    fn test_mean_aggregation_is_signed() {
+        use bigdecimal::ToPrimitive;
+
+        use crate::types::pricer::apply_aggregation;
+
+        let request: SubscriptionRequest = serde_json::from_str(
+            r#"{"msg_type": "subscribe", "pairs": ["BTC/USD"], "aggregation": "mean"}"#,
+        )
+        .unwrap();
+        let aggregation = request.aggregation.unwrap();
+        assert!(matches!(aggregation, AggregationMode::Mean));
+
+        // Median of 200, mean of 300.666...
+        let mut entries = vec![median_entry(
+            "BTC/USD",
+            200,
+            [100, 200, 602]
+                .iter()
+                .map(|price| pair_component("BTC/USD", "PRAGMA", *price, 1_718_000_000))
+                .collect(),
+        )];
+        apply_aggregation(&mut entries, aggregation).unwrap();
+        assert_eq!(entries[0].median_price, BigDecimal::from(301));
+
+        // The advertised price is the one we sign.
+        let starkex_price = build_starkex_price(&entries[0], 1718000000);
+        assert_eq!(
+            starkex_price.price,
+            BigDecimal::from(301) * BigDecimal::from(10_u64.pow(10))
+        );
+        assert_eq!(
+            BigDecimal::from(starkex_price.price.to_u128().unwrap()),
+            starkex_price.price
+        );
+        assert!(matches!(
+            apply_aggregation(&mut entries, AggregationMode::Twap),
+            Err(EntryError::UnsupportedAggregation(_))
+        ));
+    }
+
+    /// Fields recorded on the spans once created, with the name of their span.
+    #[derive(Clone, Default)]
+    struct RecordedFields(Arc<std::sync::Mutex<Vec<(String, String, String)>>>);
+
+    struct FieldsVisitor<'a> {
+        span_name: &'a str,
+        fields: &'a RecordedFields,
+    }
+
+    impl tracing::field::Visit for FieldsVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.fields.0.lock().unwrap().push((
+                self.span_name.to_string(),
+                field.name().to_string(),
+                format!("{value:?}"),
+            ));
+        }
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for RecordedFields
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let Some(span) = ctx.span(id) else {
+                return;
+            };
+            values.record(&mut FieldsVisitor {
+                span_name: span.name(),
+                fields: self,
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_pairs_are_recorded_on_the_connection_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let recorded = RecordedFields::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorded.clone()));
+
+        let mut subscription = SubscriptionState::default();
+        subscription.add_spot_pairs(vec!["BTC/USD".to_string(), "ETH/USD".to_string()], 10);
+        subscription.add_perp_pairs(vec!["BTC/USD".to_string()], 10);
+        let (socket, _client) = testing::connected_websocket().await;
+        let (mut subscriber, _) = Subscriber::new(
+            "subscribe_to_entry".into(),
+            socket,
+            std::net::Ipv4Addr::LOCALHOST.into(),
+            testing::app_state(),
+            Some(subscription),
+            10,
+        )
+        .await
+        .unwrap();
+        let handler = WsEntriesHandler {
+            format: PriceFormat::Json,
+            no_data_attestations: false,
+            snapshot: None,
+            encoding: Encoding::default(),
+            aggregation: AggregationMode::Median,
+        };
+
+        // The pairs can't be priced without database, which ends the connection.
+        let _ = tokio::time::timeout(Duration::from_secs(5), subscriber.listen(handler)).await;
+
+        let recorded = recorded.0.lock().unwrap();
+        let listen_field = |name: &str| {
+            recorded
+                .iter()
+                .find(|(span_name, field, _)| span_name == "listen" && field == name)
+                .map(|(_, _, value)| value.clone())
+        };
+        assert_eq!(listen_field("spot_pairs").as_deref(), Some("2"));
+        assert_eq!(listen_field("perp_pairs").as_deref(), Some("1"));
+    }
 }
@@ -1,28 +1,39 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::extract::ws::{WebSocket, WebSocketUpgrade};
 use axum::extract::{ConnectInfo, State};
 use axum::response::IntoResponse;
+use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 
-use pragma_common::types::DataType;
+use pragma_common::types::{AggregationMode, DataType};
 use pragma_entities::EntryError;
 use utoipa::{ToResponse, ToSchema};
 
+use crate::constants::others::MAX_ROLLING_WINDOW_IN_SECONDS;
 use crate::infra::repositories::entry_repository::MedianEntryWithComponents;
 use crate::types::pricer::{IndexPricer, Pricer};
+use crate::types::sliding_window::SlidingWindowMean;
 use crate::types::timestamp::UnixTimestamp;
-use crate::types::ws::{ChannelHandler, Subscriber, SubscriptionType};
+use crate::types::ws::{
+    too_many_connections_response, ChannelHandler, Subscriber, SubscriptionType,
+};
 use crate::utils::only_existing_pairs;
 use crate::AppState;
 
 #[derive(Debug, Default, Serialize, Deserialize, ToResponse, ToSchema)]
 pub struct AssetOraclePrice {
     num_sources_aggregated: usize,
+    /// Number of sources left out of the aggregation, only the most recent
+    /// ones being aggregated when their number is limited.
+    num_sources_dropped: usize,
     pair_id: String,
     price: String,
+    /// Mean of the prices pushed over the rolling window, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rolling_mean: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, ToResponse, ToSchema)]
@@ -30,6 +41,9 @@ pub struct SubscribeToPriceResponse {
     pub oracle_prices: Vec<AssetOraclePrice>,
     #[schema(value_type = i64)]
     pub timestamp: UnixTimestamp,
+    /// Set when the update replaces the ones the client was too slow to receive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coalesced: Option<bool>,
 }
 
 #[tracing::instrument(skip(state, ws), fields(endpoint_name = "subscribe_to_price"))]
@@ -38,7 +52,14 @@ pub async fn subscribe_to_price(
     State(state): State<AppState>,
     ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| create_new_subscriber(socket, state, client_addr))
+    let Some(permit) = state.ws_connections.try_acquire() else {
+        return too_many_connections_response(&state, "subscribe_to_price");
+    };
+    ws.on_upgrade(move |socket| async move {
+        create_new_subscriber(socket, state, client_addr).await;
+        drop(permit);
+    })
+    .into_response()
 }
 
 /// Interval in milliseconds that the channel will update the client with the latest prices.
@@ -97,16 +118,41 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEn
         subscriber: &mut Subscriber<SubscriptionState>,
         request: SubscriptionRequest,
     ) -> Result<(), EntryError> {
-        let (existing_spot_pairs, _existing_perp_pairs) =
+        if let Some(window) = request.rolling_window_in_seconds {
+            if window == 0 || window > MAX_ROLLING_WINDOW_IN_SECONDS {
+                subscriber
+                    .send_err(&format!(
+                        "Rolling window must be between 1 and {MAX_ROLLING_WINDOW_IN_SECONDS} seconds."
+                    ))
+                    .await;
+                return Ok(());
+            }
+        }
+        let existing_pairs =
             only_existing_pairs(&subscriber.app_state.offchain_pool, request.pairs).await;
+        let (existing_spot_pairs, _existing_perp_pairs) = match existing_pairs {
+            Ok(existing_pairs) => existing_pairs,
+            Err(e) => {
+                tracing::error!("Could not check the requested pairs: {e}");
+                subscriber
+                    .send_err("Could not check the requested pairs.")
+                    .await;
+                return Ok(());
+            }
+        };
         let mut state = subscriber.state.lock().await;
         match request.msg_type {
             SubscriptionType::Subscribe => {
                 state.add_spot_pairs(existing_spot_pairs);
+                if let Some(window) = request.rolling_window_in_seconds {
+                    state.set_rolling_window(window);
+                }
             }
             SubscriptionType::Unsubscribe => {
                 state.remove_spot_pairs(&existing_spot_pairs);
             }
+            // The ack already contains the subscribed pairs.
+            SubscriptionType::Status => {}
         };
         let subscribed_pairs = state.get_subscribed_spot_pairs();
         drop(state);
@@ -137,15 +183,18 @@ impl ChannelHandler<SubscriptionState, SubscriptionRequest, EntryError> for WsEn
         &mut self,
         subscriber: &mut Subscriber<SubscriptionState>,
     ) -> Result<(), EntryError> {
-        let subscription = subscriber.state.lock().await;
+        let mut subscription = subscriber.state.lock().await;
         if subscription.is_empty() {
             return Ok(());
         }
         let response = match self
-            .get_subscribed_pairs_medians(&subscriber.app_state, &subscription)
+            .get_subscribed_pairs_medians(&subscriber.app_state, &mut subscription)
             .await
         {
-            Ok(response) => response,
+            Ok(response) => SubscribeToPriceResponse {
+                coalesced: subscriber.coalesced.then_some(true),
+                ..response
+            },
             Err(e) => {
                 drop(subscription);
                 subscriber.send_err(&e.to_string()).await;
@@ -175,7 +224,7 @@ impl WsEntriesHandler {
     async fn get_subscribed_pairs_medians(
         &self,
         state: &AppState,
-        subscription: &SubscriptionState,
+        subscription: &mut SubscriptionState,
     ) -> Result<SubscribeToPriceResponse, EntryError> {
         let median_entries = self.get_all_entries(state, subscription).await?;
 
@@ -185,6 +234,15 @@ impl WsEntriesHandler {
             .into_iter()
             .map(|entry| AssetOraclePrice {
                 num_sources_aggregated: entry.components.len(),
+                num_sources_dropped: entry.num_sources_dropped,
+                rolling_mean: subscription
+                    .push_rolling_price(
+                        &entry.pair_id,
+                        entry.last_update_timestamp(),
+                        now,
+                        entry.median_price.clone(),
+                    )
+                    .map(|mean| mean.round(0).to_string()),
                 pair_id: entry.pair_id,
                 price: entry.median_price.to_string(),
             })
@@ -193,6 +251,7 @@ impl WsEntriesHandler {
         Ok(SubscribeToPriceResponse {
             timestamp: now,
             oracle_prices,
+            coalesced: None,
         })
     }
 
@@ -208,7 +267,9 @@ impl WsEntriesHandler {
             DataType::SpotEntry,
         );
 
-        let median_entries = index_pricer.compute(&state.offchain_pool).await?;
+        let median_entries = index_pricer
+            .compute(&state.offchain_pool, AggregationMode::Median)
+            .await?;
 
         Ok(median_entries)
     }
@@ -217,7 +278,12 @@ impl WsEntriesHandler {
 #[derive(Debug, Serialize, Deserialize)]
 struct SubscriptionRequest {
     msg_type: SubscriptionType,
+    #[serde(default)]
     pairs: Vec<String>,
+    /// If set, a rolling mean of the prices over this window is pushed
+    /// along with each price.
+    #[serde(default)]
+    rolling_window_in_seconds: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -226,9 +292,11 @@ struct SubscriptionAck {
     pairs: Vec<String>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default)]
 struct SubscriptionState {
     spot_pairs: HashSet<String>,
+    rolling_window_in_seconds: Option<u64>,
+    rolling_means: HashMap<String, SlidingWindowMean>,
 }
 
 impl SubscriptionState {
@@ -243,7 +311,39 @@ impl SubscriptionState {
     fn remove_spot_pairs(&mut self, pairs: &[String]) {
         for pair in pairs {
             self.spot_pairs.remove(pair);
+            self.rolling_means.remove(pair);
+        }
+    }
+
+    /// Sets the window of the rolling means, resetting them if it changed.
+    fn set_rolling_window(&mut self, window_in_seconds: u64) {
+        if self.rolling_window_in_seconds != Some(window_in_seconds) {
+            self.rolling_window_in_seconds = Some(window_in_seconds);
+            self.rolling_means.clear();
+        }
+    }
+
+    /// Pushes the price of the pair last updated at `timestamp` in its
+    /// rolling window, unless it was already pushed, slides the window to
+    /// `now` and returns the updated rolling mean, or None if no rolling
+    /// window is requested.
+    fn push_rolling_price(
+        &mut self,
+        pair_id: &str,
+        timestamp: Option<UnixTimestamp>,
+        now: UnixTimestamp,
+        price: BigDecimal,
+    ) -> Option<BigDecimal> {
+        let window_in_seconds = self.rolling_window_in_seconds?;
+        let rolling_mean = self
+            .rolling_means
+            .entry(pair_id.to_string())
+            .or_insert_with(|| SlidingWindowMean::new(window_in_seconds));
+        if let Some(timestamp) = timestamp {
+            rolling_mean.push(timestamp, price);
         }
+        rolling_mean.slide(now);
+        rolling_mean.mean()
     }
 
     /// Get the subscribed spot pairs.
@@ -251,3 +351,33 @@ impl SubscriptionState {
         self.spot_pairs.iter().cloned().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_mean_only_counts_new_entries() {
+        let mut subscription = SubscriptionState::default();
+        subscription.set_rolling_window(60);
+        let mut push = |timestamp: UnixTimestamp, now: UnixTimestamp, price: u32| {
+            subscription.push_rolling_price(
+                "BTC/USD",
+                Some(timestamp),
+                now,
+                BigDecimal::from(price),
+            )
+        };
+
+        assert_eq!(push(1_000, 1_000, 100), Some(BigDecimal::from(100)));
+        // The same entry over several updates is counted once.
+        for now in [1_001, 1_002, 1_003] {
+            assert_eq!(push(1_000, now, 100), Some(BigDecimal::from(100)));
+        }
+        assert_eq!(push(1_010, 1_010, 200), Some(BigDecimal::from(150)));
+
+        // The entries age out even if the pair isn't updated anymore.
+        assert_eq!(push(1_010, 1_065, 200), Some(BigDecimal::from(200)));
+        assert_eq!(push(1_010, 1_070, 200), None);
+    }
+}
@@ -1,7 +1,9 @@
 use lazy_static::lazy_static;
 use rdkafka::config::ClientConfig;
 use rdkafka::producer::future_producer::OwnedDeliveryResult;
-use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 lazy_static! {
     static ref KAFKA_PRODUCER: FutureProducer = {
@@ -14,6 +16,14 @@ lazy_static! {
     };
 }
 
+/// Where a message was delivered in Kafka.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct KafkaDelivery {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+}
+
 pub async fn send_message(topic: &str, message: &[u8], key: &str) -> OwnedDeliveryResult {
     let delivery_status = KAFKA_PRODUCER.send(
         FutureRecord::to(topic).payload(message).key(key),
@@ -21,3 +31,15 @@ pub async fn send_message(topic: &str, message: &[u8], key: &str) -> OwnedDelive
     );
     delivery_status.await
 }
+
+/// Checks if the Kafka brokers are reachable by fetching the cluster metadata.
+pub async fn is_reachable() -> bool {
+    tokio::task::spawn_blocking(|| {
+        KAFKA_PRODUCER
+            .client()
+            .fetch_metadata(None, std::time::Duration::from_secs(1))
+            .is_ok()
+    })
+    .await
+    .unwrap_or(false)
+}
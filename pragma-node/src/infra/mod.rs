@@ -1,3 +1,4 @@
 pub mod kafka;
 pub mod redis;
 pub mod repositories;
+pub mod rpc;
@@ -13,12 +13,14 @@ use pragma_common::types::{
 };
 use pragma_entities::error::RedisError;
 
+/// Retrieve the option data at a block, along with the number of the block
+/// the block id was resolved to.
 pub async fn get_option_data(
     redis_client: Arc<redis::Client>,
     network: Network,
     block_id: BlockId,
     instrument_name: String,
-) -> Result<OptionData, RedisError> {
+) -> Result<(u64, OptionData), RedisError> {
     let block_number = get_block_number_from_id(&redis_client, &network, &block_id).await?;
 
     let mut conn = redis_client
@@ -44,7 +46,7 @@ pub async fn get_option_data(
     }
 
     // Safe to unwrap, see condition above
-    Ok(option_response.pop().unwrap())
+    Ok((block_number, option_response.pop().unwrap()))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -82,6 +84,19 @@ impl TryFrom<RawMerkleTree> for MerkleTree {
     }
 }
 
+/// Pings Redis through the client used by the merkle feeds.
+pub async fn ping(redis_client: &redis::Client) -> Result<(), RedisError> {
+    let mut conn = redis_client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|_| RedisError::Connection)?;
+    redis::cmd("PING")
+        .query_async::<_, String>(&mut conn)
+        .await
+        .map_err(|_| RedisError::Connection)?;
+    Ok(())
+}
+
 pub async fn get_merkle_tree(
     redis_client: Arc<redis::Client>,
     network: Network,
@@ -134,6 +149,144 @@ pub async fn get_merkle_tree(
     Ok(merkle_tree)
 }
 
+/// How many blocks before the latest published one are searched when
+/// looking for the block published at a timestamp.
+const MAX_BLOCKS_LOOKBACK: u64 = 1_000;
+
+/// Retrieve the most recent block published at or before the provided
+/// unix timestamp (in seconds).
+/// The publication time of a block is the timestamp of the options stored
+/// with it; blocks without any option are skipped.
+pub async fn get_block_at_timestamp(
+    redis_client: Arc<redis::Client>,
+    network: Network,
+    timestamp: u64,
+) -> Result<u64, RedisError> {
+    let latest = get_block_number_for_tag(&redis_client, &network, &BlockTag::Pending).await?;
+
+    let conn = redis_client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|_| RedisError::Connection)?;
+    let mut block_timestamps = RedisBlockTimestamps {
+        conn,
+        network,
+        instrument_name: None,
+    };
+
+    let first = latest.saturating_sub(MAX_BLOCKS_LOOKBACK);
+    search_block_at_timestamp(&mut block_timestamps, first, latest, timestamp)
+        .await?
+        .ok_or(RedisError::NoBlockAtTimestamp(timestamp))
+}
+
+/// Source of the publication time of the blocks.
+trait BlockTimestamps {
+    /// Returns the publication time of the block, if it has any option.
+    async fn published_at(&mut self, block_number: u64) -> Result<Option<i64>, RedisError>;
+}
+
+/// Binary searches the most recent block between `first` and `last` published
+/// at or before the timestamp, as the blocks are published in order.
+/// Blocks without options are skipped by walking down to the closest one.
+async fn search_block_at_timestamp(
+    block_timestamps: &mut impl BlockTimestamps,
+    first: u64,
+    last: u64,
+    timestamp: u64,
+) -> Result<Option<u64>, RedisError> {
+    let (mut low, mut high) = (first, last);
+    let mut found = None;
+    while low <= high {
+        let middle = low + (high - low) / 2;
+
+        let mut block_number = middle;
+        let published_at = loop {
+            if let Some(published_at) = block_timestamps.published_at(block_number).await? {
+                break Some(published_at);
+            }
+            if block_number == low {
+                break None;
+            }
+            block_number -= 1;
+        };
+
+        match published_at {
+            Some(published_at) if is_published_before(published_at, timestamp) => {
+                found = Some(block_number);
+                low = middle + 1;
+            }
+            Some(_) => match block_number.checked_sub(1) {
+                Some(before) => high = before,
+                None => break,
+            },
+            // No option was published between `low` and `middle`.
+            None => low = middle + 1,
+        }
+    }
+    Ok(found)
+}
+
+/// Reads the publication time of the blocks from the timestamp of one of
+/// their options.
+/// The key of the first option found is scanned, and the same instrument is
+/// then read directly from the other blocks, falling back to a scan when
+/// they don't have it.
+struct RedisBlockTimestamps {
+    conn: redis::aio::MultiplexedConnection,
+    network: Network,
+    instrument_name: Option<String>,
+}
+
+impl RedisBlockTimestamps {
+    async fn option_timestamp(&mut self, option_key: String) -> Result<Option<i64>, RedisError> {
+        let result: Option<String> = self
+            .conn
+            .json_get(option_key, "$.current_timestamp")
+            .await
+            .map_err(|_| RedisError::Connection)?;
+        let Some(result) = result else {
+            return Ok(None);
+        };
+        let timestamps: Vec<i64> = serde_json::from_str(&result).map_err(|e| {
+            tracing::error!("Error while deserialzing: {e}");
+            RedisError::InternalServerError
+        })?;
+        Ok(timestamps.first().copied())
+    }
+}
+
+impl BlockTimestamps for RedisBlockTimestamps {
+    async fn published_at(&mut self, block_number: u64) -> Result<Option<i64>, RedisError> {
+        let options_prefix = format!("{}/{}/options/", self.network, block_number);
+
+        if let Some(instrument_name) = &self.instrument_name {
+            let option_key = format!("{options_prefix}{instrument_name}");
+            if let Some(published_at) = self.option_timestamp(option_key).await? {
+                return Ok(Some(published_at));
+            }
+        }
+
+        let option_key: Option<String> = {
+            let mut keys = self
+                .conn
+                .scan_match::<_, String>(format!("{options_prefix}*"))
+                .await
+                .map_err(|_| RedisError::Connection)?;
+            keys.next_item().await
+        };
+        let Some(option_key) = option_key else {
+            return Ok(None);
+        };
+        self.instrument_name = option_key.strip_prefix(&options_prefix).map(str::to_string);
+        self.option_timestamp(option_key).await
+    }
+}
+
+fn is_published_before(published_at: i64, timestamp: u64) -> bool {
+    u64::try_from(published_at).is_ok_and(|published_at| published_at <= timestamp)
+}
+
 /// Converts a BlockId to a block number.
 async fn get_block_number_from_id(
     redis_client: &Arc<redis::Client>,
@@ -178,3 +331,88 @@ async fn get_block_number_for_tag(
         None => Err(RedisError::NoBlocks(network.to_string())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Publication times of the blocks, counting how many were read.
+    struct InMemoryBlockTimestamps {
+        published_at: BTreeMap<u64, i64>,
+        reads: usize,
+    }
+
+    impl BlockTimestamps for InMemoryBlockTimestamps {
+        async fn published_at(&mut self, block_number: u64) -> Result<Option<i64>, RedisError> {
+            self.reads += 1;
+            Ok(self.published_at.get(&block_number).copied())
+        }
+    }
+
+    /// Blocks 0 to `last` published every 10 seconds from 1_700_000_000,
+    /// except the ones without options.
+    fn block_timestamps(last: u64, without_options: &[u64]) -> InMemoryBlockTimestamps {
+        let published_at = (0..=last)
+            .filter(|block_number| !without_options.contains(block_number))
+            .map(|block_number| (block_number, 1_700_000_000 + 10 * block_number as i64))
+            .collect();
+        InMemoryBlockTimestamps {
+            published_at,
+            reads: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_at_timestamp_is_binary_searched() {
+        let mut blocks = block_timestamps(MAX_BLOCKS_LOOKBACK, &[]);
+        let found = search_block_at_timestamp(&mut blocks, 0, MAX_BLOCKS_LOOKBACK, 1_700_004_215)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(421));
+        assert!(blocks.reads <= 11, "{} blocks read", blocks.reads);
+
+        let mut blocks = block_timestamps(MAX_BLOCKS_LOOKBACK, &[]);
+        let latest = search_block_at_timestamp(&mut blocks, 0, MAX_BLOCKS_LOOKBACK, u64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(latest, Some(MAX_BLOCKS_LOOKBACK));
+
+        let mut blocks = block_timestamps(MAX_BLOCKS_LOOKBACK, &[]);
+        let too_early =
+            search_block_at_timestamp(&mut blocks, 0, MAX_BLOCKS_LOOKBACK, 1_699_999_999)
+                .await
+                .unwrap();
+        assert_eq!(too_early, None);
+    }
+
+    #[tokio::test]
+    async fn test_blocks_without_options_are_skipped() {
+        let mut blocks = block_timestamps(100, &[48, 49, 50, 51, 100]);
+        let found = search_block_at_timestamp(&mut blocks, 0, 100, 1_700_000_505)
+            .await
+            .unwrap();
+        assert_eq!(found, Some(47));
+
+        let mut blocks = block_timestamps(100, &[48, 49, 50, 51, 100]);
+        let latest = search_block_at_timestamp(&mut blocks, 0, 100, u64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(latest, Some(99));
+
+        let mut blocks = block_timestamps(10, &(0..=10).collect::<Vec<_>>());
+        let none = search_block_at_timestamp(&mut blocks, 0, 10, u64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn test_block_is_published_before_timestamp() {
+        assert!(is_published_before(1_700_000_000, 1_700_000_000));
+        assert!(is_published_before(1_699_999_999, 1_700_000_000));
+        assert!(!is_published_before(1_700_000_001, 1_700_000_000));
+        assert!(!is_published_before(-1, 1_700_000_000));
+    }
+}
@@ -1,14 +1,19 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
+use bigdecimal::{BigDecimal, FromPrimitive, RoundingMode, ToPrimitive};
 use chrono::{DateTime, NaiveDateTime, Utc};
 use diesel::prelude::QueryableByName;
-use diesel::sql_types::{Double, Jsonb, VarChar};
+use diesel::sql_types::{
+    Array, BigInt, Double, Jsonb, Nullable, Numeric, Text, Timestamptz, VarChar,
+};
 use diesel::{ExpressionMethods, QueryDsl, Queryable, RunQueryDsl};
+use moka::future::Cache;
 use pragma_common::errors::ConversionError;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::caches::get_or_fetch;
+use crate::config::config;
 use crate::constants::others::ROUTING_FRESHNESS_THRESHOLD;
 use crate::constants::starkex_ws::{
     INITAL_INTERVAL_IN_MS, INTERVAL_INCREMENT_IN_MS, MAX_INTERVAL_WITHOUT_ENTRIES,
@@ -16,7 +21,10 @@ use crate::constants::starkex_ws::{
 };
 use crate::handlers::get_entry::RoutingParams;
 use crate::handlers::subscribe_to_entry::{AssetOraclePrice, SignedPublisherPrice};
-use crate::utils::{convert_via_quote, normalize_to_decimals, StarkexPrice};
+use crate::utils::{
+    compute_mean_price_and_time, compute_median_price_and_time, convert_via_quote,
+    normalize_to_decimals, StarkexPrice,
+};
 use pragma_common::types::{AggregationMode, DataType, Interval};
 use pragma_entities::dto;
 use pragma_entities::{
@@ -63,6 +71,8 @@ pub fn get_interval_specifier(
         Interval::OneHour if !is_twap => Ok("1_h"),
         Interval::TwoHours if is_twap => Ok("2_hours"),
         Interval::TwoHours if !is_twap => Ok("2_h"),
+        Interval::FourHours if is_twap => Ok("4_hours"),
+        Interval::FourHours if !is_twap => Ok("4_h"),
         Interval::OneDay => Ok("1_day"),
         Interval::OneWeek => Ok("1_week"),
         _ => Err(InfraError::InternalServerError),
@@ -97,7 +107,7 @@ pub async fn _get(
     Ok(dto::Entry::from(res))
 }
 
-pub async fn _get_all(
+pub async fn get_all(
     pool: &deadpool_diesel::postgres::Pool,
     filter: dto::EntriesFilter,
 ) -> Result<Vec<dto::Entry>, InfraError> {
@@ -113,6 +123,15 @@ pub async fn _get_all(
     Ok(res)
 }
 
+/// Returns the `limit` most recent entries of the pair, newest first.
+pub async fn get_recent_entries(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    limit: i64,
+) -> Result<Vec<dto::Entry>, InfraError> {
+    get_all(pool, dto::EntriesFilter::most_recent(pair_id, limit)).await
+}
+
 #[derive(Debug, Serialize, Queryable)]
 pub struct MedianEntry {
     pub time: NaiveDateTime,
@@ -164,6 +183,7 @@ pub async fn routing(
 
     match find_alternative_pair_price(pool, base, quote, routing_params).await {
         Ok(result) => Ok(result),
+        Err(InfraError::Unavailable) => Err(InfraError::Unavailable),
         Err(_) => Err(InfraError::NotFound),
     }
 }
@@ -276,9 +296,12 @@ async fn get_price_and_decimals(
     routing_params: RoutingParams,
 ) -> Result<(MedianEntry, u32), InfraError> {
     let entry = match routing_params.aggregation_mode {
+        AggregationMode::Median if routing_params.as_of.is_some() => {
+            get_median_price_as_of(pool, pair_id.clone(), routing_params).await?
+        }
         AggregationMode::Median => get_median_price(pool, pair_id.clone(), routing_params).await?,
         AggregationMode::Twap => get_twap_price(pool, pair_id.clone(), routing_params).await?,
-        AggregationMode::Mean => Err(InfraError::InternalServerError)?,
+        AggregationMode::Mean => get_mean_price(pool, pair_id.clone(), routing_params).await?,
     };
 
     let decimals = get_decimals(pool, &(pair_id)).await?;
@@ -324,6 +347,8 @@ pub async fn get_twap_price(
             pair_id = $1
             AND
             bucket <= $2
+            AND
+            bucket >= $3
             {}
         ORDER BY
             time DESC
@@ -340,12 +365,20 @@ pub async fn get_twap_price(
             routing_params.timestamp
         )),
     )?;
+    let lookback = routing_params.twap_lookback_in_seconds(config().await.twap_default_lookback());
+    let start_date_time = DateTime::from_timestamp(routing_params.timestamp - lookback, 0).ok_or(
+        InfraError::InvalidTimestamp(format!(
+            "Cannot convert to DateTime: {}",
+            routing_params.timestamp - lookback
+        )),
+    )?;
 
     let raw_entry = conn
         .interact(move |conn| {
             diesel::sql_query(&sql_request)
                 .bind::<diesel::sql_types::Text, _>(pair_id)
                 .bind::<diesel::sql_types::Timestamptz, _>(date_time)
+                .bind::<diesel::sql_types::Timestamptz, _>(start_date_time)
                 .load::<MedianEntryRaw>(conn)
         })
         .await
@@ -363,6 +396,11 @@ pub async fn get_twap_price(
     Ok(entry)
 }
 
+/// Returns the median price of the latest bucket of the interval, up to the
+/// timestamp of the routing params.
+/// If sources are requested, the bucket is aggregated like the continuous
+/// aggregates, from the entries of these sources only. Returns NotFound if
+/// none of them has data in the bucket of the timestamp or the previous one.
 pub async fn get_median_price(
     pool: &deadpool_diesel::postgres::Pool,
     pair_id: String,
@@ -370,6 +408,47 @@ pub async fn get_median_price(
 ) -> Result<MedianEntry, InfraError> {
     let conn = pool.get().await.map_err(adapt_infra_error)?;
 
+    let expiration_filter =
+        get_expiration_timestamp_filter(routing_params.data_type, routing_params.expiry.clone())?;
+    let (aggregated_prices, expiration_filter) = match routing_params.sources {
+        None => (
+            format!(
+                "price_{}_agg{}",
+                get_interval_specifier(routing_params.interval, false)?,
+                get_table_suffix(routing_params.data_type)?,
+            ),
+            expiration_filter,
+        ),
+        // The expiration is filtered before aggregating.
+        Some(_) => (
+            format!(
+                r#"(
+            SELECT
+                pair_id,
+                time_bucket(INTERVAL '{interval} seconds', timestamp) AS bucket,
+                approx_percentile(0.5, percentile_agg(price))::numeric AS median_price,
+                COUNT(DISTINCT source) AS num_sources
+            FROM
+                {table}
+            WHERE
+                pair_id = $1
+                AND
+                timestamp >= time_bucket(INTERVAL '{interval} seconds', $2) - INTERVAL '{interval} seconds'
+                AND
+                timestamp < time_bucket(INTERVAL '{interval} seconds', $2) + INTERVAL '{interval} seconds'
+                AND
+                source = ANY($3)
+                {expiration_filter}
+            GROUP BY
+                bucket, pair_id
+        ) AS sources_agg"#,
+                interval = routing_params.interval.to_seconds(),
+                table = get_table_name_from_type(routing_params.data_type),
+            ),
+            String::default(),
+        ),
+    };
+
     let sql_request: String = format!(
         r#"
         -- query the materialized realtime view
@@ -378,7 +457,7 @@ pub async fn get_median_price(
             median_price,
             num_sources
         FROM
-            price_{}_agg{}
+            {}
         WHERE
             pair_id = $1
             AND
@@ -388,9 +467,7 @@ pub async fn get_median_price(
             time DESC
         LIMIT 1;
     "#,
-        get_interval_specifier(routing_params.interval, false)?,
-        get_table_suffix(routing_params.data_type)?,
-        get_expiration_timestamp_filter(routing_params.data_type, routing_params.expiry)?,
+        aggregated_prices, expiration_filter,
     );
 
     let date_time = DateTime::from_timestamp(routing_params.timestamp, 0).ok_or(
@@ -401,11 +478,16 @@ pub async fn get_median_price(
     )?;
 
     let raw_entry = conn
-        .interact(move |conn| {
-            diesel::sql_query(&sql_request)
-                .bind::<diesel::sql_types::Text, _>(pair_id)
-                .bind::<diesel::sql_types::Timestamptz, _>(date_time)
-                .load::<MedianEntryRaw>(conn)
+        .interact(move |conn| match routing_params.sources {
+            None => diesel::sql_query(&sql_request)
+                .bind::<Text, _>(pair_id)
+                .bind::<Timestamptz, _>(date_time)
+                .load::<MedianEntryRaw>(conn),
+            Some(sources) => diesel::sql_query(&sql_request)
+                .bind::<Text, _>(pair_id)
+                .bind::<Timestamptz, _>(date_time)
+                .bind::<Array<Text>, _>(sources)
+                .load::<MedianEntryRaw>(conn),
         })
         .await
         .map_err(adapt_infra_error)?
@@ -422,11 +504,340 @@ pub async fn get_median_price(
     Ok(entry)
 }
 
+#[derive(Serialize, QueryableByName, Clone, Debug)]
+pub struct PublisherSourcePriceRaw {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub publisher: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub source: String,
+    #[diesel(sql_type = diesel::sql_types::Numeric)]
+    pub price: BigDecimal,
+    #[diesel(sql_type = diesel::sql_types::Timestamptz)]
+    pub time: NaiveDateTime,
+}
+
+/// Computes the mean of the per-publisher median prices of the pair over
+/// the interval ending at the timestamp of the routing params.
+pub async fn get_mean_price(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    routing_params: RoutingParams,
+) -> Result<MedianEntry, InfraError> {
+    let raw_prices = get_sources_prices(pool, pair_id, &routing_params).await?;
+    compute_mean_of_publishers_medians(raw_prices).ok_or(InfraError::NotFound)
+}
+
+/// Returns the latest price of each (publisher, source) of the pair over the
+/// interval ending at the timestamp of the routing params.
+pub async fn get_sources_prices(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    routing_params: &RoutingParams,
+) -> Result<Vec<PublisherSourcePriceRaw>, InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+
+    let sql_request: String = format!(
+        r#"
+        -- latest price of each (publisher, source) over the interval
+        SELECT DISTINCT ON (publisher, source)
+            publisher,
+            source,
+            price,
+            timestamp AS time
+        FROM
+            {}
+        WHERE
+            pair_id = $1
+            AND
+            timestamp BETWEEN $2 AND $3
+            {}
+        ORDER BY
+            publisher, source, timestamp DESC;
+    "#,
+        get_table_name_from_type(routing_params.data_type),
+        get_expiration_timestamp_filter(routing_params.data_type, routing_params.expiry.clone())?,
+    );
+
+    let to_date_time = |timestamp: i64| {
+        DateTime::from_timestamp(timestamp, 0).ok_or(InfraError::InvalidTimestamp(format!(
+            "Cannot convert to DateTime: {timestamp}"
+        )))
+    };
+    let end = to_date_time(routing_params.timestamp)?;
+    let start = to_date_time(routing_params.timestamp - routing_params.interval.to_seconds())?;
+
+    conn.interact(move |conn| {
+        diesel::sql_query(&sql_request)
+            .bind::<diesel::sql_types::Text, _>(pair_id)
+            .bind::<diesel::sql_types::Timestamptz, _>(start)
+            .bind::<diesel::sql_types::Timestamptz, _>(end)
+            .load::<PublisherSourcePriceRaw>(conn)
+    })
+    .await
+    .map_err(adapt_infra_error)?
+    .map_err(adapt_infra_error)
+}
+
+/// Computes the median price of each publisher over its sources, then the
+/// mean of those medians.
+fn compute_mean_of_publishers_medians(
+    raw_prices: Vec<PublisherSourcePriceRaw>,
+) -> Option<MedianEntry> {
+    let num_sources = raw_prices.len() as i64;
+
+    let mut prices_per_publisher: HashMap<String, Vec<MedianEntry>> = HashMap::new();
+    for raw_price in raw_prices {
+        prices_per_publisher
+            .entry(raw_price.publisher)
+            .or_default()
+            .push(MedianEntry {
+                time: raw_price.time,
+                median_price: raw_price.price,
+                num_sources: 1,
+            });
+    }
+
+    let publishers_medians: Vec<MedianEntry> = prices_per_publisher
+        .into_values()
+        .filter_map(|mut prices| {
+            let time = prices.iter().map(|price| price.time).max()?;
+            let (median_price, _) = compute_median_price_and_time(&mut prices)?;
+            Some(MedianEntry {
+                time,
+                median_price,
+                num_sources: prices.len() as i64,
+            })
+        })
+        .collect();
+
+    let (mean_price, time) = compute_mean_price_and_time(&publishers_medians)?;
+    Some(MedianEntry {
+        time,
+        median_price: mean_price,
+        num_sources,
+    })
+}
+
+/// Computes the median price of the pair as it was known at the `as_of`
+/// timestamp of the routing params: entries ingested after it are ignored,
+/// even if their reported timestamp is older.
+pub async fn get_median_price_as_of(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    routing_params: RoutingParams,
+) -> Result<MedianEntry, InfraError> {
+    let (entries, as_of) = get_entries_known_at(pool, pair_id, &routing_params).await?;
+    compute_median_entry_known_at(entries, as_of).ok_or(InfraError::NotFound)
+}
+
+#[derive(QueryableByName)]
+struct MidRaw {
+    #[diesel(sql_type = Nullable<Numeric>)]
+    mid: Option<BigDecimal>,
+}
+
+/// Mid of an entry: the middle of its bid and ask, or its price if it
+/// doesn't report both. Entries with a crossed book are refused at
+/// publication, the ones stored before also fall back to their price.
+const ENTRY_MID_SQL: &str = "CASE WHEN bid IS NOT NULL AND ask IS NOT NULL AND bid <= ask \
+    THEN (bid + ask) / 2 ELSE price END";
+
+/// Computes the mid of the pair, aggregated like its price: over the latest
+/// entry of each (publisher, source) of the interval, or over the TWAP
+/// lookback for the TWAP. Only the entries known at the `as_of` timestamp of
+/// the routing params are used, if set.
+/// Returns None if the pair has no entry in the interval.
+pub async fn get_mid(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    routing_params: &RoutingParams,
+) -> Result<Option<BigDecimal>, InfraError> {
+    let latest_mids = format!(
+        r#"
+        latest_mids AS (
+            SELECT DISTINCT ON (publisher, source)
+                publisher,
+                {ENTRY_MID_SQL} AS mid
+            FROM
+                entries
+            WHERE
+                pair_id = $1
+                AND
+                timestamp BETWEEN $2 AND $3
+                AND
+                ($4::timestamptz IS NULL OR COALESCE(ingested_at, timestamp) <= $4)
+                AND
+                ($5::text[] IS NULL OR source = ANY($5))
+            ORDER BY
+                publisher, source, timestamp DESC
+        )"#
+    );
+    let sql_request = match routing_params.aggregation_mode {
+        AggregationMode::Median => format!(
+            r#"
+        WITH {latest_mids},
+        ranked_mids AS (
+            SELECT
+                mid,
+                row_number() OVER (ORDER BY mid) AS rank,
+                count(*) OVER () AS num_mids
+            FROM latest_mids
+        )
+        SELECT avg(mid) AS mid
+        FROM ranked_mids
+        WHERE rank IN ((num_mids + 1) / 2, (num_mids + 2) / 2);
+    "#
+        ),
+        // The mean of the median mid of each publisher, like the mean price.
+        AggregationMode::Mean => format!(
+            r#"
+        WITH {latest_mids},
+        ranked_mids AS (
+            SELECT
+                publisher,
+                mid,
+                row_number() OVER (PARTITION BY publisher ORDER BY mid) AS rank,
+                count(*) OVER (PARTITION BY publisher) AS num_mids
+            FROM latest_mids
+        ),
+        publishers_mids AS (
+            SELECT avg(mid) AS mid
+            FROM ranked_mids
+            WHERE rank IN ((num_mids + 1) / 2, (num_mids + 2) / 2)
+            GROUP BY publisher
+        )
+        SELECT avg(mid) AS mid
+        FROM publishers_mids;
+    "#
+        ),
+        AggregationMode::Twap => format!(
+            r#"
+        SELECT average(time_weight('Linear', timestamp, mid))::numeric AS mid
+        FROM (
+            SELECT
+                timestamp,
+                ({ENTRY_MID_SQL})::double precision AS mid
+            FROM
+                entries
+            WHERE
+                pair_id = $1
+                AND
+                timestamp BETWEEN $2 AND $3
+                AND
+                ($4::timestamptz IS NULL OR COALESCE(ingested_at, timestamp) <= $4)
+                AND
+                ($5::text[] IS NULL OR source = ANY($5))
+            ORDER BY
+                timestamp
+        ) AS mids;
+    "#
+        ),
+    };
+
+    let to_date_time = |timestamp: i64| {
+        DateTime::from_timestamp(timestamp, 0).ok_or(InfraError::InvalidTimestamp(format!(
+            "Cannot convert to DateTime: {timestamp}"
+        )))
+    };
+    let lookback = match routing_params.aggregation_mode {
+        AggregationMode::Twap => {
+            routing_params.twap_lookback_in_seconds(config().await.twap_default_lookback())
+        }
+        _ => routing_params.interval.to_seconds(),
+    };
+    let start = to_date_time(routing_params.timestamp - lookback)?;
+    let end = to_date_time(routing_params.timestamp)?;
+    let as_of = routing_params.as_of.map(to_date_time).transpose()?;
+    let sources = routing_params.sources.clone();
+
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    let raw_mids = conn
+        .interact(move |conn| {
+            diesel::sql_query(&sql_request)
+                .bind::<Text, _>(pair_id)
+                .bind::<Timestamptz, _>(start)
+                .bind::<Timestamptz, _>(end)
+                .bind::<Nullable<Timestamptz>, _>(as_of)
+                .bind::<Nullable<Array<Text>>, _>(sources)
+                .load::<MidRaw>(conn)
+        })
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    Ok(raw_mids.into_iter().next().and_then(|raw_mid| raw_mid.mid))
+}
+
+/// Returns the entries of the interval of the routing params already ingested
+/// at their `as_of` timestamp, along with this timestamp.
+async fn get_entries_known_at(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    routing_params: &RoutingParams,
+) -> Result<(Vec<Entry>, NaiveDateTime), InfraError> {
+    let to_date_time = |timestamp: i64| {
+        DateTime::from_timestamp(timestamp, 0)
+            .map(|dt| dt.naive_utc())
+            .ok_or(InfraError::InvalidTimestamp(format!(
+                "Cannot convert to DateTime: {timestamp}"
+            )))
+    };
+    let as_of = to_date_time(routing_params.as_of.unwrap_or(routing_params.timestamp))?;
+    let end = to_date_time(routing_params.timestamp)?;
+    let start = to_date_time(routing_params.timestamp - routing_params.interval.to_seconds())?;
+
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    let entries = conn
+        .interact(move |conn| Entry::get_known_at(conn, pair_id, start, end, as_of))
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+    Ok((entries, as_of))
+}
+
+/// Computes the median of the latest price of each (publisher, source) among
+/// the entries already ingested at `as_of`.
+fn compute_median_entry_known_at(entries: Vec<Entry>, as_of: NaiveDateTime) -> Option<MedianEntry> {
+    let mut latest_entries: HashMap<(String, String), Entry> = HashMap::new();
+    for entry in entries {
+        if entry.ingested_at.unwrap_or(entry.timestamp) > as_of {
+            continue;
+        }
+        let key = (entry.publisher.clone(), entry.source.clone());
+        match latest_entries.get(&key) {
+            Some(latest) if latest.timestamp >= entry.timestamp => {}
+            _ => {
+                latest_entries.insert(key, entry);
+            }
+        }
+    }
+
+    let time = latest_entries.values().map(|entry| entry.timestamp).max()?;
+    let mut prices: Vec<MedianEntry> = latest_entries
+        .into_values()
+        .map(|entry| MedianEntry {
+            time: entry.timestamp,
+            median_price: entry.price,
+            num_sources: 1,
+        })
+        .collect();
+    let (median_price, _) = compute_median_price_and_time(&mut prices)?;
+
+    Some(MedianEntry {
+        time,
+        median_price,
+        num_sources: prices.len() as i64,
+    })
+}
+
+/// Returns the aggregated prices of the pair between the two timestamps, from
+/// the most recent to the oldest, limited to the `limit` most recent ones if set.
 pub async fn get_entries_between(
     pool: &deadpool_diesel::postgres::Pool,
     pair_id: String,
     start_timestamp: u64,
     end_timestamp: u64,
+    limit: Option<u64>,
 ) -> Result<Vec<MedianEntry>, InfraError> {
     let conn = pool.get().await.map_err(adapt_infra_error)?;
     let start_datetime = DateTime::from_timestamp(start_timestamp as i64, 0).ok_or(
@@ -447,15 +858,18 @@ pub async fn get_entries_between(
         AND 
             time BETWEEN $2 AND $3
         ORDER BY 
-            time DESC;
+            time DESC
+        LIMIT $4;
     "#;
 
+    let limit = limit.map(|limit| limit as i64);
     let raw_entries = conn
         .interact(move |conn| {
             diesel::sql_query(raw_sql)
                 .bind::<diesel::sql_types::Text, _>(pair_id)
                 .bind::<diesel::sql_types::Timestamptz, _>(start_datetime)
                 .bind::<diesel::sql_types::Timestamptz, _>(end_datetime)
+                .bind::<diesel::sql_types::Nullable<diesel::sql_types::BigInt>, _>(limit)
                 .load::<MedianEntryRaw>(conn)
         })
         .await
@@ -474,6 +888,84 @@ pub async fn get_entries_between(
     Ok(entries)
 }
 
+/// Returns the one minute median prices between the timestamps, sampled so
+/// there are at most `max_points` of them: only the last median of each
+/// sampling interval is kept. From the most recent to the oldest.
+pub async fn get_sampled_entries_between(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    start_timestamp: u64,
+    end_timestamp: u64,
+    max_points: u64,
+) -> Result<Vec<MedianEntry>, InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    let start_datetime = DateTime::from_timestamp(start_timestamp as i64, 0).ok_or(
+        InfraError::InvalidTimestamp(format!("Cannot convert to DateTime: {start_timestamp}")),
+    )?;
+    let end_datetime = DateTime::from_timestamp(end_timestamp as i64, 0).ok_or(
+        InfraError::InvalidTimestamp(format!("Cannot convert to DateTime: {end_timestamp}")),
+    )?;
+
+    let sampling_interval =
+        sampling_interval_in_seconds(start_timestamp, end_timestamp, max_points);
+    let raw_sql = format!(
+        r#"
+        SELECT
+            time_bucket(INTERVAL '{sampling_interval} seconds', bucket) AS time,
+            last(median_price, bucket) AS median_price,
+            last(num_sources, bucket) AS num_sources
+        FROM price_1_min_agg
+        WHERE
+            pair_id = $1
+        AND
+            bucket BETWEEN $2 AND $3
+        GROUP BY 1
+        ORDER BY
+            time DESC;
+    "#
+    );
+
+    let raw_entries = conn
+        .interact(move |conn| {
+            diesel::sql_query(raw_sql)
+                .bind::<diesel::sql_types::Text, _>(pair_id)
+                .bind::<diesel::sql_types::Timestamptz, _>(start_datetime)
+                .bind::<diesel::sql_types::Timestamptz, _>(end_datetime)
+                .load::<MedianEntryRaw>(conn)
+        })
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    let entries: Vec<MedianEntry> = raw_entries
+        .into_iter()
+        .map(|raw_entry| MedianEntry {
+            time: raw_entry.time,
+            median_price: raw_entry.median_price,
+            num_sources: raw_entry.num_sources,
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Interval between the samples of a period so it has at most `max_points`,
+/// at least the minute of the aggregated medians.
+fn sampling_interval_in_seconds(start_timestamp: u64, end_timestamp: u64, max_points: u64) -> u64 {
+    let period = end_timestamp.saturating_sub(start_timestamp);
+    period.div_ceil(max_points.max(1)).max(60)
+}
+
+/// Returns the decimals of the pair from the cache if present, or fetches them
+/// from the database and caches them.
+pub async fn get_decimals_cached(
+    pool: &deadpool_diesel::postgres::Pool,
+    cache: &Cache<String, u32>,
+    pair_id: &str,
+) -> Result<u32, InfraError> {
+    get_or_fetch(cache, pair_id.to_string(), get_decimals(pool, pair_id)).await
+}
+
 pub async fn get_decimals(
     pool: &deadpool_diesel::postgres::Pool,
     pair_id: &str,
@@ -525,6 +1017,18 @@ pub async fn get_last_updated_timestamp(
         .map_err(adapt_infra_error)
 }
 
+/// Returns the timestamps of the first and last entries of the pair.
+pub async fn get_entries_timestamps_range(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+) -> Result<(Option<NaiveDateTime>, Option<NaiveDateTime>), InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    conn.interact(|conn| Entry::get_timestamps_range(conn, pair_id))
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, ToSchema)]
 pub struct OHLCEntry {
     pub time: NaiveDateTime,
@@ -637,6 +1141,10 @@ struct RawMedianEntryWithComponents {
     pub median_price: f64,
     #[diesel(sql_type = Jsonb)]
     pub components: serde_json::Value,
+    /// Number of sources available for the pair, before keeping only the
+    /// most recent ones.
+    #[diesel(sql_type = BigInt)]
+    pub num_sources: i64,
 }
 
 impl TryFrom<RawMedianEntryWithComponents> for MedianEntryWithComponents {
@@ -668,6 +1176,7 @@ impl TryFrom<RawMedianEntryWithComponents> for MedianEntryWithComponents {
             pair_id: raw.pair_id,
             median_price,
             components,
+            num_sources_dropped: 0,
         })
     }
 }
@@ -706,12 +1215,48 @@ pub struct MedianEntryWithComponents {
     pub pair_id: String,
     pub median_price: BigDecimal,
     pub components: Vec<EntryComponent>,
+    /// Number of available sources left out of the median, when only the
+    /// most recent ones are aggregated.
+    pub num_sources_dropped: usize,
+}
+
+impl MedianEntryWithComponents {
+    /// Returns the publishers whose entries contributed to the median,
+    /// deduplicated and sorted alphabetically.
+    pub fn publishers(&self) -> Vec<String> {
+        self.components
+            .iter()
+            .map(|component| component.publisher.clone())
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns the unix timestamp in seconds of the most recent component.
+    pub fn last_update_timestamp(&self) -> Option<i64> {
+        self.components
+            .iter()
+            .filter_map(|component| component.timestamp.parse::<i64>().ok())
+            .max()
+    }
+
+    /// Replaces the median price by the mean price of the components.
+    /// The mean is rounded to an integer, like the prices we sign.
+    pub fn use_mean_price(&mut self) {
+        if self.components.is_empty() {
+            return;
+        }
+        let sum: BigDecimal = self.components.iter().map(|c| &c.price).sum();
+        self.median_price = (sum / BigDecimal::from(self.components.len() as u64))
+            .with_scale_round(0, RoundingMode::HalfEven);
+    }
 }
 
 impl TryFrom<MedianEntryWithComponents> for AssetOraclePrice {
     type Error = ConversionError;
 
     fn try_from(median_entry: MedianEntryWithComponents) -> Result<Self, Self::Error> {
+        let publishers = median_entry.publishers();
         let signed_prices: Result<Vec<SignedPublisherPrice>, ConversionError> = median_entry
             .components
             .into_iter()
@@ -727,16 +1272,22 @@ impl TryFrom<MedianEntryWithComponents> for AssetOraclePrice {
             global_asset_id: format!("0x{}", global_asset_id),
             median_price: price_with_18_decimals.to_string(),
             signed_prices: signed_prices?,
+            publishers,
             signature: Default::default(),
+            merkle_proof: None,
+            calldata: Default::default(),
         })
     }
 }
 
 /// Convert a list of raw entries into a list of valid median entries.
 /// For each pair_id, check if it has a valid median price with enough unique publishers.
+/// If a maximum number of sources was applied by the query, the sources left
+/// out are counted in the entries and logged.
 /// Returns the valid entries, filtering out any invalid ones.
 fn get_median_entries_response(
     raw_entries: Vec<RawMedianEntryWithComponents>,
+    max_sources: Option<usize>,
 ) -> Option<Vec<MedianEntryWithComponents>> {
     if raw_entries.is_empty() {
         return None;
@@ -746,7 +1297,19 @@ fn get_median_entries_response(
 
     for raw_entry in raw_entries {
         let pair_id = raw_entry.pair_id.clone();
-        let median_entry = match MedianEntryWithComponents::try_from(raw_entry) {
+        let num_sources = usize::try_from(raw_entry.num_sources).unwrap_or_default();
+        let num_sources_dropped =
+            max_sources.map_or(0, |max_sources| num_sources.saturating_sub(max_sources));
+        if num_sources_dropped > 0 {
+            tracing::warn!(
+                "Aggregated only the {} most recent sources out of {} for pair {}",
+                num_sources - num_sources_dropped,
+                num_sources,
+                pair_id
+            );
+        }
+
+        let mut median_entry = match MedianEntryWithComponents::try_from(raw_entry) {
             Ok(entry) => entry,
             Err(e) => {
                 tracing::error!(
@@ -757,6 +1320,7 @@ fn get_median_entries_response(
                 continue;
             }
         };
+        median_entry.num_sources_dropped = num_sources_dropped;
 
         let num_unique_publishers = median_entry
             .components
@@ -797,12 +1361,15 @@ const EXCLUDED_PUBLISHER: &str = "";
 /// Builds a SQL query that will fetch the recent prices between now and
 /// the given interval for each unique tuple (pair_id, publisher, source)
 /// and then calculate the median price for each pair_id.
+/// If a maximum number of sources is provided, only the entries of the sources
+/// with the most recent prices are aggregated, whatever their publisher.
 /// We also return in a JSON string the components that were used to calculate
-/// the median price.
+/// the median price, and the number of sources available for the pair.
 fn build_sql_query_for_median_with_components(
     pair_ids: &[String],
     interval_in_ms: u64,
     entry_type: DataType,
+    max_sources: Option<usize>,
 ) -> String {
     format!(
         r#"
@@ -839,6 +1406,17 @@ fn build_sql_query_for_median_with_components(
                     last_prices
                 WHERE 
                     rn = 1
+            ),
+            ranked_sources AS (
+                SELECT
+                    pair_id,
+                    source,
+                    ROW_NUMBER() OVER (PARTITION BY pair_id ORDER BY MAX(timestamp) DESC, source) AS source_rank,
+                    COUNT(*) OVER (PARTITION BY pair_id) AS num_sources
+                FROM
+                    filtered_last_prices
+                GROUP BY
+                    pair_id, source
             )
             SELECT
                 pair_id,
@@ -852,9 +1430,14 @@ fn build_sql_query_for_median_with_components(
                         'publisher_address', publisher_account_address,
 			            'publisher_signature', publisher_signature
 			        )
-			    ) AS components
+			    ) AS components,
+                MAX(num_sources) AS num_sources
             FROM
                 filtered_last_prices
+            JOIN
+                ranked_sources USING (pair_id, source)
+            WHERE
+                {sources_filter}
             GROUP BY 
                 pair_id;
             "#,
@@ -869,6 +1452,10 @@ fn build_sql_query_for_median_with_components(
         perp_filter = match entry_type {
             DataType::PerpEntry => "AND e.expiration_timestamp IS NULL",
             _ => "",
+        },
+        sources_filter = match max_sources {
+            Some(max_sources) if max_sources > 0 => format!("source_rank <= {max_sources}"),
+            _ => "TRUE".to_string(),
         }
     )
 }
@@ -877,18 +1464,25 @@ fn build_sql_query_for_median_with_components(
 /// over an interval of time.
 /// The interval is increased until we have valid entries with enough publishers.
 /// Returns any pairs that have valid data, even if some pairs are invalid.
+/// At most `max_sources` sources are aggregated per pair, if set and not zero.
 pub async fn get_current_median_entries_with_components(
     pool: &deadpool_diesel::postgres::Pool,
     pair_ids: &[String],
     entry_type: DataType,
+    max_sources: Option<usize>,
 ) -> Result<Vec<MedianEntryWithComponents>, InfraError> {
+    let max_sources = max_sources.filter(|max_sources| *max_sources > 0);
     let conn = pool.get().await.map_err(adapt_infra_error)?;
     let mut interval_in_ms = INITAL_INTERVAL_IN_MS;
     let mut last_valid_entries = Vec::new();
 
     loop {
-        let raw_sql =
-            build_sql_query_for_median_with_components(pair_ids, interval_in_ms, entry_type);
+        let raw_sql = build_sql_query_for_median_with_components(
+            pair_ids,
+            interval_in_ms,
+            entry_type,
+            max_sources,
+        );
 
         let raw_median_entries = conn
             .interact(move |conn| {
@@ -898,7 +1492,7 @@ pub async fn get_current_median_entries_with_components(
             .map_err(adapt_infra_error)?
             .map_err(adapt_infra_error)?;
 
-        if let Some(valid_entries) = get_median_entries_response(raw_median_entries) {
+        if let Some(valid_entries) = get_median_entries_response(raw_median_entries, max_sources) {
             // Keep track of the valid entries we've found
             last_valid_entries = valid_entries;
 
@@ -941,6 +1535,121 @@ pub async fn get_current_median_entries_with_components(
     Ok(last_valid_entries)
 }
 
+#[derive(QueryableByName, Debug)]
+struct PairIdRaw {
+    #[diesel(sql_type = VarChar)]
+    pair_id: String,
+}
+
+/// Returns the spot pairs having aggregated prices, sorted alphabetically.
+pub async fn get_pairs(pool: &deadpool_diesel::postgres::Pool) -> Result<Vec<String>, InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+
+    let raw_pairs = conn
+        .interact(|conn| {
+            diesel::sql_query("SELECT DISTINCT pair_id FROM price_1_day_agg ORDER BY pair_id;")
+                .load::<PairIdRaw>(conn)
+        })
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    Ok(raw_pairs.into_iter().map(|raw| raw.pair_id).collect())
+}
+
+#[derive(Serialize, QueryableByName, Clone, Debug, ToSchema)]
+pub struct PairSourceCoverage {
+    #[diesel(sql_type = VarChar)]
+    pub pair_id: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub num_sources: i64,
+}
+
+/// Returns the number of distinct sources of each pair over the last `window_in_seconds`.
+pub async fn get_sources_coverage(
+    pool: &deadpool_diesel::postgres::Pool,
+    window_in_seconds: u64,
+) -> Result<Vec<PairSourceCoverage>, InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+
+    let sql_request = format!(
+        r#"
+        SELECT
+            pair_id,
+            COUNT(DISTINCT source) AS num_sources
+        FROM
+            entries
+        WHERE
+            timestamp >= NOW() - INTERVAL '{window_in_seconds} seconds'
+        GROUP BY
+            pair_id
+        ORDER BY
+            pair_id;
+    "#
+    );
+
+    let coverage = conn
+        .interact(move |conn| diesel::sql_query(&sql_request).load::<PairSourceCoverage>(conn))
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    Ok(coverage)
+}
+
+#[derive(QueryableByName, Clone, Debug)]
+pub struct SourcesBucketRaw {
+    #[diesel(sql_type = diesel::sql_types::Timestamptz)]
+    pub bucket: NaiveDateTime,
+    #[diesel(sql_type = diesel::sql_types::Array<diesel::sql_types::Text>)]
+    pub sources: Vec<String>,
+}
+
+/// Returns the sources that published the pair during each bucket of
+/// `interval` of the range, sorted by bucket. Buckets without entries are
+/// omitted.
+pub async fn get_sources_history(
+    pool: &deadpool_diesel::postgres::Pool,
+    pair_id: String,
+    start: i64,
+    end: i64,
+    interval: Interval,
+) -> Result<Vec<SourcesBucketRaw>, InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+
+    let sql_request = format!(
+        r#"
+        SELECT
+            time_bucket(INTERVAL '{} seconds', timestamp) AS bucket,
+            array_agg(DISTINCT source ORDER BY source) AS sources
+        FROM
+            entries
+        WHERE
+            pair_id = $1
+            AND timestamp BETWEEN to_timestamp($2) AND to_timestamp($3)
+        GROUP BY
+            bucket
+        ORDER BY
+            bucket;
+    "#,
+        interval.to_seconds()
+    );
+
+    let buckets = conn
+        .interact(move |conn| {
+            diesel::sql_query(&sql_request)
+                .bind::<diesel::sql_types::Text, _>(pair_id)
+                .bind::<diesel::sql_types::BigInt, _>(start)
+                .bind::<diesel::sql_types::BigInt, _>(end)
+                .load::<SourcesBucketRaw>(conn)
+        })
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    Ok(buckets)
+}
+
 pub async fn get_expiries_list(
     pool: &deadpool_diesel::postgres::Pool,
     pair_id: String,
@@ -972,3 +1681,211 @@ pub async fn get_expiries_list(
 
     Ok(expiries)
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_interval_bounds_the_points() {
+        // Short periods keep every minute.
+        assert_eq!(sampling_interval_in_seconds(0, 3_600, 10_000), 60);
+        assert_eq!(sampling_interval_in_seconds(3_600, 0, 10_000), 60);
+
+        let one_year = 365 * 24 * 3_600;
+        let interval = sampling_interval_in_seconds(0, one_year, 10_000);
+        assert_eq!(interval, 3_154);
+        assert!(one_year / interval <= 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_decimals_are_fetched_again_once_expired() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let cache = Cache::builder()
+            .time_to_live(Duration::from_millis(100))
+            .build();
+        let db_calls = AtomicUsize::new(0);
+        let mocked_db = || async {
+            db_calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<u32, InfraError>(8)
+        };
+
+        for _ in 0..2 {
+            let decimals = get_or_fetch(&cache, "BTC/USD".to_string(), mocked_db())
+                .await
+                .unwrap();
+            assert_eq!(decimals, 8);
+        }
+        assert_eq!(db_calls.load(Ordering::SeqCst), 1);
+
+        // Once expired, the decimals are fetched again.
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        get_or_fetch(&cache, "BTC/USD".to_string(), mocked_db())
+            .await
+            .unwrap();
+        assert_eq!(db_calls.load(Ordering::SeqCst), 2);
+    }
+
+    fn component(publisher: &str, price: u64) -> EntryComponent {
+        component_at(publisher, price, 1718000000)
+    }
+
+    fn component_at(publisher: &str, price: u64, timestamp: i64) -> EntryComponent {
+        pair_component("BTC/USD", publisher, price, timestamp)
+    }
+
+    pub(crate) fn pair_component(
+        pair_id: &str,
+        publisher: &str,
+        price: u64,
+        timestamp: i64,
+    ) -> EntryComponent {
+        EntryComponent {
+            pair_id: pair_id.to_string(),
+            price: BigDecimal::from(price),
+            timestamp: timestamp.to_string(),
+            publisher: publisher.to_string(),
+            publisher_address: "0x1".to_string(),
+            publisher_signature: "0x2".to_string(),
+        }
+    }
+
+    /// Entry of the pair priced at `median_price`, with the provided components.
+    pub(crate) fn median_entry(
+        pair_id: &str,
+        median_price: u64,
+        components: Vec<EntryComponent>,
+    ) -> MedianEntryWithComponents {
+        MedianEntryWithComponents {
+            pair_id: pair_id.to_string(),
+            median_price: BigDecimal::from(median_price),
+            components,
+            num_sources_dropped: 0,
+        }
+    }
+
+    #[test]
+    fn test_median_entry_publishers_are_deduplicated_and_sorted() {
+        let median_entry = MedianEntryWithComponents {
+            pair_id: "BTC/USD".to_string(),
+            median_price: BigDecimal::from(100),
+            components: vec![
+                component("SKYNET", 99),
+                component("AVNU", 100),
+                component("SKYNET", 101),
+                component("FOURLEAF", 100),
+            ],
+            num_sources_dropped: 0,
+        };
+
+        assert_eq!(
+            median_entry.publishers(),
+            vec![
+                "AVNU".to_string(),
+                "FOURLEAF".to_string(),
+                "SKYNET".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_median_entries_are_built_from_the_limited_sources() {
+        let raw_entry = RawMedianEntryWithComponents {
+            pair_id: "BTC/USD".to_string(),
+            median_price: 102.0,
+            components: serde_json::json!([
+                {
+                    "pair_id": "BTC/USD",
+                    "price": 102,
+                    "timestamp": "2024-06-10T06:13:30+00:00",
+                    "publisher": "PRAGMA",
+                    "publisher_address": "0x1",
+                    "publisher_signature": "0x2",
+                },
+            ]),
+            num_sources: 5,
+        };
+
+        let entries = get_median_entries_response(vec![raw_entry], Some(1)).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].publishers(), vec!["PRAGMA".to_string()]);
+        assert_eq!(entries[0].median_price, BigDecimal::from(102));
+        assert_eq!(entries[0].num_sources_dropped, 4);
+    }
+
+    fn datetime(timestamp: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(timestamp, 0).unwrap().naive_utc()
+    }
+
+    fn entry(source: &str, price: u64, timestamp: i64, ingested_at: Option<i64>) -> Entry {
+        Entry {
+            id: uuid::Uuid::new_v4(),
+            pair_id: "BTC/USD".to_string(),
+            publisher: "PRAGMA".to_string(),
+            source: source.to_string(),
+            timestamp: datetime(timestamp),
+            publisher_signature: None,
+            price: BigDecimal::from(price),
+            ingested_at: ingested_at.map(datetime),
+            bid: None,
+            ask: None,
+        }
+    }
+
+    #[test]
+    fn test_late_arriving_entries_are_excluded_from_as_of_median() {
+        let as_of = datetime(1718000100);
+        let entries = || {
+            vec![
+                entry("BINANCE", 100, 1718000000, Some(1718000001)),
+                entry("OKX", 102, 1718000010, Some(1718000011)),
+                // Stored before the ingestion time was tracked.
+                entry("BYBIT", 104, 1718000020, None),
+                // Reported before `as_of` but ingested after it.
+                entry("BINANCE", 1000, 1718000050, Some(1718000200)),
+                entry("COINBASE", 1000, 1718000090, Some(1718000500)),
+            ]
+        };
+
+        let median_entry = compute_median_entry_known_at(entries(), as_of).unwrap();
+        assert_eq!(median_entry.median_price, BigDecimal::from(102));
+        assert_eq!(median_entry.num_sources, 3);
+        assert_eq!(median_entry.time, datetime(1718000020));
+
+        // Once ingested, the late entries are part of the median.
+        let median_entry = compute_median_entry_known_at(entries(), datetime(1718000500)).unwrap();
+        assert_eq!(median_entry.median_price, BigDecimal::from(552));
+        assert_eq!(median_entry.num_sources, 4);
+
+        let late_entries = vec![entry("BINANCE", 100, 1718000000, Some(1718000200))];
+        assert!(compute_median_entry_known_at(late_entries, as_of).is_none());
+    }
+
+    fn publisher_price(publisher: &str, price: u64, timestamp: i64) -> PublisherSourcePriceRaw {
+        PublisherSourcePriceRaw {
+            publisher: publisher.to_string(),
+            source: "BINANCE".to_string(),
+            price: BigDecimal::from(price),
+            time: datetime(timestamp),
+        }
+    }
+
+    #[test]
+    fn test_mean_of_publishers_medians() {
+        let mean_entry = compute_mean_of_publishers_medians(vec![
+            publisher_price("PRAGMA", 100, 1718000000),
+            publisher_price("PRAGMA", 104, 1718000010),
+            publisher_price("PRAGMA", 200, 1718000020),
+            publisher_price("SKYNET", 110, 1718000030),
+        ])
+        .unwrap();
+        // Mean of the PRAGMA median (104) and of the SKYNET one (110).
+        assert_eq!(mean_entry.median_price, BigDecimal::from(107));
+        assert_eq!(mean_entry.time, datetime(1718000030));
+        assert_eq!(mean_entry.num_sources, 4);
+
+        assert!(compute_mean_of_publishers_medians(vec![]).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
 use deadpool_diesel::postgres::Pool;
 use diesel::sql_types::{Numeric, Timestamp, VarChar};
 use diesel::{Queryable, QueryableByName, RunQueryDsl};
@@ -7,6 +8,7 @@ use pragma_common::types::Network;
 use pragma_entities::error::{adapt_infra_error, InfraError};
 
 use crate::handlers::onchain::get_checkpoints::Checkpoint;
+use crate::handlers::onchain::get_nearest_checkpoint::CheckpointLookup;
 use crate::utils::format_bigdecimal_price;
 
 #[derive(Queryable, QueryableByName)]
@@ -16,7 +18,7 @@ struct RawCheckpoint {
     #[diesel(sql_type = Numeric)]
     pub price: BigDecimal,
     #[diesel(sql_type = Timestamp)]
-    pub timestamp: chrono::NaiveDateTime,
+    pub timestamp: NaiveDateTime,
     #[diesel(sql_type = VarChar)]
     pub sender_address: String,
 }
@@ -32,6 +34,13 @@ impl RawCheckpoint {
     }
 }
 
+fn get_checkpoints_table_name(network: Network) -> &'static str {
+    match network {
+        Network::Mainnet => "mainnet_spot_checkpoints",
+        Network::Sepolia => "spot_checkpoints",
+    }
+}
+
 pub async fn get_checkpoints(
     pool: &Pool,
     network: Network,
@@ -39,10 +48,7 @@ pub async fn get_checkpoints(
     decimals: u32,
     limit: u64,
 ) -> Result<Vec<Checkpoint>, InfraError> {
-    let table_name = match network {
-        Network::Mainnet => "mainnet_spot_checkpoints",
-        Network::Sepolia => "spot_checkpoints",
-    };
+    let table_name = get_checkpoints_table_name(network);
     let raw_sql = format!(
         r#"
         SELECT
@@ -78,3 +84,145 @@ pub async fn get_checkpoints(
         .collect();
     Ok(checkpoints)
 }
+
+/// Returns the checkpoint of the pair selected by the lookup around the
+/// timestamp, or None if there is no such checkpoint.
+pub async fn get_nearest_checkpoint(
+    pool: &Pool,
+    network: Network,
+    pair_id: String,
+    decimals: u32,
+    timestamp: NaiveDateTime,
+    lookup: CheckpointLookup,
+) -> Result<Option<Checkpoint>, InfraError> {
+    let table_name = get_checkpoints_table_name(network);
+    // The last checkpoint at or before the timestamp and the first one after.
+    let raw_sql = format!(
+        r#"
+        (
+            SELECT
+                transaction_hash,
+                price,
+                timestamp,
+                sender_address
+            FROM
+                {table_name}
+            WHERE
+                pair_id = $1
+                AND timestamp <= $2
+            ORDER BY timestamp DESC
+            LIMIT 1
+        )
+        UNION ALL
+        (
+            SELECT
+                transaction_hash,
+                price,
+                timestamp,
+                sender_address
+            FROM
+                {table_name}
+            WHERE
+                pair_id = $1
+                AND timestamp > $2
+            ORDER BY timestamp ASC
+            LIMIT 1
+        );
+    "#,
+        table_name = table_name
+    );
+
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    let candidates = conn
+        .interact(move |conn| {
+            diesel::sql_query(raw_sql)
+                .bind::<diesel::sql_types::Text, _>(pair_id)
+                .bind::<Timestamp, _>(timestamp)
+                .load::<RawCheckpoint>(conn)
+        })
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    Ok(select_checkpoint(candidates, timestamp, lookup)
+        .map(|raw_checkpoint| raw_checkpoint.to_checkpoint(decimals)))
+}
+
+/// Selects the checkpoint matching the lookup among the candidates.
+/// When two checkpoints are equally near, the one before the timestamp wins.
+fn select_checkpoint(
+    candidates: Vec<RawCheckpoint>,
+    timestamp: NaiveDateTime,
+    lookup: CheckpointLookup,
+) -> Option<RawCheckpoint> {
+    match lookup {
+        CheckpointLookup::AtOrBefore => candidates
+            .into_iter()
+            .filter(|checkpoint| checkpoint.timestamp <= timestamp)
+            .max_by_key(|checkpoint| checkpoint.timestamp),
+        CheckpointLookup::Nearest => candidates.into_iter().min_by_key(|checkpoint| {
+            (
+                (checkpoint.timestamp - timestamp).abs(),
+                checkpoint.timestamp > timestamp,
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(timestamp: i64) -> NaiveDateTime {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .unwrap()
+            .naive_utc()
+    }
+
+    fn checkpoint(timestamp: i64) -> RawCheckpoint {
+        RawCheckpoint {
+            transaction_hash: format!("0x{timestamp:x}"),
+            price: BigDecimal::from(100),
+            timestamp: datetime(timestamp),
+            sender_address: "0x1".to_string(),
+        }
+    }
+
+    fn selected(timestamp: i64, lookup: CheckpointLookup) -> Option<i64> {
+        let candidates = vec![checkpoint(1718000000), checkpoint(1718000600)];
+        select_checkpoint(candidates, datetime(timestamp), lookup)
+            .map(|checkpoint| checkpoint.timestamp.and_utc().timestamp())
+    }
+
+    #[test]
+    fn test_nearest_checkpoint_between_two_checkpoints() {
+        // Closer to the next checkpoint.
+        assert_eq!(
+            selected(1718000500, CheckpointLookup::Nearest),
+            Some(1718000600)
+        );
+        assert_eq!(
+            selected(1718000500, CheckpointLookup::AtOrBefore),
+            Some(1718000000)
+        );
+        // Closer to the previous checkpoint.
+        assert_eq!(
+            selected(1718000100, CheckpointLookup::Nearest),
+            Some(1718000000)
+        );
+        // Equally near: the checkpoint active at that time wins.
+        assert_eq!(
+            selected(1718000300, CheckpointLookup::Nearest),
+            Some(1718000000)
+        );
+    }
+
+    #[test]
+    fn test_no_checkpoint_before_the_timestamp() {
+        let candidates = vec![checkpoint(1718000600)];
+        let timestamp = datetime(1718000000);
+        assert!(select_checkpoint(candidates, timestamp, CheckpointLookup::AtOrBefore).is_none());
+        let candidates = vec![checkpoint(1718000600)];
+        assert!(select_checkpoint(candidates, timestamp, CheckpointLookup::Nearest).is_some());
+    }
+}
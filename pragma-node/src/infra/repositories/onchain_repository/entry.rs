@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::future::Future;
 
 use bigdecimal::{BigDecimal, ToPrimitive, Zero};
 use deadpool_diesel::postgres::Pool;
@@ -10,25 +11,39 @@ use pragma_entities::error::{adapt_infra_error, InfraError};
 use pragma_entities::Currency;
 use pragma_monitoring::models::SpotEntry;
 
+use crate::caches::CacheRegistry;
+use crate::config::config;
 use crate::handlers::onchain::get_entry::OnchainEntry;
+use crate::infra::rpc;
 use crate::utils::{
     big_decimal_price_to_hex, convert_via_quote, get_mid_price, normalize_to_decimals,
 };
 
 use super::{get_onchain_ohlc_table_name, get_onchain_table_name};
 
-use crate::infra::repositories::entry_repository::get_decimals;
+use crate::infra::repositories::entry_repository::get_decimals_cached;
 
 // Means that we only consider the entries for the last hour when computing the aggregation &
 // retrieving the sources.
-pub const ENTRIES_BACKWARD_INTERVAL: &str = "1 hour";
+pub const ENTRIES_BACKWARD_INTERVAL_IN_SECONDS: i64 = 60 * 60;
+
+/// Point at which the onchain prices are resolved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnchainAsOf {
+    /// Unix timestamp in seconds.
+    Timestamp(u64),
+    /// Starknet block number.
+    Block(u64),
+}
 
 #[derive(Debug)]
 pub struct OnchainRoutingArguments {
     pub pair_id: String,
     pub network: Network,
-    pub timestamp: u64,
+    pub as_of: OnchainAsOf,
     pub aggregation_mode: AggregationMode,
+    /// Window of the TWAP, required with the TWAP aggregation.
+    pub interval: Option<Interval>,
     pub is_routing: bool,
 }
 
@@ -55,6 +70,7 @@ impl From<SpotEntryWithAggregatedPrice> for OnchainEntry {
             price: big_decimal_price_to_hex(&entry.spot_entry.price),
             tx_hash: entry.spot_entry.transaction_hash,
             timestamp: entry.spot_entry.timestamp.and_utc().timestamp() as u64,
+            canonical_source: None,
         }
     }
 }
@@ -67,6 +83,7 @@ impl From<&SpotEntryWithAggregatedPrice> for OnchainEntry {
             price: big_decimal_price_to_hex(&entry.spot_entry.price),
             tx_hash: entry.spot_entry.transaction_hash.clone(),
             timestamp: entry.spot_entry.timestamp.and_utc().timestamp() as u64,
+            canonical_source: None,
         }
     }
 }
@@ -74,6 +91,7 @@ impl From<&SpotEntryWithAggregatedPrice> for OnchainEntry {
 pub async fn routing(
     onchain_pool: &Pool,
     offchain_pool: &Pool,
+    caches: &CacheRegistry,
     routing_args: OnchainRoutingArguments,
 ) -> Result<Vec<RawOnchainData>, InfraError> {
     let pair_id = routing_args.pair_id;
@@ -87,12 +105,14 @@ pub async fn routing(
             onchain_pool,
             routing_args.network,
             pair_id.clone(),
-            routing_args.timestamp,
+            routing_args.as_of,
             routing_args.aggregation_mode,
+            routing_args.interval,
         )
         .await?;
         if !prices_and_entries.is_empty() {
-            let decimal = get_decimals(offchain_pool, &pair_id).await?;
+            let decimal =
+                get_onchain_decimals(offchain_pool, caches, routing_args.network, &pair_id).await?;
             for row in prices_and_entries {
                 result.push(RawOnchainData {
                     price: row.aggregated_price,
@@ -108,63 +128,221 @@ pub async fn routing(
         return Err(InfraError::NotFound);
     }
 
-    let offchain_conn = offchain_pool.get().await.map_err(adapt_infra_error)?;
+    let quote_currencies = get_routing_quote_currencies(offchain_pool).await?;
+
+    // safe unwrap since we construct the pairs string in calling function
+    let (base, quote) = pair_id.split_once('/').unwrap();
+
+    for (base_alt_pair, alt_quote_pair) in
+        routing_candidates(&existing_pair_list, base, quote, &quote_currencies)
+    {
+        let mut base_alt_result = get_sources_and_aggregate(
+            onchain_pool,
+            routing_args.network,
+            base_alt_pair.clone(),
+            routing_args.as_of,
+            routing_args.aggregation_mode,
+            routing_args.interval,
+        )
+        .await?;
+        let quote_alt_result = get_sources_and_aggregate(
+            onchain_pool,
+            routing_args.network,
+            alt_quote_pair.clone(),
+            routing_args.as_of,
+            routing_args.aggregation_mode,
+            routing_args.interval,
+        )
+        .await?;
+        // The next quote currency is tried if one of the legs has no price.
+        if base_alt_result.is_empty() || quote_alt_result.is_empty() {
+            continue;
+        }
 
-    let alternative_currencies = offchain_conn
+        let base_alt_decimal =
+            get_onchain_decimals(offchain_pool, caches, routing_args.network, &base_alt_pair)
+                .await?;
+        let quote_alt_decimal =
+            get_onchain_decimals(offchain_pool, caches, routing_args.network, &alt_quote_pair)
+                .await?;
+
+        return compute_multiple_rebased_price(
+            &mut base_alt_result,
+            &quote_alt_result,
+            vec![base_alt_pair, alt_quote_pair],
+            base_alt_decimal,
+            quote_alt_decimal,
+        );
+    }
+    Err(InfraError::NotFound)
+}
+
+/// Returns the quote currencies through which the pairs are routed, in the
+/// order they must be tried.
+pub async fn get_routing_quote_currencies(offchain_pool: &Pool) -> Result<Vec<String>, InfraError> {
+    let configured = config().await.routing_quote_currencies();
+    if !configured.is_empty() {
+        return Ok(configured
+            .iter()
+            .map(|currency| currency.trim().to_uppercase())
+            .collect());
+    }
+
+    let offchain_conn = offchain_pool.get().await.map_err(adapt_infra_error)?;
+    offchain_conn
         .interact(Currency::get_abstract_all)
         .await
         .map_err(adapt_infra_error)?
-        .map_err(adapt_infra_error)?;
-
-    // safe unwrap since we construct the pairs string in calling function
-    let (base, quote) = pair_id.split_once('/').unwrap();
+        .map_err(adapt_infra_error)
+}
 
-    for alt_currency in alternative_currencies {
-        let base_alt_pair = format!("{}/{}", base, alt_currency);
-        let alt_quote_pair = format!("{}/{}", quote, alt_currency);
-
-        if onchain_pair_exist(&existing_pair_list, &base_alt_pair)
-            && onchain_pair_exist(&existing_pair_list, &alt_quote_pair)
-        {
-            let mut base_alt_result = get_sources_and_aggregate(
-                onchain_pool,
-                routing_args.network,
-                base_alt_pair.clone(),
-                routing_args.timestamp,
-                routing_args.aggregation_mode,
+/// Returns the `(base/alt, quote/alt)` pairs through which `base/quote` can
+/// be routed, following the order of the quote currencies.
+pub fn routing_candidates(
+    existing_pair_list: &[EntryPairId],
+    base: &str,
+    quote: &str,
+    quote_currencies: &[String],
+) -> Vec<(String, String)> {
+    quote_currencies
+        .iter()
+        .map(|alt_currency| {
+            (
+                format!("{}/{}", base, alt_currency),
+                format!("{}/{}", quote, alt_currency),
             )
-            .await?;
-            let base_alt_decimal = get_decimals(offchain_pool, &base_alt_pair).await?;
-            let quote_alt_result = get_sources_and_aggregate(
-                onchain_pool,
-                routing_args.network,
-                alt_quote_pair.clone(),
-                routing_args.timestamp,
-                routing_args.aggregation_mode,
-            )
-            .await?;
-            let quote_alt_decimal = get_decimals(offchain_pool, &alt_quote_pair).await?;
-
-            let result = compute_multiple_rebased_price(
-                &mut base_alt_result,
-                &quote_alt_result,
-                vec![base_alt_pair, alt_quote_pair],
-                base_alt_decimal,
-                quote_alt_decimal,
-            );
-
-            return result;
+        })
+        .filter(|(base_alt_pair, alt_quote_pair)| {
+            onchain_pair_exist(existing_pair_list, base_alt_pair)
+                && onchain_pair_exist(existing_pair_list, alt_quote_pair)
+        })
+        .collect()
+}
+
+/// Returns the decimals of the pair, read from the oracle contract of the
+/// network if configured and from the (cached) offchain database otherwise.
+/// Falls back to the offchain database if the oracle contract can't be read.
+async fn get_onchain_decimals(
+    offchain_pool: &Pool,
+    caches: &CacheRegistry,
+    network: Network,
+    pair_id: &str,
+) -> Result<u32, InfraError> {
+    let onchain_decimals = rpc::oracle_rpc(network).await.map(|oracle_rpc| {
+        rpc::get_cached_decimals(
+            caches.onchain_decimals(),
+            network,
+            pair_id,
+            oracle_rpc.get_decimals(pair_id),
+        )
+    });
+    onchain_or_database_decimals(
+        network,
+        pair_id,
+        onchain_decimals,
+        get_decimals_cached(offchain_pool, caches.decimals(), pair_id),
+    )
+    .await
+}
+
+/// Returns the onchain decimals if they could be read, and the decimals of
+/// the database otherwise. The database is only queried when needed.
+async fn onchain_or_database_decimals(
+    network: Network,
+    pair_id: &str,
+    onchain_decimals: Option<impl Future<Output = Result<u32, InfraError>>>,
+    database_decimals: impl Future<Output = Result<u32, InfraError>>,
+) -> Result<u32, InfraError> {
+    if let Some(onchain_decimals) = onchain_decimals {
+        match onchain_decimals.await {
+            Ok(decimals) => return Ok(decimals),
+            Err(e) => tracing::warn!(
+                "Could not read the onchain decimals of {} on {}, using the database: {}",
+                pair_id,
+                network,
+                e
+            ),
         }
     }
-    Err(InfraError::NotFound)
+    database_decimals.await
 }
 
 fn build_sql_query(
     network: Network,
     aggregation_mode: AggregationMode,
-    timestamp: u64,
+    as_of: OnchainAsOf,
+    interval: Option<Interval>,
 ) -> Result<String, InfraError> {
     let table_name = get_onchain_table_name(&network, &DataType::SpotEntry)?;
+    // At a block, the window ends at the latest entry included in the block.
+    let (window_end, block_filter) = match as_of {
+        OnchainAsOf::Timestamp(timestamp) => (format!("to_timestamp({timestamp})"), String::new()),
+        OnchainAsOf::Block(block_number) => (
+            format!(
+                "(SELECT MAX(timestamp) FROM {table_name} WHERE pair_id = $1 AND block_number <= {block_number})"
+            ),
+            format!(" AND block_number <= {block_number}"),
+        ),
+    };
+    let entries_window = format!(
+        "timestamp BETWEEN ({window_end} - INTERVAL '{ENTRIES_BACKWARD_INTERVAL_IN_SECONDS} seconds') AND {window_end}{block_filter}"
+    );
+
+    // Each update of a source is weighted by the time it stayed its latest
+    // price, until the end of the window for the last one. The price of a
+    // source at the start of the window counts from the start, if it was
+    // published within the components window before it.
+    let twap_entries = match aggregation_mode {
+        AggregationMode::Twap => {
+            let twap_seconds = interval
+                .ok_or(InfraError::InternalServerError)?
+                .to_seconds();
+            let window_start =
+                format!("CAST(({window_end} - INTERVAL '{twap_seconds} seconds') AS timestamp)");
+            format!(
+                r#"TwapUpdates AS (
+                    SELECT
+                        publisher,
+                        source,
+                        price,
+                        timestamp
+                    FROM
+                        {table_name}
+                    WHERE
+                        pair_id = $1
+                        AND timestamp > {window_start} AND timestamp <= {window_end}{block_filter}
+                    UNION ALL
+                    (
+                        SELECT DISTINCT ON (publisher, source)
+                            publisher,
+                            source,
+                            price,
+                            {window_start} AS timestamp
+                        FROM
+                            {table_name}
+                        WHERE
+                            pair_id = $1
+                            AND timestamp <= {window_start}
+                            AND timestamp > {window_start} - INTERVAL '{ENTRIES_BACKWARD_INTERVAL_IN_SECONDS} seconds'{block_filter}
+                        ORDER BY
+                            publisher, source, timestamp DESC
+                    )
+                ),
+                TwapEntries AS (
+                    SELECT
+                        price,
+                        EXTRACT(EPOCH FROM (
+                            LEAD(timestamp, 1, CAST({window_end} AS timestamp))
+                                OVER (PARTITION BY publisher, source ORDER BY timestamp)
+                            - timestamp
+                        )) AS weight
+                    FROM
+                        TwapUpdates
+                ),"#
+            )
+        }
+        _ => String::new(),
+    };
 
     let complete_sql_query = {
         let aggregation_query = get_aggregation_subquery(aggregation_mode)?;
@@ -178,13 +356,14 @@ fn build_sql_query(
                         {table_name}
                     WHERE 
                         pair_id = $1
-                        AND timestamp BETWEEN (to_timestamp({timestamp}) - INTERVAL '{ENTRIES_BACKWARD_INTERVAL}') AND to_timestamp({timestamp})
+                        AND {entries_window}
                 ),
                 FilteredEntries AS (
                     SELECT *
                     FROM RankedEntries
                     WHERE rn = 1
                 ),
+                {twap_entries}
                 AggregatedPrice AS (
                     SELECT {aggregation_subquery}
                     FROM FilteredEntries
@@ -200,12 +379,16 @@ fn build_sql_query(
             "#,
             table_name = table_name,
             aggregation_subquery = aggregation_query,
-            timestamp = timestamp
+            entries_window = entries_window,
+            twap_entries = twap_entries
         )
     };
     Ok(complete_sql_query)
 }
 
+/// Returns the SQL expression aggregating the latest price of each source
+/// (the `FilteredEntries` CTE) into `aggregated_price`.
+/// The TWAP is computed over all the updates of the window (the `TwapEntries` CTE).
 fn get_aggregation_subquery(aggregation_mode: AggregationMode) -> Result<&'static str, InfraError> {
     let query = match aggregation_mode {
         AggregationMode::Mean => "AVG(price) AS aggregated_price",
@@ -221,7 +404,12 @@ fn get_aggregation_subquery(aggregation_mode: AggregationMode) -> Result<&'stati
                 ) AS MedianPrices
             ) AS aggregated_price"
         }
-        _ => Err(InfraError::InternalServerError)?,
+        AggregationMode::Twap => {
+            "(
+                SELECT COALESCE(SUM(price * weight) / NULLIF(SUM(weight), 0), AVG(price))
+                FROM TwapEntries
+            ) AS aggregated_price"
+        }
     };
     Ok(query)
 }
@@ -267,10 +455,11 @@ pub async fn get_sources_and_aggregate(
     pool: &Pool,
     network: Network,
     pair_id: String,
-    timestamp: u64,
+    as_of: OnchainAsOf,
     aggregation_mode: AggregationMode,
+    interval: Option<Interval>,
 ) -> Result<Vec<AggPriceAndEntries>, InfraError> {
-    let raw_sql = build_sql_query(network, aggregation_mode, timestamp)?;
+    let raw_sql = build_sql_query(network, aggregation_mode, as_of, interval)?;
 
     let conn = pool.get().await.map_err(adapt_infra_error)?;
     let raw_entries = conn
@@ -496,3 +685,99 @@ pub async fn get_existing_pairs(
 
     Ok(raw_entries)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing_pairs(pair_ids: &[&str]) -> Vec<EntryPairId> {
+        pair_ids
+            .iter()
+            .map(|pair_id| EntryPairId {
+                pair_id: pair_id.to_string(),
+            })
+            .collect()
+    }
+
+    fn quote_currencies(currencies: &[&str]) -> Vec<String> {
+        currencies.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_pair_only_reachable_via_usdt() {
+        let existing = existing_pairs(&["STRK/USDT", "ETH/USDT", "ETH/USD", "STRK/USDC"]);
+
+        let candidates = routing_candidates(
+            &existing,
+            "STRK",
+            "ETH",
+            &quote_currencies(&["USD", "USDT", "USDC"]),
+        );
+        assert_eq!(
+            candidates,
+            vec![("STRK/USDT".to_string(), "ETH/USDT".to_string())]
+        );
+
+        // Without USDT among the quote currencies, the pair can't be routed.
+        assert!(routing_candidates(
+            &existing,
+            "STRK",
+            "ETH",
+            &quote_currencies(&["USD", "USDC"])
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_routing_candidates_follow_the_configured_order() {
+        let existing = existing_pairs(&["BTC/USD", "ETH/USD", "BTC/USDT", "ETH/USDT"]);
+
+        let candidates =
+            routing_candidates(&existing, "BTC", "ETH", &quote_currencies(&["USDT", "USD"]));
+        assert_eq!(
+            candidates,
+            vec![
+                ("BTC/USDT".to_string(), "ETH/USDT".to_string()),
+                ("BTC/USD".to_string(), "ETH/USD".to_string()),
+            ]
+        );
+    }
+
+    async fn not_called() -> Result<u32, InfraError> {
+        panic!("the database must not be queried");
+    }
+
+    #[tokio::test]
+    async fn test_onchain_decimals_fall_back_to_the_database() {
+        let from_database = || async { Ok::<u32, InfraError>(8) };
+
+        let decimals = onchain_or_database_decimals(
+            Network::Mainnet,
+            "ETH/USD",
+            Some(async { Ok::<u32, InfraError>(18) }),
+            not_called(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(decimals, 18);
+
+        // The RPC failed or timed out.
+        let decimals = onchain_or_database_decimals(
+            Network::Mainnet,
+            "ETH/USD",
+            Some(async { Err::<u32, _>(InfraError::Unavailable) }),
+            from_database(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(decimals, 8);
+
+        // No RPC is configured for the network.
+        let no_rpc: Option<std::future::Ready<Result<u32, InfraError>>> = None;
+        let decimals =
+            onchain_or_database_decimals(Network::Sepolia, "ETH/USD", no_rpc, from_database())
+                .await
+                .unwrap();
+        assert_eq!(decimals, 8);
+    }
+}
@@ -5,7 +5,7 @@ use diesel::{prelude::QueryableByName, RunQueryDsl};
 
 use pragma_common::types::{DataType, Interval, Network};
 use pragma_entities::error::{adapt_infra_error, InfraError};
-use pragma_entities::Currency;
+use pragma_entities::EntryError;
 use serde::Serialize;
 
 use crate::infra::repositories::entry_repository::get_decimals;
@@ -15,7 +15,7 @@ use crate::utils::{
     pair_id_to_currency_pair,
 };
 
-use super::entry::{get_existing_pairs, onchain_pair_exist};
+use super::entry::{get_existing_pairs, get_routing_quote_currencies, routing_candidates};
 use super::get_onchain_aggregate_table_name;
 
 /// Query the onchain database for historical entries and if entries
@@ -120,53 +120,42 @@ pub async fn retry_with_routing(
     pair_id: String,
     timestamp_range: &TimestampRange,
     chunk_interval: &Interval,
-) -> Result<(Vec<HistoricalEntryRaw>, u32), InfraError> {
-    let (base, quote) = pair_id_to_currency_pair(&pair_id);
-
-    let offchain_conn = offchain_pool.get().await.map_err(adapt_infra_error)?;
-    let alternative_currencies = offchain_conn
-        .interact(Currency::get_abstract_all)
-        .await
-        .map_err(adapt_infra_error)?
-        .map_err(adapt_infra_error)?;
+) -> Result<(Vec<HistoricalEntryRaw>, u32), EntryError> {
+    let (base, quote) = pair_id_to_currency_pair(&pair_id)?;
 
+    let quote_currencies = get_routing_quote_currencies(offchain_pool).await?;
     let existing_pairs = get_existing_pairs(onchain_pool, network).await?;
 
-    for alt_currency in alternative_currencies {
-        let base_alt_pair = format!("{}/{}", base, alt_currency);
-        let alt_quote_pair = format!("{}/{}", quote, alt_currency);
-
-        if onchain_pair_exist(&existing_pairs, &base_alt_pair)
-            && onchain_pair_exist(&existing_pairs, &alt_quote_pair)
-        {
-            let base_alt_result = get_historical_entries_and_decimals(
-                onchain_pool,
-                offchain_pool,
-                network,
-                base_alt_pair,
-                timestamp_range,
-                chunk_interval,
-            )
-            .await?;
-            let alt_quote_result = get_historical_entries_and_decimals(
-                onchain_pool,
-                offchain_pool,
-                network,
-                alt_quote_pair,
-                timestamp_range,
-                chunk_interval,
-            )
-            .await?;
-
-            if base_alt_result.0.len() != alt_quote_result.0.len() {
-                continue;
-            }
+    for (base_alt_pair, alt_quote_pair) in
+        routing_candidates(&existing_pairs, &base, &quote, &quote_currencies)
+    {
+        let base_alt_result = get_historical_entries_and_decimals(
+            onchain_pool,
+            offchain_pool,
+            network,
+            base_alt_pair,
+            timestamp_range,
+            chunk_interval,
+        )
+        .await?;
+        let alt_quote_result = get_historical_entries_and_decimals(
+            onchain_pool,
+            offchain_pool,
+            network,
+            alt_quote_pair,
+            timestamp_range,
+            chunk_interval,
+        )
+        .await?;
 
-            return calculate_rebased_prices(base_alt_result, alt_quote_result);
+        if base_alt_result.0.len() != alt_quote_result.0.len() {
+            continue;
         }
+
+        return Ok(calculate_rebased_prices(base_alt_result, alt_quote_result)?);
     }
 
-    Err(InfraError::RoutingError)
+    Err(InfraError::RoutingError.into())
 }
 
 /// Given two vector of entries, compute a new vector containing the routed prices.
@@ -256,7 +245,8 @@ fn combine_entries(
         .naive_utc();
 
     Ok(HistoricalEntryRaw {
-        pair_id: currency_pairs_to_routed_pair_id(&base_entry.pair_id, &quote_entry.pair_id),
+        pair_id: currency_pairs_to_routed_pair_id(&base_entry.pair_id, &quote_entry.pair_id)
+            .map_err(|_| InfraError::InternalServerError)?,
         timestamp: new_timestamp,
         median_price: converted_price,
         nb_sources_aggregated: num_sources,
@@ -73,15 +73,22 @@ pub struct RawLastPublisherEntryForPair {
 }
 
 impl RawLastPublisherEntryForPair {
-    pub fn to_publisher_entry(&self, currencies: &HashMap<String, BigDecimal>) -> PublisherEntry {
-        PublisherEntry {
+    pub fn to_publisher_entry(
+        &self,
+        currencies: &HashMap<String, BigDecimal>,
+    ) -> Result<PublisherEntry, InfraError> {
+        let decimals = get_decimals_for_pair(currencies, &self.pair_id).map_err(|e| {
+            tracing::error!("Could not get the decimals of {}: {}", self.pair_id, e);
+            InfraError::InternalServerError
+        })?;
+        Ok(PublisherEntry {
             pair_id: self.pair_id.clone(),
             last_updated_timestamp: self.last_updated_timestamp.and_utc().timestamp() as u64,
             price: big_decimal_price_to_hex(&self.price),
             source: self.source.clone(),
-            decimals: get_decimals_for_pair(currencies, &self.pair_id),
+            decimals,
             daily_updates: self.daily_updates as u32,
-        }
+        })
     }
 }
 
@@ -214,7 +221,7 @@ async fn get_publisher_with_components(
     let components: Vec<PublisherEntry> = raw_components
         .into_iter()
         .map(|component| component.to_publisher_entry(currencies))
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     let last_updated_timestamp = components
         .iter()
@@ -3,7 +3,7 @@ use crate::handlers::optimistic_oracle::types::{
 };
 #[allow(unused_imports)]
 use diesel::prelude::*;
-use diesel::sql_types::Bool;
+use diesel::sql_types::{Bool, Text};
 use pragma_entities::models::optimistic_oracle_error::OptimisticOracleError;
 use pragma_monitoring::{models::OORequest, schema::oo_requests};
 
@@ -46,7 +46,7 @@ pub async fn get_assertions(
                 .offset(((page - 1) * limit) as i64)
                 .limit(limit as i64)
                 .load(conn)
-                .map_err(|_| OptimisticOracleError::DatabaseConnection)
+                .map_err(adapt_query_error)
         })
         .await
         .map_err(|_| OptimisticOracleError::DatabaseConnection)??;
@@ -69,6 +69,12 @@ pub async fn get_assertions(
 }
 
 // Function to get assertion details
+/// Lowercase hex digits of a 0x-prefixed assertion id, without leading zeros.
+fn normalized_assertion_id(assertion_id: &str) -> String {
+    let digits = assertion_id.strip_prefix("0x").unwrap_or(assertion_id);
+    digits.trim_start_matches('0').to_ascii_lowercase()
+}
+
 pub async fn get_assertion_details(
     onchain_pool: &deadpool_diesel::postgres::Pool,
     assertion_id: &str,
@@ -84,9 +90,13 @@ pub async fn get_assertion_details(
         .interact(move |conn| {
             oo_requests::table
                 .filter(diesel::dsl::sql::<Bool>("upper(_cursor) IS NULL"))
-                .filter(oo_requests::assertion_id.eq(&assertion_id))
+                // Ids are compared ignoring their case & padding, e.g 0xd0 == 0x00D0.
+                .filter(
+                    diesel::dsl::sql::<Bool>("ltrim(lower(substr(assertion_id, 3)), '0') = ")
+                        .bind::<Text, _>(normalized_assertion_id(&assertion_id)),
+                )
                 .first(conn)
-                .map_err(|_| OptimisticOracleError::AssertionDetailsIssue(assertion_id))
+                .map_err(|e| adapt_assertion_query_error(&assertion_id, e))
         })
         .await
         .map_err(|_| OptimisticOracleError::DatabaseConnection)??;
@@ -116,33 +126,48 @@ pub async fn get_assertion_details(
     })
 }
 
-// Function to get disputed assertions
+// Function to get a page of the disputed assertions, along with the total
+// number of disputed assertions
 pub async fn get_disputed_assertions(
     onchain_pool: &deadpool_diesel::postgres::Pool,
     page: u32,
     limit: u32,
-) -> Result<Vec<DisputedAssertion>, OptimisticOracleError> {
+) -> Result<(Vec<DisputedAssertion>, i64), OptimisticOracleError> {
     let conn = onchain_pool
         .get()
         .await
         .map_err(|_| OptimisticOracleError::DatabaseConnection)?;
 
-    let results: Vec<OORequest> = conn
+    let (results, total_count): (Vec<OORequest>, i64) = conn
         .interact(move |conn| {
-            let query = oo_requests::table
-                .filter(diesel::dsl::sql::<Bool>("upper(_cursor) IS NULL"))
-                .filter(oo_requests::disputed.eq(true))
-                .offset(((page - 1) * limit) as i64)
-                .limit(limit as i64);
+            // The count & the page are read from the same snapshot, so the
+            // total matches the pages even while assertions are indexed.
+            conn.build_transaction()
+                .read_only()
+                .repeatable_read()
+                .run(|conn| {
+                    let total_count: i64 = oo_requests::table
+                        .filter(diesel::dsl::sql::<Bool>("upper(_cursor) IS NULL"))
+                        .filter(oo_requests::disputed.eq(true))
+                        .count()
+                        .get_result(conn)?;
 
-            query
-                .load(conn)
-                .map_err(|_| OptimisticOracleError::DatabaseConnection)
+                    // Ordered so the pages don't overlap.
+                    let results: Vec<OORequest> = oo_requests::table
+                        .filter(diesel::dsl::sql::<Bool>("upper(_cursor) IS NULL"))
+                        .filter(oo_requests::disputed.eq(true))
+                        .order((oo_requests::updated_at.desc(), oo_requests::assertion_id))
+                        .offset(page_offset(page, limit))
+                        .limit(limit as i64)
+                        .load(conn)?;
+                    Ok((results, total_count))
+                })
+                .map_err(adapt_query_error)
         })
         .await
         .map_err(|_| OptimisticOracleError::DatabaseConnection)??;
 
-    results
+    let disputed_assertions = results
         .into_iter()
         .map(|request| {
             let disputer = request.disputer.ok_or_else(|| {
@@ -166,7 +191,9 @@ pub async fn get_disputed_assertions(
                 dispute_id: request.dispute_id.unwrap_or("None".to_string()),
             })
         })
-        .collect()
+        .collect::<Result<Vec<_>, OptimisticOracleError>>()?;
+
+    Ok((disputed_assertions, total_count))
 }
 
 // Function to get resolved assertions
@@ -188,9 +215,7 @@ pub async fn get_resolved_assertions(
                 .offset(((page - 1) * limit) as i64)
                 .limit(limit as i64);
 
-            query
-                .load(conn)
-                .map_err(|_| OptimisticOracleError::DatabaseConnection)
+            query.load(conn).map_err(adapt_query_error)
         })
         .await
         .map_err(|_| OptimisticOracleError::DatabaseConnection)??;
@@ -225,6 +250,32 @@ pub async fn get_resolved_assertions(
         .collect()
 }
 
+/// Converts the error of a query on a single assertion, distinguishing an
+/// unknown assertion from a database failure.
+fn adapt_assertion_query_error(
+    assertion_id: &str,
+    error: diesel::result::Error,
+) -> OptimisticOracleError {
+    match error {
+        diesel::result::Error::NotFound => {
+            OptimisticOracleError::NotFound(assertion_id.to_string())
+        }
+        e => adapt_query_error(e),
+    }
+}
+
+/// Logs the failure of a query and converts it to an error which doesn't
+/// leak the details of the database to the client.
+fn adapt_query_error(error: diesel::result::Error) -> OptimisticOracleError {
+    tracing::error!("cannot query the assertions: {}", error);
+    OptimisticOracleError::DatabaseError
+}
+
+/// Number of rows to skip to reach the given page, starting at 1.
+fn page_offset(page: u32, limit: u32) -> i64 {
+    i64::from(page.saturating_sub(1)) * i64::from(limit)
+}
+
 fn get_status(disputed: Option<bool>, settled: Option<bool>) -> Status {
     match (disputed, settled) {
         (Some(true), _) => Status::Disputed,
@@ -232,3 +283,58 @@ fn get_status(disputed: Option<bool>, settled: Option<bool>) -> Status {
         _ => Status::Active,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_offset() {
+        assert_eq!(page_offset(1, 100), 0);
+        assert_eq!(page_offset(3, 100), 200);
+        assert_eq!(page_offset(0, 100), 0);
+        assert_eq!(page_offset(u32::MAX, 100), 429_496_729_400);
+    }
+
+    #[test]
+    fn test_normalized_assertion_id() {
+        assert_eq!(normalized_assertion_id("0xd0"), "d0");
+        assert_eq!(normalized_assertion_id("0x00D0"), "d0");
+        assert_eq!(
+            normalized_assertion_id(&format!("0x{}d0", "0".repeat(62))),
+            "d0"
+        );
+    }
+
+    #[test]
+    fn test_unknown_assertion_is_not_found() {
+        use axum::http::StatusCode;
+        use axum::response::IntoResponse;
+
+        let error = adapt_assertion_query_error("0x1234", diesel::result::Error::NotFound);
+        assert!(matches!(&error, OptimisticOracleError::NotFound(id) if id == "0x1234"));
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+
+        let error =
+            adapt_assertion_query_error("0x1234", diesel::result::Error::BrokenTransactionManager);
+        assert!(matches!(error, OptimisticOracleError::DatabaseError));
+        assert_eq!(
+            error.into_response().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_errors_are_not_leaked() {
+        use axum::response::IntoResponse;
+
+        let error = adapt_query_error(diesel::result::Error::QueryBuilderError(
+            "relation \"oo_requests\" does not exist".into(),
+        ));
+        let body = axum::body::to_bytes(error.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "Could not query the assertions");
+    }
+}
@@ -1,6 +1,12 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use moka::future::Cache;
 use pragma_entities::{adapt_infra_error, InfraError};
 use pragma_entities::{dto, NewPublisher, Publishers};
 
+use crate::caches::{get_or_fetch, CacheRegistry};
+
 pub async fn _insert(
     pool: &deadpool_diesel::postgres::Pool,
     new_entry: NewPublisher,
@@ -32,7 +38,40 @@ pub async fn get(
     Ok(res)
 }
 
-pub async fn _get_all(
+/// Returns the publisher from the cache if present, or fetches it from the
+/// database and caches it.
+pub async fn get_cached(
+    pool: &deadpool_diesel::postgres::Pool,
+    cache: &Cache<String, dto::Publisher>,
+    name: String,
+) -> Result<dto::Publisher, InfraError> {
+    get_or_fetch(cache, name.clone(), get(pool, name)).await
+}
+
+/// Compares the cached publishers with the database at every interval, so the
+/// deactivated publishers and rotated keys are not accepted until the cache
+/// expires.
+pub async fn sync_cache_in_background(
+    pool: deadpool_diesel::postgres::Pool,
+    caches: Arc<CacheRegistry>,
+    interval: Duration,
+) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let filter = dto::PublishersFilter {
+            is_active: None,
+            name_contains: None,
+        };
+        match list_all(&pool, filter).await {
+            Ok(publishers) => caches.sync_publishers(&publishers).await,
+            Err(e) => tracing::warn!("Could not sync the cached publishers: {}", e),
+        }
+    }
+}
+
+/// Returns all the publishers matching the filter.
+pub async fn list_all(
     pool: &deadpool_diesel::postgres::Pool,
     filter: dto::PublishersFilter,
 ) -> Result<Vec<dto::Publisher>, InfraError> {
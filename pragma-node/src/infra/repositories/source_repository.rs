@@ -0,0 +1,14 @@
+use pragma_entities::{adapt_infra_error, InfraError, SourceLatency};
+
+pub async fn get_sources_latencies(
+    pool: &deadpool_diesel::postgres::Pool,
+) -> Result<Vec<SourceLatency>, InfraError> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
+    let res = conn
+        .interact(SourceLatency::get_all)
+        .await
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
+
+    Ok(res)
+}
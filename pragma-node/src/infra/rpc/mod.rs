@@ -0,0 +1,176 @@
+use std::future::Future;
+use std::time::Duration;
+
+use moka::future::Cache;
+use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::core::utils::{cairo_short_string_to_felt, get_selector_from_name};
+use starknet::providers::jsonrpc::HttpTransport;
+use starknet::providers::{JsonRpcClient, Provider, Url};
+use tokio::sync::OnceCell;
+
+use pragma_common::types::Network;
+use pragma_entities::error::InfraError;
+
+use crate::caches::get_or_fetch;
+use crate::config::config;
+use crate::constants::others::{
+    ORACLE_RPC_TIMEOUT_IN_MS, PRAGMA_ORACLE_ADDRESS_MAINNET, PRAGMA_ORACLE_ADDRESS_SEPOLIA,
+};
+
+/// Variant index of `DataType::SpotEntry` in the Pragma oracle contract.
+const SPOT_ENTRY_DATA_TYPE: u64 = 0;
+
+/// Clients of the configured Starknet RPCs, built on their first use and
+/// shared by all the requests.
+static MAINNET_ORACLE_RPC: OnceCell<Option<OracleRpc>> = OnceCell::const_new();
+static SEPOLIA_ORACLE_RPC: OnceCell<Option<OracleRpc>> = OnceCell::const_new();
+
+/// Reads the Pragma oracle contract of a network through a Starknet RPC.
+pub struct OracleRpc {
+    provider: JsonRpcClient<HttpTransport>,
+    network: Network,
+}
+
+impl OracleRpc {
+    pub fn new(rpc_url: &str, network: Network) -> Result<Self, InfraError> {
+        let url = Url::parse(rpc_url).map_err(|e| {
+            tracing::error!("Invalid Starknet RPC url {}: {}", rpc_url, e);
+            InfraError::InternalServerError
+        })?;
+        Ok(Self {
+            provider: JsonRpcClient::new(HttpTransport::new(url)),
+            network,
+        })
+    }
+
+    /// Fetches the decimals of the pair from the oracle contract.
+    pub async fn get_decimals(&self, pair_id: &str) -> Result<u32, InfraError> {
+        let oracle_address = match self.network {
+            Network::Mainnet => PRAGMA_ORACLE_ADDRESS_MAINNET,
+            Network::Sepolia => PRAGMA_ORACLE_ADDRESS_SEPOLIA,
+        };
+        let call = FunctionCall {
+            contract_address: Felt::from_hex(oracle_address)
+                .map_err(|_| InfraError::InternalServerError)?,
+            entry_point_selector: get_selector_from_name("get_decimals")
+                .map_err(|_| InfraError::InternalServerError)?,
+            calldata: get_decimals_calldata(pair_id)?,
+        };
+
+        let response = with_rpc_timeout(async {
+            self.provider
+                .call(call, BlockId::Tag(BlockTag::Latest))
+                .await
+                .map_err(|e| {
+                    tracing::error!("Could not fetch the onchain decimals of {}: {}", pair_id, e);
+                    InfraError::InternalServerError
+                })
+        })
+        .await?;
+
+        parse_decimals_response(&response)
+    }
+}
+
+/// Returns the client of the Starknet RPC configured for the network, if any.
+pub async fn oracle_rpc(network: Network) -> Option<&'static OracleRpc> {
+    let client = match network {
+        Network::Mainnet => &MAINNET_ORACLE_RPC,
+        Network::Sepolia => &SEPOLIA_ORACLE_RPC,
+    };
+    client
+        .get_or_init(|| async {
+            let rpc_url = config().await.onchain_decimals_rpc_url(network)?;
+            OracleRpc::new(rpc_url, network).ok()
+        })
+        .await
+        .as_ref()
+}
+
+/// Returns the decimals of the pair, read from the cache if present or
+/// fetched with `fetch` and then cached.
+pub async fn get_cached_decimals<Fut>(
+    cache: &Cache<String, u32>,
+    network: Network,
+    pair_id: &str,
+    fetch: Fut,
+) -> Result<u32, InfraError>
+where
+    Fut: Future<Output = Result<u32, InfraError>>,
+{
+    get_or_fetch(cache, format!("{network}:{pair_id}"), fetch).await
+}
+
+/// Fails the RPC call with `InfraError::Unavailable` if it doesn't answer in time.
+async fn with_rpc_timeout<T>(
+    call: impl Future<Output = Result<T, InfraError>>,
+) -> Result<T, InfraError> {
+    tokio::time::timeout(Duration::from_millis(ORACLE_RPC_TIMEOUT_IN_MS), call)
+        .await
+        .map_err(|_| InfraError::Unavailable)?
+}
+
+/// Builds the calldata of `get_decimals(DataType::SpotEntry(pair_id))`.
+fn get_decimals_calldata(pair_id: &str) -> Result<Vec<Felt>, InfraError> {
+    let pair_id = cairo_short_string_to_felt(pair_id).map_err(|_| InfraError::NotFound)?;
+    Ok(vec![Felt::from(SPOT_ENTRY_DATA_TYPE), pair_id])
+}
+
+/// Parses the `u32` returned by `get_decimals`.
+fn parse_decimals_response(response: &[Felt]) -> Result<u32, InfraError> {
+    let decimals = response.first().ok_or(InfraError::InternalServerError)?;
+    u32::try_from(*decimals).map_err(|_| InfraError::InternalServerError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_decimals_calldata() {
+        let calldata = get_decimals_calldata("BTC/USD").unwrap();
+        assert_eq!(
+            calldata,
+            vec![Felt::ZERO, cairo_short_string_to_felt("BTC/USD").unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_parse_decimals_response() {
+        assert_eq!(parse_decimals_response(&[Felt::from(8_u32)]).unwrap(), 8);
+        assert!(parse_decimals_response(&[]).is_err());
+        assert!(parse_decimals_response(&[Felt::MAX]).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_hanging_rpc_times_out() {
+        let hanging_rpc = std::future::pending::<Result<u32, InfraError>>();
+        assert!(matches!(
+            with_rpc_timeout(hanging_rpc).await,
+            Err(InfraError::Unavailable)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decimals_are_cached_per_network() {
+        let cache = Cache::new(16);
+        let failing_rpc = || async { Err::<u32, _>(InfraError::Unavailable) };
+
+        let decimals = get_cached_decimals(&cache, Network::Mainnet, "ETH/USD", async {
+            Ok::<u32, InfraError>(18)
+        })
+        .await
+        .unwrap();
+        assert_eq!(decimals, 18);
+        let decimals = get_cached_decimals(&cache, Network::Mainnet, "ETH/USD", failing_rpc())
+            .await
+            .unwrap();
+        assert_eq!(decimals, 18);
+
+        assert!(
+            get_cached_decimals(&cache, Network::Sepolia, "ETH/USD", failing_rpc())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -16,11 +16,15 @@ use std::sync::Arc;
 
 use caches::CacheRegistry;
 use deadpool_diesel::postgres::Pool;
-use starknet::signers::SigningKey;
 
 use pragma_entities::connection::{ENV_OFFCHAIN_DATABASE_URL, ENV_ONCHAIN_DATABASE_URL};
 
 use crate::config::config;
+use crate::types::circuit_breaker::CircuitBreaker;
+use crate::types::readiness::Readiness;
+use crate::types::signer::PragmaSigner;
+use crate::types::time_oracle::TimeOracle;
+use crate::types::ws::{bytes_rate_limiter, BytesRateLimiter, WsConnectionsLimiter};
 use crate::utils::PragmaSignerBuilder;
 
 #[derive(Clone)]
@@ -33,9 +37,19 @@ pub struct AppState {
     // Database caches
     caches: Arc<CacheRegistry>,
     // Pragma Signer used for StarkEx signing
-    pragma_signer: Option<SigningKey>,
+    pragma_signer: PragmaSigner,
     // Metrics
     metrics: Arc<MetricsRegistry>,
+    // Readiness flag, false while the node is warming up
+    readiness: Readiness,
+    // Global limit of the concurrent websocket connections
+    ws_connections: WsConnectionsLimiter,
+    // Rate limiter of the bytes sent by each IP address over its websocket connections
+    ws_bytes_rate_limiter: Arc<BytesRateLimiter>,
+    // Circuit breaker of the onchain endpoints
+    onchain_circuit_breaker: CircuitBreaker,
+    // Trusted time source of the signed prices
+    time_oracle: TimeOracle,
 }
 
 impl fmt::Debug for AppState {
@@ -45,6 +59,10 @@ impl fmt::Debug for AppState {
             .field("caches", &self.caches)
             .field("pragma_signer", &self.pragma_signer)
             .field("metrics", &self.metrics)
+            .field("readiness", &self.readiness)
+            .field("ws_connections", &self.ws_connections)
+            .field("onchain_circuit_breaker", &self.onchain_circuit_breaker)
+            .field("time_oracle", &self.time_oracle)
             .finish_non_exhaustive()
     }
 }
@@ -71,7 +89,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .expect("can't init onchain database pool");
 
     // Init the database caches
-    let caches = CacheRegistry::new();
+    let caches = CacheRegistry::new(
+        config.verified_signatures_cache_ttl(),
+        config.publishers_cache_ttl(),
+    );
 
     // Build the pragma signer
     let signer_builder = if config.is_production_mode() {
@@ -79,7 +100,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         PragmaSignerBuilder::new().non_production_mode()
     };
-    let pragma_signer = signer_builder.build().await;
+    let pragma_signer = PragmaSigner::new(
+        signer_builder.build().await,
+        config.is_production_mode(),
+        config.signer_key_max_age(),
+    );
 
     // Init the redis client - Optionnal, only for endpoints that interact with Redis,
     // i.e just the Merkle Feeds endpoint for now.
@@ -103,8 +128,48 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         caches: Arc::new(caches),
         pragma_signer,
         metrics: MetricsRegistry::new(),
+        readiness: Readiness::new(!config.reject_during_warmup()),
+        ws_connections: WsConnectionsLimiter::new(config.max_ws_connections()),
+        ws_bytes_rate_limiter: Arc::new(bytes_rate_limiter(
+            config.ws_bytes_limit_per_ip_per_second(),
+        )),
+        onchain_circuit_breaker: CircuitBreaker::new(
+            config.onchain_circuit_breaker_failure_threshold(),
+            config.onchain_circuit_breaker_cooldown(),
+        ),
+        time_oracle: TimeOracle::new(
+            config.time_oracle_url().map(str::to_string),
+            config.time_oracle_refresh_interval(),
+            config.time_oracle_timeout(),
+        ),
     };
 
+    // Reload the signer key once expired, or when a rotation is signaled
+    tokio::spawn(types::signer::refresh_in_background(
+        state.pragma_signer.clone(),
+    ));
+    #[cfg(unix)]
+    tokio::spawn(types::signer::refresh_on_hangup(
+        state.pragma_signer.clone(),
+    ));
+
+    // Evict the cached publishers once updated in the database
+    tokio::spawn(
+        infra::repositories::publisher_repository::sync_cache_in_background(
+            state.offchain_pool.clone(),
+            state.caches.clone(),
+            config.publishers_cache_sync_interval(),
+        ),
+    );
+
+    // Warm the node up in the background - data endpoints are rejected until it's done.
+    if !state.readiness.is_ready() {
+        tokio::spawn(server::warmup::warmup(
+            state.clone(),
+            config.warmup_duration(),
+        ));
+    }
+
     server::run_api_server(config, state).await;
 
     // Ensure that the tracing provider is shutdown correctly
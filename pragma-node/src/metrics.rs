@@ -1,19 +1,35 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use opentelemetry::{metrics::Counter, KeyValue};
+use opentelemetry::{
+    metrics::{Counter, Gauge, Histogram},
+    KeyValue,
+};
 use strum::Display;
 
 #[derive(Debug)]
 pub struct MetricsRegistry {
     /// TODO(akhercha): See which additional metrics we want here?
     pub ws_metrics: WsMetricsRegistry,
+    pub error_rate: ErrorRateTracker,
+    /// Delay in seconds between the newest entry of a pair and now.
+    pub ingestion_lag: Gauge<u64>,
 }
 
 impl MetricsRegistry {
     pub fn new() -> Arc<Self> {
+        let meter = opentelemetry::global::meter("pragma-node-meter");
+        let ingestion_lag = meter
+            .u64_gauge("entries_ingestion_lag")
+            .with_description("Delay between the newest entry of a pair and now")
+            .with_unit("s")
+            .init();
+
         Arc::new(Self {
             ws_metrics: Arc::try_unwrap(WsMetricsRegistry::new())
                 .unwrap_or_else(|arc| (*arc).clone()),
+            error_rate: ErrorRateTracker::default(),
+            ingestion_lag,
         })
     }
 }
@@ -51,6 +67,14 @@ impl WsMetricsRegistry {
             tracing::warn!("No metrics registered for WS endpoint: {}", endpoint_name);
         }
     }
+
+    pub fn record_ws_message_size(&self, endpoint_name: &str, direction: Direction, bytes: usize) {
+        if let Some(metrics) = self.metrics.get(endpoint_name) {
+            metrics.record_message_size(direction, bytes);
+        } else {
+            tracing::warn!("No metrics registered for WS endpoint: {}", endpoint_name);
+        }
+    }
 }
 
 #[derive(Display, Clone, Debug)]
@@ -69,9 +93,17 @@ pub enum Status {
     Error,
 }
 
+/// Direction of a WebSocket message, from the node's point of view.
+#[derive(Display, Clone, Copy, Debug)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
 #[derive(Debug, Clone)]
 pub struct WsMetrics {
     interactions: Counter<u64>,
+    message_sizes: Histogram<u64>,
 }
 
 impl WsMetrics {
@@ -85,8 +117,19 @@ impl WsMetrics {
             ))
             .with_unit("count")
             .init();
+        let message_sizes = meter
+            .u64_histogram(format!("{}_ws_message_size", endpoint_name))
+            .with_description(format!(
+                "Size of the WebSocket messages sent & received for {}",
+                endpoint_name
+            ))
+            .with_unit("By")
+            .init();
 
-        Self { interactions }
+        Self {
+            interactions,
+            message_sizes,
+        }
     }
 
     fn record_interaction(&self, interaction: Interaction, status: Status) {
@@ -98,4 +141,117 @@ impl WsMetrics {
             ],
         );
     }
+
+    fn record_message_size(&self, direction: Direction, bytes: usize) {
+        self.message_sizes.record(
+            bytes as u64,
+            &[KeyValue::new("direction", direction.to_string())],
+        );
+    }
+}
+
+/// Window over which the HTTP error rate is computed.
+const ERROR_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Number of one second buckets covering the window.
+const ERROR_RATE_BUCKETS: usize = ERROR_RATE_WINDOW.as_secs() as usize;
+
+/// Outcome of the requests received during one second.
+#[derive(Debug, Default, Clone, Copy)]
+struct RequestsBucket {
+    /// Second of the bucket, counted from the creation of the tracker.
+    second: u64,
+    requests: u64,
+    errors: u64,
+}
+
+/// Tracks the outcome of the recent HTTP requests in order to compute
+/// an error rate over the last [`ERROR_RATE_WINDOW`].
+/// The requests are counted in fixed one second buckets, so the memory
+/// and the cost of a record don't grow with the traffic.
+#[derive(Debug)]
+pub struct ErrorRateTracker {
+    started_at: Instant,
+    buckets: Mutex<[RequestsBucket; ERROR_RATE_BUCKETS]>,
+}
+
+impl Default for ErrorRateTracker {
+    fn default() -> Self {
+        Self {
+            started_at: Instant::now(),
+            buckets: Mutex::new([RequestsBucket::default(); ERROR_RATE_BUCKETS]),
+        }
+    }
+}
+
+impl ErrorRateTracker {
+    /// Records the outcome of a request.
+    pub fn record(&self, is_error: bool) {
+        self.record_at(Instant::now(), is_error);
+    }
+
+    /// Returns the ratio (between 0 and 1) of errored requests over the window.
+    pub fn error_rate(&self) -> f64 {
+        self.error_rate_at(Instant::now())
+    }
+
+    fn record_at(&self, now: Instant, is_error: bool) {
+        let second = self.second_of(now);
+        let mut buckets = self.buckets.lock().expect("error rate lock poisoned");
+        let bucket = &mut buckets[second as usize % ERROR_RATE_BUCKETS];
+        // The bucket still holds the requests of a previous window.
+        if bucket.second != second {
+            *bucket = RequestsBucket {
+                second,
+                ..Default::default()
+            };
+        }
+        bucket.requests += 1;
+        bucket.errors += u64::from(is_error);
+    }
+
+    fn error_rate_at(&self, now: Instant) -> f64 {
+        let second = self.second_of(now);
+        let buckets = self.buckets.lock().expect("error rate lock poisoned");
+        let (requests, errors) = buckets
+            .iter()
+            .filter(|bucket| second.saturating_sub(bucket.second) < ERROR_RATE_BUCKETS as u64)
+            .fold((0, 0), |(requests, errors), bucket| {
+                (requests + bucket.requests, errors + bucket.errors)
+            });
+        if requests == 0 {
+            return 0.0;
+        }
+        errors as f64 / requests as f64
+    }
+
+    fn second_of(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started_at).as_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_rate_only_counts_the_requests_of_the_window() {
+        let tracker = ErrorRateTracker::default();
+        let at = |seconds: u64| tracker.started_at + Duration::from_secs(seconds);
+        assert_eq!(tracker.error_rate_at(at(0)), 0.0);
+
+        tracker.record_at(at(0), true);
+        tracker.record_at(at(30), false);
+        tracker.record_at(at(59), false);
+        tracker.record_at(at(59), true);
+        assert_eq!(tracker.error_rate_at(at(59)), 0.5);
+
+        // The requests of the first second left the window, and their bucket
+        // is reused without them.
+        assert_eq!(tracker.error_rate_at(at(60)), 1.0 / 3.0);
+        tracker.record_at(at(60), false);
+        assert_eq!(tracker.error_rate_at(at(60)), 0.25);
+
+        assert_eq!(tracker.error_rate_at(at(200)), 0.0);
+    }
 }
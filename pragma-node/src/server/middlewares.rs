@@ -1,10 +1,17 @@
 use axum::{
     body::Body,
-    http::{Request, Response},
+    extract::State,
+    http::{header, Request, Response, StatusCode},
     middleware::Next,
+    response::IntoResponse,
 };
 use std::time::Instant;
 
+use crate::constants::others::WARMUP_RETRY_AFTER_IN_SECONDS;
+use crate::types::circuit_breaker::CircuitBreaker;
+use crate::types::readiness::Readiness;
+use crate::AppState;
+
 pub async fn track_timing(req: Request<Body>, next: Next) -> Response<Body> {
     let start = Instant::now();
     let route = req.uri().path().to_owned();
@@ -17,6 +24,69 @@ pub async fn track_timing(req: Request<Body>, next: Next) -> Response<Body> {
     response
 }
 
+/// Records whether each request ended up in a server error, used by the
+/// health score to compute the recent error rate.
+pub async fn track_error_rate(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let response = next.run(req).await;
+    state
+        .metrics
+        .error_rate
+        .record(response.status().is_server_error());
+    response
+}
+
+/// Rejects the requests with a `503` and a `Retry-After` header while the
+/// node is still warming up.
+pub async fn reject_during_warmup(
+    State(readiness): State<Readiness>,
+    req: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    if !readiness.is_ready() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(
+                header::RETRY_AFTER,
+                WARMUP_RETRY_AFTER_IN_SECONDS.to_string(),
+            )],
+            "Service is warming up",
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
+
+/// Short-circuits the requests with a `503` while the circuit breaker is open.
+/// Server errors are recorded as failures of the backend behind the breaker.
+pub async fn circuit_breaker(
+    State(breaker): State<CircuitBreaker>,
+    req: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    if !breaker.try_acquire() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(
+                header::RETRY_AFTER,
+                breaker.cooldown().as_secs().to_string(),
+            )],
+            "Service is temporarily unavailable",
+        )
+            .into_response();
+    }
+    let response = next.run(req).await;
+    if response.status().is_server_error() {
+        breaker.record_failure();
+    } else {
+        breaker.record_success();
+    }
+    response
+}
+
 #[allow(dead_code)]
 pub trait TimingLayer {
     fn with_timing(self) -> Self;
@@ -27,3 +97,53 @@ impl TimingLayer for axum::Router {
         self.layer(axum::middleware::from_fn(track_timing))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn status_of(app: Router) -> StatusCode {
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_reject_during_warmup() {
+        let readiness = Readiness::new(false);
+        let app = Router::new().route("/", get(|| async { "ok" })).layer(
+            axum::middleware::from_fn_with_state(readiness.clone(), reject_during_warmup),
+        );
+
+        assert_eq!(
+            status_of(app.clone()).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        readiness.set_ready();
+        assert_eq!(status_of(app).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_short_circuits_failing_backend() {
+        let breaker = CircuitBreaker::new(Some(2), std::time::Duration::from_secs(60));
+        let app = Router::new()
+            .route("/", get(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .layer(axum::middleware::from_fn_with_state(
+                breaker.clone(),
+                circuit_breaker,
+            ));
+
+        for _ in 0..2 {
+            assert_eq!(
+                status_of(app.clone()).await,
+                StatusCode::INTERNAL_SERVER_ERROR
+            );
+        }
+        // The backend isn't reached anymore.
+        assert_eq!(status_of(app).await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
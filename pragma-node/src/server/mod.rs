@@ -1,5 +1,6 @@
 pub(crate) mod middlewares;
 pub(crate) mod routes;
+pub(crate) mod warmup;
 
 use axum_tracing_opentelemetry::middleware::{OtelAxumLayer, OtelInResponseLayer};
 use std::net::SocketAddr;
@@ -14,7 +15,7 @@ use utoipa::{
 use utoipauto::utoipauto;
 
 use crate::errors::internal_error;
-use crate::server::middlewares::TimingLayer;
+use crate::server::middlewares::{track_error_rate, TimingLayer};
 use crate::{config::Config, server::routes::app_router, AppState};
 
 struct SecurityAddon;
@@ -65,8 +66,12 @@ pub async fn run_api_server(config: &Config, state: AppState) {
     // std::fs::write("openapi.json", json).unwrap();
 
     let app = app_router::<ApiDoc>(state.clone())
-        .with_state(state)
+        .with_state(state.clone())
         .with_timing()
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            track_error_rate,
+        ))
         // Logging so we can see whats going on
         .layer(OtelAxumLayer::default())
         .layer(OtelInResponseLayer)
@@ -6,11 +6,14 @@ use utoipa::OpenApi as OpenApiT;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::handlers::merkle_feeds::{
-    get_merkle_proof::get_merkle_feeds_proof, get_option::get_merkle_feeds_option,
+    get_block_at_timestamp::get_merkle_feeds_block_at_timestamp,
+    get_merkle_proof::get_merkle_feeds_proof, get_merkle_root::get_merkle_feeds_root,
+    get_option::get_merkle_feeds_option,
 };
 use crate::handlers::onchain::{
     get_checkpoints::get_onchain_checkpoints, get_entry::get_onchain_entry,
-    get_history::get_onchain_history, get_publishers::get_onchain_publishers,
+    get_history::get_onchain_history, get_merged_entry::get_onchain_merged_entry,
+    get_nearest_checkpoint::get_onchain_nearest_checkpoint, get_publishers::get_onchain_publishers,
     subscribe_to_ohlc::subscribe_to_onchain_ohlc,
 };
 use crate::handlers::optimistic_oracle::{
@@ -19,9 +22,13 @@ use crate::handlers::optimistic_oracle::{
     get_resolved_assertions::get_resolved_assertions,
 };
 use crate::handlers::{
-    create_entries, create_future_entries, get_entry, get_expiries, get_ohlc, get_volatility,
-    subscribe_to_entry, subscribe_to_price,
+    check_pairs_exist, create_entries, create_future_entries, get_blended_entry,
+    get_database_health, get_dependencies_health, get_entries_batch, get_entry, get_expiries,
+    get_health_score, get_ohlc, get_pair_status, get_pairs, get_perp_entry, get_publishers,
+    get_recent_entries, get_source_coverage, get_sources_history, get_sources_latency,
+    get_volatility, subscribe_to_entry, subscribe_to_price,
 };
+use crate::server::middlewares::{circuit_breaker, reject_during_warmup};
 use crate::AppState;
 
 pub fn app_router<T: OpenApiT>(state: AppState) -> Router<AppState> {
@@ -29,16 +36,31 @@ pub fn app_router<T: OpenApiT>(state: AppState) -> Router<AppState> {
     Router::new()
         .merge(SwaggerUi::new("/node/swagger-ui").url("/node/api-docs/openapi.json", open_api))
         .route("/node", get(root))
+        .nest("/node/v1/health", health_routes(state.clone()))
+        .merge(data_endpoints_router(state))
+        .fallback(handler_404)
+}
+
+/// Router containing all the data endpoints.
+/// Those endpoints are rejected while the node is warming up.
+fn data_endpoints_router(state: AppState) -> Router<AppState> {
+    Router::new()
         .nest("/node/v1/data", data_routes(state.clone()))
         .nest("/node/v1/onchain", onchain_routes(state.clone()))
         .nest("/node/v1/aggregation", aggregation_routes(state.clone()))
         .nest("/node/v1/volatility", volatility_routes(state.clone()))
+        .nest("/node/v1/sources", sources_routes(state.clone()))
+        .nest("/node/v1/metrics", metrics_routes(state.clone()))
         .nest("/node/v1/merkle_feeds", merkle_feeds_routes(state.clone()))
+        .nest("/node/v1/publishers", publishers_routes(state.clone()))
         .nest(
             "/node/v1/optimistic",
             optimistic_oracle_routes(state.clone()),
         )
-        .fallback(handler_404)
+        .layer(axum::middleware::from_fn_with_state(
+            state.readiness.clone(),
+            reject_during_warmup,
+        ))
 }
 
 async fn root() -> &'static str {
@@ -52,12 +74,29 @@ async fn handler_404() -> impl IntoResponse {
     )
 }
 
+fn health_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/score", get(get_health_score))
+        .route("/db", get(get_database_health))
+        .route("/dependencies", get(get_dependencies_health))
+        .with_state(state)
+}
+
 fn data_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/publish", post(create_entries))
         .route("/publish_future", post(create_future_entries))
+        .route("/batch", post(get_entries_batch))
+        .route("/pairs", get(get_pairs))
+        .route("/pairs/exists", post(check_pairs_exist))
         .route("/:base/:quote", get(get_entry))
         .route("/:base/:quote/future_expiries", get(get_expiries))
+        .route("/:base/:quote/status", get(get_pair_status))
+        .route("/:base/:quote/history", get(get_recent_entries))
+        .route("/:base/:quote/volatility", get(get_volatility))
+        .route("/:base/:quote/sources/history", get(get_sources_history))
+        .route("/perp/:base/:quote", get(get_perp_entry))
+        .route("/blended/:base/:quote", get(get_blended_entry))
         .route("/subscribe", get(subscribe_to_entry))
         .route("/price/subscribe", get(subscribe_to_price))
         .with_state(state)
@@ -66,10 +105,19 @@ fn data_routes(state: AppState) -> Router<AppState> {
 fn onchain_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/:base/:quote", get(get_onchain_entry))
+        .route("/merged/:base/:quote", get(get_onchain_merged_entry))
         .route("/history/:base/:quote", get(get_onchain_history))
         .route("/checkpoints/:base/:quote", get(get_onchain_checkpoints))
+        .route(
+            "/checkpoints/:base/:quote/nearest",
+            get(get_onchain_nearest_checkpoint),
+        )
         .route("/publishers", get(get_onchain_publishers))
         .route("/ohlc/subscribe", get(subscribe_to_onchain_ohlc))
+        .layer(axum::middleware::from_fn_with_state(
+            state.onchain_circuit_breaker.clone(),
+            circuit_breaker,
+        ))
         .with_state(state)
 }
 
@@ -79,15 +127,35 @@ fn volatility_routes(state: AppState) -> Router<AppState> {
         .with_state(state)
 }
 
+fn sources_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/latency", get(get_sources_latency))
+        .with_state(state)
+}
+
+fn metrics_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/source-coverage", get(get_source_coverage))
+        .with_state(state)
+}
+
 fn aggregation_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/candlestick/:base/:quote", get(get_ohlc))
         .with_state(state)
 }
 
+fn publishers_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_publishers))
+        .with_state(state)
+}
+
 fn merkle_feeds_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/proof/:option_hash", get(get_merkle_feeds_proof))
+        .route("/root", get(get_merkle_feeds_root))
+        .route("/block", get(get_merkle_feeds_block_at_timestamp))
         .route("/options/:instrument", get(get_merkle_feeds_option))
         .with_state(state)
 }
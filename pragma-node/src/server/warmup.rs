@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use crate::infra::repositories::entry_repository;
+use crate::AppState;
+
+/// Primes the databases pools & the currencies tables, waits for at least
+/// `min_duration` and then flips the readiness flag of the node.
+#[tracing::instrument(skip(state))]
+pub async fn warmup(state: AppState, min_duration: Duration) {
+    let (primed, _) = tokio::join!(prime(&state), tokio::time::sleep(min_duration));
+    if !primed {
+        tracing::warn!("⚠ Could not fully prime the node during warmup.");
+    }
+    state.readiness.set_ready();
+    tracing::info!("✅ Node warmed up, now accepting data requests");
+}
+
+/// Opens a connection on both databases & loads the currencies decimals.
+async fn prime(state: &AppState) -> bool {
+    let offchain_primed = entry_repository::get_all_currencies_decimals(&state.offchain_pool)
+        .await
+        .is_ok();
+    let onchain_primed = state.onchain_pool.get().await.is_ok();
+    offchain_primed && onchain_primed
+}
@@ -0,0 +1,169 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// State of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through.
+    Closed,
+    /// Requests are short-circuited until the cooldown is over.
+    Open,
+    /// The cooldown is over: a single request is let through to test the
+    /// recovery of the backend.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// Start of the request testing the recovery, when half-open.
+    probe_started_at: Option<Instant>,
+}
+
+/// Circuit breaker short-circuiting the requests to a failing backend.
+///
+/// After `failure_threshold` consecutive failures, the breaker opens and the
+/// requests are rejected for the cooldown duration. It then half-opens: the
+/// next request is let through and closes the breaker on success, or opens it
+/// again for another cooldown on failure.
+/// Without threshold, the breaker is disabled and always closed.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: Option<u32>,
+    cooldown: Duration,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: Option<u32>, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.filter(|threshold| *threshold > 0),
+            cooldown,
+            state: Arc::new(Mutex::new(BreakerState::default())),
+        }
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().expect("circuit breaker lock poisoned");
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Returns true if the request can go through.
+    /// When half-open, only one request at a time is let through. If it never
+    /// completes, another one is let through after a cooldown.
+    pub fn try_acquire(&self) -> bool {
+        if self.failure_threshold.is_none() {
+            return true;
+        }
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        let Some(opened_at) = state.opened_at else {
+            return true;
+        };
+        if opened_at.elapsed() < self.cooldown {
+            return false;
+        }
+        match state.probe_started_at {
+            Some(probe_started_at) if probe_started_at.elapsed() < self.cooldown => false,
+            _ => {
+                state.probe_started_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        if self.failure_threshold.is_none() {
+            return;
+        }
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        *state = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let Some(failure_threshold) = self.failure_threshold else {
+            return;
+        };
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.opened_at.is_some() || state.consecutive_failures >= failure_threshold {
+            state.opened_at = Some(Instant::now());
+            state.probe_started_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_failures_open_the_breaker_until_cooldown() {
+        let cooldown = Duration::from_millis(50);
+        let breaker = CircuitBreaker::new(Some(3), cooldown);
+
+        for _ in 0..2 {
+            assert!(breaker.try_acquire());
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire());
+        breaker.record_failure();
+
+        // The threshold is reached: requests are short-circuited.
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire());
+
+        // After the cooldown, a single request tests the recovery...
+        std::thread::sleep(cooldown);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+
+        // ... and opens the breaker again if it fails.
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.try_acquire());
+
+        // The backend recovered.
+        std::thread::sleep(cooldown);
+        assert!(breaker.try_acquire());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire());
+        assert!(breaker.try_acquire());
+    }
+
+    #[test]
+    fn test_success_resets_the_failures() {
+        let breaker = CircuitBreaker::new(Some(2), Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_disabled_breaker_never_opens() {
+        let breaker = CircuitBreaker::new(None, Duration::from_secs(60));
+        for _ in 0..100 {
+            breaker.record_failure();
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire());
+    }
+}
@@ -1,5 +1,6 @@
+use bigdecimal::BigDecimal;
 use indexmap::IndexMap;
-use pragma_entities::EntryError;
+use pragma_entities::{is_valid_price, EntryError};
 use serde::{Deserialize, Serialize};
 use serde_json::Number;
 use utoipa::ToSchema;
@@ -24,6 +25,12 @@ pub trait EntryTrait {
     fn expiration_timestamp(&self) -> Option<u64> {
         None
     }
+    fn bid(&self) -> Option<u128> {
+        None
+    }
+    fn ask(&self) -> Option<u128> {
+        None
+    }
 }
 
 // Entry = SpotEntry
@@ -33,6 +40,12 @@ pub struct Entry {
     pub pair_id: String,
     pub price: u128,
     pub volume: u128,
+    /// Best bid, optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid: Option<u128>,
+    /// Best ask, optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask: Option<u128>,
 }
 
 impl EntryTrait for Entry {
@@ -51,6 +64,14 @@ impl EntryTrait for Entry {
     fn volume(&self) -> u128 {
         self.volume
     }
+
+    fn bid(&self) -> Option<u128> {
+        self.bid
+    }
+
+    fn ask(&self) -> Option<u128> {
+        self.ask
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
@@ -121,11 +142,65 @@ pub struct PublishMessage<E: EntryTrait + Serialize> {
     pub entries: Vec<E>,
 }
 
+/// Asserts that all the entries have a strictly positive price, except for
+/// the pairs where zero prices are allowed.
+/// Prices are unsigned so negative prices can't be published.
+pub fn assert_prices_are_valid<E: EntryTrait>(
+    entries: &[E],
+    non_positive_price_pairs: &[String],
+) -> Result<(), EntryError> {
+    for entry in entries {
+        let price = BigDecimal::from(entry.price());
+        if !is_valid_price(entry.pair_id(), &price, non_positive_price_pairs) {
+            return Err(EntryError::InvalidPrice(format!(
+                "price of {} from {} must be positive, got {}",
+                entry.pair_id(),
+                entry.base().source,
+                price
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Asserts that the entries reporting a bid or an ask report both, and that
+/// their bid is not above their ask.
+pub fn assert_quotes_are_valid<E: EntryTrait>(entries: &[E]) -> Result<(), EntryError> {
+    for entry in entries {
+        match (entry.bid(), entry.ask()) {
+            (None, None) => {}
+            (Some(bid), Some(ask)) if bid <= ask => {}
+            (Some(bid), Some(ask)) => {
+                return Err(EntryError::InvalidPrice(format!(
+                    "book of {} from {} is crossed: bid {} > ask {}",
+                    entry.pair_id(),
+                    entry.base().source,
+                    bid,
+                    ask
+                )));
+            }
+            _ => {
+                return Err(EntryError::InvalidPrice(format!(
+                    "{} from {} must report both a bid and an ask, or none",
+                    entry.pair_id(),
+                    entry.base().source
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn build_publish_message<E>(entries: &[E]) -> Result<TypedData, EntryError>
 where
     E: EntryTrait + Serialize + for<'a> Deserialize<'a>,
 {
     let mut is_future = false;
+    // Bid and ask are only part of the message if one of the entries reports
+    // them, so the message of the publishers not reporting them is unchanged.
+    let has_quotes = entries
+        .iter()
+        .any(|entry| entry.bid().is_some() || entry.ask().is_some());
 
     // Construct the raw entries
     let raw_entries: Vec<PrimitiveType> = entries
@@ -172,6 +247,20 @@ where
                 );
             }
 
+            // Quotes are reported by all or none of their sides, see
+            // `assert_quotes_are_valid`: an entry without them is signed with
+            // a bid and an ask of 0.
+            if has_quotes {
+                entry_map.insert(
+                    "bid".to_string(),
+                    PrimitiveType::Number(Number::from(entry.bid().unwrap_or(0))),
+                );
+                entry_map.insert(
+                    "ask".to_string(),
+                    PrimitiveType::Number(Number::from(entry.ask().unwrap_or(0))),
+                );
+            }
+
             PrimitiveType::Object(entry_map)
         })
         .collect();
@@ -233,6 +322,18 @@ where
         }));
     }
 
+    // Include "bid" and "ask" if necessary
+    if has_quotes {
+        entry_fields.push(Field::SimpleType(SimpleField {
+            name: "bid".to_string(),
+            r#type: "u128".to_string(),
+        }));
+        entry_fields.push(Field::SimpleType(SimpleField {
+            name: "ask".to_string(),
+            r#type: "u128".to_string(),
+        }));
+    }
+
     types.insert("Entry".to_string(), entry_fields);
 
     // Define "Base" type
@@ -282,3 +383,86 @@ where
 
     Ok(typed_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pair_id: &str, price: u128) -> Entry {
+        Entry {
+            base: BaseEntry {
+                timestamp: 1718000000,
+                source: "BINANCE".to_string(),
+                publisher: "PRAGMA".to_string(),
+            },
+            pair_id: pair_id.to_string(),
+            price,
+            volume: 0,
+            bid: None,
+            ask: None,
+        }
+    }
+
+    #[test]
+    fn test_assert_prices_are_valid() {
+        assert!(assert_prices_are_valid(&[entry("BTC/USD", 1)], &[]).is_ok());
+        assert!(matches!(
+            assert_prices_are_valid(&[entry("BTC/USD", 1), entry("ETH/USD", 0)], &[]),
+            Err(EntryError::InvalidPrice(_))
+        ));
+        let allowed = vec!["POWER/EUR".to_string()];
+        assert!(assert_prices_are_valid(&[entry("POWER/EUR", 0)], &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_assert_quotes_are_valid() {
+        let quoted = |bid: Option<u128>, ask: Option<u128>| Entry {
+            bid,
+            ask,
+            ..entry("BTC/USD", 100)
+        };
+        assert!(assert_quotes_are_valid(&[quoted(None, None)]).is_ok());
+        assert!(assert_quotes_are_valid(&[quoted(Some(99), Some(101))]).is_ok());
+        assert!(assert_quotes_are_valid(&[quoted(Some(100), Some(100))]).is_ok());
+        for invalid in [
+            quoted(Some(99), None),
+            quoted(None, Some(101)),
+            quoted(Some(102), Some(101)),
+        ] {
+            assert!(matches!(
+                assert_quotes_are_valid(&[quoted(None, None), invalid]),
+                Err(EntryError::InvalidPrice(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_quotes_are_only_signed_when_reported() {
+        let entry_type = |typed_data: &TypedData| {
+            typed_data.types["Entry"]
+                .iter()
+                .map(|field| match field {
+                    Field::SimpleType(field) => field.name.clone(),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let typed_data = build_publish_message(&[entry("BTC/USD", 100)]).unwrap();
+        assert_eq!(
+            entry_type(&typed_data),
+            vec!["base", "pair_id", "price", "volume"]
+        );
+
+        let quoted = Entry {
+            bid: Some(99),
+            ask: Some(101),
+            ..entry("BTC/USD", 100)
+        };
+        let typed_data = build_publish_message(&[quoted, entry("ETH/USD", 10)]).unwrap();
+        assert_eq!(
+            entry_type(&typed_data),
+            vec!["base", "pair_id", "price", "volume", "bid", "ask"]
+        );
+    }
+}
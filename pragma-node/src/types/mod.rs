@@ -1,6 +1,11 @@
+pub mod circuit_breaker;
 pub mod entries;
 pub mod hex_hash;
 pub mod pricer;
+pub mod readiness;
+pub mod signer;
+pub mod sliding_window;
+pub mod time_oracle;
 pub mod timestamp;
 pub mod ws;
 
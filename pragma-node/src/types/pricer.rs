@@ -2,16 +2,37 @@ use std::collections::HashMap;
 
 use bigdecimal::{BigDecimal, ToPrimitive};
 use deadpool_diesel::postgres::Pool;
-use pragma_common::types::DataType;
+use pragma_common::types::{AggregationMode, DataType};
 use pragma_entities::{Currency, EntryError};
 
+use crate::config::config;
 use crate::infra::repositories::entry_repository::{
     get_current_median_entries_with_components, MedianEntryWithComponents,
 };
 
 pub trait Pricer {
     fn new(pairs: Vec<String>, pair_type: DataType) -> Self;
-    async fn compute(&self, db_pool: &Pool) -> Result<Vec<MedianEntryWithComponents>, EntryError>;
+    async fn compute(
+        &self,
+        db_pool: &Pool,
+        aggregation: AggregationMode,
+    ) -> Result<Vec<MedianEntryWithComponents>, EntryError>;
+}
+
+/// Computes the price of the entries with the requested aggregation, the
+/// entries being fetched with their median price.
+pub(crate) fn apply_aggregation(
+    entries: &mut [MedianEntryWithComponents],
+    aggregation: AggregationMode,
+) -> Result<(), EntryError> {
+    match aggregation {
+        AggregationMode::Median => {}
+        AggregationMode::Mean => entries.iter_mut().for_each(|entry| entry.use_mean_price()),
+        AggregationMode::Twap => {
+            return Err(EntryError::UnsupportedAggregation("twap".to_string()));
+        }
+    }
+    Ok(())
 }
 
 // =======================================
@@ -22,7 +43,7 @@ pub struct IndexPricer {
 }
 
 /// Computes the most recent index price for a list of pairs.
-/// The index price is the median of the pairs.
+/// The index price is the median of the pairs, or their mean if requested.
 impl Pricer for IndexPricer {
     fn new(pairs: Vec<String>, pair_type: DataType) -> Self {
         Self { pairs, pair_type }
@@ -32,13 +53,25 @@ impl Pricer for IndexPricer {
         pairs_count = self.pairs.len(),
         pair_type = ?self.pair_type
     ))]
-    async fn compute(&self, db_pool: &Pool) -> Result<Vec<MedianEntryWithComponents>, EntryError> {
+    async fn compute(
+        &self,
+        db_pool: &Pool,
+        aggregation: AggregationMode,
+    ) -> Result<Vec<MedianEntryWithComponents>, EntryError> {
         if self.pairs.is_empty() {
             return Ok(vec![]);
         }
-        get_current_median_entries_with_components(db_pool, &self.pairs, self.pair_type)
-            .await
-            .map_err(|e| e.to_entry_error(&self.pairs.join(",")))
+        let max_sources = config().await.max_sources_per_pair();
+        let mut entries = get_current_median_entries_with_components(
+            db_pool,
+            &self.pairs,
+            self.pair_type,
+            max_sources,
+        )
+        .await
+        .map_err(|e| e.to_entry_error(&self.pairs.join(",")))?;
+        apply_aggregation(&mut entries, aggregation)?;
+        Ok(entries)
     }
 }
 
@@ -67,15 +100,16 @@ impl MarkPricer {
             .collect()
     }
 
-    /// Computes the stablecoin/USD pairs median entries.
+    /// Computes the stablecoin/USD pairs index entries.
     #[tracing::instrument(skip(db_pool))]
     async fn get_stablecoins_index_entries(
         db_pool: &Pool,
         stablecoin_pairs: &[String],
+        aggregation: AggregationMode,
     ) -> Result<Vec<MedianEntryWithComponents>, EntryError> {
         let stable_to_usd_pairs = Self::build_stable_to_usd_pairs(stablecoin_pairs);
         let stablecoins_index_pricer = IndexPricer::new(stable_to_usd_pairs, DataType::SpotEntry);
-        stablecoins_index_pricer.compute(db_pool).await
+        stablecoins_index_pricer.compute(db_pool, aggregation).await
     }
 
     /// Retrieves the number of decimals for quote stablecoins.
@@ -104,15 +138,16 @@ impl MarkPricer {
         Ok(decimals)
     }
 
-    /// Computes the non USD quoted pairs median entries.
+    /// Computes the non USD quoted pairs index entries.
     #[tracing::instrument(skip(db_pool), fields(pairs_count = pairs.len()))]
     async fn get_pairs_entries(
         db_pool: &Pool,
         pairs: &[String],
         pair_type: DataType,
+        aggregation: AggregationMode,
     ) -> Result<Vec<MedianEntryWithComponents>, EntryError> {
         let pairs_entries = IndexPricer::new(pairs.to_vec(), pair_type);
-        pairs_entries.compute(db_pool).await
+        pairs_entries.compute(db_pool, aggregation).await
     }
 
     /// Given the median price of a perp pair, the median price of the spot
@@ -174,6 +209,8 @@ impl MarkPricer {
                 pair_id: perp_median_entry.pair_id.clone(),
                 median_price: mark_price,
                 components,
+                num_sources_dropped: perp_median_entry.num_sources_dropped
+                    + spot_usd_median_entry.num_sources_dropped,
             };
             merged_entries.push(mark_median_entry);
         }
@@ -194,17 +231,21 @@ impl Pricer for MarkPricer {
             pair_type = ?self.pair_type
         )
     )]
-    async fn compute(&self, db_pool: &Pool) -> Result<Vec<MedianEntryWithComponents>, EntryError> {
+    async fn compute(
+        &self,
+        db_pool: &Pool,
+        aggregation: AggregationMode,
+    ) -> Result<Vec<MedianEntryWithComponents>, EntryError> {
         tracing::debug!("Computing mark prices for pairs: {:?}", self.pairs);
         if self.pairs.is_empty() {
             return Ok(vec![]);
         }
         let (stablecoins_spot_entries, stablecoins_decimals, pairs_perp_entries) = tokio::join!(
-            Self::get_stablecoins_index_entries(db_pool, &self.pairs),
+            Self::get_stablecoins_index_entries(db_pool, &self.pairs, aggregation),
             // TODO: currently, we retrieve the decimals everytime for every loop
             // but we should not: they won't change.
             Self::get_stablecoins_decimals(db_pool, self.pairs.clone()),
-            Self::get_pairs_entries(db_pool, &self.pairs, self.pair_type)
+            Self::get_pairs_entries(db_pool, &self.pairs, self.pair_type, aggregation)
         );
         Self::merge_entries_from(
             stablecoins_spot_entries?,
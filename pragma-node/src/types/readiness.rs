@@ -0,0 +1,22 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Readiness flag of the node.
+/// While not ready, the data endpoints are rejected with a `503` so the
+/// load balancers can retry on another instance.
+#[derive(Debug, Clone, Default)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    pub fn new(is_ready: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(is_ready)))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    pub fn set_ready(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
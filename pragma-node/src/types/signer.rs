@@ -0,0 +1,313 @@
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use pragma_common::utils::field_element_as_hex_string;
+use starknet::core::utils::starknet_keccak;
+use starknet::signers::SigningKey;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::constants::others::{
+    SIGNER_RELOAD_MAX_RETRY_DELAY_IN_SECONDS, SIGNER_RELOAD_RETRY_DELAY_IN_SECONDS,
+    SIGNER_RELOAD_TIMEOUT_IN_SECONDS,
+};
+use crate::utils::build_pragma_signer_from_aws;
+
+/// Key of the Pragma signer, with the fingerprint advertised to the clients
+/// so they can detect a rotation.
+#[derive(Debug, Clone)]
+pub struct SignerKey {
+    pub key: Arc<SigningKey>,
+    pub fingerprint: String,
+}
+
+impl SignerKey {
+    pub fn new(key: SigningKey) -> Self {
+        let public_key = key.verifying_key().scalar();
+        let fingerprint = field_element_as_hex_string(&starknet_keccak(&public_key.to_bytes_be()));
+        Self {
+            key: Arc::new(key),
+            fingerprint,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SignerState {
+    key: Option<SignerKey>,
+    loaded_at: Instant,
+    stale: bool,
+    /// Consecutive failed reloads, and when the last one failed.
+    failed_reloads: u32,
+    failed_at: Instant,
+}
+
+impl SignerState {
+    /// Delay before retrying after the last failed reload, doubled after
+    /// each consecutive failure.
+    fn retry_delay(&self) -> Duration {
+        let delay = SIGNER_RELOAD_RETRY_DELAY_IN_SECONDS
+            .saturating_mul(1 << self.failed_reloads.saturating_sub(1).min(16));
+        Duration::from_secs(delay.min(SIGNER_RELOAD_MAX_RETRY_DELAY_IN_SECONDS))
+    }
+}
+
+/// Pragma signer, reloaded from AWS in the background once older than its
+/// max age or after a rotation was signaled. Only the production signer can
+/// be reloaded, the other one being a random key.
+/// The current key is served while reloading, and kept if the reload fails
+/// or times out, in which case the reload is retried with a backoff.
+#[derive(Debug, Clone)]
+pub struct PragmaSigner {
+    is_reloadable: bool,
+    is_available: bool,
+    max_age: Option<Duration>,
+    state: Arc<RwLock<SignerState>>,
+    refresh_requested: Arc<Notify>,
+}
+
+impl PragmaSigner {
+    pub fn new(key: Option<SigningKey>, is_reloadable: bool, max_age: Option<Duration>) -> Self {
+        Self {
+            is_reloadable,
+            is_available: key.is_some(),
+            max_age,
+            state: Arc::new(RwLock::new(SignerState {
+                key: key.map(SignerKey::new),
+                loaded_at: Instant::now(),
+                stale: false,
+                failed_reloads: 0,
+                failed_at: Instant::now(),
+            })),
+            refresh_requested: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Returns true if a key was loaded at startup.
+    pub fn is_available(&self) -> bool {
+        self.is_available
+    }
+
+    pub fn current(&self) -> Option<SignerKey> {
+        self.state.read().expect("signer lock poisoned").key.clone()
+    }
+
+    /// Forces the key to be reloaded, e.g after a rotation.
+    pub fn force_refresh(&self) {
+        self.state.write().expect("signer lock poisoned").stale = true;
+        self.refresh_requested.notify_one();
+    }
+
+    /// Time left before the key must be reloaded, if it expires. After a
+    /// failed reload, it's the time left before retrying.
+    fn time_until_expiry(&self) -> Option<Duration> {
+        let state = self.state.read().expect("signer lock poisoned");
+        if state.failed_reloads > 0 {
+            return Some(
+                state
+                    .retry_delay()
+                    .saturating_sub(state.failed_at.elapsed()),
+            );
+        }
+        self.max_age
+            .map(|max_age| max_age.saturating_sub(state.loaded_at.elapsed()))
+    }
+
+    /// Reloads the key if it expired or was flagged as stale.
+    /// The lock is not held during the reload, so signing is never blocked.
+    async fn refresh_if_needed<F, Fut>(&self, reload: F)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Option<SigningKey>>,
+    {
+        let needs_reload = {
+            let state = self.state.read().expect("signer lock poisoned");
+            let is_expired = self
+                .max_age
+                .is_some_and(|max_age| state.loaded_at.elapsed() >= max_age);
+            state.stale || is_expired
+        };
+        if !self.is_reloadable || !needs_reload {
+            return;
+        }
+
+        let timeout = Duration::from_secs(SIGNER_RELOAD_TIMEOUT_IN_SECONDS);
+        let new_key = match tokio::time::timeout(timeout, reload()).await {
+            Ok(Some(key)) => Some(SignerKey::new(key)),
+            Ok(None) => {
+                tracing::error!("Could not reload the Pragma signer, keeping its key.");
+                None
+            }
+            Err(_) => {
+                tracing::error!("Reloading the Pragma signer timed out, keeping its key.");
+                None
+            }
+        };
+
+        let mut state = self.state.write().expect("signer lock poisoned");
+        match new_key {
+            Some(key) => {
+                state.key = Some(key);
+                state.loaded_at = Instant::now();
+                state.stale = false;
+                state.failed_reloads = 0;
+            }
+            None => {
+                state.failed_reloads = state.failed_reloads.saturating_add(1);
+                state.failed_at = Instant::now();
+            }
+        }
+    }
+}
+
+/// Reloads the key of the signer once expired or when a rotation is signaled.
+pub async fn refresh_in_background(signer: PragmaSigner) {
+    if !signer.is_reloadable {
+        return;
+    }
+    loop {
+        match signer.time_until_expiry() {
+            Some(delay) => {
+                tokio::select! {
+                    () = signer.refresh_requested.notified() => {}
+                    () = tokio::time::sleep(delay) => {}
+                }
+            }
+            None => signer.refresh_requested.notified().await,
+        }
+        signer.refresh_if_needed(build_pragma_signer_from_aws).await;
+    }
+}
+
+/// Reloads the key of the signer every time the process receives a SIGHUP,
+/// which is sent after a rotation.
+#[cfg(unix)]
+pub async fn refresh_on_hangup(signer: PragmaSigner) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::error!("Could not listen to SIGHUP: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        tracing::info!("SIGHUP received, reloading the Pragma signer.");
+        signer.force_refresh();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starknet::core::types::Felt;
+
+    use super::*;
+
+    fn signing_key(secret: u32) -> SigningKey {
+        SigningKey::from_secret_scalar(Felt::from(secret))
+    }
+
+    fn fingerprint(secret: u32) -> String {
+        SignerKey::new(signing_key(secret)).fingerprint
+    }
+
+    #[tokio::test]
+    async fn test_rotation_within_the_max_age_advertises_the_new_key() {
+        assert_ne!(fingerprint(1), fingerprint(2));
+
+        let signer = PragmaSigner::new(Some(signing_key(1)), true, Some(Duration::from_secs(3600)));
+        signer
+            .refresh_if_needed(|| async { Some(signing_key(2)) })
+            .await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(1));
+
+        signer.force_refresh();
+        signer
+            .refresh_if_needed(|| async { Some(signing_key(2)) })
+            .await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_key_is_reloaded_once_expired() {
+        let max_age = Duration::from_secs(3600);
+        let signer = PragmaSigner::new(Some(signing_key(1)), true, Some(max_age));
+        tokio::time::advance(max_age).await;
+        assert_eq!(signer.time_until_expiry(), Some(Duration::ZERO));
+        signer
+            .refresh_if_needed(|| async { Some(signing_key(2)) })
+            .await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(2));
+        assert_eq!(signer.time_until_expiry(), Some(max_age));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_reload_is_retried_with_a_backoff() {
+        let max_age = Duration::from_secs(3600);
+        let retry_delay = Duration::from_secs(SIGNER_RELOAD_RETRY_DELAY_IN_SECONDS);
+        let signer = PragmaSigner::new(Some(signing_key(1)), true, Some(max_age));
+        tokio::time::advance(max_age).await;
+
+        // A failed reload keeps the current key, and is retried after the
+        // backoff rather than after a full max age.
+        signer.refresh_if_needed(|| async { None }).await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(1));
+        assert_eq!(signer.time_until_expiry(), Some(retry_delay));
+
+        // The backoff doubles after each consecutive failure, up to its maximum.
+        signer.refresh_if_needed(|| async { None }).await;
+        assert_eq!(signer.time_until_expiry(), Some(retry_delay * 2));
+        for _ in 0..16 {
+            signer.refresh_if_needed(|| async { None }).await;
+        }
+        assert_eq!(
+            signer.time_until_expiry(),
+            Some(Duration::from_secs(
+                SIGNER_RELOAD_MAX_RETRY_DELAY_IN_SECONDS
+            ))
+        );
+
+        // The key is still expired, so the retry reloads it.
+        tokio::time::advance(Duration::from_secs(
+            SIGNER_RELOAD_MAX_RETRY_DELAY_IN_SECONDS,
+        ))
+        .await;
+        assert_eq!(signer.time_until_expiry(), Some(Duration::ZERO));
+        signer
+            .refresh_if_needed(|| async { Some(signing_key(2)) })
+            .await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(2));
+        assert_eq!(signer.time_until_expiry(), Some(max_age));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_key_is_served_while_a_reload_hangs() {
+        let signer = PragmaSigner::new(Some(signing_key(1)), true, None);
+        signer.force_refresh();
+
+        let reloading = signer.clone();
+        let reload = tokio::spawn(async move {
+            reloading
+                .refresh_if_needed(std::future::pending::<Option<SigningKey>>)
+                .await;
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(1));
+
+        // The reload times out and the current key is kept.
+        reload.await.unwrap();
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(1));
+    }
+
+    #[tokio::test]
+    async fn test_random_signer_is_never_reloaded() {
+        let signer = PragmaSigner::new(Some(signing_key(1)), false, Some(Duration::from_secs(1)));
+        signer.force_refresh();
+        signer
+            .refresh_if_needed(|| async { Some(signing_key(2)) })
+            .await;
+        assert_eq!(signer.current().unwrap().fingerprint, fingerprint(1));
+    }
+}
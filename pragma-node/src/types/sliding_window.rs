@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+use bigdecimal::BigDecimal;
+
+use crate::types::timestamp::UnixTimestamp;
+
+/// Mean of the prices received over a sliding window of time.
+/// The sum is updated incrementally as prices are pushed and age out, so
+/// the mean never has to be recomputed from scratch.
+#[derive(Debug, Clone)]
+pub struct SlidingWindowMean {
+    window_in_seconds: i64,
+    samples: VecDeque<(UnixTimestamp, BigDecimal)>,
+    sum: BigDecimal,
+}
+
+impl SlidingWindowMean {
+    pub fn new(window_in_seconds: u64) -> Self {
+        Self {
+            window_in_seconds: window_in_seconds as i64,
+            samples: VecDeque::new(),
+            sum: BigDecimal::from(0),
+        }
+    }
+
+    /// Adds the price received at `timestamp` and drops the prices that are
+    /// now out of the window. A price not more recent than the last one is
+    /// ignored, so a price is counted once however often it is pushed.
+    pub fn push(&mut self, timestamp: UnixTimestamp, price: BigDecimal) {
+        if self
+            .samples
+            .back()
+            .is_some_and(|(last_timestamp, _)| timestamp <= *last_timestamp)
+        {
+            return;
+        }
+        self.sum += &price;
+        self.samples.push_back((timestamp, price));
+        self.slide(timestamp);
+    }
+
+    /// Drops the prices older than the window ending at `now`.
+    pub fn slide(&mut self, now: UnixTimestamp) {
+        while let Some((timestamp, price)) = self.samples.front() {
+            if *timestamp > now - self.window_in_seconds {
+                break;
+            }
+            self.sum -= price;
+            self.samples.pop_front();
+        }
+    }
+
+    /// Returns the mean of the prices in the window, or None if empty.
+    pub fn mean(&self) -> Option<BigDecimal> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(&self.sum / BigDecimal::from(self.samples.len() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_mean_updates_as_the_window_slides() {
+        let mut rolling_mean = SlidingWindowMean::new(60);
+        assert!(rolling_mean.mean().is_none());
+
+        rolling_mean.push(1_000, BigDecimal::from(100));
+        rolling_mean.push(1_020, BigDecimal::from(110));
+        rolling_mean.push(1_040, BigDecimal::from(120));
+        assert_eq!(rolling_mean.mean(), Some(BigDecimal::from(110)));
+        assert_eq!(rolling_mean.samples.len(), 3);
+
+        // The price received at 1_000 is now out of the window.
+        rolling_mean.push(1_060, BigDecimal::from(150));
+        assert_eq!(rolling_mean.samples.len(), 3);
+        assert_eq!(
+            rolling_mean.mean(),
+            Some(BigDecimal::from(380) / BigDecimal::from(3))
+        );
+
+        rolling_mean.push(1_100, BigDecimal::from(200));
+        assert_eq!(rolling_mean.mean(), Some(BigDecimal::from(175)));
+
+        // A price already pushed is not counted twice.
+        rolling_mean.push(1_100, BigDecimal::from(200));
+        rolling_mean.push(1_090, BigDecimal::from(0));
+        assert_eq!(rolling_mean.samples.len(), 2);
+        assert_eq!(rolling_mean.mean(), Some(BigDecimal::from(175)));
+
+        // Without new prices, everything ages out.
+        rolling_mean.slide(1_200);
+        assert!(rolling_mean.samples.is_empty());
+        assert!(rolling_mean.mean().is_none());
+        assert_eq!(rolling_mean.sum, BigDecimal::from(0));
+    }
+}
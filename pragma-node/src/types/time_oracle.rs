@@ -0,0 +1,208 @@
+use std::future::Future;
+use std::time::Duration;
+
+use moka::future::Cache;
+use pragma_entities::error::InfraError;
+
+use crate::caches::get_or_fetch;
+use crate::constants::others::TIME_ORACLE_RETRY_INTERVAL_IN_SECONDS;
+
+/// Time used to sign the prices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigningTime {
+    pub timestamp: i64,
+    /// True if the time oracle is configured but could not be reached, in
+    /// which case the timestamp comes from the local clock.
+    pub is_local_fallback: bool,
+}
+
+/// Trusted source of the time embedded in the signed prices.
+/// The offset between the trusted time and the local clock is fetched from
+/// the oracle once per refresh interval. Without oracle, the local clock is
+/// trusted.
+/// Once the oracle failed, the local clock is used for a short while before
+/// trying again, so the signed prices don't wait for the oracle every time.
+#[derive(Debug, Clone)]
+pub struct TimeOracle {
+    url: Option<String>,
+    client: reqwest::Client,
+    offset: Cache<(), i64>,
+    unavailable: Cache<(), ()>,
+}
+
+impl TimeOracle {
+    pub fn new(url: Option<String>, refresh_interval: Duration, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("can't build the time oracle client");
+        Self {
+            url,
+            client,
+            offset: Cache::builder()
+                .max_capacity(1)
+                .time_to_live(refresh_interval)
+                .build(),
+            unavailable: Cache::builder()
+                .max_capacity(1)
+                .time_to_live(Duration::from_secs(TIME_ORACLE_RETRY_INTERVAL_IN_SECONDS))
+                .build(),
+        }
+    }
+
+    pub async fn now(&self) -> SigningTime {
+        let Some(url) = &self.url else {
+            return SigningTime {
+                timestamp: chrono::Utc::now().timestamp(),
+                is_local_fallback: false,
+            };
+        };
+        self.trusted_time(fetch_trusted_timestamp(&self.client, url))
+            .await
+    }
+
+    /// Returns the trusted time, computed from the cached offset if present or
+    /// fetched with `fetch`, once for all the concurrent callers. Falls back to
+    /// the local clock if the fetch fails.
+    async fn trusted_time<Fut>(&self, fetch: Fut) -> SigningTime
+    where
+        Fut: Future<Output = Result<i64, InfraError>>,
+    {
+        if self.unavailable.contains_key(&()) {
+            return local_fallback();
+        }
+        let offset = get_or_fetch(&self.offset, (), async {
+            let trusted_timestamp = fetch.await?;
+            Ok(trusted_timestamp - chrono::Utc::now().timestamp())
+        })
+        .await;
+        match offset {
+            Ok(offset) => SigningTime {
+                timestamp: chrono::Utc::now().timestamp() + offset,
+                is_local_fallback: false,
+            },
+            Err(_) => {
+                tracing::warn!("⚠ Time oracle unavailable, signing with the local time.");
+                self.unavailable.insert((), ()).await;
+                local_fallback()
+            }
+        }
+    }
+}
+
+fn local_fallback() -> SigningTime {
+    SigningTime {
+        timestamp: chrono::Utc::now().timestamp(),
+        is_local_fallback: true,
+    }
+}
+
+/// Fetches the unix timestamp in seconds from the time oracle.
+async fn fetch_trusted_timestamp(client: &reqwest::Client, url: &str) -> Result<i64, InfraError> {
+    let body = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| {
+            tracing::error!("Could not reach the time oracle {}: {}", url, e);
+            InfraError::InternalServerError
+        })?
+        .text()
+        .await
+        .map_err(|_| InfraError::InternalServerError)?;
+    parse_trusted_timestamp(&body).ok_or_else(|| {
+        tracing::error!("Unexpected time oracle response: {}", body);
+        InfraError::InternalServerError
+    })
+}
+
+/// Parses the unix timestamp in seconds returned by the time oracle, either
+/// as a bare number or in the `unixtime` field of an object.
+fn parse_trusted_timestamp(body: &str) -> Option<i64> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    match value {
+        serde_json::Value::Object(object) => object.get("unixtime")?.as_i64(),
+        value => value.as_i64(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRUSTED_TIMESTAMP: i64 = 1_718_000_000;
+
+    #[test]
+    fn test_parse_trusted_timestamp() {
+        assert_eq!(
+            parse_trusted_timestamp("1718000000"),
+            Some(TRUSTED_TIMESTAMP)
+        );
+        assert_eq!(
+            parse_trusted_timestamp(r#"{"unixtime": 1718000000, "timezone": "UTC"}"#),
+            Some(TRUSTED_TIMESTAMP)
+        );
+        assert_eq!(parse_trusted_timestamp(r#"{"time": 1718000000}"#), None);
+        assert_eq!(parse_trusted_timestamp("not a timestamp"), None);
+    }
+
+    /// Oracle that must not be called, as its result is cached.
+    async fn not_called() -> Result<i64, InfraError> {
+        panic!("the time oracle must not be called");
+    }
+
+    fn time_oracle() -> TimeOracle {
+        TimeOracle::new(
+            Some("http://time.oracle".to_string()),
+            Duration::from_secs(60),
+            Duration::from_secs(1),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_signing_time_comes_from_the_time_oracle() {
+        let time_oracle = time_oracle();
+        // Mocked oracle whose clock is far behind the local one.
+        let time = time_oracle
+            .trusted_time(async { Ok::<_, InfraError>(TRUSTED_TIMESTAMP) })
+            .await;
+        assert!(!time.is_local_fallback);
+        assert!((time.timestamp - TRUSTED_TIMESTAMP).abs() <= 1);
+
+        // The offset is then applied to the local clock, which may tick meanwhile.
+        let time = time_oracle.trusted_time(not_called()).await;
+        assert!(!time.is_local_fallback);
+        assert!((time.timestamp - TRUSTED_TIMESTAMP).abs() <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_oracle_is_not_called_again_right_away() {
+        let time_oracle = time_oracle();
+        let time = time_oracle
+            .trusted_time(async { Err::<i64, _>(InfraError::InternalServerError) })
+            .await;
+        assert!(time.is_local_fallback);
+        assert!((time.timestamp - chrono::Utc::now().timestamp()).abs() <= 1);
+        assert!(time_oracle.offset.get(&()).await.is_none());
+
+        // The following prices are signed with the local clock without
+        // waiting for the oracle, until it is tried again.
+        let time = time_oracle.trusted_time(not_called()).await;
+        assert!(time.is_local_fallback);
+
+        time_oracle.unavailable.invalidate_all();
+        let time = time_oracle
+            .trusted_time(async { Ok::<_, InfraError>(TRUSTED_TIMESTAMP) })
+            .await;
+        assert!(!time.is_local_fallback);
+    }
+
+    #[tokio::test]
+    async fn test_local_clock_is_trusted_without_time_oracle() {
+        let time_oracle = TimeOracle::new(None, Duration::from_secs(60), Duration::from_secs(1));
+        let time = time_oracle.now().await;
+        assert!(!time.is_local_fallback);
+        assert!((time.timestamp - chrono::Utc::now().timestamp()).abs() <= 1);
+    }
+}
@@ -1,7 +1,9 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
 use pragma_entities::EntryError;
 use serde::{Deserialize, Deserializer};
 use std::ops::RangeInclusive;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 /// The number of seconds since the Unix epoch (00:00:00 UTC on 1 Jan 1970). The timestamp is
 /// always positive, but represented as a signed integer because that's the standard on Unix
@@ -53,3 +55,68 @@ impl<'de> Deserialize<'de> for TimestampRange {
         Ok(TimestampRange(start..=end))
     }
 }
+
+#[derive(Debug, Default, Deserialize, IntoParams, ToSchema)]
+pub struct TimezoneParams {
+    /// Optional IANA timezone name (e.g. `Europe/Paris`). If provided, each
+    /// point also contains its timestamp as a localized RFC3339 string.
+    pub tz: Option<String>,
+}
+
+impl TimezoneParams {
+    /// Returns the requested timezone, if any.
+    pub fn timezone(&self) -> Result<Option<Tz>, EntryError> {
+        self.tz.as_deref().map(parse_timezone).transpose()
+    }
+}
+
+/// Parses an IANA timezone name, e.g `America/New_York`.
+pub fn parse_timezone(name: &str) -> Result<Tz, EntryError> {
+    name.parse::<Tz>()
+        .map_err(|_| EntryError::InvalidTimezone(name.to_string()))
+}
+
+/// Formats the unix timestamp as a RFC3339 string in the provided timezone.
+pub fn to_localized_rfc3339(timestamp: UnixTimestamp, tz: &Tz) -> Option<String> {
+    DateTime::from_timestamp(timestamp, 0).map(|dt| dt.with_timezone(tz).to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Paris").unwrap(), Tz::Europe__Paris);
+        assert!(matches!(
+            parse_timezone("Mars/Olympus_Mons"),
+            Err(EntryError::InvalidTimezone(tz)) if tz == "Mars/Olympus_Mons"
+        ));
+    }
+
+    #[test]
+    fn test_localized_timestamps() {
+        // 2024-03-10T06:59:59Z, one second before the DST switch in New York.
+        let before_dst = 1710053999;
+        let new_york = parse_timezone("America/New_York").unwrap();
+        assert_eq!(
+            to_localized_rfc3339(before_dst, &new_york).unwrap(),
+            "2024-03-10T01:59:59-05:00"
+        );
+        assert_eq!(
+            to_localized_rfc3339(before_dst + 1, &new_york).unwrap(),
+            "2024-03-10T03:00:00-04:00"
+        );
+
+        let tokyo = parse_timezone("Asia/Tokyo").unwrap();
+        assert_eq!(
+            to_localized_rfc3339(before_dst, &tokyo).unwrap(),
+            "2024-03-10T15:59:59+09:00"
+        );
+        let utc = parse_timezone("UTC").unwrap();
+        assert_eq!(
+            to_localized_rfc3339(before_dst, &utc).unwrap(),
+            "2024-03-10T06:59:59+00:00"
+        );
+    }
+}
@@ -1,21 +1,24 @@
 use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
-use nonzero_ext::nonzero;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::fmt::Debug;
 use std::net::IpAddr;
+use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
-use crate::metrics::{Interaction, Status};
+use crate::config::config;
+use crate::metrics::{Direction, Interaction, Status};
 use crate::AppState;
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use futures_util::stream::{SplitSink, SplitStream};
-use futures_util::{SinkExt, StreamExt};
+use futures_util::{Sink, SinkExt, StreamExt};
 use thiserror::Error;
-use tokio::sync::{watch, Mutex};
-use tokio::time::{interval, Interval};
+use tokio::sync::{watch, Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::{interval, interval_at, Instant, Interval};
 use uuid::Uuid;
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -25,6 +28,9 @@ pub enum SubscriptionType {
     Subscribe,
     #[serde(rename = "unsubscribe")]
     Unsubscribe,
+    /// Query the current subscription state without changing it.
+    #[serde(rename = "status")]
+    Status,
 }
 
 #[derive(Debug, Error)]
@@ -37,6 +43,159 @@ pub enum WebSocketError {
     ChannelClose,
 }
 
+/// Global limit of the concurrent websocket connections of the node.
+#[derive(Debug, Clone, Default)]
+pub struct WsConnectionsLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+/// Permit held for the whole lifetime of a websocket connection.
+#[derive(Debug)]
+pub struct WsConnectionPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl WsConnectionsLimiter {
+    /// Creates the limiter, without limit if `max_connections` is None.
+    pub fn new(max_connections: Option<usize>) -> Self {
+        Self {
+            semaphore: max_connections.map(|max| Arc::new(Semaphore::new(max))),
+        }
+    }
+
+    /// Returns a permit for a new connection, or None if the node is
+    /// already at capacity.
+    pub fn try_acquire(&self) -> Option<WsConnectionPermit> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => Some(semaphore.clone().try_acquire_owned().ok()?),
+            None => None,
+        };
+        Some(WsConnectionPermit { _permit: permit })
+    }
+}
+
+/// Response of an upgrade request refused because the node is at capacity.
+pub fn too_many_connections_response(app_state: &AppState, endpoint_name: &str) -> Response {
+    app_state.metrics.ws_metrics.record_ws_interaction(
+        endpoint_name,
+        Interaction::NewConnection,
+        Status::Error,
+    );
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Service Unavailable: too many websocket connections",
+    )
+        .into_response()
+}
+
+/// Number of consecutive pings left unanswered after which the connection
+/// is considered dead.
+const MAX_UNANSWERED_PINGS: u32 = 2;
+
+/// Action to perform at each tick of the heartbeat.
+#[derive(Debug, PartialEq)]
+enum HeartbeatAction {
+    Ping,
+    Close,
+}
+
+/// Pings the client periodically so idle connections are kept alive, and
+/// detects the clients that stopped answering.
+struct Heartbeat {
+    interval: Interval,
+    unanswered_pings: u32,
+}
+
+impl Heartbeat {
+    fn new(period: Duration) -> Self {
+        Self {
+            // The client is already pinged by the handshake, so the first
+            // ping is only sent one period after the connection is opened.
+            interval: interval_at(Instant::now() + period, period),
+            unanswered_pings: 0,
+        }
+    }
+
+    async fn tick(&mut self) -> HeartbeatAction {
+        self.interval.tick().await;
+        if self.unanswered_pings >= MAX_UNANSWERED_PINGS {
+            return HeartbeatAction::Close;
+        }
+        self.unanswered_pings += 1;
+        HeartbeatAction::Ping
+    }
+
+    fn pong_received(&mut self) {
+        self.unanswered_pings = 0;
+    }
+}
+
+/// Paces the periodic updates of a client on the state of its send buffer.
+/// When enabled, the messages are buffered and flushed in the background
+/// instead of blocking until the client receives them. The updates are
+/// skipped while the buffer isn't drained, and the next update sent is
+/// flagged as coalesced.
+struct UpdatePacer {
+    enabled: bool,
+    /// True while buffered messages are not flushed to the client yet.
+    unflushed: bool,
+    /// True if updates were skipped since the last one sent.
+    skipped: bool,
+}
+
+impl UpdatePacer {
+    fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            unflushed: false,
+            skipped: false,
+        }
+    }
+
+    /// Called at each tick of the update interval.
+    /// Returns None if the update must be skipped, or whether it coalesces
+    /// the skipped ones.
+    fn next_update(&mut self) -> Option<bool> {
+        if !self.enabled {
+            return Some(false);
+        }
+        if self.unflushed {
+            self.skipped = true;
+            return None;
+        }
+        Some(std::mem::take(&mut self.skipped))
+    }
+
+    fn buffered(&mut self) {
+        self.unflushed = true;
+    }
+
+    fn flushed(&mut self) {
+        self.unflushed = false;
+    }
+
+    fn has_unflushed_messages(&self) -> bool {
+        self.unflushed
+    }
+}
+
+/// Answers the close frame of a client with a normal closure, then closes the sink.
+async fn close_normally<S>(sender: &mut S) -> Result<(), WebSocketError>
+where
+    S: Sink<Message> + Unpin,
+{
+    let close_frame = CloseFrame {
+        code: close_code::NORMAL,
+        reason: "Connection closed by the client.".into(),
+    };
+    // The close frame may already have been answered by the websocket layer.
+    let _ = sender.send(Message::Close(Some(close_frame))).await;
+    sender
+        .close()
+        .await
+        .map_err(|_| WebSocketError::ChannelClose)
+}
+
 /// Subscriber is an actor that handles a single websocket connection.
 /// It listens to the store for updates and sends them to the client.
 #[allow(dead_code)]
@@ -51,13 +210,52 @@ pub struct Subscriber<ChannelState> {
     pub receiver: SplitStream<WebSocket>,
     pub update_interval: Interval,
     pub notify_receiver: Receiver<Message>,
-    pub rate_limiter: DefaultKeyedRateLimiter<IpAddr>,
+    /// Rate limiter of the node, shared by all the connections.
+    pub rate_limiter: Arc<BytesRateLimiter>,
     pub exit: (watch::Sender<bool>, watch::Receiver<bool>),
+    /// True if the current periodic update replaces the updates the client
+    /// was too slow to receive.
+    pub coalesced: bool,
+    heartbeat: Heartbeat,
+    update_pacer: UpdatePacer,
+    /// Span of the connection, entered while listening.
+    span: tracing::Span,
 }
 
-/// The maximum number of bytes that can be sent per second per IP address.
-/// If the limit is exceeded, the connection is closed.
-const BYTES_LIMIT_PER_IP_PER_SECOND: u32 = 256 * 1024; // 256 KiB
+/// Rate limiter of the bytes sent by each IP address.
+pub type BytesRateLimiter = DefaultKeyedRateLimiter<IpAddr>;
+
+/// Builds the rate limiter of the bytes sent per second by each IP address.
+pub fn bytes_rate_limiter(bytes_limit_per_ip_per_second: NonZeroU32) -> BytesRateLimiter {
+    RateLimiter::dashmap(Quota::per_second(bytes_limit_per_ip_per_second))
+}
+
+/// Counts the bytes of the message against the quota of the IP address and
+/// returns true if the quota is exceeded.
+fn exceeds_bytes_quota(
+    rate_limiter: &DefaultKeyedRateLimiter<IpAddr>,
+    ip_address: &IpAddr,
+    msg: &Message,
+) -> bool {
+    let msg_len = match msg {
+        Message::Text(text) => text.len(),
+        Message::Binary(payload) | Message::Ping(payload) | Message::Pong(payload) => payload.len(),
+        Message::Close(_) => 0,
+    };
+    let Ok(msg_len) = u32::try_from(msg_len) else {
+        return true;
+    };
+    let Some(msg_len) = NonZeroU32::new(msg_len) else {
+        return false;
+    };
+    match rate_limiter.check_key_n(ip_address, msg_len) {
+        Ok(Ok(())) => false,
+        // The quota is exhausted until the rate limiter replenishes it.
+        Ok(Err(_not_until)) => true,
+        // The message alone is bigger than the quota.
+        Err(_insufficient_capacity) => true,
+    }
+}
 
 pub trait ChannelHandler<ChannelState, CM, Err> {
     /// Called after a message is received from the client.
@@ -80,6 +278,8 @@ where
     ChannelState: Default + Debug,
 {
     /// Create a new subscriber tied to a websocket connection.
+    /// The bytes sent by the IP address are limited over all its connections
+    /// by the rate limiter of the node.
     pub async fn new(
         endpoint_name: String,
         socket: WebSocket,
@@ -91,6 +291,8 @@ where
         let id = Uuid::new_v4();
         let (sender, receiver) = socket.split();
         let (notify_sender, notify_receiver) = mpsc::channel::<Message>(32);
+        let update_interval = Duration::from_millis(update_interval_in_ms);
+        let rate_limiter = app_state.ws_bytes_rate_limiter.clone();
 
         let mut subscriber = Subscriber {
             id,
@@ -101,12 +303,14 @@ where
             app_state,
             sender,
             receiver,
-            update_interval: interval(Duration::from_millis(update_interval_in_ms)),
+            update_interval: interval(update_interval),
             notify_receiver,
-            rate_limiter: RateLimiter::dashmap(Quota::per_second(nonzero!(
-                BYTES_LIMIT_PER_IP_PER_SECOND
-            ))),
+            rate_limiter,
             exit: watch::channel(false),
+            coalesced: false,
+            heartbeat: Heartbeat::new(config().await.ws_heartbeat_interval()),
+            update_pacer: UpdatePacer::new(config().await.ws_coalesce_updates()),
+            span: tracing::Span::none(),
         };
         subscriber.assert_is_healthy().await?;
         // Retain the recent rate limit data for the IP addresses to
@@ -128,17 +332,36 @@ where
 
     /// Listen to messages from the client and the server.
     /// The handler is responsible for processing the messages and updating the state.
+    /// The handlers can record the subscribed pairs on its span with
+    /// [`Subscriber::record_subscribed_pairs`].
+    #[tracing::instrument(
+        skip_all,
+        fields(
+            subscriber_id = %self.id,
+            ip = %self.ip_address,
+            spot_pairs = tracing::field::Empty,
+            perp_pairs = tracing::field::Empty,
+        )
+    )]
     pub async fn listen<H, CM, Err>(&mut self, mut handler: H) -> Result<(), Err>
     where
         H: ChannelHandler<ChannelState, CM, Err>,
         CM: for<'a> Deserialize<'a>,
     {
+        self.span = tracing::Span::current();
         loop {
             tokio::select! {
                 // Messages from the client
                 maybe_client_msg = self.receiver.next() => {
                     match maybe_client_msg {
                         Some(Ok(client_msg)) => {
+                            if exceeds_bytes_quota(&self.rate_limiter, &self.ip_address, &client_msg) {
+                                self.close_rate_limited().await;
+                                return Ok(());
+                            }
+                            if let Message::Pong(_) = client_msg {
+                                self.heartbeat.pong_received();
+                            }
                             handler = self.decode_and_handle(handler, client_msg).await?;
                         }
                         Some(Err(_)) => {
@@ -149,6 +372,10 @@ where
                 },
                 // Periodic updates
                 _ = self.update_interval.tick() => {
+                    let Some(coalesced) = self.update_pacer.next_update() else {
+                        continue;
+                    };
+                    self.coalesced = coalesced;
                     let status = handler.periodic_interval(self).await;
                     match status {
                         Ok(_) => {
@@ -161,6 +388,28 @@ where
                         }
                     }
                 },
+                // Heartbeat
+                action = self.heartbeat.tick() => {
+                    match action {
+                        HeartbeatAction::Ping => {
+                            let _ = self.sender.send(Message::Ping(vec![])).await;
+                        }
+                        HeartbeatAction::Close => {
+                            tracing::info!(
+                                subscriber_id = %self.id,
+                                "Pings left unanswered. Closing connection.",
+                            );
+                            self.sender.close().await.ok();
+                            self.closed = true;
+                            self.record_metric(Interaction::CloseConnection, Status::Success);
+                            return Ok(());
+                        }
+                    }
+                },
+                // Buffered messages drained to the client
+                _ = self.sender.flush(), if self.update_pacer.has_unflushed_messages() => {
+                    self.update_pacer.flushed();
+                },
                 // Messages from the server to the client
                 maybe_server_msg = self.notify_receiver.recv() => {
                     if let Some(server_msg) = maybe_server_msg {
@@ -170,8 +419,10 @@ where
                 // Exit signal
                 _ = self.exit.1.changed() => {
                     if *self.exit.1.borrow() {
-                        self.sender.close().await.ok();
-                        self.closed = true;
+                        if !self.closed {
+                            self.sender.close().await.ok();
+                            self.closed = true;
+                        }
                         self.record_metric(Interaction::CloseConnection, Status::Success);
                         return Ok(());
                     }
@@ -180,6 +431,31 @@ where
         }
     }
 
+    /// Records the number of subscribed pairs on the span of the connection,
+    /// so they are attached to all its logs.
+    pub fn record_subscribed_pairs(&self, spot_pairs: usize, perp_pairs: usize) {
+        self.span.record("spot_pairs", spot_pairs);
+        self.span.record("perp_pairs", perp_pairs);
+    }
+
+    /// Close the connection of a client that exceeded its bytes quota.
+    async fn close_rate_limited(&mut self) {
+        tracing::warn!(
+            subscriber_id = %self.id,
+            ip = %self.ip_address,
+            "Inbound rate limit exceeded. Closing connection.",
+        );
+        self.record_metric(Interaction::RateLimit, Status::Error);
+        let close_frame = CloseFrame {
+            code: close_code::POLICY,
+            reason: "Rate limit exceeded.".into(),
+        };
+        let _ = self.sender.send(Message::Close(Some(close_frame))).await;
+        self.sender.close().await.ok();
+        self.closed = true;
+        self.record_metric(Interaction::CloseConnection, Status::Success);
+    }
+
     /// Called after a message is received from the client.
     /// The handler should process the message and update the state.
     /// If the handler returns an error, the connection will be closed.
@@ -224,17 +500,14 @@ where
     ) -> Result<Option<T>, WebSocketError> {
         match msg {
             Message::Close(_) => {
-                if self.exit.0.send(true).is_ok() {
-                    self.sender
-                        .close()
-                        .await
-                        .map_err(|_| WebSocketError::ChannelClose)?;
-                    self.closed = true;
-                } else {
+                close_normally(&mut self.sender).await?;
+                self.closed = true;
+                if self.exit.0.send(true).is_err() {
                     self.record_metric(Interaction::CloseConnection, Status::Error);
                 }
             }
             Message::Text(text) => {
+                self.record_message_size(Direction::Inbound, text.len());
                 let msg = serde_json::from_str::<T>(&text);
                 if let Ok(msg) = msg {
                     return Ok(Some(msg));
@@ -244,6 +517,7 @@ where
                 }
             }
             Message::Binary(payload) => {
+                self.record_message_size(Direction::Inbound, payload.len());
                 let maybe_msg = serde_json::from_slice::<T>(&payload);
                 if let Ok(msg) = maybe_msg {
                     return Ok(Some(msg));
@@ -260,13 +534,32 @@ where
 
     /// Send a message to the client.
     pub async fn send_msg(&mut self, msg: String) -> Result<(), axum::Error> {
-        self.sender.send(Message::Text(msg)).await
+        self.record_message_size(Direction::Outbound, msg.len());
+        self.send_paced(Message::Text(msg)).await
+    }
+
+    /// Send a binary message to the client.
+    pub async fn send_binary(&mut self, payload: Vec<u8>) -> Result<(), axum::Error> {
+        self.record_message_size(Direction::Outbound, payload.len());
+        self.send_paced(Message::Binary(payload)).await
+    }
+
+    /// Sends the message, or only buffers it when the updates are paced so
+    /// a slow client doesn't block the subscriber.
+    async fn send_paced(&mut self, msg: Message) -> Result<(), axum::Error> {
+        if !self.update_pacer.enabled {
+            return self.sender.send(msg).await;
+        }
+        self.sender.feed(msg).await?;
+        self.update_pacer.buffered();
+        Ok(())
     }
 
     /// Send an error message to the client without closing the channel.
     pub async fn send_err(&mut self, err: &str) {
-        let err = json!({"error": err});
-        let _ = self.sender.send(Message::Text(err.to_string())).await;
+        let err = json!({"error": err}).to_string();
+        self.record_message_size(Direction::Outbound, err.len());
+        let _ = self.sender.send(Message::Text(err)).await;
     }
 
     /// Records a web socket metric.
@@ -277,4 +570,336 @@ where
             status,
         );
     }
+
+    /// Records the size in bytes of a message sent or received.
+    pub fn record_message_size(&self, direction: Direction, bytes: usize) {
+        self.app_state.metrics.ws_metrics.record_ws_message_size(
+            &self.endpoint_name,
+            direction,
+            bytes,
+        );
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use axum::extract::ws::WebSocketUpgrade;
+    use axum::routing::get;
+    use axum::Router;
+    use deadpool_diesel::postgres::{Manager, Pool};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+    use super::*;
+    use crate::caches::CacheRegistry;
+    use crate::metrics::MetricsRegistry;
+    use crate::types::circuit_breaker::CircuitBreaker;
+    use crate::types::readiness::Readiness;
+    use crate::types::signer::PragmaSigner;
+    use crate::types::time_oracle::TimeOracle;
+
+    pub(crate) type ClientSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// State of a node whose databases can't be reached.
+    pub(crate) fn app_state() -> Arc<AppState> {
+        let unreachable_pool = || {
+            let manager = Manager::new(
+                "postgres://localhost:1/pragma",
+                deadpool_diesel::Runtime::Tokio1,
+            );
+            Pool::builder(manager).build().unwrap()
+        };
+        Arc::new(AppState {
+            offchain_pool: unreachable_pool(),
+            onchain_pool: unreachable_pool(),
+            redis_client: None,
+            caches: Arc::new(CacheRegistry::new(None, Duration::from_secs(60))),
+            pragma_signer: PragmaSigner::new(None, false, None),
+            metrics: MetricsRegistry::new(),
+            readiness: Readiness::new(true),
+            ws_connections: WsConnectionsLimiter::new(None),
+            ws_bytes_rate_limiter: Arc::new(bytes_rate_limiter(nonzero_ext::nonzero!(
+                256_u32 * 1024
+            ))),
+            onchain_circuit_breaker: CircuitBreaker::new(None, Duration::from_secs(60)),
+            time_oracle: TimeOracle::new(None, Duration::from_secs(60), Duration::from_secs(1)),
+        })
+    }
+
+    /// Returns the server side of a websocket connection and its client side.
+    pub(crate) async fn connected_websocket() -> (WebSocket, ClientSocket) {
+        let (socket_sender, mut socket_receiver) = mpsc::channel(1);
+        let app = Router::new().route(
+            "/",
+            get(move |ws: WebSocketUpgrade| {
+                let socket_sender = socket_sender.clone();
+                async move {
+                    ws.on_upgrade(move |socket| async move {
+                        let _ = socket_sender.send(socket).await;
+                    })
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (client, _) = tokio_tungstenite::connect_async(format!("ws://{address}/"))
+            .await
+            .unwrap();
+        let socket = socket_receiver.recv().await.unwrap();
+        (socket, client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use nonzero_ext::nonzero;
+
+    use super::*;
+
+    #[test]
+    fn test_connections_above_the_global_limit_are_refused() {
+        let limiter = WsConnectionsLimiter::new(Some(2));
+
+        let first = limiter.try_acquire();
+        let second = limiter.try_acquire();
+        assert!(first.is_some());
+        assert!(second.is_some());
+        assert!(limiter.try_acquire().is_none());
+
+        // A closed connection frees its slot.
+        drop(first);
+        assert!(limiter.try_acquire().is_some());
+
+        let unlimited = WsConnectionsLimiter::new(None);
+        let permits: Vec<_> = (0..1000).map(|_| unlimited.try_acquire()).collect();
+        assert!(permits.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn test_bytes_rate_limiter_applies_the_configured_quota() {
+        let ip_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let rate_limiter = bytes_rate_limiter(nonzero!(1024_u32));
+
+        assert!(rate_limiter
+            .check_key_n(&ip_address, nonzero!(1000_u32))
+            .unwrap()
+            .is_ok());
+        // The quota of the second is exhausted.
+        assert!(rate_limiter
+            .check_key_n(&ip_address, nonzero!(100_u32))
+            .unwrap()
+            .is_err());
+        // A single message bigger than the quota can never be accepted.
+        assert!(rate_limiter
+            .check_key_n(&ip_address, nonzero!(2048_u32))
+            .is_err());
+
+        // The quota is tracked per IP address.
+        let other_ip_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        assert!(rate_limiter
+            .check_key_n(&other_ip_address, nonzero!(1000_u32))
+            .unwrap()
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_connections_of_an_ip_address_share_its_quota() {
+        let app_state = testing::app_state();
+        let ip_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut subscribers = vec![];
+        for _ in 0..2 {
+            let (socket, client) = testing::connected_websocket().await;
+            let (subscriber, _) = Subscriber::<()>::new(
+                "test".into(),
+                socket,
+                ip_address,
+                app_state.clone(),
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+            subscribers.push((subscriber, client));
+        }
+        let large_frame = Message::Text("a".repeat(64 * 1024));
+
+        let accepted_frames = (0..10)
+            .take_while(|_| {
+                !exceeds_bytes_quota(&subscribers[0].0.rate_limiter, &ip_address, &large_frame)
+            })
+            .count();
+        assert_eq!(accepted_frames, 4);
+
+        // Opening another connection doesn't give a new quota.
+        assert!(exceeds_bytes_quota(
+            &subscribers[1].0.rate_limiter,
+            &ip_address,
+            &large_frame
+        ));
+    }
+
+    #[test]
+    fn test_flood_of_large_text_frames_exceeds_the_quota() {
+        let ip_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let rate_limiter = bytes_rate_limiter(nonzero!(256_u32 * 1024));
+        let large_frame = Message::Text("a".repeat(64 * 1024));
+
+        let accepted_frames = (0..10)
+            .take_while(|_| !exceeds_bytes_quota(&rate_limiter, &ip_address, &large_frame))
+            .count();
+        assert_eq!(accepted_frames, 4);
+
+        // Other clients are not impacted by the flood.
+        let other_ip_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        assert!(!exceeds_bytes_quota(
+            &rate_limiter,
+            &other_ip_address,
+            &large_frame
+        ));
+
+        // Frames bigger than the quota are always rejected, empty ones never.
+        let huge_frame = Message::Binary(vec![0; 512 * 1024]);
+        assert!(exceeds_bytes_quota(
+            &rate_limiter,
+            &other_ip_address,
+            &huge_frame
+        ));
+        assert!(!exceeds_bytes_quota(
+            &rate_limiter,
+            &ip_address,
+            &Message::Close(None)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_close_is_answered_with_a_normal_close_frame() {
+        let mut sent: Vec<Message> = vec![];
+
+        close_normally(&mut sent).await.unwrap();
+
+        assert_eq!(
+            sent,
+            vec![Message::Close(Some(CloseFrame {
+                code: close_code::NORMAL,
+                reason: "Connection closed by the client.".into(),
+            }))]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_channel_emits_pings_until_unanswered() {
+        let period = Duration::from_millis(10);
+        let mut heartbeat = Heartbeat::new(period);
+
+        // Without any client traffic, pings are still sent periodically.
+        let started_at = Instant::now();
+        assert_eq!(heartbeat.tick().await, HeartbeatAction::Ping);
+        assert_eq!(started_at.elapsed(), period);
+        heartbeat.pong_received();
+        assert_eq!(heartbeat.tick().await, HeartbeatAction::Ping);
+        heartbeat.pong_received();
+
+        // Two consecutive pings left unanswered close the channel.
+        assert_eq!(heartbeat.tick().await, HeartbeatAction::Ping);
+        assert_eq!(heartbeat.tick().await, HeartbeatAction::Ping);
+        assert_eq!(heartbeat.tick().await, HeartbeatAction::Close);
+    }
+
+    /// Sends a large update at each tick, recording when it was sent and
+    /// whether it replaced skipped updates.
+    struct LargeUpdatesHandler {
+        started_at: Instant,
+        updates: Arc<std::sync::Mutex<Vec<(Duration, bool)>>>,
+    }
+
+    impl ChannelHandler<(), serde_json::Value, axum::Error> for LargeUpdatesHandler {
+        async fn handle_client_msg(
+            &mut self,
+            _subscriber: &mut Subscriber<()>,
+            _message: serde_json::Value,
+        ) -> Result<(), axum::Error> {
+            Ok(())
+        }
+
+        async fn periodic_interval(
+            &mut self,
+            subscriber: &mut Subscriber<()>,
+        ) -> Result<(), axum::Error> {
+            self.updates
+                .lock()
+                .unwrap()
+                .push((self.started_at.elapsed(), subscriber.coalesced));
+            subscriber.send_msg("a".repeat(2 * 1024 * 1024)).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_client_receives_coalesced_updates() {
+        let (socket, mut client) = testing::connected_websocket().await;
+        let (mut subscriber, _) = Subscriber::<()>::new(
+            "test".into(),
+            socket,
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            testing::app_state(),
+            None,
+            10,
+        )
+        .await
+        .unwrap();
+        subscriber.update_pacer = UpdatePacer::new(true);
+        let updates = Arc::new(std::sync::Mutex::new(vec![]));
+        let handler = LargeUpdatesHandler {
+            started_at: Instant::now(),
+            updates: updates.clone(),
+        };
+
+        // The client doesn't read anything during the first 300ms.
+        let client = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            let mut received = 0;
+            while let Some(Ok(msg)) = client.next().await {
+                if msg.is_text() {
+                    received += 1;
+                }
+            }
+            received
+        });
+        let _ = tokio::time::timeout(Duration::from_millis(500), subscriber.listen(handler)).await;
+        subscriber.sender.close().await.unwrap();
+        let received = client.await.unwrap();
+
+        let updates = updates.lock().unwrap().clone();
+        assert!(!updates[0].1);
+        // Once the socket buffers are full, the updates are skipped instead
+        // of piling up behind the slow client.
+        let stalled = Duration::from_millis(200)..Duration::from_millis(300);
+        assert!(!updates.iter().any(|(at, _)| stalled.contains(at)));
+        // The first update received after the stall replaces the skipped ones.
+        let &(_, resumed_coalesced) = updates.iter().find(|(at, _)| *at >= stalled.end).unwrap();
+        assert!(resumed_coalesced);
+        // Every update sent was delivered.
+        assert_eq!(received, updates.len());
+    }
+
+    #[test]
+    fn test_updates_are_skipped_while_the_buffer_is_not_flushed() {
+        let mut pacer = UpdatePacer::new(true);
+        assert_eq!(pacer.next_update(), Some(false));
+        pacer.buffered();
+
+        assert_eq!(pacer.next_update(), None);
+        assert_eq!(pacer.next_update(), None);
+        pacer.flushed();
+        assert_eq!(pacer.next_update(), Some(true));
+        assert_eq!(pacer.next_update(), Some(false));
+
+        // Disabled, every update is sent.
+        let mut pacer = UpdatePacer::new(false);
+        pacer.buffered();
+        assert_eq!(pacer.next_update(), Some(false));
+    }
 }
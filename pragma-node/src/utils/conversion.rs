@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use bigdecimal::num_bigint::Sign;
 use bigdecimal::BigDecimal;
 
 use pragma_entities::InfraError;
@@ -50,6 +51,40 @@ pub fn format_bigdecimal_price(price: BigDecimal, decimals: u32) -> String {
     formatted_price
 }
 
+/// Formats the price, adjusted by its decimals, in scientific notation with the
+/// provided number of significant digits, e.g `1.23457e15` or `1e-12`.
+/// Unlike the fixed decimals formatting, the precision doesn't depend on the
+/// magnitude of the price.
+pub fn format_bigdecimal_price_scientific(
+    price: &BigDecimal,
+    decimals: u32,
+    significant_digits: u32,
+) -> String {
+    let (digits, scale) = price.as_bigint_and_exponent();
+    let adjusted_price = BigDecimal::new(digits, scale + i64::from(decimals));
+    let (digits, scale) = adjusted_price
+        .with_prec(u64::from(significant_digits.max(1)))
+        .as_bigint_and_exponent();
+
+    let sign = if digits.sign() == Sign::Minus {
+        "-"
+    } else {
+        ""
+    };
+    let digits = digits.magnitude().to_string();
+    if digits == "0" {
+        return "0e0".to_string();
+    }
+    let exponent = digits.len() as i64 - 1 - scale;
+    let digits = digits.trim_end_matches('0');
+    let (first_digit, other_digits) = digits.split_at(1);
+    if other_digits.is_empty() {
+        format!("{sign}{first_digit}e{exponent}")
+    } else {
+        format!("{sign}{first_digit}.{other_digits}e{exponent}")
+    }
+}
+
 pub fn felt_from_decimal<'de, D>(deserializer: D) -> Result<Vec<Felt>, D::Error>
 where
     D: Deserializer<'de>,
@@ -57,3 +92,37 @@ where
     let s: Vec<String> = Vec::deserialize(deserializer)?;
     Ok(s.iter().map(|s| Felt::from_dec_str(s).unwrap()).collect())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_tiny_price_in_scientific_notation() {
+        // 1.5e-12 with 18 decimals
+        let price = BigDecimal::from(1_500_000);
+        assert_eq!(format_bigdecimal_price_scientific(&price, 18, 6), "1.5e-12");
+        assert_eq!(
+            format_bigdecimal_price_scientific(&BigDecimal::from(1), 18, 6),
+            "1e-18"
+        );
+    }
+
+    #[test]
+    fn test_format_huge_price_in_scientific_notation() {
+        let price = BigDecimal::from_str("123456789012345678901234").unwrap();
+        assert_eq!(
+            format_bigdecimal_price_scientific(&price, 8, 6),
+            "1.23457e15"
+        );
+        assert_eq!(format_bigdecimal_price_scientific(&price, 8, 2), "1.2e15");
+        assert_eq!(
+            format_bigdecimal_price_scientific(&-price, 8, 3),
+            "-1.23e15"
+        );
+        assert_eq!(
+            format_bigdecimal_price_scientific(&BigDecimal::from(0), 8, 6),
+            "0e0"
+        );
+    }
+}
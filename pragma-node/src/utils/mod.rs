@@ -1,19 +1,26 @@
-pub use aws::PragmaSignerBuilder;
+pub use aws::{build_pragma_signer_from_aws, PragmaSignerBuilder};
 pub use conversion::{
-    convert_via_quote, felt_from_decimal, format_bigdecimal_price, normalize_to_decimals,
+    convert_via_quote, felt_from_decimal, format_bigdecimal_price,
+    format_bigdecimal_price_scientific, normalize_to_decimals,
 };
 pub use custom_extractors::path_extractor::PathExtractor;
-pub use signing::starkex::StarkexPrice;
+pub use signing::starkex::{StarkexNoData, StarkexPrice};
 pub use signing::typed_data::TypedData;
-pub use signing::{assert_request_signature_is_valid, sign_data, typed_data};
+pub use signing::{
+    assert_request_signature_is_valid, get_pending_signature, sign_data, sign_data_in_batch,
+    typed_data, Signable, VerifiedSignature,
+};
 
 use bigdecimal::num_bigint::ToBigInt;
 use bigdecimal::{BigDecimal, ToPrimitive};
 use chrono::NaiveDateTime;
 use deadpool_diesel::postgres::Pool;
 use pragma_common::types::Network;
-use pragma_entities::{Entry, FutureEntry};
+use pragma_entities::{
+    adapt_infra_error, Entry, EntryError, FutureEntry, InfraError, VolatilityError,
+};
 use std::collections::HashMap;
+use std::time::Instant;
 
 use crate::infra::repositories::{
     entry_repository::MedianEntry, onchain_repository::entry::get_existing_pairs,
@@ -29,10 +36,13 @@ const ONE_YEAR_IN_SECONDS: f64 = 3153600_f64;
 /// Converts two currencies pairs to a new routed pair id.
 ///
 /// e.g "btc/usd" and "eth/usd" to "btc/eth"
-pub(crate) fn currency_pairs_to_routed_pair_id(base_pair: &str, quote_pair: &str) -> String {
-    let (base, _) = pair_id_to_currency_pair(base_pair);
-    let (quote, _) = pair_id_to_currency_pair(quote_pair);
-    format!("{}/{}", base.to_uppercase(), quote.to_uppercase())
+pub(crate) fn currency_pairs_to_routed_pair_id(
+    base_pair: &str,
+    quote_pair: &str,
+) -> Result<String, EntryError> {
+    let (base, _) = pair_id_to_currency_pair(base_pair)?;
+    let (quote, _) = pair_id_to_currency_pair(quote_pair)?;
+    Ok(format!("{}/{}", base.to_uppercase(), quote.to_uppercase()))
 }
 
 /// Converts a currency pair to a pair id.
@@ -42,12 +52,38 @@ pub(crate) fn currency_pair_to_pair_id(base: &str, quote: &str) -> String {
     format!("{}/{}", base.to_uppercase(), quote.to_uppercase())
 }
 
+/// Asserts that the base and the quote of a pair are not the same currency.
+///
+/// e.g "usd" and "USD" are rejected
+pub(crate) fn assert_currencies_are_distinct(base: &str, quote: &str) -> Result<(), EntryError> {
+    if base.eq_ignore_ascii_case(quote) {
+        return Err(EntryError::IdenticalCurrencies(currency_pair_to_pair_id(
+            base, quote,
+        )));
+    }
+    Ok(())
+}
+
 /// Converts a pair_id to a currency pair.
 ///
 /// e.g "BTC/USD" to ("BTC", "USD")
-pub(crate) fn pair_id_to_currency_pair(pair_id: &str) -> (String, String) {
-    let parts: Vec<&str> = pair_id.split('/').collect();
-    (parts[0].to_string(), parts[1].to_string())
+/// Returns an error if the pair_id isn't made of exactly two non-empty currencies.
+pub(crate) fn pair_id_to_currency_pair(pair_id: &str) -> Result<(String, String), EntryError> {
+    match pair_id.split('/').collect::<Vec<&str>>()[..] {
+        [base, quote] if !base.is_empty() && !quote.is_empty() => {
+            Ok((base.to_string(), quote.to_string()))
+        }
+        _ => Err(EntryError::InvalidPairId(pair_id.to_string())),
+    }
+}
+
+/// Returns true if the quote of the pair is USD.
+///
+/// e.g "BTC/USD" but not "BTC/USDT"
+pub(crate) fn is_usd_quoted(pair_id: &str) -> bool {
+    pair_id
+        .split_once('/')
+        .is_some_and(|(_, quote)| quote == "USD")
 }
 
 /// From a map of currencies and their decimals, returns the number of decimals for a given pair.
@@ -55,8 +91,8 @@ pub(crate) fn pair_id_to_currency_pair(pair_id: &str) -> (String, String) {
 pub(crate) fn get_decimals_for_pair(
     currencies: &HashMap<String, BigDecimal>,
     pair_id: &str,
-) -> u32 {
-    let (base, quote) = pair_id_to_currency_pair(pair_id);
+) -> Result<u32, EntryError> {
+    let (base, quote) = pair_id_to_currency_pair(pair_id)?;
     let base_decimals = match currencies.get(&base) {
         Some(decimals) => decimals.to_u32().unwrap_or_default(),
         None => 8,
@@ -65,7 +101,7 @@ pub(crate) fn get_decimals_for_pair(
         Some(decimals) => decimals.to_u32().unwrap_or_default(),
         None => 8,
     };
-    std::cmp::min(base_decimals, quote_decimals)
+    Ok(std::cmp::min(base_decimals, quote_decimals))
 }
 
 /// Returns the mid price between two prices.
@@ -79,7 +115,6 @@ pub fn get_mid_price(low: &BigDecimal, high: &BigDecimal) -> BigDecimal {
 /// The median is computed as the middle value of a sorted list of values.
 /// If the list has an even number of values, the median is computed as the average of the two middle values.
 /// If the list is empty, None is returned.
-#[allow(dead_code)]
 pub(crate) fn compute_median_price_and_time(
     entries: &mut [MedianEntry],
 ) -> Option<(BigDecimal, NaiveDateTime)> {
@@ -101,6 +136,27 @@ pub(crate) fn compute_median_price_and_time(
     Some((median_price, latest_time))
 }
 
+/// Computes the mean price and time from a list of entries.
+/// The mean price is computed as the arithmetic mean of the median prices of each entry.
+/// The time is the latest time of the entries.
+/// If the list is empty, None is returned.
+pub(crate) fn compute_mean_price_and_time(
+    entries: &[MedianEntry],
+) -> Option<(BigDecimal, NaiveDateTime)> {
+    let latest_time = entries.iter().map(|entry| entry.time).max()?;
+
+    let sum: BigDecimal = entries.iter().map(|entry| &entry.median_price).sum();
+    let mean_price = sum / BigDecimal::from(entries.len() as u64);
+
+    Some((mean_price, latest_time))
+}
+
+/// Returns the milliseconds elapsed since `started_at` if the timing was
+/// requested by the client.
+pub(crate) fn computation_time_ms(requested: bool, started_at: Instant) -> Option<f64> {
+    requested.then(|| started_at.elapsed().as_secs_f64() * 1000.0)
+}
+
 /// Given a pair and a network, returns if it exists in the
 /// onchain database.
 pub(crate) async fn is_onchain_existing_pair(pool: &Pool, pair: &String, network: Network) -> bool {
@@ -115,16 +171,23 @@ pub(crate) async fn is_onchain_existing_pair(pool: &Pool, pair: &String, network
 /// The volatility is computed as the annualized standard deviation of the log returns.
 /// The log returns are computed as the natural logarithm of the ratio between two consecutive median prices.
 /// The annualized standard deviation is computed as the square root of the variance multiplied by 10^8.
-pub(crate) fn compute_volatility(entries: &[MedianEntry]) -> f64 {
-    if entries.len() < 2 {
-        return 0.0;
+/// Entries with a non positive price are rejected, as their log return is undefined.
+pub(crate) fn compute_volatility(entries: &[MedianEntry]) -> Result<f64, EntryError> {
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.median_price <= BigDecimal::from(0))
+    {
+        return Err(EntryError::VolatilityError(
+            VolatilityError::NonPositivePrice(format!(
+                "{} at {}",
+                entry.median_price,
+                entry.time.and_utc().timestamp()
+            )),
+        ));
     }
     let mut values = Vec::new();
     for i in 1..entries.len() {
-        if entries[i].median_price.to_f64().unwrap_or(0.0) > 0.0
-            && entries[i - 1].median_price.to_f64().unwrap() > 0.0
-            && (entries[i].time - entries[i - 1].time).num_seconds() > 0
-        {
+        if (entries[i].time - entries[i - 1].time).num_seconds() > 0 {
             let log_return = (entries[i].median_price.to_f64().unwrap()
                 / entries[i - 1].median_price.to_f64().unwrap())
             .ln()
@@ -140,8 +203,11 @@ pub(crate) fn compute_volatility(entries: &[MedianEntry]) -> f64 {
         }
     }
 
+    if values.is_empty() {
+        return Ok(0.0);
+    }
     let variance: f64 = values.iter().map(|v| v.0 / v.1).sum::<f64>() / values.len() as f64;
-    variance.sqrt() * 10_f64.powi(8)
+    Ok(variance.sqrt() * 10_f64.powi(8))
 }
 
 /// Converts a big decimal price to a hex string 0x prefixed.
@@ -158,15 +224,19 @@ pub(crate) fn big_decimal_price_to_hex(price: &BigDecimal) -> String {
 /// A list of pairs can contains:
 /// - Spot pairs: formatted as usual (e.g. "BTC/USD")
 /// - Perpetual pairs: usual pair with a mark suffix (e.g. "BTC/USD:MARK").
+/// Fails if the database can't be queried.
 pub(crate) async fn only_existing_pairs(
     pool: &Pool,
     pairs: Vec<String>,
-) -> (
-    Vec<String>, // spot pairs
-    Vec<String>, // perpetual pairs
-                 // TODO: future_pairs
-) {
-    let conn = pool.get().await.expect("Couldn't connect to the database.");
+) -> Result<
+    (
+        Vec<String>, // spot pairs
+        Vec<String>, // perpetual pairs
+                     // TODO: future_pairs
+    ),
+    InfraError,
+> {
+    let conn = pool.get().await.map_err(adapt_infra_error)?;
 
     let pairs = pairs
         .iter()
@@ -182,8 +252,8 @@ pub(crate) async fn only_existing_pairs(
     let spot_pairs = conn
         .interact(move |conn| Entry::get_existing_pairs(conn, spot_pairs))
         .await
-        .expect("Couldn't check if pair exists")
-        .expect("Couldn't get table result");
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?;
 
     // Check perp entries
     let perp_pairs = pairs
@@ -195,12 +265,12 @@ pub(crate) async fn only_existing_pairs(
     let perp_pairs = conn
         .interact(move |conn| FutureEntry::get_existing_perp_pairs(conn, perp_pairs))
         .await
-        .expect("Couldn't check if pair exists")
-        .expect("Couldn't get table result")
+        .map_err(adapt_infra_error)?
+        .map_err(adapt_infra_error)?
         .into_iter()
         .collect::<Vec<String>>();
 
-    (spot_pairs, perp_pairs)
+    Ok((spot_pairs, perp_pairs))
 }
 
 #[cfg(test)]
@@ -216,10 +286,103 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_assert_currencies_are_distinct() {
+        assert!(assert_currencies_are_distinct("BTC", "USD").is_ok());
+        assert!(matches!(
+            assert_currencies_are_distinct("USD", "usd"),
+            Err(EntryError::IdenticalCurrencies(pair_id)) if pair_id == "USD/USD"
+        ));
+    }
+
+    #[test]
+    fn test_pair_id_to_currency_pair() {
+        assert_eq!(
+            pair_id_to_currency_pair("BTC/USD").unwrap(),
+            ("BTC".to_string(), "USD".to_string())
+        );
+        for pair_id in ["BTCUSD", "BTC/USD/EXTRA", "", "BTC/", "/USD"] {
+            assert!(matches!(
+                pair_id_to_currency_pair(pair_id),
+                Err(EntryError::InvalidPairId(invalid)) if invalid == pair_id
+            ));
+        }
+    }
+
+    #[test]
+    fn test_is_usd_quoted() {
+        assert!(is_usd_quoted("BTC/USD"));
+        assert!(!is_usd_quoted("BTC/USDT"));
+        assert!(!is_usd_quoted("BTC/BUSD"));
+        assert!(!is_usd_quoted("BTCUSD"));
+    }
+
+    #[test]
+    fn test_malformed_pair_id_is_propagated() {
+        let currencies = HashMap::from([
+            ("BTC".to_string(), BigDecimal::from(8)),
+            ("ETH".to_string(), BigDecimal::from(18)),
+        ]);
+        assert_eq!(get_decimals_for_pair(&currencies, "ETH/BTC").unwrap(), 8);
+        assert!(matches!(
+            get_decimals_for_pair(&currencies, "BTCUSD"),
+            Err(EntryError::InvalidPairId(_))
+        ));
+        assert_eq!(
+            currency_pairs_to_routed_pair_id("btc/usd", "eth/usd").unwrap(),
+            "BTC/ETH"
+        );
+        assert!(matches!(
+            currency_pairs_to_routed_pair_id("btc/usd", "ethusd"),
+            Err(EntryError::InvalidPairId(_))
+        ));
+    }
+
+    #[test]
+    fn test_computation_time_ms() {
+        let started_at = Instant::now() - std::time::Duration::from_millis(250);
+        assert!(computation_time_ms(false, started_at).is_none());
+
+        // Expressed in milliseconds, not in seconds.
+        let computation_time = computation_time_ms(true, started_at).unwrap();
+        assert!((250.0..60_000.0).contains(&computation_time));
+    }
+
+    #[test]
+    fn test_compute_mean_price_and_time() {
+        assert!(compute_mean_price_and_time(&[]).is_none());
+
+        let entries = vec![
+            new_entry(100, 1640995200),
+            new_entry(101, 1641081600),
+            new_entry(103, 1641000000),
+        ];
+        let (mean_price, time) = compute_mean_price_and_time(&entries).unwrap();
+        assert_eq!(mean_price, BigDecimal::from(304) / BigDecimal::from(3));
+        assert_eq!(time, entries[1].time);
+
+        // The mean is computed without precision loss.
+        let entries = vec![
+            MedianEntry {
+                median_price: "100000000000000000000001".parse().unwrap(),
+                ..new_entry(0, 1640995200)
+            },
+            MedianEntry {
+                median_price: "100000000000000000000002".parse().unwrap(),
+                ..new_entry(0, 1640995200)
+            },
+        ];
+        let (mean_price, _) = compute_mean_price_and_time(&entries).unwrap();
+        assert_eq!(
+            mean_price,
+            "100000000000000000000001.5".parse::<BigDecimal>().unwrap()
+        );
+    }
+
     #[test]
     fn test_compute_volatility_no_entries() {
         let entries = vec![];
-        assert_eq!(compute_volatility(&entries), 0.0);
+        assert_eq!(compute_volatility(&entries).unwrap(), 0.0);
     }
 
     #[test]
@@ -230,7 +393,7 @@ mod tests {
         let expected_time = ((1641081600 - 1640995200) as f64) / ONE_YEAR_IN_SECONDS;
         let expected_variance = expected_log_return / expected_time;
         let expected_volatility = expected_variance.sqrt() * 10_f64.powi(8);
-        let computed_volatility = compute_volatility(&entries);
+        let computed_volatility = compute_volatility(&entries).unwrap();
 
         const EPSILON: f64 = 1e-6;
         assert!((computed_volatility - expected_volatility).abs() < EPSILON);
@@ -245,7 +408,7 @@ mod tests {
             new_entry(45897, 1641254400),
             new_entry(43569, 1641340800),
         ];
-        assert_eq!(compute_volatility(&entries), 17264357.96367333);
+        assert_eq!(compute_volatility(&entries).unwrap(), 17264357.96367333);
     }
 
     #[test]
@@ -255,8 +418,11 @@ mod tests {
             new_entry(0, 1641081600),
             new_entry(46458, 1641168000),
         ];
-        // TODO: Shall this really return NaN?
-        assert!(f64::is_nan(compute_volatility(&entries)));
+        assert!(matches!(
+            compute_volatility(&entries),
+            Err(EntryError::VolatilityError(VolatilityError::NonPositivePrice(reason)))
+                if reason == "0 at 1641081600"
+        ));
     }
 
     #[test]
@@ -268,7 +434,7 @@ mod tests {
             new_entry(47686, 1641254400),
             new_entry(47686, 1641340800),
         ];
-        assert_eq!(compute_volatility(&entries), 0.0);
+        assert_eq!(compute_volatility(&entries).unwrap(), 0.0);
     }
 
     #[test]
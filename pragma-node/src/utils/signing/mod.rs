@@ -1,8 +1,10 @@
 pub mod starkex;
 pub mod typed_data;
 
+use moka::future::Cache;
 use pragma_common::errors::ConversionError;
-use pragma_entities::EntryError;
+use pragma_common::types::merkle_tree::{MerkleTree, MerkleTreeError};
+use pragma_entities::{EntryError, PendingSignature};
 use serde::{Deserialize, Serialize};
 use starknet::{
     core::{
@@ -21,51 +23,120 @@ pub enum SigningError {
     ConversionError,
     #[error("cannot sign: {0}")]
     SigningError(#[from] EcdsaSignError),
+    #[error("cannot build the merkle tree: {0}")]
+    MerkleTreeError(#[from] MerkleTreeError),
 }
 
 pub trait Signable {
     fn try_get_hash(&self) -> Result<Felt, ConversionError>;
 }
 
-/// Sign the passed data with the signer & return the signature 0x prefixed.
-pub fn sign_data(signer: &SigningKey, data: &impl Signable) -> Result<String, SigningError> {
+/// Sign the passed data with the signer & return the signature.
+/// The signature can be formatted as a 0x prefixed string using `format!("0x{:}", signature)`.
+pub fn sign_data(signer: &SigningKey, data: &impl Signable) -> Result<Signature, SigningError> {
     let hash_to_sign = data
         .try_get_hash()
         .map_err(|_| SigningError::ConversionError)?;
-    let signature = signer
+    signer
         .sign(&hash_to_sign)
-        .map_err(SigningError::SigningError)?;
-    Ok(format!("0x{:}", signature))
+        .map_err(SigningError::SigningError)
+}
+
+/// Sign all the passed data at once: the hashes of the data are the leaves of
+/// a merkle tree whose root is signed.
+/// Returns the tree, the leaves (in the order of the data) & the signature of the
+/// root. Each element can then be verified with its merkle proof.
+pub fn sign_data_in_batch(
+    signer: &SigningKey,
+    data: &[impl Signable],
+) -> Result<(MerkleTree, Vec<Felt>, Signature), SigningError> {
+    let leaves = data
+        .iter()
+        .map(|d| d.try_get_hash())
+        .collect::<Result<Vec<Felt>, _>>()
+        .map_err(|_| SigningError::ConversionError)?;
+    let merkle_tree = MerkleTree::new(leaves.clone())?;
+    let signature = signer.sign(&merkle_tree.root_hash)?;
+    Ok((merkle_tree, leaves, signature))
+}
+
+/// Key of a publisher signature that was successfully verified.
+/// Since it contains the hash of the signed message, a cached signature
+/// can only be reused for the exact same content.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VerifiedSignature {
+    public_key: Felt,
+    message_hash: Felt,
+    r: Felt,
+    s: Felt,
+}
+
+impl VerifiedSignature {
+    fn new(public_key: Felt, message_hash: Felt, signature: &Signature) -> Self {
+        Self {
+            public_key,
+            message_hash,
+            r: signature.r,
+            s: signature.s,
+        }
+    }
 }
 
 /// Assert that a new entries request is correctly signed
 /// by the publisher.
 /// If it is, we return the signature.
-pub fn assert_request_signature_is_valid<R, E>(
+/// When a cache is provided, signatures already verified for the same
+/// message are not verified again.
+pub async fn assert_request_signature_is_valid<R, E>(
     new_entries_request: &R,
     publisher_account: &Felt,
     publisher_public_key: &Felt,
+    verified_signatures: Option<&Cache<VerifiedSignature, ()>>,
 ) -> Result<Signature, EntryError>
 where
     R: AsRef<[Felt]> + AsRef<[E]>,
     E: EntryTrait + Serialize + for<'de> Deserialize<'de>,
 {
-    let signature = assert_signature_is_valid::<R, E>(
-        new_entries_request,
-        publisher_account,
+    let (message_hash, signature) =
+        get_message_hash_and_signature::<R, E>(new_entries_request, publisher_account)?;
+    assert_signature_is_valid(
         publisher_public_key,
-    )?;
+        &message_hash,
+        &signature,
+        verified_signatures,
+    )
+    .await?;
     Ok(signature)
 }
 
-/// Assert that a request (passed with the request for creating new
-/// entries) is correctly signed by the publisher and in a valid format.
-/// Returns the signature if it is correct.
-fn assert_signature_is_valid<R, E>(
+/// Returns the signature of a new entries request without verifying it,
+/// along with everything needed to verify it later on.
+pub fn get_pending_signature<R, E>(
+    new_entries_request: &R,
+    publisher_account: &Felt,
+    publisher_public_key: &Felt,
+) -> Result<(Signature, PendingSignature), EntryError>
+where
+    R: AsRef<[Felt]> + AsRef<[E]>,
+    E: EntryTrait + Serialize + for<'de> Deserialize<'de>,
+{
+    let (message_hash, signature) =
+        get_message_hash_and_signature::<R, E>(new_entries_request, publisher_account)?;
+    let pending_signature = PendingSignature {
+        public_key: *publisher_public_key,
+        message_hash,
+        r: signature.r,
+        s: signature.s,
+    };
+    Ok((signature, pending_signature))
+}
+
+/// Returns the hash of the message built from the entries of the request
+/// and the signature passed with it.
+fn get_message_hash_and_signature<R, E>(
     new_entries_request: &R,
     account_address: &Felt,
-    public_key: &Felt,
-) -> Result<Signature, EntryError>
+) -> Result<(Felt, Signature), EntryError>
 where
     R: AsRef<[Felt]> + AsRef<[E]>,
     E: EntryTrait + Serialize + for<'de> Deserialize<'de>,
@@ -82,12 +153,68 @@ where
         r: signature_slice[0],
         s: signature_slice[1],
     };
+    Ok((message_hash, signature))
+}
 
-    if !ecdsa_verify(public_key, &message_hash, &signature).map_err(EntryError::InvalidSignature)? {
+/// Assert that the signature of the message hash is valid for the public key.
+/// Returns true if the signature was found in the cache of verified signatures.
+async fn assert_signature_is_valid(
+    public_key: &Felt,
+    message_hash: &Felt,
+    signature: &Signature,
+    verified_signatures: Option<&Cache<VerifiedSignature, ()>>,
+) -> Result<bool, EntryError> {
+    let cache_key = VerifiedSignature::new(*public_key, *message_hash, signature);
+    if verified_signatures.is_some_and(|cache| cache.contains_key(&cache_key)) {
+        return Ok(true);
+    }
+
+    if !ecdsa_verify(public_key, message_hash, signature).map_err(EntryError::InvalidSignature)? {
         return Err(EntryError::Unauthorized(format!(
             "Invalid signature for message hash {:?}",
-            &message_hash
+            message_hash
         )));
     }
-    Ok(signature)
+
+    if let Some(cache) = verified_signatures {
+        cache.insert(cache_key, ()).await;
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verified_signatures_cache() {
+        let signer = SigningKey::from_random();
+        let public_key = signer.verifying_key().scalar();
+        let message_hash = Felt::from(42);
+        let signature = signer.sign(&message_hash).unwrap();
+        let cache = Cache::new(100);
+
+        // First publish is verified, the identical one hits the cache.
+        let hit = assert_signature_is_valid(&public_key, &message_hash, &signature, Some(&cache))
+            .await
+            .unwrap();
+        assert!(!hit);
+        let hit = assert_signature_is_valid(&public_key, &message_hash, &signature, Some(&cache))
+            .await
+            .unwrap();
+        assert!(hit);
+
+        // A changed content doesn't hit the cache and is verified again.
+        let other_hash = Felt::from(43);
+        let result =
+            assert_signature_is_valid(&public_key, &other_hash, &signature, Some(&cache)).await;
+        assert!(matches!(result, Err(EntryError::Unauthorized(_))));
+
+        let other_signature = signer.sign(&other_hash).unwrap();
+        let hit =
+            assert_signature_is_valid(&public_key, &other_hash, &other_signature, Some(&cache))
+                .await
+                .unwrap();
+        assert!(!hit);
+    }
 }
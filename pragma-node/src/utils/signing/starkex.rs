@@ -1,6 +1,10 @@
 use bigdecimal::{BigDecimal, ToPrimitive};
 use pragma_common::errors::ConversionError;
-use starknet::core::{crypto::pedersen_hash, types::Felt, utils::cairo_short_string_to_felt};
+use starknet::core::{
+    crypto::{pedersen_hash, Signature},
+    types::Felt,
+    utils::cairo_short_string_to_felt,
+};
 
 use super::Signable;
 
@@ -64,6 +68,18 @@ impl StarkexPrice {
         let v = format!("{}{}", price_as_hex, timestamp_as_hex);
         Felt::from_hex(&v).map_err(|_| ConversionError::FeltConversion)
     }
+
+    /// Serializes the price and its signature as calldata ready to be passed
+    /// to the oracle contract:
+    /// [oracle_asset_id, second_number (price & timestamp), signature_r, signature_s]
+    pub fn to_calldata(&self, signature: &Signature) -> Result<Vec<Felt>, ConversionError> {
+        Ok(vec![
+            Self::build_external_asset_id(&self.oracle_name, &self.pair_id)?,
+            Self::build_second_number(self.timestamp as u128, &self.price)?,
+            signature.r,
+            signature.s,
+        ])
+    }
 }
 
 impl Signable for StarkexPrice {
@@ -90,6 +106,31 @@ impl Signable for StarkexPrice {
     }
 }
 
+/// Short string tagging the hash of a no data attestation, so it can't be
+/// mistaken for the hash of a price.
+const NO_DATA_TAG: &str = "NO_DATA";
+
+/// Attestation that a pair had no price to sign at the timestamp.
+pub struct StarkexNoData {
+    pub oracle_name: String,
+    pub pair_id: String,
+    pub timestamp: u64,
+}
+
+impl Signable for StarkexNoData {
+    /// The signature is the pedersen hash of the no data tag with the oracle
+    /// asset id (built like for a price), hashed with the timestamp.
+    fn try_get_hash(&self) -> Result<Felt, ConversionError> {
+        let tag =
+            cairo_short_string_to_felt(NO_DATA_TAG).map_err(|_| ConversionError::FeltConversion)?;
+        let asset_id = StarkexPrice::build_external_asset_id(&self.oracle_name, &self.pair_id)?;
+        Ok(pedersen_hash(
+            &pedersen_hash(&tag, &asset_id),
+            &Felt::from(self.timestamp),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
@@ -186,4 +227,76 @@ mod tests {
             oracle_name, pair_id, price, timestamp
         );
     }
+
+    #[rstest]
+    fn test_calldata_can_be_decoded() {
+        use bigdecimal::num_bigint::BigUint;
+        use starknet::core::crypto::ecdsa_verify;
+        use starknet::signers::SigningKey;
+
+        use crate::utils::sign_data;
+
+        let signer = SigningKey::from_secret_scalar(Felt::from(42_u32));
+        let starkex_price = StarkexPrice {
+            oracle_name: "PRGM".to_string(),
+            pair_id: "BTC/USD".to_string(),
+            timestamp: 1718000000,
+            price: BigDecimal::from_str("6500012345678900000000").unwrap(),
+        };
+        let signature = sign_data(&signer, &starkex_price).unwrap();
+        let calldata = starkex_price.to_calldata(&signature).unwrap();
+        assert_eq!(calldata.len(), 4);
+
+        // Oracle asset id
+        let expected_asset_id = StarkexPrice::build_external_asset_id("PRGM", "BTC/USD").unwrap();
+        assert_eq!(calldata[0], expected_asset_id);
+
+        // Price & timestamp, packed as [price (120 bits) | timestamp (32 bits)]
+        let packed = calldata[1].to_biguint();
+        let timestamp = &packed & BigUint::from(u32::MAX);
+        let price = &packed >> 32;
+        assert_eq!(timestamp, BigUint::from(1718000000_u64));
+        assert_eq!(price, BigUint::from(6500012345678900000000_u128));
+
+        // Signature
+        let signature = Signature {
+            r: calldata[2],
+            s: calldata[3],
+        };
+        let hash = pedersen_hash(&calldata[0], &calldata[1]);
+        assert!(ecdsa_verify(&signer.verifying_key().scalar(), &hash, &signature).unwrap());
+    }
+
+    #[rstest]
+    fn test_no_data_attestation_signature_is_valid() {
+        use starknet::core::crypto::ecdsa_verify;
+        use starknet::signers::SigningKey;
+
+        use crate::utils::sign_data;
+
+        let signer = SigningKey::from_secret_scalar(Felt::from(42_u32));
+        let no_data = StarkexNoData {
+            oracle_name: "PRGM".to_string(),
+            pair_id: "BTC/USD".to_string(),
+            timestamp: 1718000000,
+        };
+        let signature = sign_data(&signer, &no_data).unwrap();
+
+        let tag = cairo_short_string_to_felt("NO_DATA").unwrap();
+        let asset_id = StarkexPrice::build_external_asset_id("PRGM", "BTC/USD").unwrap();
+        let hash = pedersen_hash(&pedersen_hash(&tag, &asset_id), &Felt::from(1718000000_u64));
+        assert!(ecdsa_verify(&signer.verifying_key().scalar(), &hash, &signature).unwrap());
+
+        // Can't be mistaken for a priced attestation.
+        let zero_price = StarkexPrice {
+            oracle_name: "PRGM".to_string(),
+            pair_id: "BTC/USD".to_string(),
+            timestamp: 1718000000,
+            price: BigDecimal::from(0),
+        };
+        assert_ne!(
+            no_data.try_get_hash().unwrap(),
+            zero_price.try_get_hash().unwrap()
+        );
+    }
 }
@@ -2,6 +2,7 @@ pub mod kafka;
 pub mod offchain_db;
 pub mod onchain_db;
 pub mod pragma_node;
+pub mod redis;
 pub mod utils;
 pub mod zookeeper;
 
@@ -9,7 +10,9 @@ use std::sync::Arc;
 
 use pragma_node::PragmaNode;
 use testcontainers::ContainerAsync;
-use testcontainers_modules::{kafka::Kafka, postgres::Postgres, zookeeper::Zookeeper};
+use testcontainers_modules::{
+    kafka::Kafka, postgres::Postgres, redis::RedisStack, zookeeper::Zookeeper,
+};
 
 // Postgres from testcontainers-modules works the same as Timescale.
 // Instead of creating a whole new Image we just use this one but rename it
@@ -22,5 +25,6 @@ pub struct Containers {
     pub onchain_db: Arc<ContainerAsync<Timescale>>,
     pub zookeeper: Arc<ContainerAsync<Zookeeper>>,
     pub kafka: Arc<ContainerAsync<Kafka>>,
+    pub redis: Arc<ContainerAsync<RedisStack>>,
     pub pragma_node: Arc<ContainerAsync<PragmaNode>>,
 }
@@ -8,7 +8,7 @@ use testcontainers::{
 
 use super::{
     offchain_db::OFFCHAIN_DB_CONTAINER_NAME, onchain_db::ONCHAIN_DB_CONTAINER_NAME,
-    utils::image_builder::ImageBuilder,
+    redis::REDIS_CONTAINER_NAME, utils::image_builder::ImageBuilder,
 };
 
 const PRAGMA_NODE_BUILD_NAME: &str = "pragma-node-e2e";
@@ -33,9 +33,11 @@ pub async fn setup_pragma_node() -> ContainerAsync<PragmaNode> {
     PragmaNode::default()
         .with_offchain_url(&db_connection_url(OFFCHAIN_DB_CONTAINER_NAME))
         .with_onchain_url(&db_connection_url(ONCHAIN_DB_CONTAINER_NAME))
+        .with_redis_host(REDIS_CONTAINER_NAME)
         // We run as mode "dev" even though it's production, so we don't build the PragmaSigner
         // for now.
         .with_mode("dev")
+        .with_max_sources_per_pair("3")
         .with_mapped_port(SERVER_PORT, SERVER_PORT.tcp())
         .with_mapped_port(METRICS_PORT, METRICS_PORT.tcp())
         .with_network("pragma-tests-network")
@@ -90,6 +92,20 @@ impl PragmaNode {
             .insert("ONCHAIN_DATABASE_URL".to_owned(), db_url.to_owned());
         self
     }
+
+    /// Sets the maximum number of sources aggregated per pair. Unlimited by default.
+    pub fn with_max_sources_per_pair(mut self, max_sources: &str) -> Self {
+        self.env_vars
+            .insert("MAX_SOURCES_PER_PAIR".to_owned(), max_sources.to_owned());
+        self
+    }
+
+    /// Sets the Redis host, listening on the default port.
+    pub fn with_redis_host(mut self, host: &str) -> Self {
+        self.env_vars
+            .insert("REDIS_HOST".to_owned(), host.to_owned());
+        self
+    }
 }
 
 impl Image for PragmaNode {
@@ -0,0 +1,16 @@
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, ImageExt};
+use testcontainers_modules::redis::RedisStack;
+
+pub const REDIS_CONTAINER_NAME: &str = "test-redis";
+
+// Redis Stack ships the JSON module used to store the merkle feeds.
+#[rstest::fixture]
+pub async fn setup_redis() -> ContainerAsync<RedisStack> {
+    RedisStack::default()
+        .with_network("pragma-tests-network")
+        .with_container_name(REDIS_CONTAINER_NAME)
+        .start()
+        .await
+        .unwrap()
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use deadpool_diesel::{postgres::Pool, Manager};
 use testcontainers::ContainerAsync;
 use testcontainers_modules::kafka::Kafka;
+use testcontainers_modules::redis::{RedisStack, REDIS_PORT};
 use testcontainers_modules::zookeeper::Zookeeper;
 
 use crate::common::containers::{
@@ -10,6 +11,7 @@ use crate::common::containers::{
     offchain_db::setup_offchain_db,
     onchain_db::{run_onchain_migrations, setup_onchain_db},
     pragma_node::{setup_pragma_node, PragmaNode, SERVER_PORT},
+    redis::setup_redis,
     zookeeper::setup_zookeeper,
     Containers, Timescale,
 };
@@ -21,6 +23,7 @@ pub struct TestHelper {
     pub node_base_url: String,
     pub onchain_pool: Pool,
     pub offchain_pool: Pool,
+    pub redis_client: redis::Client,
     pub containers: Containers,
 }
 
@@ -37,6 +40,7 @@ pub async fn setup_containers(
     #[future] setup_onchain_db: ContainerAsync<Timescale>,
     #[future] setup_zookeeper: ContainerAsync<Zookeeper>,
     #[future] setup_kafka: ContainerAsync<Kafka>,
+    #[future] setup_redis: ContainerAsync<RedisStack>,
     #[future] setup_pragma_node: ContainerAsync<PragmaNode>,
 ) -> TestHelper {
     tracing::info!("🔨 Setup offchain db..");
@@ -59,6 +63,12 @@ pub async fn setup_containers(
     init_kafka_topics(&kafka).await;
     tracing::info!("✅ ... kafka!\n");
 
+    tracing::info!("🔨 Setup redis..");
+    let redis = setup_redis.await;
+    let redis_port = redis.get_host_port_ipv4(REDIS_PORT).await.unwrap();
+    let redis_client = redis::Client::open(format!("redis://localhost:{redis_port}")).unwrap();
+    tracing::info!("✅ ... redis!\n");
+
     tracing::info!("🔨 Setup pragma_node...");
     let pragma_node = setup_pragma_node.await;
     tracing::info!("✅ ... pragma-node!\n");
@@ -68,6 +78,7 @@ pub async fn setup_containers(
         offchain_db: Arc::new(offchain_db),
         zookeeper: Arc::new(zookeeper),
         kafka: Arc::new(kafka),
+        redis: Arc::new(redis),
         pragma_node: Arc::new(pragma_node),
     };
 
@@ -76,6 +87,7 @@ pub async fn setup_containers(
         containers,
         onchain_pool,
         offchain_pool,
+        redis_client,
     }
 }
 
@@ -0,0 +1,120 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDateTime};
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+use pragma_entities::schema::{entries, future_entries};
+use pragma_entities::{Entry, FutureEntry, NewEntry, NewFutureEntry, PublisherPriority};
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+use crate::common::setup::{setup_containers, TestHelper};
+
+/// Upserts the entry of the publisher at the same (pair, source, timestamp)
+/// as the previous ones, and returns the publisher and price then stored.
+type Upsert = fn(&mut PgConnection, &str, u64, &PublisherPriority) -> (String, BigDecimal);
+
+fn conflicting_timestamp() -> NaiveDateTime {
+    DateTime::from_timestamp(1_700_000_000, 0)
+        .unwrap()
+        .naive_utc()
+}
+
+fn upsert_spot_entry(
+    conn: &mut PgConnection,
+    publisher: &str,
+    price: u64,
+    priority: &PublisherPriority,
+) -> (String, BigDecimal) {
+    let entry = NewEntry {
+        pair_id: "BTC/USD".to_string(),
+        publisher: publisher.to_string(),
+        source: "BINANCE".to_string(),
+        timestamp: conflicting_timestamp(),
+        publisher_signature: "0x0".to_string(),
+        price: BigDecimal::from(price),
+        bid: None,
+        ask: None,
+    };
+    Entry::create_many(conn, vec![entry], priority).unwrap();
+    entries::table
+        .filter(entries::pair_id.eq("BTC/USD"))
+        .filter(entries::timestamp.eq(conflicting_timestamp()))
+        .select((entries::publisher, entries::price))
+        .first(conn)
+        .unwrap()
+}
+
+fn upsert_future_entry(
+    conn: &mut PgConnection,
+    publisher: &str,
+    price: u64,
+    priority: &PublisherPriority,
+) -> (String, BigDecimal) {
+    let expiration_timestamp = DateTime::from_timestamp(1_700_086_400, 0)
+        .unwrap()
+        .naive_utc();
+    let entry = NewFutureEntry {
+        pair_id: "BTC/USD".to_string(),
+        publisher: publisher.to_string(),
+        source: "BINANCE".to_string(),
+        timestamp: conflicting_timestamp(),
+        expiration_timestamp: Some(expiration_timestamp),
+        publisher_signature: "0x0".to_string(),
+        price: BigDecimal::from(price),
+    };
+    FutureEntry::create_many(conn, vec![entry], priority).unwrap();
+    future_entries::table
+        .filter(future_entries::pair_id.eq("BTC/USD"))
+        .filter(future_entries::timestamp.eq(conflicting_timestamp()))
+        .select((future_entries::publisher, future_entries::price))
+        .first(conn)
+        .unwrap()
+}
+
+#[rstest]
+#[tokio::test]
+async fn spot_and_future_conflicts_are_settled_by_the_publisher_priority(
+    #[future] setup_containers: TestHelper,
+) {
+    let hlpr = setup_containers.await;
+
+    let conn = hlpr.offchain_pool.get().await.unwrap();
+    conn.interact(|conn| {
+        let stored = |publisher: &str, price: u64| (publisher.to_string(), BigDecimal::from(price));
+        let priority = PublisherPriority::new(vec!["PRAGMA".into(), "FOURLEAF".into()]);
+
+        for upsert in [upsert_spot_entry as Upsert, upsert_future_entry] {
+            assert_eq!(
+                upsert(conn, "FOURLEAF", 100, &priority),
+                stored("FOURLEAF", 100)
+            );
+
+            // A publisher of higher priority overwrites the stored entry.
+            assert_eq!(
+                upsert(conn, "PRAGMA", 101, &priority),
+                stored("PRAGMA", 101)
+            );
+
+            // Publishers of lower priority, or not listed, don't.
+            assert_eq!(
+                upsert(conn, "FOURLEAF", 102, &priority),
+                stored("PRAGMA", 101)
+            );
+            assert_eq!(
+                upsert(conn, "UNKNOWN", 103, &priority),
+                stored("PRAGMA", 101)
+            );
+
+            // On equal rank, the last entry received wins, as without priority.
+            assert_eq!(
+                upsert(conn, "PRAGMA", 104, &priority),
+                stored("PRAGMA", 104)
+            );
+            assert_eq!(
+                upsert(conn, "UNKNOWN", 105, &PublisherPriority::default()),
+                stored("UNKNOWN", 105)
+            );
+        }
+    })
+    .await
+    .unwrap();
+}
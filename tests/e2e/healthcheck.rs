@@ -17,3 +17,25 @@ async fn healthcheck_ok(#[future] setup_containers: TestHelper) {
 
     assert_eq!(body.trim(), "Server is running!");
 }
+
+#[rstest]
+#[tokio::test]
+async fn database_healthcheck_reports_a_down_database(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    let response = reqwest::get(hlpr.endpoint("node/v1/health/db"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    hlpr.containers.onchain_db.stop().await.unwrap();
+
+    let response = reqwest::get(hlpr.endpoint("node/v1/health/db"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(body["offchain"]["healthy"], true);
+    assert_eq!(body["onchain"]["healthy"], false);
+    assert!(body["onchain"]["error"].is_string());
+}
@@ -1,3 +1,8 @@
 pub mod common;
 
+pub mod entries_upsert;
 pub mod healthcheck;
+pub mod merkle_feeds;
+pub mod offchain_entry;
+pub mod onchain_entry;
+pub mod optimistic_oracle;
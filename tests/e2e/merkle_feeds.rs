@@ -0,0 +1,70 @@
+use pretty_assertions::assert_eq;
+use redis::{AsyncCommands, JsonAsyncCommands};
+use rstest::rstest;
+use serde_json::{json, Value};
+
+use crate::common::setup::{setup_containers, TestHelper};
+
+const FIRST_PUBLISHED_AT: u64 = 1_700_000_000;
+
+/// Publishes the blocks `0..=latest` every 10 seconds, with one option each
+/// except the blocks listed without options.
+async fn publish_blocks(hlpr: &TestHelper, latest: u64, without_options: &[u64]) {
+    let mut conn = hlpr
+        .redis_client
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    for block_number in (0..=latest).filter(|block| !without_options.contains(block)) {
+        let option = json!({
+            "instrument_name": "BTC-27SEP24-60000-C",
+            "current_timestamp": FIRST_PUBLISHED_AT + 10 * block_number,
+        });
+        let _: () = conn
+            .json_set(
+                format!("mainnet/{block_number}/options/BTC-27SEP24-60000-C"),
+                "$",
+                &option,
+            )
+            .await
+            .unwrap();
+    }
+    let _: () = conn
+        .set("mainnet/latest_published_block", latest)
+        .await
+        .unwrap();
+}
+
+async fn get_block_at_timestamp(hlpr: &TestHelper, timestamp: u64) -> (u16, Value) {
+    let path = format!("node/v1/merkle_feeds/block?network=mainnet&timestamp={timestamp}");
+    let response = reqwest::get(hlpr.endpoint(&path)).await.unwrap();
+    let status = response.status().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text().await.unwrap()).unwrap(),
+    )
+}
+
+#[rstest]
+#[tokio::test]
+async fn block_at_timestamp_is_found(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    publish_blocks(&hlpr, 1_000, &[420, 421, 1_000]).await;
+
+    let (status, body) = get_block_at_timestamp(&hlpr, FIRST_PUBLISHED_AT + 4_225).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["block_number"], 422);
+
+    // Blocks without options are skipped.
+    let (status, body) = get_block_at_timestamp(&hlpr, FIRST_PUBLISHED_AT + 4_215).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["block_number"], 419);
+
+    let (status, body) = get_block_at_timestamp(&hlpr, FIRST_PUBLISHED_AT + 20_000).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["block_number"], 999);
+
+    let (status, _) = get_block_at_timestamp(&hlpr, FIRST_PUBLISHED_AT - 1).await;
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,316 @@
+use deadpool_diesel::postgres::Pool;
+use diesel::connection::SimpleConnection;
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use serde_json::Value;
+
+use crate::common::setup::{setup_containers, TestHelper};
+
+/// An entry of a publisher for a source, with its bid and ask if reported.
+struct OffchainEntry {
+    publisher: &'static str,
+    source: &'static str,
+    price: u64,
+    quotes: Option<(u64, u64)>,
+    seconds_ago: u64,
+}
+
+impl OffchainEntry {
+    /// An entry without quotes, reported `seconds_ago`.
+    fn new(publisher: &'static str, source: &'static str, price: u64, seconds_ago: u64) -> Self {
+        Self {
+            publisher,
+            source,
+            price,
+            quotes: None,
+            seconds_ago,
+        }
+    }
+
+    fn with_quotes(self, bid: u64, ask: u64) -> Self {
+        Self {
+            quotes: Some((bid, ask)),
+            ..self
+        }
+    }
+}
+
+/// Inserts the entries for the pair, each ingested when reported.
+async fn insert_entries(pool: &Pool, pair_id: &str, entries: &[OffchainEntry]) {
+    let values = entries
+        .iter()
+        .map(|entry| {
+            let (bid, ask) = entry
+                .quotes
+                .map_or(("NULL".to_string(), "NULL".to_string()), |(bid, ask)| {
+                    (bid.to_string(), ask.to_string())
+                });
+            let time = format!("NOW() - INTERVAL '{} seconds'", entry.seconds_ago);
+            format!(
+                "('{pair_id}', '{}', '{}', {time}, {}, {bid}, {ask}, {time})",
+                entry.publisher, entry.source, entry.price
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let sql = format!(
+        "INSERT INTO entries (pair_id, publisher, source, timestamp, price, bid, ask, ingested_at) VALUES {values};"
+    );
+
+    let conn = pool.get().await.unwrap();
+    conn.interact(move |conn| conn.batch_execute(&sql))
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+async fn get_json(hlpr: &TestHelper, path: &str) -> Value {
+    let body = reqwest::get(hlpr.endpoint(path))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    serde_json::from_str(&body).unwrap()
+}
+
+#[rstest]
+#[tokio::test]
+async fn entry_mid_follows_the_aggregation_mode(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    insert_entries(
+        &hlpr.offchain_pool,
+        "BTC/USD",
+        &[
+            OffchainEntry::new("PRAGMA", "BINANCE", 100, 10).with_quotes(96, 100),
+            OffchainEntry::new("PRAGMA", "OKX", 104, 10).with_quotes(100, 104),
+            // Doesn't report quotes: its price is used as mid.
+            OffchainEntry::new("SKYNET", "BYBIT", 110, 10),
+        ],
+    )
+    .await;
+
+    // The median of the mids 98, 102 and 110.
+    let entry = get_json(
+        &hlpr,
+        "node/v1/data/btc/usd?aggregation=median&interval=1min&mid=true",
+    )
+    .await;
+    assert_eq!(entry["mid"], format!("0x{:x}", 102));
+
+    // The mean of the median mid of PRAGMA, 100, and the mid of SKYNET, 110.
+    let entry = get_json(
+        &hlpr,
+        "node/v1/data/btc/usd?aggregation=mean&interval=1min&mid=true",
+    )
+    .await;
+    assert_eq!(entry["mid"], format!("0x{:x}", 105));
+
+    // Restricted to the requested sources.
+    let entry = get_json(
+        &hlpr,
+        "node/v1/data/btc/usd?aggregation=median&interval=1min&mid=true&sources=binance,bybit",
+    )
+    .await;
+    assert_eq!(entry["mid"], format!("0x{:x}", 104));
+}
+
+#[rstest]
+#[tokio::test]
+async fn entry_median_of_the_requested_sources(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    insert_entries(
+        &hlpr.offchain_pool,
+        "BTC/USD",
+        &[
+            OffchainEntry::new("PRAGMA", "BINANCE", 100_0000_0000, 10),
+            OffchainEntry::new("SKYNET", "BINANCE", 102_0000_0000, 10),
+            OffchainEntry::new("PRAGMA", "OKX", 104_0000_0000, 10),
+            OffchainEntry::new("PRAGMA", "BYBIT", 1000_0000_0000, 10),
+        ],
+    )
+    .await;
+
+    // The median of all the prices of the sources in the bucket, like the
+    // median without sources, approximated the same way.
+    let path = "node/v1/data/btc/usd?aggregation=median&interval=1min";
+    let entry = get_json(&hlpr, &format!("{path}&sources=binance")).await;
+    let price = entry["price"].as_str().unwrap().trim_start_matches("0x");
+    let price = u64::from_str_radix(price, 16).unwrap();
+    assert!((100_5000_0000..=101_5000_0000).contains(&price), "{price}");
+    assert_eq!(entry["num_sources_aggregated"], 1);
+
+    let response = reqwest::get(hlpr.endpoint(&format!("{path}&sources=kraken")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("BTC/USD from the sources KRAKEN"));
+}
+
+#[rstest]
+#[tokio::test]
+async fn source_coverage_counts_the_recent_sources_of_each_pair(
+    #[future] setup_containers: TestHelper,
+) {
+    let hlpr = setup_containers.await;
+
+    insert_entries(
+        &hlpr.offchain_pool,
+        "BTC/USD",
+        &[
+            OffchainEntry::new("PRAGMA", "BINANCE", 100, 10),
+            // A source published by two publishers is counted once.
+            OffchainEntry::new("SKYNET", "BINANCE", 100, 20),
+            OffchainEntry::new("PRAGMA", "OKX", 100, 30),
+        ],
+    )
+    .await;
+    insert_entries(
+        &hlpr.offchain_pool,
+        "ETH/USD",
+        &[
+            OffchainEntry::new("PRAGMA", "BINANCE", 10, 10),
+            // Out of the 5 minutes window.
+            OffchainEntry::new("PRAGMA", "OKX", 10, 10 * 60),
+        ],
+    )
+    .await;
+    insert_entries(
+        &hlpr.offchain_pool,
+        "SOL/USD",
+        &[OffchainEntry::new("PRAGMA", "BINANCE", 1, 10 * 60)],
+    )
+    .await;
+
+    let coverage = get_json(&hlpr, "node/v1/metrics/source-coverage").await;
+    assert_eq!(coverage["window_in_seconds"], 300);
+    assert_eq!(
+        coverage["pairs"],
+        serde_json::json!([
+            {"pair_id": "BTC/USD", "num_sources": 2},
+            {"pair_id": "ETH/USD", "num_sources": 1},
+        ])
+    );
+    assert_eq!(
+        coverage["distribution"],
+        serde_json::json!({"1": 1, "2": 1})
+    );
+}
+
+#[rstest]
+#[tokio::test]
+async fn perp_entry_only_aggregates_the_most_recent_sources(
+    #[future] setup_containers: TestHelper,
+) {
+    let hlpr = setup_containers.await;
+
+    // The node aggregates at most 3 sources per pair. The timestamps are ahead
+    // of the node so they are all part of its first lookup window.
+    let values = [
+        ("KRAKEN", 100, 59),
+        ("OKX", 102, 58),
+        ("BYBIT", 104, 57),
+        ("BINANCE", 1000, 56),
+        ("COINBASE", 1000, 55),
+    ]
+    .iter()
+    .map(|(source, price, seconds_ahead)| {
+        format!(
+            "('BTC/USD', 'PRAGMA', '{source}', NOW() + INTERVAL '{seconds_ahead} seconds', NULL, {price}, '0x0')"
+        )
+    })
+    .collect::<Vec<_>>()
+    .join(",");
+    let sql = format!(
+        "INSERT INTO future_entries (pair_id, publisher, source, timestamp, expiration_timestamp, price, publisher_signature) VALUES {values};"
+    );
+    let conn = hlpr.offchain_pool.get().await.unwrap();
+    conn.interact(move |conn| conn.batch_execute(&sql))
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The median of KRAKEN, OKX and BYBIT, the 2 oldest sources being dropped.
+    let entry = get_json(&hlpr, "node/v1/data/perp/btc/usd").await;
+    assert_eq!(entry["price"], format!("0x{:x}", 102));
+    assert_eq!(entry["num_sources_aggregated"], 3);
+    assert_eq!(entry["num_sources_dropped"], 2);
+}
+
+#[rstest]
+#[tokio::test]
+async fn sources_history_groups_the_sources_by_bucket(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    // Entries of the minutes following `start`, an hour ago. They are placed
+    // within their minute so the delay before their insertion doesn't move
+    // them to the next one.
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let start = (now - 3600) / 60 * 60;
+    let at = |seconds_after_start: u64| now - (start + seconds_after_start);
+    insert_entries(
+        &hlpr.offchain_pool,
+        "BTC/USD",
+        &[
+            OffchainEntry::new("PRAGMA", "BINANCE", 100, at(10)),
+            OffchainEntry::new("PRAGMA", "OKX", 100, at(20)),
+            OffchainEntry::new("PRAGMA", "BINANCE", 100, at(70)),
+            // A source published by two publishers is listed once.
+            OffchainEntry::new("SKYNET", "BINANCE", 100, at(80)),
+            OffchainEntry::new("PRAGMA", "BYBIT", 100, at(190)),
+            OffchainEntry::new("PRAGMA", "BINANCE", 100, at(200)),
+            // After the requested range.
+            OffchainEntry::new("PRAGMA", "KRAKEN", 100, at(400)),
+        ],
+    )
+    .await;
+    insert_entries(
+        &hlpr.offchain_pool,
+        "ETH/USD",
+        &[OffchainEntry::new("PRAGMA", "COINBASE", 10, at(10))],
+    )
+    .await;
+
+    let history = get_json(
+        &hlpr,
+        &format!(
+            "node/v1/data/btc/usd/sources/history?timestamp={start},{}&interval=1min",
+            start + 299
+        ),
+    )
+    .await;
+    assert_eq!(history["pair_id"], "BTC/USD");
+    assert_eq!(
+        history["buckets"],
+        serde_json::json!([
+            {
+                "timestamp": start,
+                "sources": ["BINANCE", "OKX"],
+                "joined": ["BINANCE", "OKX"],
+                "left": [],
+            },
+            {
+                "timestamp": start + 60,
+                "sources": ["BINANCE"],
+                "joined": [],
+                "left": ["OKX"],
+            },
+            // The minute without entries has no bucket.
+            {
+                "timestamp": start + 180,
+                "sources": ["BINANCE", "BYBIT"],
+                "joined": ["BYBIT"],
+                "left": [],
+            },
+        ])
+    );
+}
@@ -0,0 +1,190 @@
+use deadpool_diesel::postgres::Pool;
+use diesel::connection::SimpleConnection;
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use serde_json::Value;
+
+use crate::common::setup::{setup_containers, TestHelper};
+
+/// Inserts one sepolia spot entry per price for the pair, each from another source.
+async fn insert_onchain_spot_entries(pool: &Pool, pair_id: &str, prices: &[u64]) {
+    insert_onchain_spot_entries_at_block(pool, pair_id, 1, 0, prices).await;
+}
+
+/// Inserts one sepolia spot entry per price for the pair in the block, published
+/// `minutes_ago`, each from another source.
+async fn insert_onchain_spot_entries_at_block(
+    pool: &Pool,
+    pair_id: &str,
+    block_number: u64,
+    minutes_ago: u64,
+    prices: &[u64],
+) {
+    let values = prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| {
+            let time = format!("NOW() - INTERVAL '{minutes_ago} minutes'");
+            format!(
+                "('sepolia', '{pair_id}', '{pair_id}-{block_number}-{i}', '0x{block_number}', {block_number}, {time}, '0x{block_number}{i}', {price}, {time}, 'PRAGMA', 'SOURCE_{i}', 0, {block_number})"
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let sql = format!(
+        "INSERT INTO spot_entry (network, pair_id, data_id, block_hash, block_number, block_timestamp, transaction_hash, price, timestamp, publisher, source, volume, _cursor) VALUES {values};"
+    );
+
+    let conn = pool.get().await.unwrap();
+    conn.interact(move |conn| conn.batch_execute(&sql))
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+async fn get_onchain_entry(hlpr: &TestHelper, pair: &str, routing: bool) -> Value {
+    let path = format!(
+        "node/v1/onchain/{pair}?network=sepolia&aggregation=mean&routing={routing}&variations=false"
+    );
+    get_json(hlpr, &path).await
+}
+
+async fn get_json(hlpr: &TestHelper, path: &str) -> Value {
+    let body = reqwest::get(hlpr.endpoint(path))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    serde_json::from_str(&body).unwrap()
+}
+
+#[rstest]
+#[tokio::test]
+async fn onchain_entry_mean_aggregation(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    insert_onchain_spot_entries(
+        &hlpr.onchain_pool,
+        "BTC/USD",
+        &[100_0000_0000, 200_0000_0000, 600_0000_0000],
+    )
+    .await;
+    insert_onchain_spot_entries(
+        &hlpr.onchain_pool,
+        "ETH/USD",
+        &[3000_0000_0000, 3100_0000_0000, 3500_0000_0000],
+    )
+    .await;
+
+    // The mean of the sources, where the median would be 200.
+    let entry = get_onchain_entry(&hlpr, "BTC/USD", false).await;
+    assert_eq!(entry["price"], format!("0x{:x}", 300_0000_0000_u64));
+    assert_eq!(entry["nb_sources_aggregated"], 3);
+    assert_eq!(entry["components"].as_array().unwrap().len(), 3);
+
+    // Routed through USD: mean(ETH/USD) / mean(BTC/USD) = 3200 / 300, with
+    // the components of both pairs.
+    let entry = get_onchain_entry(&hlpr, "ETH/BTC", true).await;
+    assert_eq!(entry["price"], format!("0x{:x}", 10_6666_6666_u64));
+    assert_eq!(entry["decimals"], 8);
+    assert_eq!(entry["nb_sources_aggregated"], 6);
+    assert_eq!(entry["components"].as_array().unwrap().len(), 6);
+    assert!(entry.get("computation_time_ms").is_none());
+
+    // The computation time is only returned when requested.
+    let entry = get_json(
+        &hlpr,
+        "node/v1/onchain/ETH/BTC?network=sepolia&aggregation=mean&routing=true&variations=false&timing=true",
+    )
+    .await;
+    assert!(entry["computation_time_ms"].as_f64().unwrap() > 0.0);
+}
+
+#[rstest]
+#[tokio::test]
+async fn onchain_entry_at_historical_block(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    insert_onchain_spot_entries_at_block(
+        &hlpr.onchain_pool,
+        "EUR/USD",
+        10,
+        20,
+        &[100_0000_0000, 110_0000_0000],
+    )
+    .await;
+    insert_onchain_spot_entries_at_block(
+        &hlpr.onchain_pool,
+        "EUR/USD",
+        11,
+        0,
+        &[150_0000_0000, 170_0000_0000],
+    )
+    .await;
+
+    let path = "node/v1/onchain/EUR/USD?network=sepolia&aggregation=mean&variations=false";
+    let latest = get_json(&hlpr, path).await;
+    assert_eq!(latest["price"], format!("0x{:x}", 160_0000_0000_u64));
+
+    // Only the entries included up to the block are aggregated.
+    let at_block = get_json(&hlpr, &format!("{path}&block=10")).await;
+    assert_eq!(at_block["price"], format!("0x{:x}", 105_0000_0000_u64));
+    assert_eq!(at_block["nb_sources_aggregated"], 2);
+
+    let response = reqwest::get(hlpr.endpoint(&format!("{path}&block=10&timestamp=1718000000")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[rstest]
+#[tokio::test]
+async fn onchain_entry_twap_aggregation(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    // One source updated 40 & 10 minutes before the requested timestamp.
+    let timestamp = 1_718_000_000;
+    let sql = format!(
+        "INSERT INTO spot_entry (network, pair_id, data_id, block_hash, block_number, block_timestamp, transaction_hash, price, timestamp, publisher, source, volume, _cursor) VALUES \
+        ('sepolia', 'BTC/USD', 'twap-0', '0x1', 1, to_timestamp({t0}), '0x1', 10000000000, to_timestamp({t0}), 'PRAGMA', 'SOURCE_0', 0, 1), \
+        ('sepolia', 'BTC/USD', 'twap-1', '0x2', 2, to_timestamp({t1}), '0x2', 20000000000, to_timestamp({t1}), 'PRAGMA', 'SOURCE_0', 0, 2);",
+        t0 = timestamp - 40 * 60,
+        t1 = timestamp - 10 * 60,
+    );
+    let conn = hlpr.onchain_pool.get().await.unwrap();
+    conn.interact(move |conn| conn.batch_execute(&sql))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let path = format!(
+        "node/v1/onchain/BTC/USD?network=sepolia&aggregation=twap&timestamp={timestamp}&variations=false"
+    );
+    let response = reqwest::get(hlpr.endpoint(&path)).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // 100 during 30 minutes then 200 during 10 minutes.
+    let entry = get_json(&hlpr, &format!("{path}&interval=1h")).await;
+    assert_eq!(entry["price"], format!("0x{:x}", 125_0000_0000_u64));
+    assert_eq!(entry["last_updated_timestamp"], timestamp - 10 * 60);
+
+    // The window can't be longer than the hour of the components.
+    let response = reqwest::get(hlpr.endpoint(&format!("{path}&interval=1d")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // The price in effect 15 minutes before counts from the start of the
+    // window: 100 during 5 minutes then 200 during 10 minutes.
+    let entry = get_json(&hlpr, &format!("{path}&interval=15min")).await;
+    assert_eq!(entry["price"], format!("0x{:x}", 166_6666_6666_u64));
+
+    // The merged entry also requires the window of the TWAP.
+    let response = reqwest::get(hlpr.endpoint(&format!(
+        "node/v1/onchain/merged/BTC/USD?aggregation=twap&timestamp={timestamp}"
+    )))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,101 @@
+use deadpool_diesel::postgres::Pool;
+use diesel::connection::SimpleConnection;
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+use serde_json::Value;
+
+use crate::common::setup::{setup_containers, TestHelper};
+
+/// Inserts `count` live assertions, disputed or not, updated a minute apart.
+async fn insert_assertions(pool: &Pool, id_prefix: &str, count: usize, disputed: bool) {
+    let values = (0..count)
+        .map(|i| {
+            format!(
+                "('sepolia', '0x{id_prefix}{i}', 'claim {i}', '0xdisputer', {disputed}, '{i}', NOW() + INTERVAL '1 day', 1000, int8range(1, NULL), 'ASSERT_TRUTH', NOW() - INTERVAL '{i} minutes', '0xtx{i}', '0xcurrency')"
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let sql = format!(
+        "INSERT INTO oo_requests (network, assertion_id, claim, disputer, disputed, dispute_id, expiration_timestamp, bond, _cursor, identifier, updated_at, updated_at_tx, currency) VALUES {values};"
+    );
+
+    let conn = pool.get().await.unwrap();
+    conn.interact(move |conn| conn.batch_execute(&sql))
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+async fn get_disputed_assertions_page(hlpr: &TestHelper, page: u32, limit: u32) -> Value {
+    let path = format!("node/v1/optimistic/disputed-assertions?page={page}&limit={limit}");
+    let body = reqwest::get(hlpr.endpoint(&path))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    serde_json::from_str(&body).unwrap()
+}
+
+fn assertion_ids(page: &Value) -> Vec<String> {
+    page["disputed_assertions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|disputed| {
+            disputed["assertion"]["assertion_id"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[rstest]
+#[tokio::test]
+async fn disputed_assertions_are_paginated(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    insert_assertions(&hlpr.onchain_pool, "d", 5, true).await;
+    insert_assertions(&hlpr.onchain_pool, "a", 3, false).await;
+
+    let mut listed = Vec::new();
+    for (page, expected_len) in [(1, 2), (2, 2), (3, 1), (4, 0)] {
+        let response = get_disputed_assertions_page(&hlpr, page, 2).await;
+        assert_eq!(response["total_count"], 5);
+        assert_eq!(response["total_pages"], 3);
+        assert_eq!(response["current_page"], page);
+
+        let ids = assertion_ids(&response);
+        assert_eq!(ids.len(), expected_len);
+        listed.extend(ids);
+    }
+
+    // The pages list every disputed assertion once, most recently updated first.
+    assert_eq!(listed, vec!["0xd0", "0xd1", "0xd2", "0xd3", "0xd4"]);
+}
+
+#[rstest]
+#[tokio::test]
+async fn assertion_is_found_whatever_its_case_and_padding(#[future] setup_containers: TestHelper) {
+    let hlpr = setup_containers.await;
+
+    // Every column is set, as the details of the assertion are returned.
+    let sql = "INSERT INTO oo_requests (network, data_id, assertion_id, domain_id, claim, asserter, disputer, disputed, dispute_id, callback_recipient, escalation_manager, caller, expiration_timestamp, settled, settlement_resolution, settle_caller, currency, bond, _cursor, identifier, updated_at, updated_at_tx) VALUES ('sepolia', '0xdata', '0xd0', '0xdomain', 'claim', '0xasserter', '0xdisputer', true, '0', '0xcallback', '0xmanager', '0xcaller', NOW() + INTERVAL '1 day', false, false, '0xsettler', '0xcurrency', 1000, int8range(1, NULL), 'ASSERT_TRUTH', NOW(), '0xtx');";
+    let conn = hlpr.onchain_pool.get().await.unwrap();
+    conn.interact(move |conn| conn.batch_execute(sql))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let padded_id = format!("0x{}D0", "0".repeat(62));
+    for assertion_id in ["0xd0", "0xD0", "0x00d0", padded_id.as_str()] {
+        let path = format!("node/v1/optimistic/assertions/{assertion_id}");
+        let response = reqwest::get(hlpr.endpoint(&path)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["assertion"]["assertion_id"], "0xd0");
+    }
+}